@@ -0,0 +1,239 @@
+//! Aliases, as `alias gco='git checkout $1'`: a word starting a command
+//! replaced by a line of its own. `$1`...`$N` in the line take the words
+//! after the alias and `$@` all of them. Words past the highest `$N` used
+//! are appended, unless `$@` already placed them. A body may run several
+//! commands, as `alias mkcd='mkdir $1 && cd $1'`, split by `commands`.
+
+use crate::parse;
+
+/// `body` with the placeholders filled from `args`, which are words as
+/// typed, quotes and all. Single-quoted and escaped `$` are left alone.
+pub fn expand(body: &str, args: &[&str]) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut used = 0;
+    let mut all = false;
+    let mut quote: Option<char> = None;
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                out.push(c);
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            }
+            '\'' | '"' if quote.is_none() => {
+                quote = Some(c);
+                out.push(c);
+            }
+            c if quote == Some(c) => {
+                quote = None;
+                out.push(c);
+            }
+            '$' if quote != Some('\'') && chars.peek() == Some(&'@') => {
+                chars.next();
+                out.push_str(&args.join(" "));
+                all = true;
+            }
+            '$' if quote != Some('\'') && chars.peek().is_some_and(|c| ('1'..='9').contains(c)) => {
+                let mut n = 0;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    n = n * 10 + digit as usize;
+                    chars.next();
+                }
+                if let Some(arg) = args.get(n - 1) {
+                    out.push_str(arg);
+                }
+                used = used.max(n);
+            }
+            _ => out.push(c),
+        }
+    }
+    if !all {
+        for arg in args.iter().skip(used) {
+            out.push(' ');
+            out.push_str(arg);
+        }
+    }
+    out
+}
+
+/// `line` with the alias starting each pipeline stage expanded, or `None`
+/// when no stage starts with one.
+pub fn expand_line<'a>(line: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> Option<String> {
+    let mut expanded = false;
+    let stages: Vec<String> = parse::split_pipeline(line)
+        .into_iter()
+        .map(|stage| {
+            let words = parse::split_words(&stage);
+            match words.split_first() {
+                Some((name, args)) => match lookup(name) {
+                    Some(body) => {
+                        expanded = true;
+                        expand(body, args)
+                    }
+                    None => stage,
+                },
+                None => stage,
+            }
+        })
+        .collect();
+    expanded.then(|| stages.join(" | "))
+}
+
+/// An expanded line split on unquoted `;` and `&&` into its commands,
+/// each flagged when it only runs if the one before succeeded. `;` and `&&`
+/// inside `$(...)` stay with their command.
+pub fn commands(line: &str) -> Vec<(String, bool)> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut after_success = false;
+    let mut quote: Option<char> = None;
+    let mut depth = 0usize;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                current.push(c);
+                current.extend(chars.next());
+                continue;
+            }
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            c if quote == Some(c) => quote = None,
+            '(' if quote.is_none() => depth += 1,
+            ')' if quote.is_none() => depth = depth.saturating_sub(1),
+            ';' if quote.is_none() && depth == 0 => {
+                commands.push((std::mem::take(&mut current), after_success));
+                after_success = false;
+                continue;
+            }
+            '&' if quote.is_none() && depth == 0 && chars.peek() == Some(&'&') => {
+                chars.next();
+                commands.push((std::mem::take(&mut current), after_success));
+                after_success = true;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    commands.push((current, after_success));
+    commands
+        .into_iter()
+        .map(|(command, after_success)| (command.trim().to_string(), after_success))
+        .filter(|(command, _)| !command.is_empty())
+        .collect()
+}
+
+/// What a command line comes to once its aliases are expanded.
+#[derive(Debug, PartialEq)]
+pub enum Expansion {
+    /// One command to run now.
+    Line(String),
+    /// An alias body's several commands, to run in turn, each flagged when
+    /// it only runs if the one before succeeded. They're already expanded
+    /// and mustn't be again, or `alias ls='ls -F; pwd'` would never end.
+    Commands(Vec<(String, bool)>),
+}
+
+/// Expands the aliases in `line`, splitting a body with several commands.
+pub fn expand_statement<'a>(line: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> Expansion {
+    let Some(expanded) = expand_line(line, lookup) else {
+        return Expansion::Line(line.to_string());
+    };
+    let commands = commands(&expanded);
+    if commands.len() > 1 {
+        Expansion::Commands(commands)
+    } else {
+        Expansion::Line(expanded)
+    }
+}
+
+/// A name `alias` accepts: one word, with nothing a command line would
+/// read as quoting, a variable or an operator.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "'\"\\$|&;=()<>`".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders_and_appends_the_rest() {
+        assert_eq!(
+            expand("git checkout $1", &["main", "-q"]),
+            "git checkout main -q"
+        );
+        assert_eq!(expand("ls -la", &["'my dir'"]), "ls -la 'my dir'");
+        assert_eq!(expand("cp $2 $1", &["a", "b"]), "cp b a");
+        assert_eq!(expand("echo [$@] done", &["a", "b"]), "echo [a b] done");
+        assert_eq!(expand("echo '$1' \\$2 $3", &["a"]), "echo '$1' \\$2 ");
+        assert_eq!(expand("echo $10", &["x"]), "echo ");
+
+        let lookup = |name: &str| (name == "ll").then_some("ls -l $1");
+        assert_eq!(
+            expand_line("ll src | grep rs", lookup).as_deref(),
+            Some("ls -l src | grep rs")
+        );
+        assert_eq!(
+            expand_line("ls | ll", lookup).as_deref(),
+            Some("ls | ls -l ")
+        );
+        assert_eq!(expand_line("echo ll", lookup), None);
+        assert!(!is_valid_name("a=b") && is_valid_name("g.."));
+    }
+
+    #[test]
+    fn bodies_with_several_commands() {
+        let lookup = |name: &str| (name == "mkcd").then_some("mkdir $1 && cd $1");
+        let line = expand_line("mkcd 'my dir'", lookup).unwrap();
+        assert_eq!(line, "mkdir 'my dir' && cd 'my dir'");
+        assert_eq!(
+            commands(&line),
+            [
+                ("mkdir 'my dir'".to_string(), false),
+                ("cd 'my dir'".to_string(), true)
+            ]
+        );
+        assert_eq!(
+            commands("a; b && c &"),
+            [
+                ("a".to_string(), false),
+                ("b".to_string(), false),
+                ("c &".to_string(), true)
+            ]
+        );
+        assert_eq!(commands("echo 'a;b' $(x && y)").len(), 1);
+    }
+
+    #[test]
+    fn self_referencing_bodies_expand_once() {
+        let lookup = |name: &str| (name == "ls").then_some("ls -F; pwd");
+        // Run the way `loop_queue` does: queued commands aren't expanded.
+        let mut queue = vec![("ls".to_string(), true)];
+        let mut ran = Vec::new();
+        while let Some((line, expand)) = queue.pop() {
+            assert!(ran.len() < 10, "expanded forever: {ran:?}");
+            let expansion = if expand {
+                expand_statement(&line, lookup)
+            } else {
+                Expansion::Line(line)
+            };
+            match expansion {
+                Expansion::Line(line) => ran.push(line),
+                Expansion::Commands(commands) => {
+                    queue.extend(commands.into_iter().rev().map(|(c, _)| (c, false)));
+                }
+            }
+        }
+        assert_eq!(ran, ["ls -F", "pwd"]);
+        assert_eq!(
+            expand_statement("ls -a", |name| (name == "ls").then_some("ls -F")),
+            Expansion::Line("ls -F -a".to_string())
+        );
+    }
+}
@@ -0,0 +1,1371 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Key, Modifiers};
+
+use self::bench::BenchJob;
+use self::dirs::DirStack;
+use self::find_preview::FindPreview;
+use self::follow::Follow;
+use self::for_loop::Queued;
+use self::jobs::QueuedJob;
+use self::running::RunningCommand;
+use self::scroll::ScrollAnchor;
+use self::tasks::Task;
+use self::top::Top;
+use crate::cli::Args;
+use crate::command_form::CommandForm;
+use crate::completion_spec::Specs;
+use crate::config::{Config, CursorStyle};
+use crate::frecency::DirHistory;
+use crate::highlight::{self, Highlights};
+use crate::history::History;
+use crate::hooks::{Event, Hooks};
+use crate::line::{
+    CommandTiming, TerminalLine, ERROR_COLOR, INPUT_COLOR, MATCH_BACKGROUND, OUTPUT_COLOR,
+    TIMESTAMP_COLOR,
+};
+use crate::links::FileLink;
+use crate::macros::{Macros, Step};
+use crate::paths::Profile;
+use crate::session_log::SessionLog;
+use crate::suggest::PathCommands;
+use crate::theme::{SystemTheme, Theme};
+use crate::window::Geometry;
+use crate::{banner, fonts, input, links, script};
+
+mod bench;
+mod builder;
+mod compare;
+mod complete;
+mod copy;
+mod dirs;
+mod exec;
+mod find_preview;
+mod follow;
+mod for_loop;
+mod header;
+mod highlights;
+mod history;
+mod hooks;
+mod jobs;
+mod keybindings;
+mod man;
+mod paste_url;
+mod pins;
+mod pty;
+mod recording;
+mod running;
+mod scroll;
+mod session;
+mod tasks;
+mod top;
+mod vars;
+mod watch_fs;
+mod window;
+
+const PROMPT: &str = "> ";
+
+/// How often background work is checked for output.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// The same while idle, except for running commands, which keep
+/// `POLL_INTERVAL` so their output isn't held back.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Opacity of the black wash drawn over an idle window.
+const IDLE_DIM_ALPHA: u8 = 110;
+
+const COMMON_COMMANDS: &[&str] = &[
+    "cargo", "cd", "clear", "curl", "echo", "exit", "git", "help", "mkdir", "npm", "ssh", "tar",
+];
+
+/// Shared by Linux and macOS.
+const UNIX_COMMANDS: &[&str] = &[
+    "cat", "chmod", "cp", "find", "grep", "head", "kill", "less", "ls", "make", "mv", "ps", "pwd",
+    "python3", "rm", "rmdir", "tail", "top", "touch", "vim", "wget",
+];
+
+const LINUX_COMMANDS: &[&str] = &["apt", "ip", "journalctl", "sudo", "systemctl", "xdg-open"];
+
+const MACOS_COMMANDS: &[&str] = &[
+    "brew",
+    "caffeinate",
+    "defaults",
+    "diskutil",
+    "mdfind",
+    "open",
+    "pbcopy",
+    "pbpaste",
+    "say",
+    "sudo",
+];
+
+const WINDOWS_COMMANDS: &[&str] = &[
+    "cls",
+    "copy",
+    "del",
+    "dir",
+    "findstr",
+    "ipconfig",
+    "move",
+    "powershell",
+    "python",
+    "ren",
+    "robocopy",
+    "taskkill",
+    "tasklist",
+    "type",
+    "where",
+    "winget",
+    "xcopy",
+];
+
+/// `COMMON_COMMANDS` plus the set for the platform we're running on.
+fn platform_commands() -> Vec<String> {
+    let platform: &[&[&str]] = if cfg!(windows) {
+        &[WINDOWS_COMMANDS]
+    } else if cfg!(target_os = "macos") {
+        &[UNIX_COMMANDS, MACOS_COMMANDS]
+    } else if cfg!(target_os = "linux") {
+        &[UNIX_COMMANDS, LINUX_COMMANDS]
+    } else {
+        &[UNIX_COMMANDS]
+    };
+    let mut commands: Vec<String> = std::iter::once(COMMON_COMMANDS)
+        .chain(platform.iter().copied())
+        .flatten()
+        .map(|c| c.to_string())
+        .collect();
+    commands.sort();
+    commands.dedup();
+    commands
+}
+
+pub struct TerminalApp {
+    input_buffer: String,
+    /// Char index into `input_buffer`; see `input` for the conversion rules.
+    cursor_pos: usize,
+    lines: Vec<TerminalLine>,
+    current_dir: PathBuf,
+    command_history: History,
+    /// Entry shown by ArrowUp/ArrowDown, or `None` while editing a new line.
+    history_index: Option<usize>,
+    /// The last line run from the prompt, history expanded, for Ctrl+P.
+    last_command: Option<String>,
+    /// Keys and typing since Ctrl+Shift+M started recording.
+    recording: Option<Vec<Step>>,
+    /// Ctrl+Shift+E or `:errors-only`: output other than errors is hidden,
+    /// each run of it replaced by a count.
+    errors_only: bool,
+    /// A URL pasted at the prompt, held back until it's opened, downloaded
+    /// or inserted.
+    pasted_url: Option<String>,
+    /// The last recording, which the macro key replays.
+    last_macro: Vec<Step>,
+    /// Set while a macro is being replayed, so it can't start another.
+    replaying: bool,
+    /// Recordings kept with `:macro save`.
+    macros: Macros,
+    /// The line being typed before history navigation replaced it.
+    history_draft: String,
+    /// Directories visited with `cd`, ranked for `z`.
+    dir_history: DirHistory,
+    /// Directories left by each `cd`, newest last, for `cd -` and `cd --back`.
+    back_dirs: Vec<PathBuf>,
+    /// The `pushd`/`popd` stack, top last.
+    dir_stack: DirStack,
+    /// Command names offered for completion and typo suggestions, in
+    /// addition to whatever is on `$PATH`.
+    common_commands: Vec<String>,
+    /// What's on `$PATH`, for completing command names.
+    path_commands: PathCommands,
+    /// Subcommands and flags for Tab after a command's name.
+    completion_specs: Specs,
+    /// `:highlight` rules applied to output as it's drawn.
+    highlights: Highlights,
+    config: Config,
+    /// Whose config, history and directory database are loaded and saved.
+    profile: Profile,
+    /// Set by `:set`; the config file is only rewritten on exit if true.
+    config_dirty: bool,
+    applied_theme: Option<Theme>,
+    /// From `--theme`; wins over the config and the system preference.
+    theme_override: Option<Theme>,
+    /// The font loaded into egui, once the first frame has set one.
+    applied_font: Option<String>,
+    /// From `--font`; wins over the `font` setting.
+    font_override: Option<String>,
+    /// Whether the font has the Powerline arrows, or ASCII stands in.
+    powerline_glyphs: bool,
+    /// Cleared by `--no-git`, which wins over the `git-prompt` setting.
+    git_prompt_enabled: bool,
+    system_theme: SystemTheme,
+    exit_requested: bool,
+    /// Exiting asked whether to leave running jobs; the next key answers.
+    pending_exit: bool,
+    /// Last key, text, pointer or scroll event; see `idle`.
+    last_activity: Instant,
+    /// Where `:next-error` resumes its search; reset to each new command.
+    error_cursor: Option<usize>,
+    /// The command line Ctrl+Up/Down last jumped to; `None` is the bottom.
+    prompt_cursor: Option<usize>,
+    /// Commands registered with `trap`.
+    hooks: Hooks,
+    /// True while hooks or the rc file run, so they don't set off hooks.
+    in_hook: bool,
+    /// The hook `run_hooks` is executing, so commands it starts can drop
+    /// it when they fail.
+    running_hook: Option<(Event, String)>,
+    /// Input line of the command being executed, for output that outlives
+    /// `execute_command` to report its duration against.
+    command_line: Option<usize>,
+    /// Pattern `:search` repeats when run without one.
+    last_search: String,
+    /// Line to bring into view on the next frame, and where in the view
+    /// to put it.
+    scroll_target: Option<(usize, egui::Align)>,
+    /// Text for the clipboard, handed to egui on the next frame.
+    pending_copy: Option<String>,
+    /// Where the window is, saved on exit for the next launch.
+    window: Geometry,
+    /// Set once the restored position has been checked against the
+    /// monitors.
+    window_checked: bool,
+    /// Lines entered while a foreground command ran, under `busy-input
+    /// queue`, run in order once it's done.
+    queued_input: VecDeque<String>,
+    /// Background jobs waiting for `max-jobs` to allow them.
+    queued_jobs: VecDeque<QueuedJob>,
+    /// The rest of a `for` loop's or an alias's commands, run one at a
+    /// time.
+    loop_queue: VecDeque<Queued>,
+    /// Lines `:pin` keeps in view above the scrollback.
+    pinned: Vec<TerminalLine>,
+    /// Whether the `:keybindings` window is open.
+    show_keybindings: bool,
+    /// Stdout of the last streamed command as raw bytes, for `:hexdump`.
+    last_output: Vec<u8>,
+    /// Line drawn with a highlight background, e.g. the current error.
+    highlighted_line: Option<usize>,
+    /// Receives every line added to the scrollback when `--log-file` is set.
+    session_log: Option<SessionLog>,
+    /// A `bench` whose runs are still coming in from its worker thread.
+    bench: Option<BenchJob>,
+    /// External commands still streaming output into the scrollback.
+    running: Vec<RunningCommand>,
+    /// Slow internal tools working on threads of their own.
+    tasks: Vec<Task>,
+    /// Branch shown in the header, or `None` outside a git repository.
+    git_branch: Option<String>,
+    /// Directory and time `git_branch` was last read for.
+    branch_checked: Option<(PathBuf, Instant)>,
+    /// Whether the repository has uncommitted changes, for `{dirty}` in
+    /// the right prompt.
+    git_dirty: bool,
+    /// Directory and time `git_dirty` was last checked for.
+    dirty_checked: Option<(PathBuf, Instant)>,
+    /// The answer of a check still running, with its directory.
+    dirty_check: Option<Receiver<(PathBuf, bool)>>,
+    /// Exit status of the last foreground command, for `{status}`.
+    last_status: Option<i32>,
+    /// Local branches listed when the header's branch menu was opened.
+    branches: Vec<String>,
+    /// A `tail -f` in progress.
+    follow: Option<Follow>,
+    /// The built-in `top`, while it's open.
+    top: Option<Top>,
+    /// The `:build` form, while it's open.
+    builder: Option<CommandForm>,
+    /// The `:compare` columns, while they're open.
+    compare: Option<compare::Compare>,
+    /// The page `man` opened, while it's shown.
+    man: Option<man::ManView>,
+    /// Variables set with a bare `NAME=VALUE`, which commands don't
+    /// inherit until they're exported.
+    variables: HashMap<String, String>,
+    /// Defined with `alias NAME=LINE`.
+    aliases: BTreeMap<String, String>,
+    /// `NAME=VALUE` prefixes of the command being started, added to the
+    /// environment of each program it starts.
+    command_env: Vec<(String, String)>,
+    /// Match count for a `find` being typed, shown in the header.
+    find_preview: Option<FindPreview>,
+    /// Top visible line from the last frame, restored after a resize.
+    scroll_anchor: Option<ScrollAnchor>,
+    view_width: f32,
+    /// The view in character cells, given to programs as `COLUMNS` and
+    /// `LINES` when they start.
+    terminal_size: (usize, usize),
+    /// Height of a scrollback row with its spacing, once a frame has
+    /// measured it.
+    row_height: f32,
+    /// False once the user scrolls up; a reflow then keeps their place
+    /// instead of following new output.
+    at_bottom: bool,
+    autocomplete_suggestions: Vec<String>,
+    /// What the popup shows for each suggestion when it differs from the
+    /// text inserted, e.g. `1234  firefox` for a pid; empty otherwise.
+    autocomplete_labels: Vec<String>,
+    autocomplete_index: usize,
+    show_autocomplete: bool,
+    /// True once Tab has written a popup entry into the buffer, so the next
+    /// Tab advances to the following one.
+    autocomplete_cycling: bool,
+    /// True once the arrow keys have moved the popup's highlight, so it's
+    /// scrolled into view and the mouse only takes over when it moves.
+    autocomplete_keyed: bool,
+    /// Char index where the word being completed starts.
+    autocomplete_start: usize,
+    /// Screen rect of the input cursor from the last frame, for anchoring
+    /// the completion popup.
+    cursor_rect: Option<egui::Rect>,
+}
+
+impl TerminalApp {
+    pub fn new(cc: &eframe::CreationContext<'_>, args: Args) -> Self {
+        let mut app = Self {
+            input_buffer: String::new(),
+            cursor_pos: 0,
+            lines: Vec::new(),
+            current_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
+            command_history: History::load(&args.profile),
+            history_index: None,
+            last_command: None,
+            recording: None,
+            pasted_url: None,
+            errors_only: false,
+            last_macro: Vec::new(),
+            replaying: false,
+            macros: Macros::load(&args.profile),
+            history_draft: String::new(),
+            dir_history: DirHistory::load(&args.profile),
+            back_dirs: Vec::new(),
+            dir_stack: DirStack::default(),
+            common_commands: platform_commands(),
+            path_commands: PathCommands::new(),
+            completion_specs: Specs::load(&args.profile),
+            highlights: Highlights::load(&args.profile),
+            config: Config::load(&args.profile),
+            config_dirty: false,
+            profile: args.profile.clone(),
+            applied_theme: None,
+            theme_override: args.theme,
+            applied_font: None,
+            font_override: args.font.clone(),
+            powerline_glyphs: false,
+            git_prompt_enabled: !args.no_git,
+            system_theme: SystemTheme::watch(cc.egui_ctx.clone()),
+            exit_requested: false,
+            pending_exit: false,
+            last_activity: Instant::now(),
+            error_cursor: None,
+            prompt_cursor: None,
+            hooks: Hooks::default(),
+            in_hook: false,
+            running_hook: None,
+            command_line: None,
+            last_search: String::new(),
+            scroll_target: None,
+            pending_copy: None,
+            last_output: Vec::new(),
+            show_keybindings: false,
+            pinned: Vec::new(),
+            queued_input: VecDeque::new(),
+            queued_jobs: VecDeque::new(),
+            loop_queue: VecDeque::new(),
+            window: Geometry::load(&args.profile),
+            window_checked: false,
+            highlighted_line: None,
+            session_log: None,
+            bench: None,
+            running: Vec::new(),
+            tasks: Vec::new(),
+            git_branch: None,
+            branch_checked: None,
+            git_dirty: false,
+            dirty_checked: None,
+            dirty_check: None,
+            last_status: None,
+            branches: Vec::new(),
+            follow: None,
+            top: None,
+            builder: None,
+            compare: None,
+            man: None,
+            variables: HashMap::new(),
+            aliases: BTreeMap::new(),
+            command_env: Vec::new(),
+            find_preview: None,
+            scroll_anchor: None,
+            view_width: 0.0,
+            terminal_size: (0, 0),
+            row_height: 0.0,
+            at_bottom: true,
+            autocomplete_suggestions: Vec::new(),
+            autocomplete_labels: Vec::new(),
+            autocomplete_index: 0,
+            show_autocomplete: false,
+            autocomplete_cycling: false,
+            autocomplete_keyed: false,
+            autocomplete_start: 0,
+            cursor_rect: None,
+        };
+        if let Some(path) = &args.log_file {
+            match SessionLog::open(path, &app.config.log_timestamp_format) {
+                Ok(log) => app.session_log = Some(log),
+                Err(e) => app.add_line(
+                    &format!("Failed to open log file {}: {e}", path.display()),
+                    ERROR_COLOR,
+                ),
+            }
+        }
+        if app.config.banner {
+            app.show_banner();
+        }
+        app.apply_font(&cc.egui_ctx);
+        app.run_rc();
+        if let Some(command) = args.command.filter(|c| !c.trim().is_empty()) {
+            app.run_line(command);
+        }
+        app
+    }
+
+    /// The theme in effect: `--theme`, then the config, then the system
+    /// preference, falling back to dark when detection fails.
+    fn theme(&self) -> Theme {
+        self.theme_override
+            .or(self.config.theme)
+            .or_else(|| self.system_theme.get())
+            .unwrap_or(Theme::Dark)
+    }
+
+    /// Loads `--font`, or else the `font` setting, into egui when it
+    /// differs from the one loaded. A font that won't load leaves egui's
+    /// own in place, with a warning on stderr and in the scrollback.
+    fn apply_font(&mut self, ctx: &egui::Context) {
+        let name = self
+            .font_override
+            .clone()
+            .unwrap_or_else(|| self.config.font.clone());
+        if self.applied_font.as_ref() == Some(&name) {
+            return;
+        }
+        let definitions = fonts::definitions(&name).unwrap_or_else(|message| {
+            eprintln!("ai_terminal: font: {message}; using the default");
+            self.add_line(&format!("font: {message}; using the default"), ERROR_COLOR);
+            egui::FontDefinitions::default()
+        });
+        ctx.set_fonts(definitions);
+        self.applied_font = Some(name);
+    }
+
+    fn show_banner(&mut self) {
+        let template = banner::load(&self.profile);
+        let rendered = banner::render(
+            &template,
+            |name| std::env::var(name).ok(),
+            |command| self.capture_output(command),
+        );
+        match rendered {
+            Ok(lines) => {
+                for line in lines {
+                    self.add_line(&line, OUTPUT_COLOR);
+                }
+            }
+            Err(message) => self.add_line(&format!("banner: {message}"), ERROR_COLOR),
+        }
+    }
+
+    fn add_line(&mut self, text: &str, color: Color32) {
+        self.push_line(TerminalLine::plain(text, color));
+    }
+
+    /// Appends a line to the scrollback. Every finished line goes through
+    /// here or `replace_line` so the session log sees exactly what the user
+    /// sees.
+    fn push_line(&mut self, line: TerminalLine) {
+        let line = self.finish_line(line);
+        self.lines.push(line);
+    }
+
+    /// Replaces a line that was drawn while still arriving with its final
+    /// text.
+    fn replace_line(&mut self, index: usize, mut line: TerminalLine) {
+        line.added = self.lines[index].added;
+        self.lines[index] = self.finish_line(line);
+    }
+
+    fn finish_line(&mut self, mut line: TerminalLine) -> TerminalLine {
+        if !line.is_input {
+            line.added.get_or_insert_with(chrono::Local::now);
+            line.expand_tabs(self.config.tab_width);
+            let text = line.text();
+            if line.links.is_empty() {
+                line.links = links::find(&text, &self.current_dir);
+            }
+            line.highlights = self.highlights.find(&text);
+        }
+        if let Some(log) = &mut self.session_log {
+            log.write_line(&line.text());
+        }
+        line
+    }
+
+    fn push_lines(&mut self, lines: impl IntoIterator<Item = TerminalLine>) {
+        for line in lines {
+            self.push_line(line);
+        }
+    }
+
+    fn submit_input(&mut self) {
+        if self.submit_while_busy() {
+            return;
+        }
+        let command = std::mem::take(&mut self.input_buffer);
+        self.cursor_pos = 0;
+        self.run_line(command);
+    }
+
+    /// Ctrl+P: runs the last command again, echoed as if it were retyped,
+    /// leaving whatever is being typed alone.
+    fn redo_last_command(&mut self) {
+        if self.busy() {
+            return;
+        }
+        if let Some(command) = self.last_command.clone() {
+            self.run_line(command);
+        }
+    }
+
+    /// Echoes `command` after the prompt and runs it, exactly as if it had
+    /// been typed.
+    fn run_line(&mut self, typed: String) {
+        self.history_index = None;
+        let expansion = self.command_history.expand(&typed);
+        let command = match &expansion {
+            Ok(Some(expanded)) => expanded.clone(),
+            _ => typed.clone(),
+        };
+        self.command_history.push(&command);
+        self.last_command = Some(command.clone());
+        self.last_status = None;
+        if !command.starts_with(':') {
+            self.error_cursor = Some(self.lines.len());
+            self.prompt_cursor = None;
+            self.highlighted_line = None;
+        }
+        let origin = self.lines.len();
+        self.push_line(TerminalLine {
+            timing: Some(CommandTiming {
+                started: chrono::Local::now(),
+                duration: None,
+            }),
+            ..TerminalLine::input(format!("{PROMPT}{typed}"))
+        });
+        match expansion {
+            Ok(Some(_)) => self.add_line(&command, OUTPUT_COLOR),
+            Ok(None) => {}
+            Err(message) => {
+                self.add_line(&message, ERROR_COLOR);
+                self.finish_timing(origin, Duration::ZERO);
+                self.trim_scrollback();
+                return;
+            }
+        }
+        self.run_hooks(Event::Preexec);
+        let snapshot = self.fs_snapshot(&command);
+        let started = Instant::now();
+        self.command_line = Some(origin);
+        self.execute_command(&command);
+        if let Some(snapshot) = snapshot {
+            match self.running.iter_mut().find(|c| c.origin == Some(origin)) {
+                Some(running) => running.snapshot = Some(snapshot),
+                None => self.report_fs_changes(&snapshot),
+            }
+        }
+        // `clear` or a smaller scrollback may have moved or removed the line.
+        if let Some(origin) = self.command_line.take() {
+            if !self.running.iter().any(|c| c.origin == Some(origin)) {
+                self.finish_timing(origin, started.elapsed());
+                self.hint_sudo(origin);
+                self.hint_git_typo(origin);
+            }
+        }
+        if !self.busy() {
+            self.run_hooks(Event::Precmd);
+        }
+        self.trim_scrollback();
+    }
+
+    fn finish_timing(&mut self, origin: usize, duration: Duration) {
+        if let Some(timing) = self.lines.get_mut(origin).and_then(|l| l.timing.as_mut()) {
+            timing.duration = Some(duration);
+        }
+    }
+
+    fn trim_scrollback(&mut self) {
+        if self.lines.len() > self.config.scrollback {
+            let excess = self.lines.len() - self.config.scrollback;
+            self.lines.drain(..excess);
+            let shift = |index: Option<usize>| index.and_then(|i| i.checked_sub(excess));
+            self.error_cursor = shift(self.error_cursor);
+            self.prompt_cursor = shift(self.prompt_cursor);
+            self.command_line = shift(self.command_line);
+            self.highlighted_line = shift(self.highlighted_line);
+            self.scroll_target = self
+                .scroll_target
+                .and_then(|(i, align)| Some((i.checked_sub(excess)?, align)));
+            if let Some(job) = &mut self.bench {
+                job.progress_line = shift(job.progress_line);
+            }
+            for command in &mut self.running {
+                command.shift_lines(excess);
+            }
+            for task in &mut self.tasks {
+                task.origin = shift(task.origin);
+            }
+            self.scroll_anchor = self.scroll_anchor.and_then(|anchor| anchor.shifted(excess));
+        }
+    }
+
+    fn clear_scrollback(&mut self) {
+        self.lines.clear();
+        self.error_cursor = None;
+        self.prompt_cursor = None;
+        self.command_line = None;
+        self.highlighted_line = None;
+        self.scroll_target = None;
+        self.scroll_anchor = None;
+        if let Some(job) = &mut self.bench {
+            job.progress_line = None;
+        }
+        for command in &mut self.running {
+            command.forget_lines();
+        }
+        for task in &mut self.tasks {
+            task.origin = None;
+        }
+    }
+
+    /// Typing at the prompt, one key's worth or an IME commit.
+    fn type_text(&mut self, text: &str) {
+        self.close_autocomplete();
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some('?'), None) if self.input_buffer.is_empty() => {
+                self.show_keybindings = true;
+            }
+            (Some(c), None) if self.config.auto_pair => {
+                input::insert_paired(&mut self.input_buffer, &mut self.cursor_pos, c);
+            }
+            _ => input::insert_str(&mut self.input_buffer, &mut self.cursor_pos, text),
+        }
+    }
+
+    fn handle_key(&mut self, key: Key, modifiers: Modifiers) {
+        // With the popup open, the arrows pick a suggestion and Enter
+        // takes it; history and running the line wait until it closes.
+        if self.show_autocomplete && !self.autocomplete_suggestions.is_empty() {
+            match key {
+                Key::ArrowUp => return self.select_completion(false),
+                Key::ArrowDown => return self.select_completion(true),
+                Key::Enter => return self.accept_completion(),
+                _ => {}
+            }
+        }
+        if key != Key::Tab {
+            self.close_autocomplete();
+        }
+        let len = input::char_count(&self.input_buffer);
+        match key {
+            Key::Tab => self.autocomplete(),
+            Key::Escape => self.show_keybindings = false,
+            Key::Enter if script::incomplete(&self.input_buffer) => {
+                input::insert_str(&mut self.input_buffer, &mut self.cursor_pos, "\n");
+            }
+            Key::Enter => self.submit_input(),
+            Key::Backspace if modifiers.ctrl => {
+                let start = input::word_start_before(&self.input_buffer, self.cursor_pos);
+                let end = self.cursor_pos;
+                input::delete_range(&mut self.input_buffer, &mut self.cursor_pos, start, end);
+            }
+            Key::Backspace
+                if self.config.auto_pair
+                    && input::delete_pair(&mut self.input_buffer, &mut self.cursor_pos) => {}
+            Key::Backspace => {
+                input::delete_before(&mut self.input_buffer, &mut self.cursor_pos);
+            }
+            Key::Delete => {
+                input::delete_at(&mut self.input_buffer, self.cursor_pos);
+            }
+            Key::ArrowUp if modifiers.ctrl => self.jump_to_prompt(false),
+            Key::ArrowDown if modifiers.ctrl => self.jump_to_prompt(true),
+            Key::ArrowUp => self.history_previous(),
+            Key::ArrowDown => self.history_next(),
+            Key::ArrowLeft if modifiers.ctrl => {
+                self.cursor_pos = input::word_start_before(&self.input_buffer, self.cursor_pos);
+            }
+            Key::ArrowRight if modifiers.ctrl => {
+                self.cursor_pos = input::word_end_after(&self.input_buffer, self.cursor_pos);
+            }
+            Key::ArrowLeft => {
+                self.cursor_pos = input::prev_cell(&self.input_buffer, self.cursor_pos);
+            }
+            Key::ArrowRight if self.cursor_pos == len => {
+                if let Some(ghost) = self.ghost_suggestion() {
+                    input::insert_str(&mut self.input_buffer, &mut self.cursor_pos, &ghost);
+                }
+            }
+            Key::ArrowRight => {
+                self.cursor_pos = input::next_cell(&self.input_buffer, self.cursor_pos);
+            }
+            Key::Home => self.cursor_pos = 0,
+            Key::End => self.cursor_pos = len,
+            Key::A if modifiers.ctrl => self.cursor_pos = 0,
+            Key::E if modifiers.ctrl => self.cursor_pos = len,
+            Key::U if modifiers.ctrl => {
+                let end = self.cursor_pos;
+                input::delete_range(&mut self.input_buffer, &mut self.cursor_pos, 0, end);
+            }
+            Key::K if modifiers.ctrl => {
+                let start = self.cursor_pos;
+                input::delete_range(&mut self.input_buffer, &mut self.cursor_pos, start, len);
+            }
+            Key::L if modifiers.ctrl => self.clear_scrollback(),
+            Key::P if modifiers.ctrl => self.redo_last_command(),
+            Key::D if modifiers.ctrl && self.close_running_input() => {}
+            Key::D if modifiers.ctrl && self.input_buffer.is_empty() => {
+                self.exit_requested = self.confirm_exit();
+            }
+            Key::F8 => self.jump_to_error(!modifiers.shift),
+            _ => {}
+        }
+    }
+
+    /// Time left before the window counts as idle (zero once it is), or
+    /// `None` when `idle-timeout` is off.
+    fn idle_remaining(&self) -> Option<Duration> {
+        let timeout = Duration::from_secs(self.config.idle_timeout);
+        (!timeout.is_zero()).then(|| timeout.saturating_sub(self.last_activity.elapsed()))
+    }
+
+    fn process_input(&mut self, ctx: &egui::Context) {
+        let events = ctx.input(|i| i.events.clone());
+        let active = events.iter().any(|event| {
+            matches!(
+                event,
+                egui::Event::Text(_)
+                    | egui::Event::Key { .. }
+                    | egui::Event::Copy
+                    | egui::Event::Paste(_)
+                    | egui::Event::PointerMoved(_)
+                    | egui::Event::PointerButton { .. }
+                    | egui::Event::MouseWheel { .. }
+                    | egui::Event::Scroll(_)
+            )
+        });
+        if active {
+            self.last_activity = Instant::now();
+        }
+        for event in events {
+            if self.pending_exit {
+                match event {
+                    egui::Event::Text(text) => self.answer_exit(text.eq_ignore_ascii_case("y")),
+                    egui::Event::Key {
+                        key: Key::Enter | Key::Escape,
+                        pressed: true,
+                        ..
+                    } => self.answer_exit(false),
+                    _ => {}
+                }
+                continue;
+            }
+            if self.forward_to_pty(&event) {
+                continue;
+            }
+            if self.pasted_url.is_some() {
+                match event {
+                    egui::Event::Key {
+                        key: Key::Enter,
+                        pressed: true,
+                        ..
+                    } => {
+                        self.insert_pasted_url();
+                        continue;
+                    }
+                    egui::Event::Key {
+                        key: Key::Escape,
+                        pressed: true,
+                        ..
+                    } => {
+                        self.pasted_url = None;
+                        continue;
+                    }
+                    // Typing on takes the URL as text, then carries on.
+                    egui::Event::Text(_)
+                    | egui::Event::Paste(_)
+                    | egui::Event::Key { pressed: true, .. } => self.insert_pasted_url(),
+                    _ => {}
+                }
+            }
+            if self.compare.is_some() {
+                if matches!(
+                    event,
+                    egui::Event::Text(_)
+                        | egui::Event::Copy
+                        | egui::Event::Key { pressed: true, .. }
+                ) {
+                    self.compare = None;
+                }
+                continue;
+            }
+            if self.man.is_some() {
+                // The view's find box takes the typing.
+                if let egui::Event::Key {
+                    key: Key::Escape,
+                    pressed: true,
+                    ..
+                } = event
+                {
+                    self.man = None;
+                }
+                continue;
+            }
+            if self.builder.is_some() {
+                // The form's own text fields take the typing.
+                if let egui::Event::Key {
+                    key: Key::Escape,
+                    pressed: true,
+                    ..
+                } = event
+                {
+                    self.builder = None;
+                }
+                continue;
+            }
+            if self.top.is_some() {
+                match event {
+                    egui::Event::Text(text) if text == "q" => self.top = None,
+                    egui::Event::Copy
+                    | egui::Event::Key {
+                        key: Key::Escape,
+                        pressed: true,
+                        ..
+                    } => self.top = None,
+                    _ => {}
+                }
+                continue;
+            }
+            match event {
+                egui::Event::Key {
+                    key: Key::M,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } if modifiers.ctrl && modifiers.shift => self.toggle_recording(),
+                egui::Event::Key {
+                    key: Key::E,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } if modifiers.ctrl && modifiers.shift => self.errors_only = !self.errors_only,
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } if self.recording.is_none() && self.is_macro_key(key, modifiers) => {
+                    let steps = self.last_macro.clone();
+                    self.replay(&steps);
+                }
+                egui::Event::Text(text) => {
+                    self.record(Step::Text(text.clone()));
+                    self.type_text(&text);
+                }
+                egui::Event::Copy if ctx.input(|i| i.modifiers.shift) => self.copy_scrollback(),
+                egui::Event::Copy => self.interrupt(),
+                egui::Event::Paste(text) if links::as_url(&text).is_some() => {
+                    self.pasted_url = links::as_url(&text).map(str::to_string);
+                }
+                egui::Event::Paste(text) => {
+                    // A pasted loop keeps its lines; anything else is one line.
+                    let text = if script::is_for(&text) {
+                        text.replace('\r', "")
+                    } else {
+                        text.replace(['\r', '\n'], " ")
+                    };
+                    self.record(Step::Text(text.clone()));
+                    input::insert_str(&mut self.input_buffer, &mut self.cursor_pos, &text);
+                }
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => {
+                    self.record(Step::Key(key, modifiers));
+                    self.handle_key(key, modifiers);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Lays out the prompt and input buffer. A block cursor is drawn as an
+    /// inverted char over `cursor_pos` (or a trailing space at the end of
+    /// the line) and an underline cursor as an underlined one; a bar cursor
+    /// is painted separately by `render_input`.
+    fn input_layout(&self, font: &FontId) -> LayoutJob {
+        let theme = self.theme();
+        let foreground = theme.foreground();
+        let plain = TextFormat::simple(font.clone(), foreground);
+        let cursor = match self.config.cursor_style {
+            CursorStyle::Block => TextFormat {
+                background: foreground,
+                ..TextFormat::simple(font.clone(), theme.background())
+            },
+            CursorStyle::Underline => TextFormat {
+                underline: egui::Stroke::new(2.0, foreground),
+                ..plain.clone()
+            },
+            CursorStyle::Bar => plain.clone(),
+        };
+
+        let (before, after) = input::split_at_cursor(&self.input_buffer, self.cursor_pos);
+        let ghost = self.ghost_suggestion();
+        // At the end of the line the cursor sits on the first ghost char.
+        let rest = if after.is_empty() {
+            ghost.as_deref().unwrap_or("")
+        } else {
+            after
+        };
+        // The block covers a whole cell: both columns of a wide char, plus
+        // any combining marks drawn on it.
+        let (under_cursor, rest) =
+            rest.split_at(input::byte_offset(rest, input::next_cell(rest, 0)));
+        let under_cursor = if under_cursor.is_empty() {
+            " "
+        } else {
+            under_cursor
+        };
+
+        let mut job = LayoutJob::default();
+        job.append(
+            PROMPT,
+            0.0,
+            TextFormat::simple(font.clone(), theme.adapt(INPUT_COLOR)),
+        );
+        job.append(before, 0.0, plain.clone());
+        job.append(under_cursor, 0.0, cursor);
+        let dim = TextFormat::simple(font.clone(), foreground.gamma_multiply(0.4));
+        if after.is_empty() {
+            job.append(rest, 0.0, dim.clone());
+        } else {
+            job.append(rest, 0.0, plain);
+        }
+        if let Some(hint) = self.busy_hint() {
+            job.append(&hint, 0.0, dim);
+        }
+        job
+    }
+
+    /// Fish-style autosuggestion: the rest of the most recent history entry
+    /// that starts with the typed line, offered only at the end of the line.
+    fn ghost_suggestion(&self) -> Option<String> {
+        if self.input_buffer.is_empty()
+            || self.history_index.is_some()
+            || self.cursor_pos < input::char_count(&self.input_buffer)
+        {
+            return None;
+        }
+        self.command_history
+            .entries()
+            .iter()
+            .rev()
+            .find_map(|entry| entry.command.strip_prefix(self.input_buffer.as_str()))
+            .filter(|rest| !rest.is_empty())
+            .map(String::from)
+    }
+
+    /// Draws the input line, wrapping it to the panel width like output
+    /// lines unless wrapping is off, and records the cursor's screen rect.
+    fn render_input(&mut self, ui: &mut egui::Ui, font: &FontId) {
+        let rprompt = self.rprompt_text();
+        let mut job = self.input_layout(font);
+        if self.config.wrap {
+            job.wrap.max_width = ui.available_width();
+        }
+        let galley = ui.fonts(|f| f.layout_job(job));
+        let response = ui.label(galley.clone());
+        let ccursor = egui::text::CCursor::new(PROMPT.chars().count() + self.cursor_pos);
+        let rect = galley
+            .pos_from_ccursor(ccursor)
+            .translate(response.rect.min.to_vec2());
+        self.cursor_rect = Some(rect);
+        if self.config.cursor_style == CursorStyle::Bar {
+            let stroke = egui::Stroke::new(2.0, self.theme().foreground());
+            ui.painter().vline(rect.min.x, rect.y_range(), stroke);
+        }
+        if !rprompt.is_empty() && galley.rows.len() == 1 {
+            let color = self.theme().adapt(TIMESTAMP_COLOR);
+            let right = ui.painter().layout_no_wrap(rprompt, font.clone(), color);
+            let left = ui.max_rect().right() - right.size().x;
+            // Out of the way once the typing would run into it.
+            if left - response.rect.right() >= 2.0 * font.size {
+                let pos = egui::pos2(left, response.rect.top());
+                ui.painter().galley(pos, right, color);
+            }
+        }
+    }
+
+    /// `:set` lists every option, `:set key` shows one and `:set key value`
+    /// changes it for this session and the next.
+    fn set_option(&mut self, args: &[String]) {
+        let Some(key) = args.first() else {
+            for (key, value) in self.config.entries() {
+                self.add_line(&format!("{key} = {value}"), OUTPUT_COLOR);
+            }
+            return;
+        };
+        let (key, value) = match key.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None if args.len() > 1 => (key.clone(), args[1..].join(" ")),
+            None => {
+                match self.config.entries().into_iter().find(|(k, _)| k == key) {
+                    Some((k, v)) => self.add_line(&format!("{k} = {v}"), OUTPUT_COLOR),
+                    None => self.add_line(&format!(":set: unknown option '{key}'"), ERROR_COLOR),
+                }
+                return;
+            }
+        };
+        match self.config.set(&key, &value) {
+            Ok(()) => {
+                self.config_dirty = true;
+                if key == "theme" {
+                    // An explicit choice now beats the launch flag.
+                    self.theme_override = None;
+                }
+                if key == "font" {
+                    self.font_override = None;
+                }
+                if key == "git-prompt" {
+                    self.git_prompt_enabled = true;
+                }
+                self.trim_scrollback();
+                if let Some(log) = &mut self.session_log {
+                    log.set_timestamp_format(&self.config.log_timestamp_format);
+                }
+            }
+            Err(message) => self.add_line(&format!(":set: {message}"), ERROR_COLOR),
+        }
+    }
+}
+
+/// Stands in for a run of output hidden by errors-only.
+fn hidden_lines_label(ui: &mut egui::Ui, font: &FontId, theme: Theme, count: usize) {
+    let text = match count {
+        1 => "… 1 line hidden (errors only)".to_string(),
+        n => format!("… {n} lines hidden (errors only)"),
+    };
+    let color = theme.adapt(TIMESTAMP_COLOR).gamma_multiply(0.7);
+    ui.label(egui::RichText::new(text).font(font.clone()).color(color));
+}
+
+/// Draws a line that contains file links, showing a hand cursor over them.
+/// `shift` is where the line's own text starts in `job`. Returns the link
+/// that was clicked this frame, if any.
+fn link_label(
+    ui: &mut egui::Ui,
+    mut job: LayoutJob,
+    line: &TerminalLine,
+    shift: usize,
+    wrap: bool,
+) -> (egui::Response, Option<FileLink>) {
+    if wrap {
+        job.wrap.max_width = ui.available_width();
+    }
+    let text = job.text.clone();
+    let galley = ui.fonts(|fonts| fonts.layout_job(job));
+    let response = ui.add(egui::Label::new(galley.clone()).sense(egui::Sense::click()));
+    let hovered = response.hover_pos().and_then(|pos| {
+        let cursor = galley.cursor_from_pos(pos - response.rect.min);
+        let byte = input::byte_offset(&text, cursor.ccursor.index);
+        line.link_at(byte.checked_sub(shift)?)
+    });
+    let Some(link) = hovered else {
+        return (response, None);
+    };
+    let clicked = response.clicked().then(|| link.clone());
+    let response = response.on_hover_cursor(egui::CursorIcon::PointingHand);
+    (response, clicked)
+}
+
+impl eframe::App for TerminalApp {
+    fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        self.scale_wheel_scroll(raw_input);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.process_input(ctx);
+        self.track_window(ctx);
+        if let Some(text) = self.pending_copy.take() {
+            ctx.output_mut(|output| output.copied_text = text);
+        }
+        if let Some(due) = self.session_log.as_mut().and_then(SessionLog::flush_if_due) {
+            // The last lines reach the disk even if nothing else repaints.
+            ctx.request_repaint_after(due);
+        }
+        let benching = self.poll_bench();
+        let following = self.poll_follow();
+        let previewing = self.poll_find_preview();
+        let monitoring = self.poll_top();
+        let working = self.poll_tasks();
+        let checking = self.poll_git_dirty();
+        let comparing = self.poll_compare();
+        let formatting = self.poll_man();
+        let idle_remaining = self.idle_remaining();
+        let idle = idle_remaining.is_some_and(|remaining| remaining.is_zero());
+        if self.poll_running() || working || comparing || formatting {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        } else if monitoring {
+            ctx.request_repaint_after(top::REFRESH_INTERVAL);
+        } else if benching || following || previewing || checking {
+            let interval = if idle {
+                IDLE_POLL_INTERVAL
+            } else {
+                POLL_INTERVAL
+            };
+            ctx.request_repaint_after(interval);
+        } else if let Some(remaining) = idle_remaining.filter(|r| !r.is_zero()) {
+            // Wake once more when the timeout passes, to draw the dimming.
+            ctx.request_repaint_after(remaining);
+        }
+        if self.config.rprompt.contains("{time}") {
+            // Keep the right prompt's clock ticking while nothing else moves.
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        if self.exit_requested {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        } else if ctx.input(|i| i.viewport().close_requested())
+            && (self.pending_exit || !self.confirm_exit())
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        }
+
+        let theme = self.theme();
+        if self.applied_theme != Some(theme) {
+            ctx.set_visuals(theme.visuals());
+            self.applied_theme = Some(theme);
+        }
+        self.apply_font(ctx);
+
+        let font = FontId::monospace(self.config.font_size);
+        let arrows = format!("{}{}", fonts::POWERLINE_RIGHT, fonts::POWERLINE_LEFT);
+        self.powerline_glyphs = ctx.fonts(|f| f.has_glyphs(&font, &arrows));
+        let mut clicked_link = None;
+        let mut toggled_fold = None;
+        let mut clicked_action = None;
+        let mut copied_error = None;
+        let mut searched_error = None;
+        if self.config.prompt_header {
+            self.render_header(ctx, &font, theme);
+        }
+        self.render_pins(ctx, &font, theme);
+        if self.show_keybindings {
+            self.render_keybindings(ctx, &font, theme);
+        }
+        self.render_top(ctx, &font, theme);
+        self.render_builder(ctx, &font, theme);
+        if self.render_compare(ctx, &font, theme) || self.render_man(ctx, &font, theme) {
+            return;
+        }
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame::none()
+                    .fill(theme.background())
+                    .inner_margin(self.config.inner_margin),
+            )
+            .show(ctx, |ui| {
+                let scroll = if self.config.wrap {
+                    egui::ScrollArea::vertical()
+                } else {
+                    egui::ScrollArea::both()
+                };
+                // When a width change reflows wrapped lines, bring the line
+                // that was at the top of the view back to the same place.
+                let width = ui.available_width();
+                let resized = (width - self.view_width).abs() > 0.5;
+                self.view_width = width;
+                self.track_terminal_size(ui, &font);
+                let restore = self
+                    .scroll_anchor
+                    .filter(|_| resized && self.config.wrap && !self.at_bottom);
+                let output = scroll
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        ui.spacing_mut().item_spacing.y = self.config.line_spacing;
+                        let scroll_target = self.scroll_target.take();
+                        let clip = ui.clip_rect();
+                        let mut anchor = None;
+                        let mut folded = false;
+                        let mut hidden = 0;
+                        for (index, line) in self.lines.iter().enumerate() {
+                            let header = self.config.command_headers && line.timing.is_some();
+                            if line.is_input {
+                                folded = header && line.collapsed;
+                            } else if folded {
+                                continue;
+                            }
+                            if self.hidden_by_errors_only(line) {
+                                hidden += 1;
+                                continue;
+                            }
+                            if hidden > 0 {
+                                hidden_lines_label(ui, &font, theme, hidden);
+                                hidden = 0;
+                            }
+                            let mut job = if header {
+                                line.header_layout(&font, theme, self.config.color)
+                            } else {
+                                line.layout(&font, theme, self.config.color)
+                            };
+                            let shift = if self.config.timestamps && !line.is_input {
+                                line.prepend_timestamp(&mut job, &font, theme, self.config.color)
+                            } else {
+                                0
+                            };
+                            for link in &line.links {
+                                let range = link.range.start + shift..link.range.end + shift;
+                                links::underline(&mut job, range);
+                            }
+                            if self.config.color && !line.is_input {
+                                highlight::apply(&mut job, &line.highlights, shift, theme);
+                            }
+                            if self.highlighted_line == Some(index) {
+                                let background = theme.adapt_background(MATCH_BACKGROUND);
+                                for section in &mut job.sections {
+                                    section.format.background = background;
+                                }
+                            }
+                            let response = if header {
+                                let label = egui::Label::new(job)
+                                    .wrap(self.config.wrap)
+                                    .sense(egui::Sense::click());
+                                let response = ui
+                                    .add(label)
+                                    .on_hover_cursor(egui::CursorIcon::PointingHand);
+                                if response.clicked() {
+                                    toggled_fold = Some(index);
+                                }
+                                response
+                            } else if let Some(action) = &line.action {
+                                let label = egui::Label::new(job)
+                                    .wrap(self.config.wrap)
+                                    .sense(egui::Sense::click());
+                                let response = ui
+                                    .add(label)
+                                    .on_hover_cursor(egui::CursorIcon::PointingHand);
+                                if response.clicked() {
+                                    clicked_action = Some(action.clone());
+                                }
+                                response
+                            } else if line.links.is_empty() {
+                                ui.add(egui::Label::new(job).wrap(self.config.wrap))
+                            } else {
+                                let (response, link) =
+                                    link_label(ui, job, line, shift, self.config.wrap);
+                                if link.is_some() {
+                                    clicked_link = link;
+                                }
+                                response
+                            };
+                            if line.is_error() {
+                                response.context_menu(|ui| {
+                                    if ui.button("Copy error").clicked() {
+                                        copied_error = Some(line.copy_text());
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Search this error online").clicked() {
+                                        searched_error = Some(line.text());
+                                        ui.close_menu();
+                                    }
+                                });
+                            }
+                            if let Some((_, align)) = scroll_target.filter(|(i, _)| *i == index) {
+                                response.scroll_to_me(Some(align));
+                            }
+                            if anchor.is_none() && response.rect.bottom() > clip.top() {
+                                anchor = Some(ScrollAnchor::new(index, &response.rect, &clip));
+                            }
+                            if let Some(restore) = restore.filter(|r| r.line == index) {
+                                restore.scroll_to(ui, &response.rect);
+                            }
+                        }
+                        if hidden > 0 {
+                            hidden_lines_label(ui, &font, theme, hidden);
+                        }
+                        if !self.render_pty(ui, &font, theme) {
+                            self.render_input(ui, &font);
+                            self.render_url_actions(ui, &font, theme);
+                        }
+                        anchor
+                    });
+                // Positions measured on a reflow frame are pre-scroll, so
+                // keep the anchor from before the resize.
+                if restore.is_none() {
+                    self.scroll_anchor = output.inner;
+                }
+                let bottom = output.state.offset.y + output.inner_rect.height();
+                self.at_bottom = bottom >= output.content_size.y - 1.0;
+            });
+        if let Some(index) = toggled_fold {
+            self.lines[index].collapsed = !self.lines[index].collapsed;
+        }
+        if let Some(text) = copied_error {
+            ctx.output_mut(|output| output.copied_text = text);
+        }
+        if let Some(text) = searched_error {
+            if let Err(message) = links::open_default(&links::search_url(&text)) {
+                self.add_line(&format!("open: {message}"), ERROR_COLOR);
+            }
+        }
+        if let Some(command) = clicked_action {
+            self.run_line(command);
+        }
+        if let Some(link) = clicked_link {
+            if let Err(message) = links::open(&link, &self.config.editor) {
+                self.add_line(&format!("open: {message}"), ERROR_COLOR);
+            }
+        }
+        self.render_autocomplete(ctx, &font);
+        if idle && self.config.idle_dim {
+            let layer = egui::LayerId::new(egui::Order::Foreground, egui::Id::new("idle_dim"));
+            ctx.layer_painter(layer).rect_filled(
+                ctx.screen_rect(),
+                0.0,
+                Color32::from_black_alpha(IDLE_DIM_ALPHA),
+            );
+        }
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(e) = self.command_history.save() {
+            eprintln!("Failed to save history: {e}");
+        }
+        if let Some(log) = &mut self.session_log {
+            log.flush();
+        }
+        if self.config_dirty {
+            if let Err(e) = self.config.save(&self.profile) {
+                eprintln!("Failed to save config: {e}");
+            }
+        }
+        if let Err(e) = self.window.save(&self.profile) {
+            eprintln!("Failed to save window size: {e}");
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::exec::run_external;
+use super::TerminalApp;
+use crate::builtins::{self, StageOutput};
+use crate::line::{Segment, TerminalLine, ERROR_COLOR, HEADING_COLOR, INPUT_COLOR, OUTPUT_COLOR};
+use crate::parse;
+use crate::shell::ExecMode;
+use crate::timing::{format_duration, Stats};
+
+/// Upper bound on `bench N` so a typo can't queue a day of runs.
+const MAX_RUNS: usize = 10_000;
+
+enum BenchEvent {
+    Run(Duration),
+    Failed(String),
+}
+
+/// A `bench` in progress on its worker thread.
+pub(super) struct BenchJob {
+    command: String,
+    total: usize,
+    runs: Vec<Duration>,
+    events: Receiver<BenchEvent>,
+    /// Scrollback line rewritten in place as runs complete.
+    pub(super) progress_line: Option<usize>,
+}
+
+/// Runs a pipeline once, discarding its output. Errors from a stage stop
+/// the benchmark, since timings of a failing command mean little.
+fn run_once(stages: &[Vec<String>], cwd: &Path, env: &[(String, String)]) -> Result<(), String> {
+    let mut input: Option<StageOutput> = None;
+    for args in stages {
+        let stdin = input.as_ref().map(StageOutput::bytes);
+        let output = match builtins::run_stage(args, input.as_ref(), cwd) {
+            Some(output) => output,
+            // Always direct: a login shell's startup would swamp the timings.
+            None => run_external(args, stdin.as_deref(), cwd, ExecMode::Direct, env)
+                .map_err(|e| format!("{}: {e}", args[0]))?,
+        };
+        input = Some(output);
+    }
+    Ok(())
+}
+
+fn spawn_runs(
+    stages: Vec<Vec<String>>,
+    cwd: PathBuf,
+    env: Vec<(String, String)>,
+    total: usize,
+) -> Receiver<BenchEvent> {
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || {
+        for _ in 0..total {
+            let start = Instant::now();
+            let event = match run_once(&stages, &cwd, &env) {
+                Ok(()) => BenchEvent::Run(start.elapsed()),
+                Err(message) => BenchEvent::Failed(message),
+            };
+            let failed = matches!(event, BenchEvent::Failed(_));
+            if sender.send(event).is_err() || failed {
+                return;
+            }
+        }
+    });
+    events
+}
+
+impl TerminalApp {
+    /// `bench N COMMAND...` times COMMAND (which may be a pipeline) N times
+    /// on a worker thread, then prints summary statistics.
+    pub(super) fn start_bench(&mut self, rest: &str) {
+        if self.bench.is_some() {
+            self.add_line("bench: a benchmark is already running", ERROR_COLOR);
+            return;
+        }
+        let rest = rest.trim_start();
+        let (count, command) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let command = command.trim();
+        let total = match count.parse::<usize>() {
+            Ok(n) if (1..=MAX_RUNS).contains(&n) && !command.is_empty() => n,
+            Ok(_) if !command.is_empty() => {
+                self.add_line(
+                    &format!("bench: run count must be between 1 and {MAX_RUNS}"),
+                    ERROR_COLOR,
+                );
+                return;
+            }
+            _ => {
+                self.add_line("usage: bench N COMMAND...", ERROR_COLOR);
+                return;
+            }
+        };
+
+        let stages: Vec<Vec<String>> = parse::split_pipeline(command)
+            .iter()
+            .map(|stage| parse::split_args(stage))
+            .collect();
+        if stages.iter().any(Vec::is_empty) {
+            self.add_line("syntax error near unexpected token `|'", ERROR_COLOR);
+            return;
+        }
+
+        self.add_line(&format!("bench: 0/{total}"), OUTPUT_COLOR);
+        self.bench = Some(BenchJob {
+            command: command.to_string(),
+            total,
+            runs: Vec::with_capacity(total),
+            events: spawn_runs(stages, self.current_dir.clone(), self.child_env(), total),
+            progress_line: Some(self.lines.len() - 1),
+        });
+    }
+
+    /// Drains finished runs from the worker, updating the progress line and
+    /// printing the summary once all runs are in. Returns true while a
+    /// benchmark is still running.
+    pub(super) fn poll_bench(&mut self) -> bool {
+        let Some(job) = &mut self.bench else {
+            return false;
+        };
+        let mut failure = None;
+        loop {
+            match job.events.try_recv() {
+                Ok(BenchEvent::Run(duration)) => job.runs.push(duration),
+                Ok(BenchEvent::Failed(message)) => failure = Some(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if job.runs.len() < job.total && failure.is_none() {
+                        failure = Some("worker stopped early".to_string());
+                    }
+                    break;
+                }
+            }
+        }
+
+        let done = job.runs.len();
+        let last = job.runs.last().copied();
+        if let (Some(index), Some(last)) = (job.progress_line, last) {
+            if let Some(line) = self.lines.get_mut(index) {
+                *line = TerminalLine::plain(
+                    format!(
+                        "bench: {done}/{} (last {})",
+                        job.total,
+                        format_duration(last)
+                    ),
+                    OUTPUT_COLOR,
+                );
+            }
+        }
+
+        if let Some(message) = failure {
+            self.bench = None;
+            self.add_line(&format!("bench: {message}"), ERROR_COLOR);
+            return false;
+        }
+        if done < job.total {
+            return true;
+        }
+        let job = self.bench.take().expect("checked above");
+        self.print_bench_summary(&job);
+        false
+    }
+
+    fn print_bench_summary(&mut self, job: &BenchJob) {
+        let Some(stats) = Stats::from_runs(&job.runs) else {
+            return;
+        };
+        self.add_line(
+            &format!("bench: '{}' × {}", job.command, job.total),
+            HEADING_COLOR,
+        );
+        let rows = [
+            ("min", stats.min),
+            ("max", stats.max),
+            ("mean", stats.mean),
+            ("median", stats.median),
+            ("stddev", stats.stddev),
+        ];
+        for (label, value) in rows {
+            self.push_line(TerminalLine::from_segments(vec![
+                Segment::new(format!("  {label:<8}"), INPUT_COLOR),
+                Segment::new(format!("{:>10}", format_duration(value)), OUTPUT_COLOR),
+            ]));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::TerminalApp;
+use crate::command_form::CommandForm;
+use crate::input;
+use crate::line::{ERROR_COLOR, INPUT_COLOR, TIMESTAMP_COLOR};
+use crate::theme::Theme;
+
+impl TerminalApp {
+    /// `:build COMMAND` opens a form of the command's flags from its
+    /// completion spec.
+    pub(super) fn start_builder(&mut self, args: &[String]) {
+        if args.is_empty() {
+            self.add_line(
+                ":build: usage: :build COMMAND, e.g. find, tar or docker run",
+                ERROR_COLOR,
+            );
+            return;
+        }
+        let command = args.join(" ");
+        match CommandForm::new(&self.completion_specs, &command) {
+            Some(form) => self.builder = Some(form),
+            None => self.add_line(
+                &format!(":build: no flags known for '{command}' (help autocomplete)"),
+                ERROR_COLOR,
+            ),
+        }
+    }
+
+    /// The `:build` window. "Use" puts the assembled command in the input
+    /// to review and run; Esc or "Cancel" drops it.
+    pub(super) fn render_builder(&mut self, ctx: &egui::Context, font: &FontId, theme: Theme) {
+        let Some(form) = &mut self.builder else {
+            return;
+        };
+        let mut open = true;
+        let mut chosen = None;
+        let text = |text: &str, color| RichText::new(text).font(font.clone()).color(color);
+        egui::Window::new(format!("Build: {}", form.command))
+            .open(&mut open)
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(ctx.screen_rect().height() * 0.6)
+                    .show(ui, |ui| {
+                        egui::Grid::new("builder")
+                            .num_columns(3)
+                            .spacing([12.0, 4.0])
+                            .show(ui, |ui| {
+                                for field in &mut form.fields {
+                                    let flag = text(&field.flag, theme.adapt(INPUT_COLOR));
+                                    ui.checkbox(&mut field.on, flag);
+                                    if field.arg.is_some() {
+                                        let edit = egui::TextEdit::singleline(&mut field.value)
+                                            .font(font.clone())
+                                            .desired_width(160.0);
+                                        // Typing a value turns the flag on.
+                                        if ui.add(edit).changed() && !field.value.is_empty() {
+                                            field.on = true;
+                                        }
+                                    } else {
+                                        ui.label("");
+                                    }
+                                    ui.label(text(&field.description, theme.foreground()));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    ui.label(text("Arguments", theme.foreground()));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut form.args)
+                            .font(font.clone())
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+                ui.add_space(8.0);
+                let line = form.line();
+                ui.label(text(&line, theme.adapt(INPUT_COLOR)));
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Use").clicked() {
+                        chosen = Some(line);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        chosen = Some(String::new());
+                    }
+                    let hint = "Use puts the command at the prompt to review";
+                    ui.label(text(hint, theme.adapt(TIMESTAMP_COLOR)));
+                });
+            });
+        if let Some(line) = chosen {
+            if !line.is_empty() {
+                self.cursor_pos = input::char_count(&line);
+                self.input_buffer = line;
+            }
+            open = false;
+        }
+        if !open {
+            self.builder = None;
+        }
+    }
+}
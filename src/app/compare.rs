@@ -0,0 +1,336 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::tasks::run_stages;
+use super::TerminalApp;
+use crate::builtins::{self, diff};
+use crate::line::{
+    TerminalLine, DELETED_COLOR, ERROR_COLOR, HEADING_COLOR, INSERTED_COLOR, OUTPUT_COLOR,
+    TIMESTAMP_COLOR,
+};
+use crate::parse;
+use crate::shell::{self, ExecMode};
+use crate::theme::Theme;
+
+/// Two commands' outputs shown side by side by `:compare`.
+pub(super) struct Compare {
+    commands: [String; 2],
+    outputs: [Vec<String>; 2],
+    /// Line indices into each output drawn level with each other; `None`
+    /// leaves that side blank where the other has extra lines.
+    rows: Vec<(Option<usize>, Option<usize>)>,
+    /// Both columns scroll together, following whichever was scrolled.
+    offset: f32,
+    /// Output from the commands still running, by column.
+    updates: Receiver<(usize, Update)>,
+    /// Which columns' commands haven't finished.
+    running: [bool; 2],
+}
+
+/// What a column's worker sends as its command runs.
+enum Update {
+    Line(String),
+    Error(String),
+    Done,
+}
+
+/// Everything a column's command needs to run away from the app.
+struct Side {
+    stages: Vec<Vec<String>>,
+    cwd: PathBuf,
+    mode: ExecMode,
+    env: Vec<(String, String)>,
+}
+
+/// Runs one column's pipeline, sending its last stage's output line by line
+/// as it arrives. Earlier stages and internal tools finish before anything
+/// is sent, as in a pipeline in the scrollback.
+fn run_side(side: Side, send: impl Fn(Update) -> bool) {
+    let Side {
+        stages,
+        cwd,
+        mode,
+        env,
+    } = side;
+    let (last, earlier) = stages.split_last().expect("a pipeline has a stage");
+    let mut input = None;
+    if !earlier.is_empty() {
+        match run_stages(earlier, &cwd, mode, &env) {
+            Ok(output) => {
+                for error in &output.errors {
+                    send(Update::Error(error.clone()));
+                }
+                input = Some(output);
+            }
+            Err((program, e)) => {
+                send(Update::Error(format!("{program}: {e}")));
+                return;
+            }
+        }
+    }
+    if let Some(output) = builtins::run_stage(last, input.as_ref(), &cwd) {
+        for error in output.errors {
+            send(Update::Error(error));
+        }
+        for line in output.lines {
+            send(Update::Line(line.text()));
+        }
+        return;
+    }
+    let spawned = shell::command(last, mode)
+        .current_dir(&cwd)
+        .envs(env)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            send(Update::Error(format!("{}: {e}", last[0])));
+            return;
+        }
+    };
+    if let (Some(input), Some(mut pipe)) = (input, child.stdin.take()) {
+        thread::spawn(move || pipe.write_all(&input.bytes()));
+    }
+    let errors = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut text = Vec::new();
+            let _ = pipe.read_to_end(&mut text);
+            text
+        })
+    });
+    if let Some(stdout) = child.stdout.take() {
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+            let text = String::from_utf8_lossy(&line);
+            if !send(Update::Line(
+                text.trim_end_matches(['\n', '\r']).to_string(),
+            )) {
+                // The comparison was closed; stop the command too.
+                let _ = child.kill();
+                break;
+            }
+            line.clear();
+        }
+    }
+    if let Some(errors) = errors.and_then(|reader| reader.join().ok()) {
+        for error in String::from_utf8_lossy(&errors).lines() {
+            send(Update::Error(error.to_string()));
+        }
+    }
+    let _ = child.wait();
+}
+
+impl TerminalApp {
+    /// `:compare A ||| B` runs both commands and shows their outputs in
+    /// two columns, lines that differ colored. The columns fill in as the
+    /// commands write.
+    pub(super) fn start_compare(&mut self, rest: &str) {
+        let sides = rest
+            .split_once("|||")
+            .map(|(a, b)| (a.trim(), b.trim()))
+            .filter(|(a, b)| !a.is_empty() && !b.is_empty());
+        let Some((a, b)) = sides else {
+            self.add_line(":compare: usage: :compare COMMAND ||| COMMAND", ERROR_COLOR);
+            return;
+        };
+        let stages = [a, b].map(|command| {
+            parse::split_pipeline(command)
+                .iter()
+                .map(|stage| parse::split_args(stage))
+                .collect::<Vec<_>>()
+        });
+        if stages.iter().flatten().any(Vec::is_empty) {
+            self.add_line("syntax error near unexpected token `|'", ERROR_COLOR);
+            return;
+        }
+        let (sender, updates) = mpsc::channel();
+        for (i, stages) in stages.into_iter().enumerate() {
+            let side = Side {
+                stages,
+                cwd: self.current_dir.clone(),
+                mode: self.config.exec_mode,
+                env: self.child_env(),
+            };
+            let sender: Sender<(usize, Update)> = sender.clone();
+            thread::spawn(move || {
+                run_side(side, |update| sender.send((i, update)).is_ok());
+                let _ = sender.send((i, Update::Done));
+            });
+        }
+        self.compare = Some(Compare {
+            commands: [a.to_string(), b.to_string()],
+            outputs: [Vec::new(), Vec::new()],
+            rows: Vec::new(),
+            offset: 0.0,
+            updates,
+            running: [true; 2],
+        });
+    }
+
+    /// Adds the output that has arrived to the columns. Returns true while
+    /// either command is still running.
+    pub(super) fn poll_compare(&mut self) -> bool {
+        let Some(compare) = &mut self.compare else {
+            return false;
+        };
+        let mut errors = Vec::new();
+        let mut changed = false;
+        loop {
+            match compare.updates.try_recv() {
+                Ok((i, Update::Line(line))) => {
+                    compare.outputs[i].push(line);
+                    changed = true;
+                }
+                Ok((_, Update::Error(error))) => errors.push(error),
+                Ok((i, Update::Done)) => compare.running[i] = false,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    compare.running = [false; 2];
+                    break;
+                }
+            }
+        }
+        if changed {
+            let lines = compare
+                .outputs
+                .each_ref()
+                .map(|output| output.iter().map(String::as_str).collect::<Vec<_>>());
+            compare.rows = diff::align(&lines[0], &lines[1]);
+        }
+        let running = compare.running.contains(&true);
+        for error in errors {
+            self.add_line(&error, ERROR_COLOR);
+        }
+        running
+    }
+
+    /// Draws the comparison in place of the scrollback. Returns false when
+    /// there's none open.
+    pub(super) fn render_compare(
+        &mut self,
+        ctx: &egui::Context,
+        font: &FontId,
+        theme: Theme,
+    ) -> bool {
+        let color = self.config.color;
+        let line_spacing = self.config.line_spacing;
+        let Some(compare) = &mut self.compare else {
+            return false;
+        };
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame::none()
+                    .fill(theme.background())
+                    .inner_margin(self.config.inner_margin),
+            )
+            .show(ctx, |ui| {
+                let differing = compare
+                    .rows
+                    .iter()
+                    .filter(|&&row| side(compare, row, 0) != side(compare, row, 1))
+                    .count();
+                let state = if compare.running.contains(&true) {
+                    "so far, still running"
+                } else {
+                    "differ"
+                };
+                ui.label(
+                    RichText::new(format!(
+                        "{differing} of {} lines {state}; any key returns",
+                        compare.rows.len()
+                    ))
+                    .font(font.clone())
+                    .color(theme.adapt(TIMESTAMP_COLOR)),
+                );
+                let mut scrolled = None;
+                ui.columns(2, |columns| {
+                    for (i, ui) in columns.iter_mut().enumerate() {
+                        ui.label(
+                            RichText::new(&compare.commands[i])
+                                .font(font.clone())
+                                .color(theme.adapt(HEADING_COLOR)),
+                        );
+                        ui.separator();
+                        let output = egui::ScrollArea::both()
+                            .id_source(("compare", i))
+                            .auto_shrink([false, false])
+                            .vertical_scroll_offset(compare.offset)
+                            .show(ui, |ui| {
+                                ui.spacing_mut().item_spacing.y = line_spacing;
+                                for &row in &compare.rows {
+                                    let text = side(compare, row, i);
+                                    let tint = if text == side(compare, row, 1 - i) {
+                                        OUTPUT_COLOR
+                                    } else if i == 0 {
+                                        DELETED_COLOR
+                                    } else {
+                                        INSERTED_COLOR
+                                    };
+                                    // A blank side still takes a row, keeping
+                                    // the columns level.
+                                    let line = TerminalLine::plain(text.unwrap_or(" "), tint);
+                                    ui.add(
+                                        egui::Label::new(line.layout(font, theme, color))
+                                            .wrap(false),
+                                    );
+                                }
+                            });
+                        if (output.state.offset.y - compare.offset).abs() > 0.5 {
+                            scrolled = Some(output.state.offset.y);
+                        }
+                    }
+                });
+                if let Some(offset) = scrolled {
+                    compare.offset = offset;
+                }
+            });
+        true
+    }
+}
+
+/// The text on side `i` of a row, if that side has a line there.
+fn side(compare: &Compare, row: (Option<usize>, Option<usize>), i: usize) -> Option<&str> {
+    let index = if i == 0 { row.0 } else { row.1 };
+    index.map(|index| compare.outputs[i][index].as_str())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn sends_lines_and_errors_as_they_come() {
+        let args = ["sh", "-c", "echo one; echo two; echo oops >&2"];
+        let side = Side {
+            stages: vec![args.map(String::from).to_vec()],
+            cwd: PathBuf::from("."),
+            mode: ExecMode::Direct,
+            env: Vec::new(),
+        };
+        let got = RefCell::new(Vec::new());
+        run_side(side, |update| {
+            got.borrow_mut().push(match update {
+                Update::Line(line) => line,
+                Update::Error(error) => format!("error: {error}"),
+                Update::Done => "done".to_string(),
+            });
+            true
+        });
+        assert_eq!(got.into_inner(), ["one", "two", "error: oops"]);
+    }
+}
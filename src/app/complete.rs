@@ -0,0 +1,221 @@
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::TerminalApp;
+use crate::completion_spec::Completion;
+use crate::{completion, input};
+
+impl TerminalApp {
+    /// Tab: completes a lone match outright; otherwise extends the word to
+    /// the longest shared prefix and opens the popup. Further Tabs cycle
+    /// through the popup, writing each suggestion into the buffer in turn.
+    pub(super) fn autocomplete(&mut self) {
+        if self.show_autocomplete && !self.autocomplete_suggestions.is_empty() {
+            if self.autocomplete_cycling {
+                self.autocomplete_index =
+                    (self.autocomplete_index + 1) % self.autocomplete_suggestions.len();
+            }
+            self.autocomplete_cycling = true;
+            self.apply_completion(self.autocomplete_index);
+            return;
+        }
+
+        self.update_autocomplete();
+        match self.autocomplete_suggestions.len() {
+            0 => {}
+            1 => {
+                self.apply_completion(0);
+                self.close_autocomplete();
+            }
+            _ => {
+                // A shared prefix of pids or flags isn't a useful thing to
+                // type; the labels say which to pick.
+                if self.autocomplete_labels.is_empty() {
+                    let prefix = completion::common_prefix(&self.autocomplete_suggestions);
+                    self.replace_word(&prefix);
+                }
+                self.show_autocomplete = true;
+            }
+        }
+    }
+
+    /// Recomputes suggestions for the word under the cursor: command names
+    /// in command position, processes after `kill`, flags and types for
+    /// `find`, whatever a command's completion spec says, paths everywhere
+    /// else.
+    pub(super) fn update_autocomplete(&mut self) {
+        let (start, word) = completion::word_at_cursor(&self.input_buffer, self.cursor_pos);
+        self.autocomplete_start = start;
+        self.autocomplete_index = 0;
+        self.autocomplete_cycling = false;
+        self.autocomplete_keyed = false;
+
+        let command = self.input_buffer.split_whitespace().next().unwrap_or("");
+        self.autocomplete_labels.clear();
+        let before: String = self.input_buffer.chars().take(start).collect();
+        let before: Vec<&str> = before.split_whitespace().collect();
+        let find_matches = if command == "find" {
+            completion::complete_find(before.last().unwrap_or(&""), &word)
+        } else {
+            None
+        };
+        self.autocomplete_suggestions =
+            if completion::is_command_position(&self.input_buffer, start) && !word.contains('/') {
+                let commands = self
+                    .common_commands
+                    .iter()
+                    .chain(self.path_commands.names())
+                    .map(String::as_str)
+                    .chain(super::exec::BUILTINS.iter().copied());
+                completion::complete_command(&word, commands)
+            } else if command == "kill" && !word.starts_with('-') {
+                let (pids, labels) = completion::complete_process(&word).into_iter().unzip();
+                self.autocomplete_labels = labels;
+                pids
+            } else if let Some(matches) = find_matches {
+                let (values, labels) = matches.into_iter().unzip();
+                self.autocomplete_labels = labels;
+                values
+            } else if command == "z" {
+                self.dir_history
+                    .matches(&[word.as_str()])
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect()
+            } else if let Some(completion) = self.completion_specs.complete(&before, &word) {
+                match completion {
+                    Completion::Words(matches) => {
+                        let (values, labels) = matches.into_iter().unzip();
+                        self.autocomplete_labels = labels;
+                        values
+                    }
+                    Completion::Paths { dirs_only } => {
+                        let mut paths = completion::complete_path(&word, &self.current_dir);
+                        paths.retain(|path| !dirs_only || path.ends_with('/'));
+                        paths
+                    }
+                    Completion::Nothing => Vec::new(),
+                }
+            } else {
+                completion::complete_path(&word, &self.current_dir)
+            };
+    }
+
+    pub(super) fn close_autocomplete(&mut self) {
+        self.show_autocomplete = false;
+        self.autocomplete_cycling = false;
+        self.autocomplete_keyed = false;
+        self.autocomplete_suggestions.clear();
+        self.autocomplete_labels.clear();
+    }
+
+    /// ArrowDown/ArrowUp in the popup: moves the highlight, wrapping. While
+    /// Tab is cycling, the buffer follows the highlight as it does for Tab.
+    pub(super) fn select_completion(&mut self, forward: bool) {
+        let count = self.autocomplete_suggestions.len();
+        self.autocomplete_index = if forward {
+            (self.autocomplete_index + 1) % count
+        } else {
+            (self.autocomplete_index + count - 1) % count
+        };
+        self.autocomplete_keyed = true;
+        if self.autocomplete_cycling {
+            self.apply_completion(self.autocomplete_index);
+        }
+    }
+
+    /// Enter in the popup: completes with the highlighted suggestion.
+    pub(super) fn accept_completion(&mut self) {
+        self.apply_completion(self.autocomplete_index);
+        self.close_autocomplete();
+    }
+
+    fn apply_completion(&mut self, index: usize) {
+        if let Some(suggestion) = self.autocomplete_suggestions.get(index).cloned() {
+            self.replace_word(&suggestion);
+        }
+    }
+
+    /// Replaces the text between `autocomplete_start` and the cursor.
+    fn replace_word(&mut self, replacement: &str) {
+        let end = self.cursor_pos;
+        input::delete_range(
+            &mut self.input_buffer,
+            &mut self.cursor_pos,
+            self.autocomplete_start,
+            end,
+        );
+        input::insert_str(
+            &mut self.input_buffer,
+            &mut self.cursor_pos,
+            &completion::escape(replacement),
+        );
+    }
+
+    /// Draws the suggestion popup just below the input cursor, resizable
+    /// from its corner. Hovering a suggestion highlights it; clicking
+    /// accepts it.
+    pub(super) fn render_autocomplete(&mut self, ctx: &egui::Context, font: &FontId) {
+        if !self.show_autocomplete || self.autocomplete_suggestions.is_empty() {
+            return;
+        }
+        let Some(cursor) = self.cursor_rect else {
+            return;
+        };
+
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("autocomplete_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(cursor.left_bottom() + egui::vec2(0.0, 2.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    // Dragging the corner resizes it; egui keeps the size.
+                    egui::Resize::default()
+                        .id_source("autocomplete_size")
+                        .default_size([320.0, 240.0])
+                        .min_size([120.0, 48.0])
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical()
+                                .auto_shrink([false, false])
+                                .show(ui, |ui| {
+                                    for (index, suggestion) in
+                                        self.autocomplete_suggestions.iter().enumerate()
+                                    {
+                                        let selected = index == self.autocomplete_index;
+                                        let text = self
+                                            .autocomplete_labels
+                                            .get(index)
+                                            .unwrap_or(suggestion);
+                                        let label = RichText::new(text).font(font.clone());
+                                        let response = ui
+                                            .selectable_label(selected, label)
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand);
+                                        let pointer_moved =
+                                            ui.input(|i| i.pointer.delta() != egui::Vec2::ZERO);
+                                        if response.hovered()
+                                            && (!self.autocomplete_keyed || pointer_moved)
+                                        {
+                                            self.autocomplete_index = index;
+                                            self.autocomplete_keyed = false;
+                                        }
+                                        if response.clicked() {
+                                            clicked = Some(index);
+                                        }
+                                        if selected
+                                            && (self.autocomplete_cycling
+                                                || self.autocomplete_keyed)
+                                        {
+                                            response.scroll_to_me(None);
+                                        }
+                                    }
+                                });
+                        });
+                });
+            });
+
+        if let Some(index) = clicked {
+            self.apply_completion(index);
+            self.close_autocomplete();
+        }
+    }
+}
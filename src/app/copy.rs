@@ -0,0 +1,71 @@
+use super::TerminalApp;
+use crate::builtins::hexdump;
+use crate::line::{TerminalLine, ERROR_COLOR, OUTPUT_COLOR, TIMESTAMP_COLOR};
+
+/// Bytes `:hexdump` shows before summarizing the rest.
+const HEXDUMP_LIMIT: usize = 4096;
+
+impl TerminalApp {
+    /// A line as copied: its text, after its time under `copy-timestamps`.
+    fn copied_line(&self, line: &TerminalLine) -> String {
+        match line.timestamp() {
+            Some(timestamp) if self.config.copy_timestamps && !line.is_input => {
+                format!("{timestamp} {}", line.copy_text())
+            }
+            _ => line.copy_text(),
+        }
+    }
+
+    /// Ctrl+Shift+C: the whole scrollback as shown, leaving out folded
+    /// output and, in errors-only, everything but errors.
+    pub(super) fn copy_scrollback(&mut self) {
+        let mut text = String::new();
+        let mut folded = false;
+        for line in &self.lines {
+            if line.is_input {
+                folded = self.config.command_headers && line.timing.is_some() && line.collapsed;
+            } else if folded || self.hidden_by_errors_only(line) {
+                continue;
+            }
+            text.push_str(&self.copied_line(line));
+            text.push('\n');
+        }
+        self.pending_copy = Some(text);
+    }
+
+    /// `:copy-last`: the output of the command before this one, without
+    /// its prompt line.
+    pub(super) fn copy_last_output(&mut self) {
+        let before = self.command_line.unwrap_or(self.lines.len());
+        let Some(start) = self.lines[..before].iter().rposition(|l| l.is_input) else {
+            self.add_line(":copy-last: no previous command", ERROR_COLOR);
+            return;
+        };
+        let output: Vec<String> = self.lines[start + 1..]
+            .iter()
+            .take_while(|line| !line.is_input)
+            .map(|line| self.copied_line(line))
+            .collect();
+        let count = output.len();
+        self.pending_copy = Some(output.join("\n"));
+        let plural = if count == 1 { "" } else { "s" };
+        self.add_line(&format!("Copied {count} line{plural}"), OUTPUT_COLOR);
+    }
+
+    /// `:hexdump`: the last foreground command's stdout as bytes, for
+    /// output that wasn't text.
+    pub(super) fn hexdump_last_output(&mut self) {
+        if self.last_output.is_empty() {
+            self.add_line(":hexdump: the last command printed nothing", ERROR_COLOR);
+            return;
+        }
+        let shown = self.last_output.len().min(HEXDUMP_LIMIT);
+        for line in hexdump::lines(&self.last_output[..shown]) {
+            self.add_line(&line, OUTPUT_COLOR);
+        }
+        let rest = self.last_output.len() - shown;
+        if rest > 0 {
+            self.add_line(&format!("… {rest} more bytes"), TIMESTAMP_COLOR);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::TerminalApp;
+use crate::line::{Segment, TerminalLine, ERROR_COLOR, LINE_NUMBER_COLOR, OUTPUT_COLOR};
+use crate::{parse, paths, suggest};
+
+/// Why `cd` couldn't enter a directory.
+enum CdError {
+    NotADirectory,
+    Io(io::Error),
+}
+
+/// Directories `cd --back` can step back through.
+const MAX_BACK: usize = 100;
+
+/// `path` with the home directory shown as `~`, as `dirs` prints it.
+pub(super) fn tilde(path: &Path) -> String {
+    match paths::home_dir().and_then(|home| path.strip_prefix(home).ok().map(PathBuf::from)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// `target` with its last component swapped for each of the nearest
+/// existing directories beside it, for a `cd` that found none.
+fn similar_directories(cwd: &Path, target: &str) -> Vec<String> {
+    let target = target.trim_end_matches('/');
+    let (parent, name) = match target.rfind('/') {
+        Some(i) => (&target[..=i], &target[i + 1..]),
+        None => ("", target),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return Vec::new();
+    }
+    let Ok(entries) = std::fs::read_dir(paths::resolve(cwd, parent)) else {
+        return Vec::new();
+    };
+    let dirs: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|dir| !dir.starts_with('.') || name.starts_with('.'))
+        .collect();
+    // One edit in a short name is already a different word.
+    let max_distance = (name.chars().count() / 4).clamp(1, 2);
+    suggest::nearest(name, dirs.iter().map(String::as_str), max_distance)
+        .into_iter()
+        .map(|dir| format!("{parent}{dir}"))
+        .collect()
+}
+
+/// The `pushd`/`popd` stack, top last. Changing directory is left to the
+/// caller, and the stack only changes once that has worked.
+#[derive(Default)]
+pub(super) struct DirStack {
+    dirs: Vec<PathBuf>,
+}
+
+impl DirStack {
+    /// `pushd`: enters `target`, or the top of the stack without one, and
+    /// saves `current` if that worked. None if there's nothing to swap with.
+    fn push(
+        &mut self,
+        current: PathBuf,
+        target: Option<&str>,
+        enter: impl FnOnce(&str) -> bool,
+    ) -> Option<bool> {
+        let entered = match target {
+            Some(target) => enter(target),
+            None => {
+                let top = self.dirs.last()?.display().to_string();
+                let entered = enter(&top);
+                if entered {
+                    self.dirs.pop();
+                }
+                entered
+            }
+        };
+        if entered {
+            self.dirs.push(current);
+        }
+        Some(entered)
+    }
+
+    /// `popd`: enters the top of the stack and drops it if that worked.
+    /// None if the stack is empty.
+    fn pop(&mut self, enter: impl FnOnce(&str) -> bool) -> Option<bool> {
+        let top = self.dirs.last()?.display().to_string();
+        let entered = enter(&top);
+        if entered {
+            self.dirs.pop();
+        }
+        Some(entered)
+    }
+
+    /// What `dirs` lists: `current` followed by the stack, top first.
+    fn listing(&self, current: &Path) -> Vec<String> {
+        std::iter::once(current)
+            .chain(self.dirs.iter().rev().map(PathBuf::as_path))
+            .map(tilde)
+            .collect()
+    }
+}
+
+impl TerminalApp {
+    /// `auto-cd`: a line that is only a directory changes into it, unless
+    /// the word is also a command.
+    pub(super) fn auto_cd(&mut self, line: &str) -> bool {
+        if !self.config.auto_cd {
+            return false;
+        }
+        let args = parse::split_args(line);
+        let [target] = args.as_slice() else {
+            return false;
+        };
+        let command = super::exec::BUILTINS.contains(&target.as_str())
+            || (!target.contains('/') && suggest::on_path(target));
+        if command || !paths::resolve(&self.current_dir, target).is_dir() {
+            return false;
+        }
+        self.change_directory(Some(target))
+    }
+
+    /// `cd [dir]`. Returns false (after reporting why) if the directory
+    /// can't be entered.
+    pub(super) fn change_directory(&mut self, target: Option<&str>) -> bool {
+        let target = target.unwrap_or("~");
+        let error = match self.try_directory(target) {
+            Ok(()) => return true,
+            Err(error) => error,
+        };
+        match error {
+            CdError::NotADirectory => {
+                self.add_line(&format!("cd: not a directory '{target}'"), ERROR_COLOR);
+            }
+            // Only a name that doesn't exist is worth correcting; one that
+            // can't be entered is reported as it is.
+            CdError::Io(e) if e.kind() != io::ErrorKind::NotFound => {
+                self.add_line(&format!("cd: {target}: {e}"), ERROR_COLOR);
+            }
+            CdError::Io(_) => {
+                let suggestions = similar_directories(&self.current_dir, target);
+                match suggestions.as_slice() {
+                    [only] if self.config.cd_correct => {
+                        self.add_line(&format!("cd: correcting to '{only}'"), OUTPUT_COLOR);
+                        if self.try_directory(only).is_ok() {
+                            return true;
+                        }
+                        self.add_line(&format!("cd: can't enter '{only}'"), ERROR_COLOR);
+                    }
+                    [] => {
+                        self.add_line(&format!("cd: no such directory '{target}'"), ERROR_COLOR);
+                    }
+                    _ => {
+                        let quoted: Vec<String> =
+                            suggestions.iter().map(|s| format!("'{s}'")).collect();
+                        let message = format!(
+                            "cd: no such directory '{target}'. Did you mean {}?",
+                            quoted.join(" or ")
+                        );
+                        self.add_line(&message, ERROR_COLOR);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Enters `target` if it's a directory, without reporting anything.
+    fn try_directory(&mut self, target: &str) -> Result<(), CdError> {
+        let path = paths::resolve(&self.current_dir, target)
+            .canonicalize()
+            .map_err(CdError::Io)?;
+        if !path.is_dir() {
+            return Err(CdError::NotADirectory);
+        }
+        self.enter_directory(path);
+        Ok(())
+    }
+
+    /// Moves to `path`, remembering where we were for `cd -` and `cd --back`.
+    fn enter_directory(&mut self, path: PathBuf) {
+        if path == self.current_dir {
+            return;
+        }
+        self.dir_history.visit(&path);
+        let previous = std::mem::replace(&mut self.current_dir, path);
+        self.back_dirs.push(previous);
+        if self.back_dirs.len() > MAX_BACK {
+            self.back_dirs.remove(0);
+        }
+        if self.config.clear_on_cd {
+            self.clear_scrollback();
+        }
+    }
+
+    /// `cd -`: the previous directory. Repeating it toggles between two.
+    pub(super) fn cd_previous(&mut self) {
+        let Some(previous) = self.back_dirs.last().cloned() else {
+            self.add_line("cd: no previous directory", ERROR_COLOR);
+            return;
+        };
+        self.add_line(&tilde(&previous), OUTPUT_COLOR);
+        self.change_directory(Some(&previous.display().to_string()));
+    }
+
+    /// `cd --back`: undoes directory changes one at a time, skipping
+    /// directories that have since been removed.
+    pub(super) fn cd_back(&mut self) {
+        while let Some(previous) = self.back_dirs.pop() {
+            if previous.is_dir() {
+                if self.config.clear_on_cd {
+                    self.clear_scrollback();
+                }
+                self.add_line(&tilde(&previous), OUTPUT_COLOR);
+                self.current_dir = previous;
+                return;
+            }
+        }
+        self.add_line("cd: no earlier directory", ERROR_COLOR);
+    }
+
+    /// `pushd DIR` changes to DIR and saves the old directory on the stack;
+    /// bare `pushd` swaps the current directory with the top of the stack.
+    pub(super) fn pushd(&mut self, target: Option<&str>) {
+        let mut stack = std::mem::take(&mut self.dir_stack);
+        let old = self.current_dir.clone();
+        let entered = stack.push(old, target, |dir| self.change_directory(Some(dir)));
+        self.dir_stack = stack;
+        match entered {
+            Some(true) => self.list_dir_stack(false),
+            Some(false) => {}
+            None => self.add_line("pushd: no other directory", ERROR_COLOR),
+        }
+    }
+
+    /// `popd`: returns to the directory on top of the stack.
+    pub(super) fn popd(&mut self) {
+        let mut stack = std::mem::take(&mut self.dir_stack);
+        let entered = stack.pop(|dir| self.change_directory(Some(dir)));
+        self.dir_stack = stack;
+        match entered {
+            Some(true) => self.list_dir_stack(false),
+            Some(false) => {}
+            None => self.add_line("popd: directory stack empty", ERROR_COLOR),
+        }
+    }
+
+    /// `dirs [-v]`: the current directory followed by the stack, top first.
+    pub(super) fn list_dir_stack(&mut self, numbered: bool) {
+        let dirs = self.dir_stack.listing(&self.current_dir);
+        if !numbered {
+            self.add_line(&dirs.join(" "), OUTPUT_COLOR);
+            return;
+        }
+        for (index, dir) in dirs.into_iter().enumerate() {
+            self.push_line(TerminalLine::from_segments(vec![
+                Segment::new(format!("{index:>3}  "), LINE_NUMBER_COLOR),
+                Segment::new(dir, OUTPUT_COLOR),
+            ]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(dirs: &[&str]) -> DirStack {
+        DirStack {
+            dirs: dirs.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn pushd_saves_the_old_directory_only_once_entered() {
+        let mut dirs = stack(&["/a"]);
+        assert_eq!(dirs.push("/b".into(), Some("/c"), |_| false), Some(false));
+        assert_eq!(dirs.listing(Path::new("/b")), ["/b", "/a"]);
+        assert_eq!(
+            dirs.push("/b".into(), Some("/c"), |d| d == "/c"),
+            Some(true)
+        );
+        assert_eq!(dirs.listing(Path::new("/c")), ["/c", "/b", "/a"]);
+    }
+
+    #[test]
+    fn bare_pushd_swaps_with_the_top_only_once_entered() {
+        let mut dirs = stack(&["/a", "/b"]);
+        assert_eq!(dirs.push("/c".into(), None, |_| false), Some(false));
+        assert_eq!(dirs.listing(Path::new("/c")), ["/c", "/b", "/a"]);
+        assert_eq!(dirs.push("/c".into(), None, |d| d == "/b"), Some(true));
+        assert_eq!(dirs.listing(Path::new("/b")), ["/b", "/c", "/a"]);
+        assert_eq!(DirStack::default().push("/c".into(), None, |_| true), None);
+    }
+
+    #[test]
+    fn popd_keeps_the_top_when_it_cant_be_entered() {
+        let mut dirs = stack(&["/a", "/b"]);
+        assert_eq!(dirs.pop(|_| false), Some(false));
+        assert_eq!(dirs.listing(Path::new("/c")), ["/c", "/b", "/a"]);
+        assert_eq!(dirs.pop(|d| d == "/b"), Some(true));
+        assert_eq!(dirs.listing(Path::new("/b")), ["/b", "/a"]);
+        assert_eq!(DirStack::default().pop(|_| true), None);
+    }
+}
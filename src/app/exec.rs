@@ -0,0 +1,683 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use super::for_loop::Queued;
+use super::{running, TerminalApp};
+use crate::alias::Expansion;
+use crate::builtins::{self, calc, StageOutput};
+use crate::config::BusyInput;
+use crate::line::{TerminalLine, ERROR_COLOR, OUTPUT_COLOR};
+use crate::shell::{self, ExecMode};
+use crate::tty::{self, TtyFallback};
+use crate::{alias, git, help, links, parse, paths, script, stream, suggest};
+
+/// Commands handled by `run_builtin` or internal tools, offered for
+/// completion alongside external command names.
+pub(super) const BUILTINS: &[&str] = &[
+    ":build",
+    ":compare",
+    ":copy-last",
+    ":errors-only",
+    ":export-history",
+    ":export-html",
+    ":hexdump",
+    ":highlight",
+    ":keybindings",
+    ":load",
+    ":macro",
+    ":next-error",
+    ":pin",
+    ":prev-error",
+    ":save",
+    ":search",
+    ":set",
+    ":unpin",
+    ":wrap",
+    "alias",
+    "base64",
+    "bench",
+    "cal",
+    "calc",
+    "cd",
+    "chmod",
+    "clear",
+    "cowsay",
+    "date",
+    "diff",
+    "dirs",
+    "echo",
+    "exit",
+    "export",
+    "for",
+    "fortune",
+    "grep",
+    "hash",
+    "head",
+    "help",
+    "history",
+    "http",
+    "jobs",
+    "kill",
+    "man",
+    "open",
+    "popd",
+    "ps",
+    "pushd",
+    "sed",
+    "sort",
+    "tail",
+    "tldr",
+    "top",
+    "trap",
+    "trash-restore",
+    "tree",
+    "unalias",
+    "uniq",
+    "unset",
+    "wc",
+    "z",
+];
+
+impl TerminalApp {
+    pub(super) fn execute_command(&mut self, command: &str) {
+        self.execute_line(command, true);
+    }
+
+    /// Runs `command`, expanding its aliases first unless it's already the
+    /// expansion of one.
+    pub(super) fn execute_line(&mut self, command: &str, expand_aliases: bool) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        let expansion = if expand_aliases {
+            alias::expand_statement(command, |name| self.aliases.get(name).map(String::as_str))
+        } else {
+            Expansion::Line(command.to_string())
+        };
+        let command = match expansion {
+            Expansion::Line(line) => line,
+            Expansion::Commands(commands) => {
+                // Ahead of whatever was already waiting, such as the rest
+                // of a loop this alias is in.
+                for (command, after_success) in commands.into_iter().rev() {
+                    self.loop_queue.push_front(Queued {
+                        command,
+                        after_success,
+                        expanded: true,
+                    });
+                }
+                self.continue_loop();
+                return;
+            }
+        };
+        let command = command.as_str();
+        // The body's variables are bound per word, so nothing is expanded yet.
+        match script::parse_for(command) {
+            Some(Ok(for_loop)) => {
+                self.run_for_loop(for_loop);
+                return;
+            }
+            Some(Err(message)) => {
+                self.add_line(&message, ERROR_COLOR);
+                return;
+            }
+            None => {}
+        }
+
+        let command = parse::expand_variables(command, &|name| self.variable(name));
+        let command =
+            match parse::substitute_commands(&command, &mut |inner| self.capture_output(inner)) {
+                Ok(command) => command,
+                Err(message) => {
+                    self.add_line(&format!("syntax error: {message}"), ERROR_COLOR);
+                    return;
+                }
+            };
+
+        let (assignments, rest) = parse::take_assignments(&command);
+        if assignments.is_empty() {
+            self.run_expanded(command);
+        } else if rest.is_empty() {
+            for (name, value) in assignments {
+                self.assign(name, value);
+            }
+        } else {
+            // Commands started for the rest of the line get them; queued
+            // jobs start later, so they take a copy with them.
+            let rest = rest.to_string();
+            self.command_env = assignments;
+            self.run_expanded(rest);
+            self.command_env.clear();
+        }
+    }
+
+    /// Runs a line whose variables and substitutions are already expanded.
+    fn run_expanded(&mut self, command: String) {
+        if calc::looks_like_arithmetic(&command) {
+            let output = calc::run(&[command]);
+            self.show_output(output);
+            return;
+        }
+
+        // `bench` times a whole pipeline, so it sees the line before `|` splits it.
+        if let Some(rest) = command.strip_prefix("bench") {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                self.start_bench(rest);
+                return;
+            }
+        }
+
+        // As does `:compare`, whose `|||` would otherwise split into stages.
+        if let Some(rest) = command.strip_prefix(":compare") {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                self.start_compare(rest);
+                return;
+            }
+        }
+
+        let (command, background) = match parse::strip_background(&command) {
+            Some(rest) => (rest.to_string(), true),
+            None => (command, false),
+        };
+        let stages = parse::split_pipeline(&command);
+        if stages.len() == 1 && self.run_builtin(&stages[0]) {
+            return;
+        }
+        if stages.len() == 1 && !background && self.auto_cd(&stages[0]) {
+            return;
+        }
+        self.run_pipeline(&stages, background);
+    }
+
+    /// Handles commands that change the terminal's own state. Returns false
+    /// if `stage` isn't one of them.
+    fn run_builtin(&mut self, stage: &str) -> bool {
+        let args = parse::split_args(stage);
+        let Some(name) = args.first() else {
+            return false;
+        };
+        match name.as_str() {
+            "cd" => match args.get(1).map(String::as_str) {
+                Some("-") => self.cd_previous(),
+                Some("--back") => self.cd_back(),
+                target => {
+                    let changed = self.change_directory(target);
+                    self.last_status = Some(if changed { 0 } else { 1 });
+                }
+            },
+            "pushd" => self.pushd(args.get(1).map(String::as_str)),
+            "popd" => self.popd(),
+            "export" => self.export(&args[1..]),
+            "unset" => self.unset(&args[1..]),
+            "alias" => self.alias(&args[1..]),
+            "unalias" => self.unalias(&args[1..]),
+            "jobs" => self.list_jobs(),
+            "trap" => self.trap(&args[1..]),
+            "tail" => match builtins::head_tail::parse(&args[1..], true) {
+                Some(options) if options.follow => self.start_follow(options),
+                _ => return false,
+            },
+            "top" if args.len() == 1 => self.start_top(),
+            "dirs" => self.list_dir_stack(args.get(1).is_some_and(|a| a == "-v")),
+            "z" => self.jump_to_frecent(&args[1..]),
+            "clear" => self.clear_scrollback(),
+            "exit" => self.exit_requested = self.confirm_exit(),
+            ":set" => self.set_option(&args[1..]),
+            ":wrap" => {
+                let value = args
+                    .get(1)
+                    .cloned()
+                    .unwrap_or_else(|| if self.config.wrap { "off" } else { "on" }.to_string());
+                self.set_option(&["wrap".to_string(), value]);
+            }
+            ":export-history" => self.export_history(&args[1..]),
+            ":export-html" => self.export_html(args.get(1).map(String::as_str)),
+            "history" => self.list_history(&args[1..].join(" ")),
+            ":save" => self.save_session(args.get(1).map(String::as_str)),
+            ":load" => self.load_session(args.get(1).map(String::as_str)),
+            ":next-error" => self.jump_to_error(true),
+            ":prev-error" => self.jump_to_error(false),
+            ":search" => self.search_scrollback(&args[1..]),
+            ":copy-last" => self.copy_last_output(),
+            ":errors-only" => self.errors_only = !self.errors_only,
+            ":hexdump" => self.hexdump_last_output(),
+            ":build" => self.start_builder(&args[1..]),
+            ":macro" => self.macro_command(&args[1..]),
+            ":pin" => self.pin(),
+            ":unpin" => self.unpin(&args[1..]),
+            ":highlight" => self.highlight(&args[1..]),
+            ":keybindings" => self.show_keybindings = true,
+            "man" => self.show_man_page(&args[1..]),
+            // Flags are for the system `open` (macOS `open -a`, `-R` ...).
+            "open" if !args[1..].iter().any(|arg| arg.starts_with('-')) => {
+                self.open_paths(&args[1..])
+            }
+            "rm" if self.config.trash => {
+                let output = builtins::trash::rm(&args[1..], &self.current_dir);
+                self.show_output(output);
+            }
+            "trash-restore" => {
+                let output = builtins::trash::restore(&args[1..], &self.current_dir);
+                self.show_output(output);
+            }
+            "help" => {
+                let lines = help::render(args.get(1).map(String::as_str));
+                self.push_lines(lines);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// `z TERMS...` jumps to the best-ranked visited directory matching all
+    /// terms; bare `z` lists the ranking.
+    fn jump_to_frecent(&mut self, terms: &[String]) {
+        let terms: Vec<&str> = terms.iter().map(String::as_str).collect();
+        let matches = self.dir_history.matches(&terms);
+        if terms.is_empty() {
+            for dir in matches.iter().take(20) {
+                self.add_line(&dir.display().to_string(), OUTPUT_COLOR);
+            }
+            return;
+        }
+        match matches.first() {
+            Some(dir) => {
+                let dir = dir.display().to_string();
+                self.add_line(&dir, OUTPUT_COLOR);
+                self.change_directory(Some(&dir));
+            }
+            None => self.add_line(
+                &format!("z: no match for '{}'", terms.join(" ")),
+                ERROR_COLOR,
+            ),
+        }
+    }
+
+    /// Runs a pipeline. An external last stage is streamed so its output
+    /// shows up, and progress bars redraw, while it runs; earlier stages
+    /// are collected and fed to it as stdin. With `background`, the last
+    /// stage becomes a numbered job.
+    fn run_pipeline(&mut self, stages: &[String], background: bool) {
+        let Some((last, upstream)) = stages.split_last() else {
+            return;
+        };
+        let args = parse::split_args(last);
+        if args.is_empty() {
+            self.add_line("syntax error near unexpected token `|'", ERROR_COLOR);
+            return;
+        }
+        if tty::needs_tty(&args[0], &self.config.tty_commands) {
+            self.run_tty_command(&args, stages, background);
+            return;
+        }
+        let slow = stages
+            .iter()
+            .any(|stage| builtins::may_block(&parse::split_args(stage)));
+        if slow && !background {
+            self.start_task(stages);
+            return;
+        }
+        let input = if upstream.is_empty() {
+            None
+        } else {
+            let Some(output) = self.pipeline_output(upstream) else {
+                return;
+            };
+            for error in &output.errors {
+                self.add_line(error, ERROR_COLOR);
+            }
+            Some(output)
+        };
+
+        if let Some(output) = builtins::run_stage(&args, input.as_ref(), &self.current_dir) {
+            self.show_output(output);
+            return;
+        }
+        let input = input.as_ref().map(StageOutput::bytes);
+        if background {
+            self.start_job(stages.join(" | "), args, input);
+            return;
+        }
+        let spawned = running::spawn(
+            &stages.join(" | "),
+            &args,
+            input,
+            self.config.busy_input == BusyInput::Stdin && !self.in_hook,
+            &self.current_dir,
+            self.config.exec_mode,
+            &self.child_env(),
+        );
+        match spawned {
+            Ok(mut command) => {
+                command.origin = self.command_line;
+                command.from_hook = self.in_hook;
+                command.hook = self.running_hook.clone();
+                self.running.push(command);
+            }
+            Err(e) => self.report_spawn_error(&args[0], &e),
+        }
+    }
+
+    /// Runs a `$(...)` command and returns its stdout for substitution;
+    /// its errors still go to the scrollback.
+    pub(super) fn capture_output(&mut self, command: &str) -> String {
+        if command.trim().is_empty() {
+            return String::new();
+        }
+        let stages = parse::split_pipeline(command.trim());
+        match self.pipeline_output(&stages) {
+            Some(output) => {
+                for error in &output.errors {
+                    self.add_line(error, ERROR_COLOR);
+                }
+                output.text()
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Runs each stage in turn, feeding one stage's stdout to the next, and
+    /// returns the last stage's output. Internal tools take over a stage when
+    /// they support its arguments. Failures are reported and yield `None`.
+    fn pipeline_output(&mut self, stages: &[String]) -> Option<StageOutput> {
+        let mut input: Option<StageOutput> = None;
+        let last = stages.len() - 1;
+
+        for (index, stage) in stages.iter().enumerate() {
+            let args = parse::split_args(stage);
+            if args.is_empty() {
+                self.add_line("syntax error near unexpected token `|'", ERROR_COLOR);
+                return None;
+            }
+
+            let output = match builtins::run_stage(&args, input.as_ref(), &self.current_dir) {
+                Some(output) => output,
+                None => match run_external(
+                    &args,
+                    input.as_ref().map(StageOutput::bytes).as_deref(),
+                    &self.current_dir,
+                    self.config.exec_mode,
+                    &self.child_env(),
+                ) {
+                    Ok(output) => output,
+                    Err(e) => {
+                        self.report_spawn_error(&args[0], &e);
+                        return None;
+                    }
+                },
+            };
+
+            if index == last {
+                return Some(output);
+            }
+            for error in &output.errors {
+                self.add_line(error, ERROR_COLOR);
+            }
+            input = Some(output);
+        }
+        None
+    }
+
+    /// A program that needs a real terminal: run on a pty, opened in an
+    /// external terminal, or refused with an explanation, as `tty-fallback`
+    /// says. A job in the background can't have the screen, so it gets an
+    /// external terminal instead of a pty.
+    fn run_tty_command(&mut self, args: &[String], stages: &[String], background: bool) {
+        let program = args[0].as_str();
+        let command = &stages.join(" | ");
+        #[cfg(unix)]
+        if self.config.tty_fallback == TtyFallback::Pty && !background {
+            // A pager at the end of a pipeline reads its keys from the
+            // terminal, so the whole pipeline runs on the pty.
+            let shell;
+            let args = if stages.len() == 1 {
+                args
+            } else {
+                shell = ["sh", "-c", command].map(String::from);
+                &shell[..]
+            };
+            self.start_pty(command, args);
+            return;
+        }
+        #[cfg(not(unix))]
+        let _ = background;
+        let refusal =
+            format!("'{program}' requires an interactive terminal, which isn't supported yet");
+        if self.config.tty_fallback == TtyFallback::Message {
+            self.add_line(&refusal, ERROR_COLOR);
+            self.add_line(
+                "Use :set tty-fallback external to open it in a terminal window",
+                OUTPUT_COLOR,
+            );
+            return;
+        }
+        let Some(args) = tty::external_args(&self.config.terminal, command, &self.current_dir)
+        else {
+            self.add_line(&refusal, ERROR_COLOR);
+            self.add_line(
+                "No terminal emulator found; set one with :set terminal 'xterm -e'",
+                OUTPUT_COLOR,
+            );
+            return;
+        };
+        let spawned = Command::new(&args[0])
+            .args(&args[1..])
+            .current_dir(&self.current_dir)
+            .envs(self.child_env())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+                self.add_line(
+                    &format!("Opened '{program}' in a new {} window", args[0]),
+                    OUTPUT_COLOR,
+                );
+            }
+            Err(e) => self.add_line(
+                &format!("{refusal}; starting {} failed: {e}", args[0]),
+                ERROR_COLOR,
+            ),
+        }
+    }
+
+    /// `open PATH|URL...` hands each target to the desktop's default
+    /// application. Paths are resolved against the current directory and
+    /// must exist.
+    fn open_paths(&mut self, targets: &[String]) {
+        if targets.is_empty() {
+            self.add_line("open: usage: open PATH|URL...", ERROR_COLOR);
+        }
+        for target in targets {
+            let is_url = target.contains("://") || target.starts_with("mailto:");
+            let resolved = if is_url {
+                target.clone()
+            } else {
+                let path = paths::resolve(&self.current_dir, target);
+                if !path.exists() {
+                    self.add_line(&format!("open: no such file '{target}'"), ERROR_COLOR);
+                    continue;
+                }
+                path.display().to_string()
+            };
+            if let Err(message) = links::open_default(&resolved) {
+                self.add_line(&format!("open: {message}"), ERROR_COLOR);
+            }
+        }
+    }
+
+    pub(super) fn show_output(&mut self, output: StageOutput) {
+        if !self.in_hook {
+            self.last_output = output.text().into_bytes();
+        }
+        if !output.errors.is_empty() {
+            self.last_status = Some(1);
+        }
+        for error in &output.errors {
+            self.add_line(error, ERROR_COLOR);
+        }
+        self.push_lines(output.lines);
+    }
+
+    pub(super) fn report_spawn_error(&mut self, program: &str, error: &io::Error) {
+        self.last_status = Some(127);
+        if error.kind() == io::ErrorKind::NotFound {
+            self.report_not_found(program);
+        } else {
+            self.add_line(
+                &format!("Failed to execute '{program}': {error}"),
+                ERROR_COLOR,
+            );
+        }
+    }
+
+    /// After the command echoed at line `origin` has finished, suggests
+    /// `sudo!!` if its output says it was denied permission.
+    pub(super) fn hint_sudo(&mut self, origin: usize) {
+        if !cfg!(unix) {
+            return;
+        }
+        let Some(command) = self.lines.get(origin).filter(|line| line.is_input) else {
+            return;
+        };
+        let command = command.text();
+        let command = command.strip_prefix(super::PROMPT).unwrap_or(&command);
+        if command.starts_with("sudo") || command.starts_with(':') {
+            return;
+        }
+        let denied = self.lines[origin + 1..]
+            .iter()
+            .filter(|line| !line.is_input)
+            .any(|line| is_permission_error(&line.text()));
+        if denied {
+            self.add_line(
+                "Permission denied: run 'sudo!!' to retry as root",
+                OUTPUT_COLOR,
+            );
+        }
+    }
+
+    /// After a `git` command at line `origin` fails on a mistyped
+    /// subcommand, offers the corrected command as a line that runs it
+    /// when clicked.
+    pub(super) fn hint_git_typo(&mut self, origin: usize) {
+        let Some(command) = self.lines.get(origin).filter(|line| line.is_input) else {
+            return;
+        };
+        let command = command.text();
+        let command = command.strip_prefix(super::PROMPT).unwrap_or(&command);
+        if parse::split_args(command).first().map(String::as_str) != Some("git") {
+            return;
+        }
+        let output: Vec<String> = self.lines[origin + 1..]
+            .iter()
+            .take_while(|line| !line.is_input)
+            .map(|line| line.text())
+            .collect();
+        let Some((typo, meant)) = git::typo_correction(&output) else {
+            return;
+        };
+        // Swap the first whole word that is the typo, leaving the rest of
+        // the line as typed.
+        let words: Vec<&str> = command.split(' ').collect();
+        let Some(index) = words.iter().position(|word| *word == typo) else {
+            return;
+        };
+        let mut corrected = words;
+        corrected[index] = &meant;
+        let corrected = corrected.join(" ");
+        self.push_line(TerminalLine {
+            action: Some(corrected.clone()),
+            ..TerminalLine::plain(
+                format!("Did you mean '{corrected}'? Click to run it"),
+                OUTPUT_COLOR,
+            )
+        });
+    }
+
+    /// Reports an unknown command along with up to three close matches
+    /// from `common_commands` and `$PATH`.
+    fn report_not_found(&mut self, name: &str) {
+        self.add_line(&format!("{name}: command not found"), ERROR_COLOR);
+        let candidates = self
+            .common_commands
+            .iter()
+            .chain(self.path_commands.names())
+            .map(String::as_str);
+        let matches = suggest::closest(name, candidates, 2, 3);
+        let quoted: Vec<String> = matches.iter().map(|m| format!("'{m}'")).collect();
+        match quoted.len() {
+            0 => {}
+            1 => self.add_line(&format!("Did you mean {}?", quoted[0]), OUTPUT_COLOR),
+            _ => self.add_line(
+                &format!("Did you mean one of {}?", quoted.join(", ")),
+                OUTPUT_COLOR,
+            ),
+        }
+    }
+}
+
+/// True for the messages programs print when they fail with EACCES or
+/// EPERM, or refuse to run without root.
+fn is_permission_error(text: &str) -> bool {
+    let text = text.to_lowercase();
+    [
+        "permission denied",
+        "operation not permitted",
+        "eacces",
+        "are you root",
+        "must be root",
+        "must be run as root",
+        "requires root",
+    ]
+    .iter()
+    .any(|pattern| text.contains(pattern))
+}
+
+/// Spawns `args` in `cwd` with `env` added to its environment, feeding it
+/// `stdin`, and collects its output.
+pub(super) fn run_external(
+    args: &[String],
+    stdin: Option<&[u8]>,
+    cwd: &Path,
+    mode: ExecMode,
+    env: &[(String, String)],
+) -> io::Result<StageOutput> {
+    let mut child = shell::command(args, mode)
+        .current_dir(cwd)
+        .envs(env.iter().cloned())
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from a separate thread so a child that fills its stdout
+    // pipe before draining stdin can't deadlock us.
+    if let (Some(bytes), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        let bytes = bytes.to_vec();
+        thread::spawn(move || {
+            let _ = pipe.write_all(&bytes);
+        });
+    }
+
+    let result = child.wait_with_output()?;
+
+    Ok(StageOutput {
+        lines: stream::split_lines(&result.stdout)
+            .into_iter()
+            .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+            .collect(),
+        errors: stream::split_lines(&result.stderr),
+        no_newline: !result.stdout.is_empty() && !result.stdout.ends_with(b"\n"),
+        stdout: Some(result.stdout),
+    })
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::TerminalApp;
+use crate::find::Query;
+use crate::parse;
+
+/// How long the input must stay unchanged before a count starts, so each
+/// keystroke doesn't start a walk of the tree.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Entries visited before the count gives up and shows a lower bound.
+const VISIT_LIMIT: usize = 200_000;
+
+/// Matches found, and whether the walk finished rather than hit the limit.
+type Count = (usize, bool);
+
+/// The header's live count of what a `find` being typed would match.
+pub(super) struct FindPreview {
+    /// The input the count is for.
+    command: String,
+    changed: Instant,
+    /// Set once the count has finished.
+    count: Option<Count>,
+    search: Option<(Receiver<Count>, Arc<AtomicBool>)>,
+}
+
+impl Drop for FindPreview {
+    fn drop(&mut self) {
+        if let Some((_, stop)) = &self.search {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl FindPreview {
+    /// Header text: the count, or an ellipsis while it's still running.
+    pub(super) fn label(&self) -> Option<String> {
+        match self.count {
+            Some((1, true)) => Some("1 match".to_string()),
+            Some((n, true)) => Some(format!("{n} matches")),
+            Some((n, false)) => Some(format!("{n}+ matches")),
+            None if self.search.is_some() => Some("counting…".to_string()),
+            None => None,
+        }
+    }
+}
+
+impl TerminalApp {
+    /// Keeps the preview in step with the input: restarts the debounce when
+    /// the `find` changes, starts the count once it settles, and collects
+    /// the result. Returns true while waiting on either.
+    pub(super) fn poll_find_preview(&mut self) -> bool {
+        let args = parse::split_args(&self.input_buffer);
+        let query = match args.split_first() {
+            // The count is shown in the header; don't walk the tree for nothing.
+            _ if !self.config.prompt_header => None,
+            Some((command, rest)) if command == "find" => Query::parse(rest),
+            _ => None,
+        };
+        let Some(query) = query else {
+            self.find_preview = None;
+            return false;
+        };
+        if self
+            .find_preview
+            .as_ref()
+            .is_none_or(|preview| preview.command != self.input_buffer)
+        {
+            self.find_preview = Some(FindPreview {
+                command: self.input_buffer.clone(),
+                changed: Instant::now(),
+                count: None,
+                search: None,
+            });
+        }
+        let cwd = self.current_dir.clone();
+        let Some(preview) = &mut self.find_preview else {
+            return false;
+        };
+        if preview.count.is_some() {
+            return false;
+        }
+        match &preview.search {
+            Some((results, _)) => {
+                if let Ok(count) = results.try_recv() {
+                    preview.count = Some(count);
+                    preview.search = None;
+                }
+            }
+            None if preview.changed.elapsed() >= DEBOUNCE => {
+                let (sender, results) = mpsc::channel();
+                let stop = Arc::new(AtomicBool::new(false));
+                let worker_stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    let _ = sender.send(query.count(&cwd, VISIT_LIMIT, &worker_stop));
+                });
+                preview.search = Some((results, stop));
+            }
+            None => {}
+        }
+        preview.count.is_none()
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use super::TerminalApp;
+use crate::builtins::head_tail::{self, Options};
+use crate::line::{ERROR_COLOR, OUTPUT_COLOR};
+
+/// How often the followed file is checked for growth.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+enum FollowEvent {
+    Line(String),
+    /// The file shrank, e.g. a log was rotated in place; reading restarts
+    /// from the beginning.
+    Truncated,
+    /// The file can no longer be read; following stops.
+    Failed(String),
+}
+
+/// A `tail -f` streaming lines appended to a file until Ctrl+C.
+pub(super) struct Follow {
+    file: String,
+    events: Receiver<FollowEvent>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Follow {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Polls `path` from byte `pos`, sending each complete line appended to it.
+fn watch(path: PathBuf, mut pos: u64, sender: Sender<FollowEvent>, stop: Arc<AtomicBool>) {
+    let mut pending = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(POLL_INTERVAL);
+        let len = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                let _ = sender.send(FollowEvent::Failed(e.to_string()));
+                return;
+            }
+        };
+        if len < pos {
+            pos = 0;
+            pending.clear();
+            if sender.send(FollowEvent::Truncated).is_err() {
+                return;
+            }
+        }
+        if len == pos {
+            continue;
+        }
+        let read = File::open(&path).and_then(|mut file| {
+            file.seek(SeekFrom::Start(pos))?;
+            file.take(len - pos).read_to_end(&mut pending)
+        });
+        match read {
+            Ok(n) => pos += n as u64,
+            Err(e) => {
+                let _ = sender.send(FollowEvent::Failed(e.to_string()));
+                return;
+            }
+        }
+        while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let text = String::from_utf8_lossy(&line[..end]);
+            let text = text.trim_end_matches('\r').to_string();
+            if sender.send(FollowEvent::Line(text)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl TerminalApp {
+    /// `tail -f FILE`: prints the selected lines, then keeps printing lines
+    /// as they're appended. Replaces any follow already running.
+    pub(super) fn start_follow(&mut self, options: Options) {
+        let [file] = options.files.as_slice() else {
+            self.add_line("tail: -f follows exactly one file", ERROR_COLOR);
+            return;
+        };
+        let path = self.current_dir.join(file);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.add_line(&format!("tail: {file}: {e}"), ERROR_COLOR);
+                return;
+            }
+        };
+        // A half-written last line is left for the watcher to print whole.
+        let start = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let text = String::from_utf8_lossy(&bytes[..start]);
+        for line in head_tail::select(&text, &options, true) {
+            self.add_line(line, OUTPUT_COLOR);
+        }
+
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher_stop = Arc::clone(&stop);
+        thread::spawn(move || watch(path, start as u64, sender, watcher_stop));
+        self.follow = Some(Follow {
+            file: file.clone(),
+            events,
+            stop,
+        });
+    }
+
+    /// Moves newly appended lines into the scrollback. Returns true while a
+    /// file is being followed.
+    pub(super) fn poll_follow(&mut self) -> bool {
+        let Some(follow) = &self.follow else {
+            return false;
+        };
+        let mut lines = Vec::new();
+        let mut ended = false;
+        loop {
+            match follow.events.try_recv() {
+                Ok(FollowEvent::Line(text)) => lines.push((text, OUTPUT_COLOR)),
+                Ok(FollowEvent::Truncated) => {
+                    lines.push((
+                        format!("tail: {}: file truncated", follow.file),
+                        ERROR_COLOR,
+                    ));
+                }
+                Ok(FollowEvent::Failed(message)) => {
+                    lines.push((format!("tail: {}: {message}", follow.file), ERROR_COLOR));
+                    ended = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    ended = true;
+                    break;
+                }
+            }
+        }
+        if ended {
+            self.follow = None;
+        }
+        for (text, color) in lines {
+            self.add_line(&text, color);
+        }
+        self.trim_scrollback();
+        self.follow.is_some()
+    }
+
+    /// Ctrl+C: stops `tail -f` or the foreground command, and drops what's
+    /// left of a `for` loop and any lines queued behind it.
+    pub(super) fn interrupt(&mut self) {
+        self.loop_queue.clear();
+        if self.follow.take().is_some() {
+            self.add_line("^C", OUTPUT_COLOR);
+            return;
+        }
+        if let Some(command) = self.running.iter().find(|c| c.holds_prompt()) {
+            command.interrupt();
+            self.queued_input.clear();
+            self.add_line("^C", OUTPUT_COLOR);
+        } else if !self.tasks.is_empty() {
+            // The worker finishes on its own; its output is dropped.
+            self.tasks.clear();
+            self.queued_input.clear();
+            self.add_line("^C", OUTPUT_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn next(events: &Receiver<FollowEvent>) -> FollowEvent {
+        events
+            .recv_timeout(Duration::from_secs(5))
+            .expect("the watcher sends an event")
+    }
+
+    fn line(event: FollowEvent) -> String {
+        match event {
+            FollowEvent::Line(text) => text,
+            FollowEvent::Truncated => panic!("unexpected truncation"),
+            FollowEvent::Failed(message) => panic!("watcher failed: {message}"),
+        }
+    }
+
+    #[test]
+    fn streams_appended_lines_and_notices_truncation() {
+        let path = std::env::temp_dir().join(format!("follow-{}.log", std::process::id()));
+        fs::write(&path, "old\n").unwrap();
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher_stop = Arc::clone(&stop);
+        let watched = path.clone();
+        let watcher = thread::spawn(move || watch(watched, 4, sender, watcher_stop));
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        // The half line waits for its newline.
+        file.write_all(b"first\r\nsec").unwrap();
+        assert_eq!(line(next(&events)), "first");
+        file.write_all(b"ond\n").unwrap();
+        assert_eq!(line(next(&events)), "second");
+
+        fs::write(&path, "new\n").unwrap();
+        assert!(matches!(next(&events), FollowEvent::Truncated));
+        assert_eq!(line(next(&events)), "new");
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(next(&events), FollowEvent::Failed(_)));
+        watcher.join().unwrap();
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let path = std::env::temp_dir().join(format!("follow-stop-{}.log", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher_stop = Arc::clone(&stop);
+        let watched = path.clone();
+        let watcher = thread::spawn(move || watch(watched, 0, sender, watcher_stop));
+        stop.store(true, Ordering::Relaxed);
+        watcher.join().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(events.try_recv().is_err());
+    }
+}
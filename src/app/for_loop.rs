@@ -0,0 +1,56 @@
+use super::running::RunningCommand;
+use super::TerminalApp;
+use crate::line::ERROR_COLOR;
+use crate::parse;
+use crate::script::{self, ForLoop};
+
+/// A command waiting in `loop_queue`.
+pub(super) struct Queued {
+    pub(super) command: String,
+    /// Only runs if the command before it succeeded, after `&&`.
+    pub(super) after_success: bool,
+    /// Part of an alias body, whose aliases were already expanded.
+    pub(super) expanded: bool,
+}
+
+impl TerminalApp {
+    /// Queues the loop's body once per word, with the variable bound, and
+    /// starts running it.
+    pub(super) fn run_for_loop(&mut self, for_loop: ForLoop) {
+        let list = parse::expand_variables(&for_loop.list, &|name| self.variable(name));
+        let list = match parse::substitute_commands(&list, &mut |inner| self.capture_output(inner))
+        {
+            Ok(list) => list,
+            Err(message) => {
+                self.add_line(&format!("syntax error: {message}"), ERROR_COLOR);
+                return;
+            }
+        };
+        for word in parse::split_args(&list) {
+            for command in &for_loop.body {
+                self.loop_queue.push_back(Queued {
+                    command: script::bind(command, &for_loop.var, &word),
+                    after_success: false,
+                    expanded: false,
+                });
+            }
+        }
+        self.continue_loop();
+    }
+
+    /// Runs queued loop commands until one is left running in the
+    /// foreground; the rest wait for it to finish. One after `&&` is
+    /// skipped when the last command failed.
+    pub(super) fn continue_loop(&mut self) {
+        while self.tasks.is_empty() && !self.running.iter().any(RunningCommand::holds_prompt) {
+            let Some(queued) = self.loop_queue.pop_front() else {
+                return;
+            };
+            if queued.after_success && self.last_status.is_some_and(|status| status != 0) {
+                continue;
+            }
+            self.last_status = Some(0);
+            self.execute_line(&queued.command, !queued.expanded);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::dirs::tilde;
+use super::TerminalApp;
+use crate::line::{INPUT_COLOR, PATH_COLOR, TIMESTAMP_COLOR};
+use crate::theme::Theme;
+use crate::{completion, fonts, git, rprompt};
+
+/// How often HEAD is re-read, so a checkout typed at the prompt shows up
+/// without waiting for a `cd`.
+const BRANCH_REFRESH: Duration = Duration::from_secs(1);
+
+/// How often `{dirty}` re-runs `git status`, which costs more than
+/// reading HEAD.
+const DIRTY_REFRESH: Duration = Duration::from_secs(5);
+
+impl TerminalApp {
+    fn refresh_git_branch(&mut self) {
+        if !self.git_prompt_enabled || !self.config.git_prompt {
+            self.git_branch = None;
+            self.branch_checked = None;
+            return;
+        }
+        let due = match &self.branch_checked {
+            Some((dir, at)) => *dir != self.current_dir || at.elapsed() >= BRANCH_REFRESH,
+            None => true,
+        };
+        if due {
+            self.git_branch = git::current_branch(&self.current_dir);
+            self.branch_checked = Some((self.current_dir.clone(), Instant::now()));
+        }
+    }
+
+    fn refresh_git_dirty(&mut self) {
+        if self.git_branch.is_none() {
+            self.git_dirty = false;
+            return;
+        }
+        let due = match &self.dirty_checked {
+            Some((dir, at)) => *dir != self.current_dir || at.elapsed() >= DIRTY_REFRESH,
+            None => true,
+        };
+        if !due || self.dirty_check.is_some() {
+            return;
+        }
+        // `git status` can take seconds in a big repository, so it runs on
+        // its own thread and the prompt shows the last answer meanwhile.
+        let moved = self
+            .dirty_checked
+            .as_ref()
+            .is_some_and(|(dir, _)| *dir != self.current_dir);
+        if moved {
+            self.git_dirty = false;
+        }
+        let dir = self.current_dir.clone();
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let dirty = git::is_dirty(&dir);
+            let _ = sender.send((dir, dirty));
+        });
+        self.dirty_check = Some(result);
+        self.dirty_checked = Some((self.current_dir.clone(), Instant::now()));
+    }
+
+    /// Takes the answer of a `{dirty}` check once it's in. Returns true
+    /// while one is running.
+    pub(super) fn poll_git_dirty(&mut self) -> bool {
+        let Some(result) = &self.dirty_check else {
+            return false;
+        };
+        let (dir, dirty) = match result.try_recv() {
+            Ok(answer) => answer,
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => {
+                self.dirty_check = None;
+                return false;
+            }
+        };
+        self.dirty_check = None;
+        // A check started before a `cd` is about the old directory.
+        if dir == self.current_dir {
+            self.git_dirty = dirty;
+        }
+        false
+    }
+
+    /// The right prompt's text from the `rprompt` format, empty when it's
+    /// unset. Git is only asked about when the format uses it.
+    pub(super) fn rprompt_text(&mut self) -> String {
+        let format = self.config.rprompt.clone();
+        if format.is_empty() {
+            return String::new();
+        }
+        if format.contains("{branch}") || format.contains("{dirty}") {
+            self.refresh_git_branch();
+        }
+        if format.contains("{dirty}") {
+            self.refresh_git_dirty();
+        }
+        let now = chrono::Local::now();
+        rprompt::render(&format, |field| match field {
+            "time" => Some(now.format("%H:%M:%S").to_string()),
+            "date" => Some(now.format("%Y-%m-%d").to_string()),
+            "branch" => self.git_branch.clone(),
+            "dirty" => self.git_dirty.then(|| "*".to_string()),
+            "status" => self
+                .last_status
+                .filter(|&status| status != 0)
+                .map(|status| format!("[{status}]")),
+            "sep" => Some(self.separator(fonts::POWERLINE_LEFT, "<")),
+            _ => None,
+        })
+    }
+
+    /// `glyph` when the font has the Powerline arrows, else `ascii`.
+    fn separator(&self, glyph: char, ascii: &str) -> String {
+        if self.powerline_glyphs {
+            glyph.to_string()
+        } else {
+            ascii.to_string()
+        }
+    }
+
+    /// Draws the bar above the scrollback: the current directory and, inside
+    /// a git repository, the branch. Clicking the branch lists the local
+    /// branches; picking one runs `git checkout` as if it had been typed.
+    /// On the right: how many background jobs are running, and while a
+    /// `find` is being typed, its match count.
+    pub(super) fn render_header(&mut self, ctx: &egui::Context, font: &FontId, theme: Theme) {
+        self.refresh_git_branch();
+        let mut checkout = None;
+        egui::TopBottomPanel::top("header")
+            .frame(
+                egui::Frame::none()
+                    .fill(theme.background())
+                    .inner_margin(egui::Margin::symmetric(self.config.inner_margin, 6.0)),
+            )
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if self.config.powerline {
+                        checkout = ui
+                            .scope(|ui| self.powerline_segments(ui, font, theme))
+                            .inner;
+                    } else {
+                        let dir = RichText::new(tilde(&self.current_dir))
+                            .font(font.clone())
+                            .color(theme.adapt(PATH_COLOR));
+                        ui.label(dir);
+                        if let Some(branch) = self.git_branch.clone() {
+                            checkout = self.branch_menu(ui, font, theme, &branch);
+                        }
+                    }
+                    let find = self.find_preview.as_ref().and_then(|p| p.label());
+                    let jobs = match self.job_count() {
+                        0 => None,
+                        1 => Some("[1 job]".to_string()),
+                        n => Some(format!("[{n} jobs]")),
+                    };
+                    let layout = egui::Layout::right_to_left(egui::Align::Center);
+                    ui.with_layout(layout, |ui| {
+                        let recording = self.recording.is_some().then(|| "[recording]".to_string());
+                        let errors_only = self.errors_only.then(|| "[errors only]".to_string());
+                        let labels = [
+                            jobs,
+                            find.map(|count| format!("find: {count}")),
+                            recording,
+                            errors_only,
+                        ];
+                        for label in labels.into_iter().flatten() {
+                            let text = RichText::new(label)
+                                .font(font.clone())
+                                .color(theme.adapt(TIMESTAMP_COLOR));
+                            ui.label(text);
+                        }
+                    });
+                });
+            });
+        if let Some(name) = checkout {
+            self.run_line(format!("git checkout {}", completion::escape(&name)));
+            self.branch_checked = None;
+        }
+    }
+
+    /// The directory and branch as solid blocks, each closed by an arrow
+    /// in its own color; returns the branch picked, if any.
+    fn powerline_segments(
+        &mut self,
+        ui: &mut egui::Ui,
+        font: &FontId,
+        theme: Theme,
+    ) -> Option<String> {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        let separator = self.separator(fonts::POWERLINE_RIGHT, ">");
+        let dir_color = theme.adapt(PATH_COLOR);
+        let dir = RichText::new(format!(" {} ", tilde(&self.current_dir)))
+            .font(font.clone())
+            .color(theme.background())
+            .background_color(dir_color);
+        ui.label(dir);
+        let mut checkout = None;
+        let mut last = dir_color;
+        if let Some(branch) = self.git_branch.clone() {
+            let branch_color = theme.adapt(INPUT_COLOR);
+            let arrow = RichText::new(&separator)
+                .font(font.clone())
+                .color(dir_color)
+                .background_color(branch_color);
+            ui.label(arrow);
+            checkout = self.branch_menu(ui, font, theme, &branch);
+            last = branch_color;
+        }
+        ui.label(RichText::new(separator).font(font.clone()).color(last));
+        checkout
+    }
+
+    /// The branch button and its popup; returns the branch picked, if any.
+    fn branch_menu(
+        &mut self,
+        ui: &mut egui::Ui,
+        font: &FontId,
+        theme: Theme,
+        branch: &str,
+    ) -> Option<String> {
+        let mut checkout = None;
+        let button = if self.config.powerline {
+            let label = RichText::new(format!(" {branch} "))
+                .font(font.clone())
+                .color(theme.background())
+                .background_color(theme.adapt(INPUT_COLOR));
+            egui::Button::new(label).frame(false)
+        } else {
+            let label = RichText::new(branch)
+                .font(font.clone())
+                .color(theme.adapt(INPUT_COLOR));
+            egui::Button::new(label)
+        };
+        let response = ui
+            .add(button)
+            .on_hover_text("Switch branch")
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+        let popup = ui.make_persistent_id("branch_menu");
+        if response.clicked() {
+            self.branches = git::local_branches(&self.current_dir);
+            ui.memory_mut(|memory| memory.toggle_popup(popup));
+        }
+        egui::popup_below_widget(ui, popup, &response, |ui| {
+            ui.set_min_width(160.0);
+            for name in &self.branches {
+                let text = RichText::new(name).font(font.clone());
+                let picked = ui.selectable_label(name == branch, text).clicked();
+                if picked && name != branch {
+                    checkout = Some(name.clone());
+                }
+            }
+        });
+        checkout
+    }
+}
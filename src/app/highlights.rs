@@ -0,0 +1,54 @@
+use super::TerminalApp;
+use crate::line::{Segment, TerminalLine, ERROR_COLOR, OUTPUT_COLOR};
+
+impl TerminalApp {
+    /// `:highlight` lists the rules, `:highlight REGEX COLOR` adds one and
+    /// `:highlight clear` removes them all. Changes are saved right away.
+    pub(super) fn highlight(&mut self, args: &[String]) {
+        match args {
+            [] if self.highlights.rules.is_empty() => {
+                self.add_line("No highlight rules", OUTPUT_COLOR);
+                return;
+            }
+            [] => {
+                let lines: Vec<TerminalLine> = self
+                    .highlights
+                    .rules
+                    .iter()
+                    .enumerate()
+                    .map(|(i, rule)| {
+                        TerminalLine::from_segments(vec![
+                            Segment::new(
+                                format!("{:>3}  {:<10}", i + 1, rule.color_name),
+                                OUTPUT_COLOR,
+                            ),
+                            Segment::new(rule.pattern.clone(), OUTPUT_COLOR),
+                        ])
+                    })
+                    .collect();
+                self.push_lines(lines);
+                return;
+            }
+            [clear] if clear == "clear" => self.highlights.rules.clear(),
+            [pattern, color] => {
+                if let Err(message) = self.highlights.add(pattern, color) {
+                    self.add_line(&format!(":highlight: {message}"), ERROR_COLOR);
+                    return;
+                }
+            }
+            _ => {
+                self.add_line(
+                    ":highlight: usage: :highlight [REGEX COLOR | clear]",
+                    ERROR_COLOR,
+                );
+                return;
+            }
+        }
+        for line in self.lines.iter_mut().filter(|line| !line.is_input) {
+            line.highlights = self.highlights.find(&line.text());
+        }
+        if let Err(e) = self.highlights.save() {
+            self.add_line(&format!(":highlight: {e}"), ERROR_COLOR);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+
+use regex::Regex;
+
+use super::TerminalApp;
+use crate::builtins::grep;
+use crate::history::HistoryEntry;
+use crate::input;
+use crate::line::{Segment, TerminalLine, ERROR_COLOR, LINE_NUMBER_COLOR, OUTPUT_COLOR};
+use crate::paths;
+
+/// Writes a multi-line command as backslash-continued lines, which zsh
+/// reads back from its extended history file as a single entry.
+fn continue_lines(command: &str) -> String {
+    command.replace('\n', "\\\n")
+}
+
+/// The history as `:export-history` writes it: zsh's extended
+/// `: time:0;command` lines, or for bash each command as typed, multi-line
+/// ones with their real newlines. bash deletes a backslash-newline, which
+/// would join `do` to the next line, so the bash file only keeps its
+/// meaning as a script: sourced, it runs each command whole; loaded as
+/// history, each line of a multi-line command is an entry of its own.
+fn export_text(entries: &[HistoryEntry], zsh: bool) -> String {
+    let mut text = String::new();
+    for entry in entries {
+        if zsh {
+            let command = continue_lines(&entry.command);
+            text.push_str(&format!(": {}:0;{command}\n", entry.timestamp));
+        } else {
+            text.push_str(&entry.command);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+impl TerminalApp {
+    /// ArrowUp: steps back through the entries that start with the typed
+    /// line (all of them if it's empty), saving it first so ArrowDown past
+    /// the newest match can restore it.
+    pub(super) fn history_previous(&mut self) {
+        let before = match self.history_index {
+            None if self.command_history.is_empty() => return,
+            None => {
+                self.history_draft = self.input_buffer.clone();
+                self.command_history.len()
+            }
+            Some(index) => index,
+        };
+        let found =
+            self.command_history
+                .search_back(before, &self.history_draft, &self.input_buffer);
+        if found.is_some() {
+            self.show_history_entry(found);
+        }
+    }
+
+    pub(super) fn history_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        let found =
+            self.command_history
+                .search_forward(index, &self.history_draft, &self.input_buffer);
+        self.show_history_entry(found);
+    }
+
+    fn show_history_entry(&mut self, index: Option<usize>) {
+        self.history_index = index;
+        self.input_buffer = match index.and_then(|i| self.command_history.get(i)) {
+            Some(entry) => entry.command.clone(),
+            None => std::mem::take(&mut self.history_draft),
+        };
+        self.cursor_pos = input::char_count(&self.input_buffer);
+    }
+
+    /// `history [PATTERN]`: lists every entry, or those containing
+    /// `PATTERN` with the match highlighted, numbered as `!N` refers to them.
+    /// The pattern is case-insensitive unless it has an uppercase letter.
+    pub(super) fn list_history(&mut self, pattern: &str) {
+        let smart_case = if pattern.chars().any(char::is_uppercase) {
+            ""
+        } else {
+            "(?i)"
+        };
+        let filter = if pattern.is_empty() {
+            None
+        } else {
+            Regex::new(&format!("{smart_case}{}", regex::escape(pattern))).ok()
+        };
+        let width = self.command_history.len().to_string().len();
+        let lines: Vec<TerminalLine> = self
+            .command_history
+            .entries()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let command = match &filter {
+                    Some(re) if !re.is_match(&entry.command) => return None,
+                    Some(re) => grep::highlight(&entry.command, re),
+                    None => vec![Segment::new(&entry.command, OUTPUT_COLOR)],
+                };
+                let number = Segment::new(format!("{:>width$}  ", index + 1), LINE_NUMBER_COLOR);
+                Some(TerminalLine::from_segments(
+                    std::iter::once(number).chain(command).collect(),
+                ))
+            })
+            .collect();
+        if lines.is_empty() && filter.is_some() {
+            self.add_line(
+                &format!("history: no entries match '{pattern}'"),
+                OUTPUT_COLOR,
+            );
+        }
+        self.push_lines(lines);
+    }
+
+    /// `:export-history [--zsh] FILE` writes the history as plain lines
+    /// for bash, or in zsh's `: time:0;command` extended format.
+    pub(super) fn export_history(&mut self, args: &[String]) {
+        let zsh = args.iter().any(|a| a == "--zsh" || a == "-t");
+        let Some(target) = args.iter().find(|a| !a.starts_with('-')) else {
+            self.add_line(
+                ":export-history: usage: :export-history [--zsh] <file>",
+                ERROR_COLOR,
+            );
+            return;
+        };
+
+        let text = export_text(self.command_history.entries(), zsh);
+        let path = paths::resolve(&self.current_dir, target);
+        match fs::write(&path, text) {
+            Ok(()) => self.add_line(
+                &format!(
+                    "Exported {} commands to {}",
+                    self.command_history.len(),
+                    path.display()
+                ),
+                OUTPUT_COLOR,
+            ),
+            Err(e) => self.add_line(
+                &format!(":export-history: {}: {e}", path.display()),
+                ERROR_COLOR,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entries read back from a zsh extended history file, where a line
+    /// ending in a backslash continues on the next.
+    fn read_zsh(text: &str) -> Vec<(u64, String)> {
+        let mut entries = Vec::new();
+        let mut pending: Option<(u64, String)> = None;
+        for line in text.lines() {
+            let (line, continues) = match line.strip_suffix('\\') {
+                Some(line) => (line, true),
+                None => (line, false),
+            };
+            let (time, command) = match pending.take() {
+                Some((time, command)) => (time, format!("{command}\n{line}")),
+                None => {
+                    let (time, command) = line[2..].split_once(":0;").unwrap();
+                    (time.parse().unwrap(), command.to_string())
+                }
+            };
+            if continues {
+                pending = Some((time, command));
+            } else {
+                entries.push((time, command));
+            }
+        }
+        entries
+    }
+
+    #[test]
+    fn zsh_export_round_trips_multi_line_commands() {
+        let entry = |timestamp, command: &str| HistoryEntry {
+            command: command.to_string(),
+            timestamp,
+        };
+        let entries = [
+            entry(5, "ls -la"),
+            entry(9, "for f in *; do\necho $f\ndone"),
+        ];
+        let text = export_text(&entries, true);
+        assert_eq!(
+            text,
+            ": 5:0;ls -la\n: 9:0;for f in *; do\\\necho $f\\\ndone\n"
+        );
+        assert_eq!(
+            read_zsh(&text),
+            [
+                (5, "ls -la".to_string()),
+                (9, "for f in *; do\necho $f\ndone".to_string())
+            ]
+        );
+        assert_eq!(export_text(&entries[..1], false), "ls -la\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bash_export_keeps_multi_line_commands_valid() {
+        let entries = [
+            HistoryEntry {
+                command: "for f in a b; do\necho $f\ndone".to_string(),
+                timestamp: 9,
+            },
+            HistoryEntry {
+                command: "echo end".to_string(),
+                timestamp: 10,
+            },
+        ];
+        let text = export_text(&entries, false);
+        assert_eq!(text, "for f in a b; do\necho $f\ndone\necho end\n");
+        let Ok(output) = std::process::Command::new("bash")
+            .args(["-c", &text])
+            .output()
+        else {
+            return;
+        };
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a\nb\nend\n");
+        let checked = std::process::Command::new("bash")
+            .args(["-n", "-c", &text])
+            .status()
+            .unwrap();
+        assert!(checked.success());
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+
+use super::TerminalApp;
+use crate::hooks::{self, Event, Trap};
+use crate::line::{ERROR_COLOR, OUTPUT_COLOR};
+
+impl TerminalApp {
+    pub(super) fn trap(&mut self, args: &[String]) {
+        match hooks::parse_trap(args) {
+            Ok(Trap::List) => {
+                let entries: Vec<String> = self
+                    .hooks
+                    .entries()
+                    .iter()
+                    .map(|(event, command)| format!("trap '{command}' {}", event.name()))
+                    .collect();
+                for entry in entries {
+                    self.add_line(&entry, OUTPUT_COLOR);
+                }
+            }
+            Ok(Trap::ListEvents) => {
+                let names: Vec<&str> = Event::ALL.iter().map(|e| e.name()).collect();
+                self.add_line(&names.join(" "), OUTPUT_COLOR);
+            }
+            Ok(Trap::Add(command, events)) => {
+                for event in events {
+                    self.hooks.add(event, &command);
+                }
+            }
+            Ok(Trap::Clear(events)) => {
+                for event in events {
+                    self.hooks.clear(event);
+                }
+            }
+            Err(message) => self.add_line(&format!("trap: {message}"), ERROR_COLOR),
+        }
+    }
+
+    /// Runs the hooks for `event`. Hooks don't trigger hooks, and one that
+    /// fails is dropped so it can't fail on every prompt: a built-in when it
+    /// returns a non-zero status or reports an error, a program when it
+    /// exits non-zero.
+    pub(super) fn run_hooks(&mut self, event: Event) {
+        if self.in_hook {
+            return;
+        }
+        self.in_hook = true;
+        // The status `$?` shows is the last prompt command's, not a hook's.
+        let status = self.last_status.take();
+        for command in self.hooks.commands(event) {
+            let before = self.lines.len();
+            self.running_hook = Some((event, command.clone()));
+            self.execute_command(&command);
+            self.running_hook = None;
+            let reported = self.lines.get(before..).is_some_and(|added| {
+                added.iter().any(|line| {
+                    line.segments
+                        .first()
+                        .is_some_and(|s| s.color == ERROR_COLOR)
+                })
+            });
+            let status = self.last_status.take();
+            if reported || status.is_some_and(|code| code != 0) {
+                self.drop_failed_hook(event, &command);
+            }
+        }
+        self.last_status = status;
+        self.in_hook = false;
+    }
+
+    /// Removes a hook that failed, saying so.
+    pub(super) fn drop_failed_hook(&mut self, event: Event, command: &str) {
+        self.hooks.remove(event, command);
+        self.add_line(
+            &format!(
+                "trap: removed {} hook '{command}' after it failed",
+                event.name()
+            ),
+            ERROR_COLOR,
+        );
+    }
+
+    /// Runs each line of the profile's rc file, skipping blanks and `#`
+    /// comments, then the `start` hooks it registered.
+    pub(super) fn run_rc(&mut self) {
+        let path = self.profile.config_dir().map(|dir| dir.join("rc"));
+        if let Some(text) = path.and_then(|path| fs::read_to_string(path).ok()) {
+            self.in_hook = true;
+            for line in text.lines().map(str::trim) {
+                if !line.is_empty() && !line.starts_with('#') {
+                    self.execute_command(line);
+                }
+            }
+            self.in_hook = false;
+        }
+        self.run_hooks(Event::Start);
+        self.run_hooks(Event::Precmd);
+    }
+}
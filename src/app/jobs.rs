@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use super::running::{self, RunningCommand};
+use super::TerminalApp;
+use crate::config::JobPolicy;
+use crate::line::{ERROR_COLOR, OUTPUT_COLOR};
+use crate::timing::format_duration;
+
+/// A background job waiting for a free slot under `max-jobs`.
+pub(super) struct QueuedJob {
+    command: String,
+    args: Vec<String>,
+    stdin: Option<Vec<u8>>,
+    cwd: PathBuf,
+    /// `NAME=VALUE` prefixes from the command line.
+    env: Vec<(String, String)>,
+}
+
+/// `Done`, or `Exit N` / `Killed by signal N` for an unsuccessful job, as
+/// `jobs` and completion notices show it.
+fn describe(command: &RunningCommand) -> String {
+    match command.status {
+        None => "Running".to_string(),
+        Some(Some(status)) if status.success() => "Done".to_string(),
+        Some(Some(status)) => match status.code() {
+            Some(code) => format!("Exit {code}"),
+            None => signal_description(status),
+        },
+        Some(None) => "Lost".to_string(),
+    }
+}
+
+/// The status a shell would put in `$?`: 128 plus the signal for a
+/// killed command.
+pub(super) fn exit_code(status: std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+#[cfg(unix)]
+fn signal_description(status: std::process::ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().map_or("Terminated".to_string(), |signal| {
+        format!("Killed by signal {signal}")
+    })
+}
+
+#[cfg(not(unix))]
+fn signal_description(_status: std::process::ExitStatus) -> String {
+    "Terminated".to_string()
+}
+
+/// Shows a desktop notification without blocking the UI on the
+/// notification service.
+fn notify(summary: String, body: String) {
+    thread::spawn(move || {
+        let _ = notify_rust::Notification::new()
+            .summary(&summary)
+            .body(&body)
+            .appname("ai_terminal")
+            .show();
+    });
+}
+
+impl TerminalApp {
+    /// The lowest job number not in use, as shells assign them.
+    pub(super) fn next_job_number(&self) -> usize {
+        (1..)
+            .find(|n| !self.running.iter().any(|c| c.job == Some(*n)))
+            .expect("job numbers are unbounded")
+    }
+
+    /// Background jobs still running.
+    pub(super) fn job_count(&self) -> usize {
+        self.running.iter().filter(|c| c.job.is_some()).count()
+    }
+
+    fn job_slot_free(&self) -> bool {
+        self.config.max_jobs == 0 || self.job_count() < self.config.max_jobs
+    }
+
+    /// Starts `args` as a numbered background job, or queues or refuses it
+    /// when `max-jobs` are already running.
+    pub(super) fn start_job(&mut self, command: String, args: Vec<String>, stdin: Option<Vec<u8>>) {
+        let job = QueuedJob {
+            command,
+            args,
+            stdin,
+            cwd: self.current_dir.clone(),
+            env: self.child_env(),
+        };
+        if self.job_slot_free() {
+            self.spawn_job(job, self.command_line, self.in_hook);
+            return;
+        }
+        match self.config.max_jobs_policy {
+            JobPolicy::Reject => {
+                let limit = self.config.max_jobs;
+                self.add_line(
+                    &format!("jobs: {limit} jobs already running (max-jobs); not started"),
+                    ERROR_COLOR,
+                );
+            }
+            JobPolicy::Queue => {
+                self.add_line(&format!("[queued] {}", job.command), OUTPUT_COLOR);
+                self.queued_jobs.push_back(job);
+            }
+        }
+    }
+
+    /// `origin` and `from_hook` as for `RunningCommand`; a job started from
+    /// the queue has neither.
+    fn spawn_job(&mut self, job: QueuedJob, origin: Option<usize>, from_hook: bool) {
+        let mode = self.config.exec_mode;
+        let spawned = running::spawn(
+            &job.command,
+            &job.args,
+            job.stdin,
+            false,
+            &job.cwd,
+            mode,
+            &job.env,
+        );
+        match spawned {
+            Ok(mut command) => {
+                let number = self.next_job_number();
+                self.add_line(&format!("[{number}] {}", command.pid), OUTPUT_COLOR);
+                command.job = Some(number);
+                command.origin = origin;
+                command.from_hook = from_hook;
+                self.running.push(command);
+            }
+            Err(e) => self.report_spawn_error(&job.args[0], &e),
+        }
+    }
+
+    /// Starts queued jobs while there's room for them.
+    pub(super) fn start_queued_jobs(&mut self) {
+        while self.job_slot_free() {
+            let Some(job) = self.queued_jobs.pop_front() else {
+                return;
+            };
+            self.spawn_job(job, None, false);
+        }
+    }
+
+    /// `exit`, Ctrl+D or closing the window. With jobs still running, asks
+    /// first and returns false; the answer comes to `answer_exit`.
+    pub(super) fn confirm_exit(&mut self) -> bool {
+        let jobs = self.job_count();
+        if jobs == 0 {
+            return true;
+        }
+        self.pending_exit = true;
+        let plural = if jobs == 1 { "" } else { "s" };
+        self.add_line(
+            &format!("You have {jobs} running job{plural}. Exit anyway? (y/n)"),
+            ERROR_COLOR,
+        );
+        false
+    }
+
+    /// The key typed after `confirm_exit` asked: `y` hangs up the jobs and
+    /// exits, anything else stays.
+    pub(super) fn answer_exit(&mut self, yes: bool) {
+        self.pending_exit = false;
+        if yes {
+            self.hang_up_jobs();
+            self.exit_requested = true;
+        } else {
+            self.add_line("exit cancelled", OUTPUT_COLOR);
+        }
+    }
+
+    /// Sends SIGHUP to the background jobs, as a shell does when it exits;
+    /// jobs started with `nohup` ignore it and keep running.
+    #[cfg(unix)]
+    fn hang_up_jobs(&self) {
+        for command in self.running.iter().filter(|c| c.job.is_some()) {
+            // SAFETY: kill(2) has no memory-safety preconditions.
+            unsafe { libc::kill(command.pid as libc::pid_t, libc::SIGHUP) };
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn hang_up_jobs(&self) {}
+
+    pub(super) fn list_jobs(&mut self) {
+        let jobs: Vec<String> = self
+            .running
+            .iter()
+            .filter_map(|command| {
+                let job = command.job?;
+                Some(format!(
+                    "[{job}] {:<8} {}  {}",
+                    describe(command),
+                    command.pid,
+                    command.command
+                ))
+            })
+            .collect();
+        let queued = self
+            .queued_jobs
+            .iter()
+            .map(|job| format!("[-] {:<8} -  {}", "Queued", job.command));
+        let jobs: Vec<String> = jobs.into_iter().chain(queued).collect();
+        for job in jobs {
+            self.add_line(&job, OUTPUT_COLOR);
+        }
+    }
+
+    /// Reports a background job that has exited, with a desktop
+    /// notification if it ran long enough that the user may have looked
+    /// away.
+    pub(super) fn command_finished(&mut self, command: &RunningCommand) {
+        let Some(job) = command.job else {
+            return;
+        };
+        let elapsed = command.started.elapsed();
+        let status = describe(command);
+        let color = if status == "Done" {
+            OUTPUT_COLOR
+        } else {
+            ERROR_COLOR
+        };
+        self.add_line(&format!("[{job}] {status}  {}", command.command), color);
+        let threshold = Duration::from_secs(self.config.notify_after);
+        if self.config.notify && elapsed >= threshold {
+            notify(
+                format!("{status}: {}", command.command),
+                format!("Job [{job}] finished after {}", format_duration(elapsed)),
+            );
+        }
+    }
+}
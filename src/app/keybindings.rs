@@ -0,0 +1,48 @@
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::TerminalApp;
+use crate::help;
+use crate::line::{HEADING_COLOR, INPUT_COLOR};
+use crate::theme::Theme;
+
+impl TerminalApp {
+    /// The `:keybindings` window: the tables from `help keybindings`, over
+    /// the scrollback until Esc or its close button.
+    pub(super) fn render_keybindings(&mut self, ctx: &egui::Context, font: &FontId, theme: Theme) {
+        let mut open = self.show_keybindings;
+        egui::Window::new("Keybindings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                for (i, section) in help::sections("keybindings").iter().enumerate() {
+                    if i > 0 {
+                        ui.add_space(8.0);
+                    }
+                    let heading = RichText::new(section.heading)
+                        .font(font.clone())
+                        .color(theme.adapt(HEADING_COLOR));
+                    ui.label(heading);
+                    egui::Grid::new(section.heading)
+                        .num_columns(2)
+                        .spacing([16.0, 2.0])
+                        .show(ui, |ui| {
+                            for (key, action) in section.entries {
+                                let key = RichText::new(*key)
+                                    .font(font.clone())
+                                    .color(theme.adapt(INPUT_COLOR));
+                                ui.label(key);
+                                let action = RichText::new(*action)
+                                    .font(font.clone())
+                                    .color(theme.foreground());
+                                ui.label(action);
+                                ui.end_row();
+                            }
+                        });
+                }
+            });
+        self.show_keybindings = open;
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{FontId, RichText, Stroke};
+
+use super::TerminalApp;
+use crate::builtins::man::{self, Line, Style};
+use crate::line::{
+    ERROR_COLOR, HEADING_COLOR, MATCH_BACKGROUND, OUTPUT_COLOR, PATH_COLOR, TIMESTAMP_COLOR,
+};
+use crate::theme::Theme;
+
+/// How much larger than the text a section heading is drawn.
+const HEADING_SCALE: f32 = 1.2;
+
+/// A man page shown by `man`, in place of the scrollback until Escape.
+pub(super) struct ManView {
+    title: String,
+    /// The page, once `man` has formatted it on its worker thread.
+    page: Option<Vec<Line>>,
+    result: Option<Receiver<Result<Vec<Line>, String>>>,
+    /// Text to look for, typed into the view's find box.
+    find: String,
+    /// The line the last search landed on.
+    found: Option<usize>,
+    /// Scroll to `found` on the next frame.
+    jump: bool,
+}
+
+impl ManView {
+    /// The next line after `found` containing `find`, ignoring case,
+    /// wrapping around to the top.
+    fn next_match(&self) -> Option<usize> {
+        let page = self.page.as_ref()?;
+        let find = self.find.to_lowercase();
+        if find.is_empty() {
+            return None;
+        }
+        let start = self.found.map_or(0, |found| found + 1);
+        (0..page.len())
+            .map(|i| (start + i) % page.len())
+            .find(|&i| page[i].text().to_lowercase().contains(&find))
+    }
+}
+
+/// `line` as a layout: headings larger, bold in the heading color and
+/// underlined text underlined.
+fn layout(line: &Line, font: &FontId, theme: Theme, matched: bool) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let background = if matched {
+        theme.adapt_background(MATCH_BACKGROUND)
+    } else {
+        egui::Color32::TRANSPARENT
+    };
+    for (style, text) in &line.runs {
+        let mut format = TextFormat {
+            font_id: font.clone(),
+            color: theme.adapt(OUTPUT_COLOR),
+            background,
+            ..Default::default()
+        };
+        match style {
+            _ if line.heading => {
+                format.font_id.size *= HEADING_SCALE;
+                format.color = theme.adapt(HEADING_COLOR);
+            }
+            Style::Plain => {}
+            Style::Bold => format.color = theme.adapt(HEADING_COLOR),
+            Style::Underline => {
+                format.color = theme.adapt(PATH_COLOR);
+                format.underline = Stroke::new(1.0, format.color);
+            }
+        }
+        job.append(text, 0.0, format);
+    }
+    if job.text.is_empty() {
+        // An empty job has no height; keep blank lines as tall as text.
+        job.append(
+            " ",
+            0.0,
+            TextFormat {
+                font_id: font.clone(),
+                ..Default::default()
+            },
+        );
+    }
+    job
+}
+
+impl TerminalApp {
+    /// `man PAGE`: formats the page on a worker thread and opens it in its
+    /// own view.
+    pub(super) fn show_man_page(&mut self, args: &[String]) {
+        // Stay within what man lays out well.
+        let columns = self.terminal_size.0.clamp(40, 120);
+        let (sender, result) = mpsc::channel();
+        let request = args.to_vec();
+        thread::spawn(move || {
+            let _ = sender.send(man::fetch(&request, columns));
+        });
+        self.man = Some(ManView {
+            title: format!("man {}", args.join(" ")),
+            page: None,
+            result: Some(result),
+            find: String::new(),
+            found: None,
+            jump: false,
+        });
+    }
+
+    /// Takes the page once it's formatted. Returns true while waiting.
+    pub(super) fn poll_man(&mut self) -> bool {
+        let Some(view) = &mut self.man else {
+            return false;
+        };
+        let Some(result) = &view.result else {
+            return false;
+        };
+        let page = match result.try_recv() {
+            Ok(page) => page,
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => Err("man: formatting the page failed".to_string()),
+        };
+        view.result = None;
+        match page {
+            Ok(page) => view.page = Some(page),
+            Err(message) => {
+                self.man = None;
+                self.add_line(&message, ERROR_COLOR);
+                self.last_status = Some(1);
+            }
+        }
+        false
+    }
+
+    /// Draws the man page in place of the scrollback. Returns false when
+    /// there's none open.
+    pub(super) fn render_man(&mut self, ctx: &egui::Context, font: &FontId, theme: Theme) -> bool {
+        let line_spacing = self.config.line_spacing;
+        let Some(view) = &mut self.man else {
+            return false;
+        };
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame::none()
+                    .fill(theme.background())
+                    .inner_margin(self.config.inner_margin),
+            )
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(&view.title)
+                            .font(font.clone())
+                            .color(theme.adapt(HEADING_COLOR)),
+                    );
+                    let find = ui.add(
+                        egui::TextEdit::singleline(&mut view.find)
+                            .font(font.clone())
+                            .hint_text("find"),
+                    );
+                    if find.changed() {
+                        view.found = None;
+                    }
+                    let entered =
+                        find.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if find.changed() || entered {
+                        view.found = view.next_match();
+                        view.jump = true;
+                    }
+                    if !find.has_focus() {
+                        find.request_focus();
+                    }
+                    ui.label(
+                        RichText::new("Enter finds the next match · Escape closes")
+                            .font(font.clone())
+                            .color(theme.adapt(TIMESTAMP_COLOR)),
+                    );
+                });
+                ui.separator();
+                let Some(page) = &view.page else {
+                    ui.label(
+                        RichText::new("Formatting the page…")
+                            .font(font.clone())
+                            .color(theme.adapt(TIMESTAMP_COLOR)),
+                    );
+                    return;
+                };
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.spacing_mut().item_spacing.y = line_spacing;
+                        for (i, line) in page.iter().enumerate() {
+                            let matched = view.found == Some(i);
+                            let response = ui.add(
+                                egui::Label::new(layout(line, font, theme, matched)).wrap(false),
+                            );
+                            if matched && view.jump {
+                                response.scroll_to_me(Some(egui::Align::Center));
+                            }
+                        }
+                    });
+                view.jump = false;
+            });
+        true
+    }
+}
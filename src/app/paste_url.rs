@@ -0,0 +1,72 @@
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::TerminalApp;
+use crate::line::{ERROR_COLOR, TIMESTAMP_COLOR};
+use crate::theme::Theme;
+use crate::{input, links, shell};
+
+impl TerminalApp {
+    /// Puts the pasted URL at the cursor, as the paste would have.
+    pub(super) fn insert_pasted_url(&mut self) {
+        if let Some(url) = self.pasted_url.take() {
+            input::insert_str(&mut self.input_buffer, &mut self.cursor_pos, &url);
+        }
+    }
+
+    /// Below the input while a pasted URL waits: open it, download it into
+    /// the current directory with curl, or type it after all.
+    pub(super) fn render_url_actions(&mut self, ui: &mut egui::Ui, font: &FontId, theme: Theme) {
+        let Some(url) = self.pasted_url.clone() else {
+            return;
+        };
+        let busy = self.busy();
+        let mut chosen = None;
+        ui.horizontal(|ui| {
+            let label = RichText::new(format!("Pasted {url}"))
+                .font(font.clone())
+                .color(theme.adapt(TIMESTAMP_COLOR));
+            ui.add(egui::Label::new(label).truncate(true));
+            for (action, text) in [
+                (Action::Open, "Open in browser"),
+                (Action::Download, "Download here"),
+                (Action::Insert, "Insert as text (Enter)"),
+            ] {
+                // The download runs as a command, so it waits its turn.
+                let enabled = action != Action::Download || !busy;
+                if ui
+                    .add_enabled(enabled, egui::Button::new(text).small())
+                    .clicked()
+                {
+                    chosen = Some(action);
+                }
+            }
+        });
+        match chosen {
+            Some(Action::Open) => {
+                self.pasted_url = None;
+                if let Err(message) = links::open_default(&url) {
+                    self.add_line(&format!("open: {message}"), ERROR_COLOR);
+                }
+            }
+            Some(Action::Download) => {
+                self.pasted_url = None;
+                let name = links::download_name(&url);
+                self.run_line(format!(
+                    "curl -fL -o {} {}",
+                    shell::quote(&name),
+                    shell::quote(&url)
+                ));
+            }
+            Some(Action::Insert) => self.insert_pasted_url(),
+            None => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Action {
+    Open,
+    Download,
+    Insert,
+}
@@ -0,0 +1,76 @@
+use eframe::egui;
+use egui::FontId;
+
+use super::TerminalApp;
+use crate::line::ERROR_COLOR;
+use crate::theme::Theme;
+
+/// Lines kept pinned at once; pinning another drops the oldest.
+const MAX_PINS: usize = 5;
+
+impl TerminalApp {
+    /// `:pin` copies the highlighted line (from F8, `:search` or
+    /// Ctrl+Up/Down), else the last line of the previous command's output,
+    /// into the panel above the scrollback.
+    pub(super) fn pin(&mut self) {
+        let before = self.command_line.unwrap_or(self.lines.len());
+        let target = self.highlighted_line.filter(|&i| i < before).or_else(|| {
+            self.lines[..before]
+                .iter()
+                .rposition(|line| !line.is_input && !line.text().trim().is_empty())
+        });
+        let Some(index) = target else {
+            self.add_line(":pin: no output to pin", ERROR_COLOR);
+            return;
+        };
+        let mut line = self.lines[index].clone();
+        line.collapsed = false;
+        line.timing = None;
+        if self.pinned.len() == MAX_PINS {
+            self.pinned.remove(0);
+        }
+        self.pinned.push(line);
+    }
+
+    /// `:unpin [N]` removes pinned line N, or all of them.
+    pub(super) fn unpin(&mut self, args: &[String]) {
+        match args.first() {
+            None => self.pinned.clear(),
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(n) if (1..=self.pinned.len()).contains(&n) => {
+                    self.pinned.remove(n - 1);
+                }
+                _ => self.add_line(&format!(":unpin: no pinned line '{arg}'"), ERROR_COLOR),
+            },
+        }
+    }
+
+    /// The pinned lines, above the scrollback, each with a button that
+    /// unpins it.
+    pub(super) fn render_pins(&mut self, ctx: &egui::Context, font: &FontId, theme: Theme) {
+        if self.pinned.is_empty() {
+            return;
+        }
+        let mut unpinned = None;
+        egui::TopBottomPanel::top("pinned")
+            .frame(
+                egui::Frame::none()
+                    .fill(ctx.style().visuals.faint_bg_color)
+                    .inner_margin(egui::Margin::symmetric(self.config.inner_margin, 4.0)),
+            )
+            .show(ctx, |ui| {
+                for (i, line) in self.pinned.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("×").on_hover_text("Unpin").clicked() {
+                            unpinned = Some(i);
+                        }
+                        let job = line.layout(font, theme, self.config.color);
+                        ui.add(egui::Label::new(job).truncate(true));
+                    });
+                }
+            });
+        if let Some(i) = unpinned {
+            self.pinned.remove(i);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::Write;
+
+use eframe::egui;
+use egui::FontId;
+
+use super::running::PtySession;
+use super::TerminalApp;
+use crate::theme::Theme;
+use crate::vt;
+
+/// The screen size before the first frame has measured the view, as when
+/// the rc file starts a program.
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+impl TerminalApp {
+    /// Runs `args` on a pty, its screen drawn in place of the prompt until
+    /// it exits.
+    #[cfg(unix)]
+    pub(super) fn start_pty(&mut self, command: &str, args: &[String]) {
+        let size = match self.terminal_size {
+            (0, _) | (_, 0) => DEFAULT_SIZE,
+            size => size,
+        };
+        let spawned = super::running::spawn_pty(
+            command,
+            args,
+            &self.current_dir,
+            self.config.exec_mode,
+            &self.child_env(),
+            size,
+        );
+        match spawned {
+            Ok(mut command) => {
+                command.origin = self.command_line;
+                command.from_hook = self.in_hook;
+                command.hook = self.running_hook.clone();
+                self.running.push(command);
+            }
+            Err(e) => self.report_spawn_error(&args[0], &e),
+        }
+    }
+
+    /// Draws what a pty command sent on its screen. Lines it scrolls off
+    /// the top go to the scrollback.
+    pub(super) fn show_pty_output(&mut self, session: &mut PtySession, bytes: &[u8]) {
+        let scrolled = session.screen.feed(bytes);
+        self.push_lines(scrolled.iter().map(|row| vt::to_line(row)));
+        if !session.screen.replies.is_empty() {
+            let replies = std::mem::take(&mut session.screen.replies);
+            let _ = session.master.write_all(&replies);
+        }
+    }
+
+    /// The foreground command's pty, if it runs on one.
+    fn foreground_pty(&mut self) -> Option<&mut PtySession> {
+        self.running
+            .iter_mut()
+            .find(|c| c.holds_prompt())
+            .and_then(|c| c.pty.as_mut())
+    }
+
+    /// Sends input to the foreground pty command, as a terminal would.
+    /// Returns false, leaving the event alone, when there's none.
+    pub(super) fn forward_to_pty(&mut self, event: &egui::Event) -> bool {
+        let Some(session) = self.foreground_pty() else {
+            return false;
+        };
+        let bytes = match event {
+            egui::Event::Text(text) | egui::Event::Paste(text) => Some(text.as_bytes().to_vec()),
+            // Ctrl+C and Ctrl+X arrive as clipboard commands.
+            egui::Event::Copy => Some(vec![0x03]),
+            egui::Event::Cut => Some(vec![0x18]),
+            egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } => vt::encode_key(*key, *modifiers, session.screen.app_cursor),
+            _ => None,
+        };
+        if let Some(bytes) = bytes {
+            let _ = session.master.write_all(&bytes);
+        }
+        true
+    }
+
+    /// Follows a change in the view's size on every pty screen.
+    pub(super) fn resize_ptys(&mut self, cols: usize, rows: usize) {
+        let mut scrolled = Vec::new();
+        for command in &mut self.running {
+            if let Some(session) = &mut command.pty {
+                #[cfg(unix)]
+                crate::pty::resize(&session.master, cols, rows);
+                scrolled.extend(session.screen.resize(cols, rows));
+            }
+        }
+        self.push_lines(scrolled.iter().map(|row| vt::to_line(row)));
+    }
+
+    /// Draws the foreground pty command's screen. Returns false when there
+    /// isn't one, so the prompt is drawn instead.
+    pub(super) fn render_pty(&mut self, ui: &mut egui::Ui, font: &FontId, theme: Theme) -> bool {
+        let color = self.config.color;
+        let Some(session) = self.foreground_pty() else {
+            return false;
+        };
+        for line in session.screen.lines() {
+            let job = line.layout(font, theme, color);
+            ui.add(egui::Label::new(job).wrap(false));
+        }
+        true
+    }
+}
@@ -0,0 +1,118 @@
+use eframe::egui;
+use egui::{Key, Modifiers};
+
+use super::TerminalApp;
+use crate::line::{ERROR_COLOR, OUTPUT_COLOR};
+use crate::macros::{self, Step};
+
+impl TerminalApp {
+    /// Ctrl+Shift+M: starts recording keys and typing at the prompt, or
+    /// stops and keeps what was recorded for the macro key.
+    pub(super) fn toggle_recording(&mut self) {
+        let Some(steps) = self.recording.take() else {
+            self.recording = Some(Vec::new());
+            return;
+        };
+        if steps.is_empty() {
+            self.add_line("Macro recording stopped; nothing recorded", OUTPUT_COLOR);
+            return;
+        }
+        let message = format!(
+            "Recorded a macro of {} keys; {} replays it, :macro save NAME keeps it",
+            steps.len(),
+            self.config.macro_key
+        );
+        self.add_line(&message, OUTPUT_COLOR);
+        self.last_macro = steps;
+    }
+
+    pub(super) fn record(&mut self, step: Step) {
+        if let Some(steps) = &mut self.recording {
+            steps.push(step);
+        }
+    }
+
+    pub(super) fn is_macro_key(&self, key: Key, modifiers: Modifiers) -> bool {
+        modifiers.is_none() && Key::from_name(&self.config.macro_key) == Some(key)
+    }
+
+    /// Runs `steps` as if they were being typed. A macro that replays a
+    /// macro would never end, so that's refused.
+    pub(super) fn replay(&mut self, steps: &[Step]) {
+        if self.replaying {
+            self.add_line("macro: a macro can't replay a macro", ERROR_COLOR);
+            return;
+        }
+        self.replaying = true;
+        for step in steps {
+            match step {
+                Step::Key(key, modifiers) => self.handle_key(*key, *modifiers),
+                Step::Text(text) => self.type_text(text),
+            }
+        }
+        self.replaying = false;
+    }
+
+    /// `:macro` lists the saved macros; `:macro save NAME` keeps the last
+    /// recording under a name, `:macro run NAME` replays one and
+    /// `:macro rm NAME` forgets it.
+    pub(super) fn macro_command(&mut self, args: &[String]) {
+        match args {
+            [] if self.macros.named.is_empty() => {
+                self.add_line("No saved macros", OUTPUT_COLOR);
+                return;
+            }
+            [] => {
+                let lines: Vec<String> = self
+                    .macros
+                    .named
+                    .iter()
+                    .map(|(name, steps)| {
+                        let steps: Vec<String> = steps.iter().map(macros::encode).collect();
+                        format!("{name:<12} {}", steps.join(" "))
+                    })
+                    .collect();
+                for line in lines {
+                    self.add_line(&line, OUTPUT_COLOR);
+                }
+                return;
+            }
+            [save, name] if save == "save" => {
+                // Tabs and newlines separate the fields and lines of the file.
+                if name.contains(['\t', '\n']) {
+                    self.add_line(":macro: names can't contain tabs or newlines", ERROR_COLOR);
+                    return;
+                }
+                if self.last_macro.is_empty() {
+                    self.add_line(
+                        ":macro: nothing recorded yet (Ctrl+Shift+M records)",
+                        ERROR_COLOR,
+                    );
+                    return;
+                }
+                let steps = self.last_macro.clone();
+                self.macros.named.insert(name.clone(), steps);
+            }
+            [run, name] if run == "run" => {
+                match self.macros.named.get(name).cloned() {
+                    Some(steps) => self.replay(&steps),
+                    None => self.add_line(&format!(":macro: no macro '{name}'"), ERROR_COLOR),
+                }
+                return;
+            }
+            [rm, name] if rm == "rm" => {
+                if self.macros.named.remove(name).is_none() {
+                    self.add_line(&format!(":macro: no macro '{name}'"), ERROR_COLOR);
+                    return;
+                }
+            }
+            _ => {
+                self.add_line(":macro: usage: :macro [save|run|rm NAME]", ERROR_COLOR);
+                return;
+            }
+        }
+        if let Err(e) = self.macros.save() {
+            self.add_line(&format!(":macro: {e}"), ERROR_COLOR);
+        }
+    }
+}
@@ -0,0 +1,572 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Instant;
+
+use eframe::egui::Color32;
+
+use super::TerminalApp;
+use crate::config::BusyInput;
+use crate::hooks::Event;
+use crate::line::{TerminalLine, ERROR_COLOR, INPUT_COLOR, OUTPUT_COLOR, TIMESTAMP_COLOR};
+use crate::parse;
+use crate::shell::{self, ExecMode};
+use crate::snapshot::Snapshot;
+use crate::stream::{LineAssembler, LineEvent};
+use crate::vt::{self, Screen};
+
+/// Raw stdout kept for `:hexdump`; the rest is counted but dropped.
+const RAW_LIMIT: usize = 1 << 20;
+
+enum StreamEvent {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    /// One of the two output pipes reached end of file.
+    Closed,
+    Exited(Option<ExitStatus>),
+}
+
+/// One output pipe of a running command and the scrollback line its
+/// unfinished last line is being drawn on.
+#[derive(Default)]
+struct Stream {
+    assembler: LineAssembler,
+    open_line: Option<usize>,
+    /// The bytes as received, up to `RAW_LIMIT`. Only kept for stdout.
+    raw: Vec<u8>,
+}
+
+/// A command run on a pty: the screen it draws, and the master side its
+/// keystrokes are written to.
+pub(super) struct PtySession {
+    pub(super) screen: Screen,
+    pub(super) master: File,
+}
+
+/// An external command whose output is streamed into the scrollback as it
+/// arrives, rather than collected after it exits.
+pub(super) struct RunningCommand {
+    /// The command line as typed, for `jobs` and completion notices.
+    pub(super) command: String,
+    pub(super) pid: u32,
+    pub(super) started: Instant,
+    /// Job number when started with a trailing `&`.
+    pub(super) job: Option<usize>,
+    /// Started by a `trap` hook or the rc file rather than at the prompt.
+    pub(super) from_hook: bool,
+    /// The `trap` hook that started it, dropped if the command fails.
+    pub(super) hook: Option<(Event, String)>,
+    /// The input line the command was typed on, whose header shows how
+    /// long it ran.
+    pub(super) origin: Option<usize>,
+    /// Set once the process has exited; `None` inside if waiting failed.
+    pub(super) status: Option<Option<ExitStatus>>,
+    /// The directory listing from before it started, for `watch-fs`.
+    pub(super) snapshot: Option<Snapshot>,
+    /// Lines typed while it runs, under `busy-input stdin`; dropping it
+    /// closes the command's stdin.
+    input: Option<Sender<Vec<u8>>>,
+    events: Receiver<StreamEvent>,
+    stdout: Stream,
+    stderr: Stream,
+    open_pipes: usize,
+    /// Set when the command runs on a pty; its output then goes to the
+    /// screen rather than line by line.
+    pub(super) pty: Option<PtySession>,
+    /// Asks the thread waiting on the child to kill it, where there's no
+    /// signal to send by pid.
+    #[cfg(not(unix))]
+    stop: Option<Sender<()>>,
+}
+
+impl RunningCommand {
+    fn new(
+        command: &str,
+        pid: u32,
+        events: Receiver<StreamEvent>,
+        open_pipes: usize,
+        input: Option<Sender<Vec<u8>>>,
+    ) -> Self {
+        Self {
+            command: command.to_string(),
+            pid,
+            started: Instant::now(),
+            job: None,
+            origin: None,
+            from_hook: false,
+            hook: None,
+            status: None,
+            snapshot: None,
+            input,
+            events,
+            stdout: Stream::default(),
+            stderr: Stream::default(),
+            open_pipes,
+            pty: None,
+            #[cfg(not(unix))]
+            stop: None,
+        }
+    }
+
+    /// Ctrl+C: SIGINT, as a terminal sends to its foreground job.
+    #[cfg(unix)]
+    pub(super) fn interrupt(&self) {
+        // SAFETY: kill(2) has no memory-safety preconditions.
+        unsafe { libc::kill(self.pid as libc::pid_t, libc::SIGINT) };
+    }
+
+    /// Ctrl+C: without signals, the child is killed outright.
+    #[cfg(not(unix))]
+    pub(super) fn interrupt(&self) {
+        if let Some(stop) = &self.stop {
+            let _ = stop.send(());
+        }
+    }
+
+    pub(super) fn shift_lines(&mut self, removed: usize) {
+        for stream in [&mut self.stdout, &mut self.stderr] {
+            stream.open_line = stream.open_line.and_then(|i| i.checked_sub(removed));
+        }
+        self.origin = self.origin.and_then(|i| i.checked_sub(removed));
+    }
+
+    /// A foreground command typed at the prompt: `precmd` hooks wait for
+    /// it to finish.
+    pub(super) fn holds_prompt(&self) -> bool {
+        self.job.is_none() && !self.from_hook
+    }
+
+    pub(super) fn forget_lines(&mut self) {
+        self.stdout.open_line = None;
+        self.stderr.open_line = None;
+        self.origin = None;
+    }
+}
+
+fn forward(mut pipe: impl Read, sender: Sender<StreamEvent>, wrap: fn(Vec<u8>) -> StreamEvent) {
+    let mut buf = [0u8; 8192];
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if sender.send(wrap(buf[..n].to_vec())).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = sender.send(StreamEvent::Closed);
+}
+
+/// Spawns `args` in `cwd`, with `env` added to its environment and its
+/// output forwarded to the UI thread chunk by chunk. Without `stdin`,
+/// `interactive` keeps its stdin open for lines typed while it runs;
+/// otherwise it reads end of file.
+pub(super) fn spawn(
+    command: &str,
+    args: &[String],
+    stdin: Option<Vec<u8>>,
+    interactive: bool,
+    cwd: &Path,
+    mode: ExecMode,
+    env: &[(String, String)],
+) -> io::Result<RunningCommand> {
+    let interactive = interactive && stdin.is_none();
+    let mut child = shell::command(args, mode)
+        .current_dir(cwd)
+        .envs(env.iter().cloned())
+        .stdin(if stdin.is_some() || interactive {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut input = None;
+    if let Some(mut pipe) = child.stdin.take() {
+        let (sender, chunks) = mpsc::channel::<Vec<u8>>();
+        if let Some(bytes) = stdin {
+            let _ = sender.send(bytes);
+        } else {
+            input = Some(sender);
+        }
+        // A command that isn't reading mustn't block the UI thread.
+        thread::spawn(move || {
+            for bytes in chunks {
+                if pipe.write_all(&bytes).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    let (sender, events) = mpsc::channel();
+    let mut open_pipes = 0;
+    if let Some(stdout) = child.stdout.take() {
+        let sender = sender.clone();
+        thread::spawn(move || forward(stdout, sender, StreamEvent::Stdout));
+        open_pipes += 1;
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let sender = sender.clone();
+        thread::spawn(move || forward(stderr, sender, StreamEvent::Stderr));
+        open_pipes += 1;
+    }
+    let pid = child.id();
+    #[cfg(unix)]
+    {
+        thread::spawn(move || {
+            let status = child.wait().ok();
+            let _ = sender.send(StreamEvent::Exited(status));
+        });
+        Ok(RunningCommand::new(command, pid, events, open_pipes, input))
+    }
+    #[cfg(not(unix))]
+    {
+        let stop = wait_or_kill(child, sender);
+        Ok(RunningCommand {
+            stop: Some(stop),
+            ..RunningCommand::new(command, pid, events, open_pipes, input)
+        })
+    }
+}
+
+/// Waits for `child` on its own thread, polling so that a message on the
+/// returned sender can kill it in between.
+#[cfg(not(unix))]
+fn wait_or_kill(mut child: std::process::Child, sender: Sender<StreamEvent>) -> Sender<()> {
+    let (stop, stopped) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) => {}
+                Err(_) => break None,
+            }
+            if stopped.try_recv().is_ok() {
+                let _ = child.kill();
+                break child.wait().ok();
+            }
+            thread::sleep(std::time::Duration::from_millis(50));
+        };
+        let _ = sender.send(StreamEvent::Exited(status));
+    });
+    stop
+}
+
+/// Spawns `args` on a pty of `cols` × `rows`, its output forwarded to the
+/// UI thread for the screen.
+#[cfg(unix)]
+pub(super) fn spawn_pty(
+    command: &str,
+    args: &[String],
+    cwd: &Path,
+    mode: ExecMode,
+    env: &[(String, String)],
+    (cols, rows): (usize, usize),
+) -> io::Result<RunningCommand> {
+    let (mut child, master) = crate::pty::spawn(args, cwd, mode, env, cols, rows)?;
+    let reader = master.try_clone()?;
+    let (sender, events) = mpsc::channel();
+    {
+        let sender = sender.clone();
+        // Reading fails with EIO once the program and its children exit.
+        thread::spawn(move || forward(reader, sender, StreamEvent::Stdout));
+    }
+    let pid = child.id();
+    thread::spawn(move || {
+        let status = child.wait().ok();
+        let _ = sender.send(StreamEvent::Exited(status));
+    });
+    let mut running = RunningCommand::new(command, pid, events, 1, None);
+    running.pty = Some(PtySession {
+        screen: Screen::new(cols, rows),
+        master,
+    });
+    Ok(running)
+}
+
+impl TerminalApp {
+    /// Moves output from running commands into the scrollback. Returns true
+    /// while any command is still running.
+    pub(super) fn poll_running(&mut self) -> bool {
+        let mut running = std::mem::take(&mut self.running);
+        let mut prompt_freed = false;
+        running.retain_mut(|command| {
+            loop {
+                match command.events.try_recv() {
+                    Ok(StreamEvent::Stdout(bytes)) if command.pty.is_some() => {
+                        if let Some(session) = &mut command.pty {
+                            self.show_pty_output(session, &bytes);
+                        }
+                    }
+                    Ok(StreamEvent::Stdout(bytes)) => {
+                        let stdout = &mut command.stdout;
+                        let room = RAW_LIMIT.saturating_sub(stdout.raw.len());
+                        stdout
+                            .raw
+                            .extend_from_slice(&bytes[..room.min(bytes.len())]);
+                        let events = stdout.assembler.push(&bytes);
+                        // Binary data only fills the scrollback with noise.
+                        if !stdout.assembler.looks_binary() {
+                            for event in events {
+                                self.show_stream_line(stdout, event, OUTPUT_COLOR);
+                            }
+                        }
+                    }
+                    Ok(StreamEvent::Stderr(bytes)) => {
+                        for event in command.stderr.assembler.push(&bytes) {
+                            self.show_stream_line(&mut command.stderr, event, ERROR_COLOR);
+                        }
+                    }
+                    Ok(StreamEvent::Closed) => command.open_pipes -= 1,
+                    Ok(StreamEvent::Exited(status)) => command.status = Some(status),
+                    Err(TryRecvError::Empty) => return true,
+                    Err(TryRecvError::Disconnected) => break,
+                }
+                if command.status.is_some() && command.open_pipes == 0 {
+                    break;
+                }
+            }
+            // What's left on the main screen stays in the scrollback, as in
+            // a terminal; a full-screen program's alternate screen doesn't.
+            if let Some(session) = command.pty.take() {
+                let rows = session.screen.remaining();
+                self.push_lines(rows.iter().map(|row| vt::to_line(row)));
+            }
+            for (stream, color) in [
+                (&mut command.stdout, OUTPUT_COLOR),
+                (&mut command.stderr, ERROR_COLOR),
+            ] {
+                if let Some(event) = stream.assembler.finish() {
+                    if !stream.assembler.looks_binary() {
+                        self.show_stream_line(stream, event, color);
+                    }
+                }
+            }
+            // Only what the prompt waited for is "the last output"; a job
+            // or hook finishing later doesn't replace it.
+            let foreground = command.holds_prompt();
+            self.note_binary_output(&command.stdout.assembler, foreground);
+            if foreground {
+                self.last_output = std::mem::take(&mut command.stdout.raw);
+            }
+            if let Some(origin) = command.origin {
+                self.finish_timing(origin, command.started.elapsed());
+                self.hint_sudo(origin);
+                self.hint_git_typo(origin);
+            }
+            self.command_finished(command);
+            let code = command.status.flatten().map(super::jobs::exit_code);
+            if command.holds_prompt() {
+                self.last_status = code;
+            }
+            if let Some((event, hook)) = command.hook.take() {
+                if code.is_some_and(|code| code != 0) {
+                    self.drop_failed_hook(event, &hook);
+                }
+            }
+            if let Some(snapshot) = command.snapshot.take() {
+                self.report_fs_changes(&snapshot);
+            }
+            prompt_freed |= command.holds_prompt();
+            false
+        });
+        self.running = running;
+        self.start_queued_jobs();
+        if prompt_freed {
+            self.continue_loop();
+        }
+        if prompt_freed && !self.busy() {
+            self.run_hooks(Event::Precmd);
+            self.run_queued_input();
+        }
+        self.trim_scrollback();
+        !self.running.is_empty()
+    }
+
+    /// Whether a foreground command is still running, so Enter shouldn't
+    /// start another.
+    pub(super) fn busy(&self) -> bool {
+        self.running.iter().any(RunningCommand::holds_prompt)
+            || !self.tasks.is_empty()
+            || !self.loop_queue.is_empty()
+    }
+
+    /// Enter while `busy`: queues the line, leaves it in the input, or
+    /// sends it to the command, as `busy-input` says. Returns false when
+    /// the line should run now: nothing is running, or it's a `kill`,
+    /// `jobs` or `:` command, which never wait their turn.
+    pub(super) fn submit_while_busy(&mut self) -> bool {
+        if !self.busy() || parse::runs_while_busy(&self.input_buffer) {
+            return false;
+        }
+        let busy_input = self.config.busy_input;
+        match busy_input {
+            BusyInput::Ignore => {}
+            BusyInput::Stdin if self.send_to_running() => {}
+            BusyInput::Queue | BusyInput::Stdin => {
+                let line = std::mem::take(&mut self.input_buffer);
+                self.cursor_pos = 0;
+                self.queued_input.push_back(line);
+            }
+        }
+        true
+    }
+
+    /// Writes the input line to the foreground command's stdin, echoing it
+    /// but keeping it out of history, since it may be a password.
+    fn send_to_running(&mut self) -> bool {
+        let command = self.running.iter().find(|c| c.holds_prompt());
+        let Some(input) = command.and_then(|c| c.input.as_ref()) else {
+            return false;
+        };
+        let line = std::mem::take(&mut self.input_buffer);
+        self.cursor_pos = 0;
+        let _ = input.send(format!("{line}\n").into_bytes());
+        self.add_line(&line, INPUT_COLOR);
+        true
+    }
+
+    /// Ctrl+D: closes the foreground command's stdin. False if there's no
+    /// open stdin to close.
+    pub(super) fn close_running_input(&mut self) -> bool {
+        let command = self.running.iter_mut().find(|c| c.holds_prompt());
+        command.and_then(|c| c.input.take()).is_some()
+    }
+
+    /// Runs lines queued while a command was busy, until one of them
+    /// starts another foreground command.
+    pub(super) fn run_queued_input(&mut self) {
+        while !self.busy() {
+            let Some(line) = self.queued_input.pop_front() else {
+                return;
+            };
+            self.run_line(line);
+        }
+    }
+
+    /// Dim text after the input while Enter can't run a line, saying what
+    /// it does instead.
+    pub(super) fn busy_hint(&self) -> Option<String> {
+        if !self.busy() {
+            return None;
+        }
+        let queued = self.queued_input.len();
+        Some(match self.config.busy_input {
+            _ if queued > 0 => format!("  [running; {queued} queued]"),
+            BusyInput::Queue => "  [running; Enter queues]".to_string(),
+            BusyInput::Ignore => "  [running]".to_string(),
+            BusyInput::Stdin => "  [running; Enter types to it, Ctrl+D ends input]".to_string(),
+        })
+    }
+
+    /// Says so when stdout wasn't all UTF-8, and whether it was held back
+    /// as binary. `kept` is whether `:hexdump` will show this output.
+    fn note_binary_output(&mut self, assembler: &LineAssembler, kept: bool) {
+        if assembler.invalid == 0 && !assembler.nul {
+            return;
+        }
+        let mut notice = format!(
+            "[binary output: {} bytes, {} invalid",
+            assembler.bytes, assembler.invalid
+        );
+        match (assembler.looks_binary(), kept) {
+            (true, true) => notice.push_str("; not shown, :hexdump to view]"),
+            (true, false) => notice.push_str("; not shown]"),
+            (false, true) => notice.push_str("; :hexdump to view]"),
+            (false, false) => notice.push(']'),
+        }
+        self.add_line(&notice, TIMESTAMP_COLOR);
+    }
+
+    /// Draws a partial line in place, or finishes it. Only finished lines
+    /// go through `push_line`, so the session log sees each line once.
+    fn show_stream_line(&mut self, stream: &mut Stream, event: LineEvent, color: Color32) {
+        let open = stream.open_line.filter(|&i| i < self.lines.len());
+        match (event, open) {
+            (LineEvent::Partial(text), Some(index)) => {
+                self.lines[index] = TerminalLine::plain(text, color);
+            }
+            (LineEvent::Partial(text), None) => {
+                self.lines.push(TerminalLine::plain(text, color));
+                stream.open_line = Some(self.lines.len() - 1);
+            }
+            (LineEvent::Complete(text), Some(index)) => {
+                stream.open_line = None;
+                self.replace_line(index, TerminalLine::plain(text, color));
+            }
+            (LineEvent::Complete(text), None) => {
+                stream.open_line = None;
+                self.push_line(TerminalLine::plain(text, color));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn interrupt_stops_the_command() {
+        let args = ["sleep".to_string(), "30".to_string()];
+        let command = spawn(
+            "sleep 30",
+            &args,
+            None,
+            false,
+            Path::new("."),
+            ExecMode::Direct,
+            &[],
+        )
+        .expect("sleep runs");
+        command.interrupt();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match command.events.recv_timeout(left) {
+                Ok(StreamEvent::Exited(status)) => {
+                    assert!(!status.expect("waited").success());
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => panic!("sleep still running after Ctrl+C: {e}"),
+            }
+        }
+    }
+
+    #[test]
+    fn assignments_reach_only_the_command() {
+        let args = ["printenv".to_string(), "SYNTH_GREETING".to_string()];
+        let env = [("SYNTH_GREETING".to_string(), "hello".to_string())];
+        let command = spawn(
+            "printenv",
+            &args,
+            None,
+            false,
+            Path::new("."),
+            ExecMode::Direct,
+            &env,
+        )
+        .expect("printenv runs");
+        let (mut output, mut closed) = (Vec::new(), 0);
+        for event in command.events.iter() {
+            match event {
+                StreamEvent::Stdout(bytes) => output.extend(bytes),
+                StreamEvent::Closed => closed += 1,
+                _ => {}
+            }
+            if closed == 2 {
+                break;
+            }
+        }
+        assert_eq!(output, b"hello\n");
+        assert!(std::env::var_os("SYNTH_GREETING").is_none());
+    }
+}
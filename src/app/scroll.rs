@@ -0,0 +1,144 @@
+use eframe::egui;
+use regex::Regex;
+
+use super::TerminalApp;
+use crate::line::{TerminalLine, ERROR_COLOR, OUTPUT_COLOR};
+
+/// The first visible line and how far its top sits from the top of the
+/// viewport, so the same view can be restored after wrapped lines reflow.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct ScrollAnchor {
+    pub(super) line: usize,
+    offset: f32,
+}
+
+impl ScrollAnchor {
+    pub(super) fn new(line: usize, rect: &egui::Rect, viewport: &egui::Rect) -> Self {
+        Self {
+            line,
+            offset: rect.top() - viewport.top(),
+        }
+    }
+
+    /// The anchor after `removed` lines were dropped from the scrollback.
+    pub(super) fn shifted(self, removed: usize) -> Option<Self> {
+        Some(Self {
+            line: self.line.checked_sub(removed)?,
+            ..self
+        })
+    }
+
+    /// Scrolls so the anchor line, now laid out at `rect`, sits at its
+    /// recorded offset again.
+    pub(super) fn scroll_to(self, ui: &egui::Ui, rect: &egui::Rect) {
+        let top = rect.top() - self.offset;
+        let target = egui::Rect::from_min_size(egui::pos2(rect.left(), top), egui::vec2(1.0, 1.0));
+        ui.scroll_to_rect(target, Some(egui::Align::TOP));
+    }
+}
+
+impl TerminalApp {
+    /// Unfolds the command block containing `index` so it can be shown,
+    /// and leaves errors-only if that would hide it.
+    fn reveal_line(&mut self, index: usize) {
+        if let Some(header) = self.lines[..=index].iter().rposition(|l| l.is_input) {
+            self.lines[header].collapsed = false;
+        }
+        if self.hidden_by_errors_only(&self.lines[index]) {
+            self.errors_only = false;
+        }
+    }
+
+    /// Output that errors-only leaves out: everything but prompts and
+    /// lines in the error color, which is how stderr arrives.
+    pub(super) fn hidden_by_errors_only(&self, line: &TerminalLine) -> bool {
+        self.errors_only && !line.is_input && !line.is_error()
+    }
+
+    /// Ctrl+Up/Down: scrolls to the previous (or next) command line,
+    /// starting from the newest. Past the newest, returns to the bottom.
+    pub(super) fn jump_to_prompt(&mut self, forward: bool) {
+        let prompts = |range: std::ops::Range<usize>| {
+            range
+                .filter(|&i| self.lines[i].is_input)
+                .collect::<Vec<_>>()
+        };
+        let found = match (self.prompt_cursor, forward) {
+            (Some(i), true) => prompts(i + 1..self.lines.len()).first().copied(),
+            (Some(i), false) => prompts(0..i).last().copied().or(Some(i)),
+            (None, true) => None,
+            (None, false) => prompts(0..self.lines.len()).last().copied(),
+        };
+        self.prompt_cursor = found;
+        match found {
+            Some(index) => {
+                self.highlighted_line = Some(index);
+                self.scroll_target = Some((index, egui::Align::TOP));
+            }
+            None => {
+                self.highlighted_line = None;
+                if let Some(last) = self.lines.len().checked_sub(1) {
+                    self.scroll_target = Some((last, egui::Align::BOTTOM));
+                }
+            }
+        }
+    }
+
+    /// Scrolls to the next (or previous) line matching the configured error
+    /// pattern, wrapping around the scrollback so repeated jumps cycle.
+    pub(super) fn jump_to_error(&mut self, forward: bool) {
+        match Regex::new(&self.config.error_pattern) {
+            Ok(re) => self.jump_to_match(&re, forward, "No lines match the error pattern"),
+            Err(e) => self.add_line(&format!("error-pattern: {e}"), ERROR_COLOR),
+        }
+    }
+
+    /// `:search PATTERN` jumps to the next output line matching a regex,
+    /// case-insensitively unless it has capitals; `:search` alone repeats
+    /// the last search and `:search -b` goes backwards.
+    pub(super) fn search_scrollback(&mut self, args: &[String]) {
+        let backward = args.first().is_some_and(|a| a == "-b");
+        let pattern = args[usize::from(backward)..].join(" ");
+        if !pattern.is_empty() {
+            self.last_search = pattern;
+        }
+        if self.last_search.is_empty() {
+            self.add_line(":search: usage: :search [-b] PATTERN", ERROR_COLOR);
+            return;
+        }
+        let smart_case = if self.last_search.chars().any(char::is_uppercase) {
+            ""
+        } else {
+            "(?i)"
+        };
+        match Regex::new(&format!("{smart_case}{}", self.last_search)) {
+            Ok(re) => self.jump_to_match(&re, !backward, "No lines match the search"),
+            Err(e) => self.add_line(&format!(":search: {e}"), ERROR_COLOR),
+        }
+    }
+
+    /// Highlights and scrolls to the next output line matching `re` after
+    /// the error cursor, wrapping around the scrollback.
+    fn jump_to_match(&mut self, re: &Regex, forward: bool, not_found: &str) {
+        let count = self.lines.len();
+        let start = self.error_cursor;
+        let found = (1..=count)
+            .map(|step| match (start, forward) {
+                (Some(i), true) => (i + step) % count,
+                (Some(i), false) => (i + count - step % count) % count,
+                (None, true) => step - 1,
+                (None, false) => count - step,
+            })
+            .find(|&i| !self.lines[i].is_input && re.is_match(&self.lines[i].text()));
+
+        match found {
+            Some(index) => {
+                self.error_cursor = Some(index);
+                self.highlighted_line = Some(index);
+                self.scroll_target = Some((index, egui::Align::Center));
+                self.reveal_line(index);
+            }
+            None => self.add_line(not_found, OUTPUT_COLOR),
+        }
+    }
+}
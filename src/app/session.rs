@@ -0,0 +1,141 @@
+//! `:save` and `:load` for scrollback snapshots, and `:export-html`.
+//!
+//! Saved sessions are plain text with a one-letter tag per line so the
+//! input/output distinction survives a round trip:
+//!
+//! ```text
+//! # ai_terminal session
+//! I > ls
+//! O Cargo.toml
+//! E ls: cannot access 'x': No such file or directory
+//! ```
+//!
+//! Only that distinction is kept: colors, links and highlights aren't
+//! saved, so loaded output comes back in the plain output color.
+
+use std::fs;
+
+use super::TerminalApp;
+use crate::line::{TerminalLine, ERROR_COLOR, HEADING_COLOR, OUTPUT_COLOR};
+use crate::{html, paths};
+
+const HEADER: &str = "# ai_terminal session";
+
+fn tag(line: &TerminalLine) -> char {
+    if line.is_input {
+        'I'
+    } else if line.is_error() {
+        'E'
+    } else {
+        'O'
+    }
+}
+
+/// The lines of a saved session. Untagged lines are kept whole, as output.
+fn parse(text: &str) -> Vec<TerminalLine> {
+    let mut lines = Vec::new();
+    for raw in text.lines() {
+        if raw.starts_with('#') {
+            continue;
+        }
+        let mut chars = raw.chars();
+        let kind = chars.next();
+        let rest = chars.as_str();
+        let content = rest.strip_prefix(' ').unwrap_or(rest);
+        lines.push(match kind {
+            Some('I') => TerminalLine::input(content),
+            Some('E') => TerminalLine::plain(content, ERROR_COLOR),
+            Some('O') => TerminalLine::plain(content, OUTPUT_COLOR),
+            _ => TerminalLine::plain(raw, OUTPUT_COLOR),
+        });
+    }
+    lines
+}
+
+impl TerminalApp {
+    pub(super) fn save_session(&mut self, target: Option<&str>) {
+        let Some(target) = target else {
+            self.add_line(":save: usage: :save <file>", ERROR_COLOR);
+            return;
+        };
+        let mut text = format!("{HEADER}\n");
+        for line in &self.lines {
+            text.push(tag(line));
+            text.push(' ');
+            text.push_str(&line.copy_text());
+            text.push('\n');
+        }
+        let path = paths::resolve(&self.current_dir, target);
+        match fs::write(&path, text) {
+            Ok(()) => self.add_line(
+                &format!("Saved {} lines to {}", self.lines.len(), path.display()),
+                OUTPUT_COLOR,
+            ),
+            Err(e) => self.add_line(&format!(":save: {}: {e}", path.display()), ERROR_COLOR),
+        }
+    }
+
+    /// Writes the scrollback as an HTML page with the colors it has on
+    /// screen, for sharing.
+    pub(super) fn export_html(&mut self, target: Option<&str>) {
+        let Some(target) = target else {
+            self.add_line(":export-html: usage: :export-html <file>", ERROR_COLOR);
+            return;
+        };
+        let page = html::render(&self.lines, self.theme(), self.config.color);
+        let path = paths::resolve(&self.current_dir, target);
+        match fs::write(&path, page) {
+            Ok(()) => self.add_line(
+                &format!("Exported {} lines to {}", self.lines.len(), path.display()),
+                OUTPUT_COLOR,
+            ),
+            Err(e) => self.add_line(
+                &format!(":export-html: {}: {e}", path.display()),
+                ERROR_COLOR,
+            ),
+        }
+    }
+
+    /// Appends a saved session to the scrollback for review. Nothing in it
+    /// is executed.
+    pub(super) fn load_session(&mut self, target: Option<&str>) {
+        let Some(target) = target else {
+            self.add_line(":load: usage: :load <file>", ERROR_COLOR);
+            return;
+        };
+        let path = paths::resolve(&self.current_dir, target);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                self.add_line(&format!(":load: {}: {e}", path.display()), ERROR_COLOR);
+                return;
+            }
+        };
+
+        let lines = parse(&text);
+
+        let name = path.display().to_string();
+        self.add_line(&format!("── session {name} ──"), HEADING_COLOR);
+        self.push_lines(lines);
+        self.add_line(&format!("── end of {name} ──"), HEADING_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tags_and_survives_multibyte_starts() {
+        let lines = parse(&format!(
+            "{HEADER}\nI > ls\nO Cargo.toml\nE oops\n\nébauche\n"
+        ));
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].is_input);
+        assert_eq!(lines[1].copy_text(), "Cargo.toml");
+        assert!(lines[2].is_error());
+        assert_eq!(lines[3].copy_text(), "");
+        assert!(!lines[4].is_input && !lines[4].is_error());
+        assert_eq!(lines[4].copy_text(), "ébauche");
+    }
+}
@@ -0,0 +1,119 @@
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Instant;
+
+use super::exec::run_external;
+use super::TerminalApp;
+use crate::builtins::{self, StageOutput};
+use crate::hooks::Event;
+use crate::line::ERROR_COLOR;
+use crate::parse;
+use crate::shell::ExecMode;
+
+/// A stage that couldn't be started: the program and why.
+pub(super) type Failure = (String, io::Error);
+
+/// A pipeline with an internal tool that can take a while, such as a
+/// download or a walk of a large tree, run on a worker thread as `bench`
+/// runs its pipeline. It holds the prompt like a foreground command, and
+/// its output arrives in one piece when it's done.
+pub(super) struct Task {
+    /// The input line it was typed on, whose header shows how long it ran.
+    pub(super) origin: Option<usize>,
+    started: Instant,
+    result: Receiver<Result<StageOutput, Failure>>,
+}
+
+/// Runs each stage in turn as `pipeline_output` does, collecting the
+/// earlier stages' errors ahead of the last one's.
+pub(super) fn run_stages(
+    stages: &[Vec<String>],
+    cwd: &Path,
+    mode: ExecMode,
+    env: &[(String, String)],
+) -> Result<StageOutput, Failure> {
+    let mut input: Option<StageOutput> = None;
+    let mut errors = Vec::new();
+    for (index, args) in stages.iter().enumerate() {
+        let mut output = match builtins::run_stage(args, input.as_ref(), cwd) {
+            Some(output) => output,
+            None => run_external(
+                args,
+                input.as_ref().map(StageOutput::bytes).as_deref(),
+                cwd,
+                mode,
+                env,
+            )
+            .map_err(|e| (args[0].clone(), e))?,
+        };
+        errors.append(&mut output.errors);
+        if index == stages.len() - 1 {
+            output.errors = errors;
+            return Ok(output);
+        }
+        input = Some(output);
+    }
+    Ok(StageOutput::default())
+}
+
+impl TerminalApp {
+    /// Starts `stages` on a worker thread.
+    pub(super) fn start_task(&mut self, stages: &[String]) {
+        let stages: Vec<Vec<String>> = stages.iter().map(|s| parse::split_args(s)).collect();
+        if stages.iter().any(Vec::is_empty) {
+            self.add_line("syntax error near unexpected token `|'", ERROR_COLOR);
+            return;
+        }
+        let (sender, result) = mpsc::channel();
+        let cwd = self.current_dir.clone();
+        let mode = self.config.exec_mode;
+        let env = self.child_env();
+        thread::spawn(move || {
+            let _ = sender.send(run_stages(&stages, &cwd, mode, &env));
+        });
+        self.tasks.push(Task {
+            origin: self.command_line,
+            started: Instant::now(),
+            result,
+        });
+    }
+
+    /// Shows the output of tasks that have finished. Returns true while any
+    /// is still running.
+    pub(super) fn poll_tasks(&mut self) -> bool {
+        let mut finished = Vec::new();
+        self.tasks.retain(|task| match task.result.try_recv() {
+            Ok(result) => {
+                finished.push((task.origin, task.started, Some(result)));
+                false
+            }
+            Err(TryRecvError::Empty) => true,
+            Err(TryRecvError::Disconnected) => {
+                finished.push((task.origin, task.started, None));
+                false
+            }
+        });
+        if finished.is_empty() {
+            return !self.tasks.is_empty();
+        }
+        for (origin, started, result) in finished {
+            match result {
+                Some(Ok(output)) => self.show_output(output),
+                Some(Err((program, error))) => self.report_spawn_error(&program, &error),
+                None => {}
+            }
+            if let Some(origin) = origin {
+                self.finish_timing(origin, started.elapsed());
+            }
+        }
+        self.continue_loop();
+        if !self.busy() {
+            self.run_hooks(Event::Precmd);
+            self.run_queued_input();
+        }
+        self.trim_scrollback();
+        !self.tasks.is_empty()
+    }
+}
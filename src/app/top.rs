@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use egui::{FontId, RichText};
+
+use super::TerminalApp;
+use crate::line::{ERROR_COLOR, HEADING_COLOR, TIMESTAMP_COLOR};
+use crate::procs::{self, Row, Snapshot};
+use crate::theme::Theme;
+
+/// How often the table is refreshed; CPU use is measured over this span.
+pub(super) const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Processes shown; the rest are idle enough not to matter.
+const ROWS: usize = 40;
+
+/// The built-in `top`: a live table of the busiest processes, over the
+/// scrollback until `q`.
+pub(super) struct Top {
+    last: Snapshot,
+    taken: Instant,
+    rows: Vec<Row>,
+}
+
+impl TerminalApp {
+    /// `top`: opens the monitor, or says it can't where processes can't
+    /// be listed.
+    pub(super) fn start_top(&mut self) {
+        let Some(last) = Snapshot::take() else {
+            self.add_line("top: not supported on this system", ERROR_COLOR);
+            self.last_status = Some(1);
+            return;
+        };
+        // Nothing to compare against yet: show memory now, CPU next tick.
+        let rows = Snapshot::take().map_or_else(Vec::new, |now| last.rows(&now));
+        self.top = Some(Top {
+            last,
+            taken: Instant::now(),
+            rows,
+        });
+    }
+
+    /// Refreshes the table once `REFRESH_INTERVAL` has passed. Returns
+    /// true while the monitor is open.
+    pub(super) fn poll_top(&mut self) -> bool {
+        let Some(top) = &mut self.top else {
+            return false;
+        };
+        if top.taken.elapsed() >= REFRESH_INTERVAL {
+            if let Some(now) = Snapshot::take() {
+                top.rows = top.last.rows(&now);
+                top.last = now;
+            }
+            top.taken = Instant::now();
+        }
+        true
+    }
+
+    pub(super) fn render_top(&mut self, ctx: &egui::Context, font: &FontId, theme: Theme) {
+        let Some(top) = &self.top else {
+            return;
+        };
+        let mut open = true;
+        let text = |text: String, color| RichText::new(text).font(font.clone()).color(color);
+        egui::Window::new("top")
+            .open(&mut open)
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("top")
+                    .num_columns(4)
+                    .spacing([16.0, 2.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let heading = theme.adapt(HEADING_COLOR);
+                        for title in ["PID", "COMMAND", "%CPU", "MEM"] {
+                            ui.label(text(title.to_string(), heading));
+                        }
+                        ui.end_row();
+                        for row in top.rows.iter().take(ROWS) {
+                            let foreground = theme.foreground();
+                            ui.label(text(row.pid.to_string(), foreground));
+                            ui.label(text(row.name.clone(), foreground));
+                            ui.label(text(format!("{:.1}", row.cpu), foreground));
+                            ui.label(text(procs::format_memory(row.memory), foreground));
+                            ui.end_row();
+                        }
+                    });
+                ui.add_space(8.0);
+                let hint = format!("{} processes · q to quit", top.rows.len());
+                ui.label(text(hint, theme.adapt(TIMESTAMP_COLOR)));
+            });
+        if !open {
+            self.top = None;
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::env;
+
+use super::TerminalApp;
+use crate::line::{ERROR_COLOR, OUTPUT_COLOR};
+use crate::{alias, parse, shell};
+
+impl TerminalApp {
+    /// `$NAME`: a session variable, the view's size for `COLUMNS` and
+    /// `LINES`, else the environment.
+    pub(super) fn variable(&self, name: &str) -> Option<String> {
+        self.variables
+            .get(name)
+            .cloned()
+            .or_else(|| {
+                self.size_env()
+                    .into_iter()
+                    .find(|(size, _)| size == name)
+                    .map(|(_, value)| value)
+            })
+            .or_else(|| env::var(name).ok())
+    }
+
+    /// `COLUMNS` and `LINES` for the view, once a frame has measured it.
+    fn size_env(&self) -> Vec<(String, String)> {
+        match self.terminal_size {
+            (0, _) | (_, 0) => Vec::new(),
+            (columns, rows) => vec![
+                ("COLUMNS".to_string(), columns.to_string()),
+                ("LINES".to_string(), rows.to_string()),
+            ],
+        }
+    }
+
+    /// What to add to the environment of a program started now: the view's
+    /// size, as a terminal gives it, then the line's `NAME=VALUE` prefixes.
+    pub(super) fn child_env(&self) -> Vec<(String, String)> {
+        let mut env = self.size_env();
+        env.extend(self.command_env.iter().cloned());
+        env
+    }
+
+    /// A bare `NAME=VALUE`. Like a shell, an already exported variable
+    /// stays exported; anything else is kept from child processes.
+    pub(super) fn assign(&mut self, name: String, value: String) {
+        if env::var_os(&name).is_some() {
+            env::set_var(name, value);
+        } else {
+            self.variables.insert(name, value);
+        }
+    }
+
+    /// `export NAME[=VALUE]...`: passes variables to every command run
+    /// after. Alone, lists the environment.
+    pub(super) fn export(&mut self, args: &[String]) {
+        if args.is_empty() {
+            let mut vars: Vec<(String, String)> = env::vars().collect();
+            vars.sort();
+            for (name, value) in vars {
+                self.add_line(&format!("{name}={value}"), OUTPUT_COLOR);
+            }
+            return;
+        }
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !parse::is_name(name) {
+                self.add_line(&format!("export: '{arg}': not a valid name"), ERROR_COLOR);
+                continue;
+            }
+            let session = self.variables.remove(name);
+            if let Some(value) = value.or(session) {
+                env::set_var(name, value);
+            }
+        }
+    }
+
+    /// `unset NAME...`: forgets session and exported variables alike.
+    pub(super) fn unset(&mut self, names: &[String]) {
+        for name in names {
+            if !parse::is_name(name) {
+                self.add_line(&format!("unset: '{name}': not a valid name"), ERROR_COLOR);
+                continue;
+            }
+            self.variables.remove(name);
+            env::remove_var(name);
+        }
+    }
+
+    /// `alias NAME=LINE...` defines aliases; a bare `NAME` shows one, and
+    /// alone it lists them all.
+    pub(super) fn alias(&mut self, args: &[String]) {
+        let show = |name: &str, body: &str| format!("alias {name}={}", shell::quote(body));
+        if args.is_empty() {
+            let lines: Vec<String> = self
+                .aliases
+                .iter()
+                .map(|(name, body)| show(name, body))
+                .collect();
+            for line in lines {
+                self.add_line(&line, OUTPUT_COLOR);
+            }
+            return;
+        }
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, body)) if alias::is_valid_name(name) => {
+                    self.aliases.insert(name.to_string(), body.to_string());
+                }
+                Some(_) => {
+                    self.add_line(&format!("alias: '{arg}': not a valid name"), ERROR_COLOR);
+                }
+                None => match self.aliases.get(arg.as_str()) {
+                    Some(body) => {
+                        let line = show(arg, body);
+                        self.add_line(&line, OUTPUT_COLOR);
+                    }
+                    None => self.add_line(&format!("alias: {arg}: not found"), ERROR_COLOR),
+                },
+            }
+        }
+    }
+
+    /// `unalias NAME...`, or `unalias -a` for every alias.
+    pub(super) fn unalias(&mut self, names: &[String]) {
+        if names.first().is_some_and(|name| name == "-a") {
+            self.aliases.clear();
+            return;
+        }
+        for name in names {
+            if self.aliases.remove(name.as_str()).is_none() {
+                self.add_line(&format!("unalias: {name}: not found"), ERROR_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use super::TerminalApp;
+use crate::line::{Segment, TerminalLine, DELETED_COLOR, HEADING_COLOR, INSERTED_COLOR};
+use crate::parse;
+use crate::snapshot::Snapshot;
+
+/// Names listed per kind of change before the rest are summarized.
+const MAX_LISTED: usize = 20;
+
+impl TerminalApp {
+    /// A listing of the current directory to compare against once
+    /// `command` finishes, if `watch-fs` is on and its program is watched.
+    pub(super) fn fs_snapshot(&self, command: &str) -> Option<Snapshot> {
+        if !self.config.watch_fs {
+            return None;
+        }
+        let args = parse::split_args(command);
+        let program = args.first()?;
+        parse::in_list(program, &self.config.watch_fs_commands)
+            .then(|| Snapshot::take(&self.current_dir))
+    }
+
+    /// Prints what was created, deleted or modified in `before.dir` since
+    /// `before` was taken. Prints nothing if nothing changed.
+    pub(super) fn report_fs_changes(&mut self, before: &Snapshot) {
+        let changes = before.diff(&Snapshot::take(&before.dir));
+        if changes.is_empty() {
+            return;
+        }
+        let mut lines = vec![TerminalLine::plain(
+            format!(
+                "Files: {} created, {} deleted, {} modified",
+                changes.created.len(),
+                changes.deleted.len(),
+                changes.modified.len()
+            ),
+            HEADING_COLOR,
+        )];
+        for (names, marker, color) in [
+            (&changes.created, "+", INSERTED_COLOR),
+            (&changes.deleted, "-", DELETED_COLOR),
+            (&changes.modified, "~", HEADING_COLOR),
+        ] {
+            for name in names.iter().take(MAX_LISTED) {
+                lines.push(TerminalLine::from_segments(vec![Segment::new(
+                    format!("  {marker} {name}"),
+                    color,
+                )]));
+            }
+            if names.len() > MAX_LISTED {
+                lines.push(TerminalLine::plain(
+                    format!("  {marker} ...and {} more", names.len() - MAX_LISTED),
+                    color,
+                ));
+            }
+        }
+        self.push_lines(lines);
+    }
+}
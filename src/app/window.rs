@@ -0,0 +1,99 @@
+use eframe::egui;
+use egui::FontId;
+
+use super::TerminalApp;
+
+impl TerminalApp {
+    /// Follows the window's size and position for saving on exit. A
+    /// maximized, minimized or fullscreen window keeps the geometry it had
+    /// before, so un-maximizing next time goes back to it.
+    pub(super) fn track_window(&mut self, ctx: &egui::Context) {
+        let info = ctx.input(|i| i.viewport().clone());
+        if !self.window_checked {
+            self.window_checked = true;
+            self.fit_window_on_screen(ctx, &info);
+        }
+        if info.minimized == Some(true) || info.fullscreen == Some(true) {
+            return;
+        }
+        self.window.maximized = info.maximized == Some(true);
+        if self.window.maximized {
+            return;
+        }
+        if let Some(inner) = info.inner_rect {
+            self.window.size = inner.size().into();
+        }
+        if let Some(outer) = info.outer_rect {
+            self.window.position = Some(outer.min.into());
+        }
+    }
+
+    /// Follows the view's size in character cells for the programs run in
+    /// it. Those on pipes are given it as `COLUMNS` and `LINES` when they
+    /// start and keep that size; programs on a pty are resized, which
+    /// sends them SIGWINCH.
+    pub(super) fn track_terminal_size(&mut self, ui: &egui::Ui, font: &FontId) {
+        let (advance, row_height) = ui.fonts(|f| (f.glyph_width(font, 'M'), f.row_height(font)));
+        let view = ui.available_size();
+        let columns = (view.x / advance).floor().max(1.0) as usize;
+        self.row_height = row_height + self.config.line_spacing;
+        let rows = (view.y / self.row_height).floor().max(1.0) as usize;
+        if (columns, rows) != self.terminal_size {
+            self.terminal_size = (columns, rows);
+            self.resize_ptys(columns, rows);
+        }
+    }
+
+    /// Makes a wheel notch scroll `scroll-lines` rows rather than egui's
+    /// fixed 50 points. Shift+wheel scrolls sideways, which the scrollback
+    /// only does when lines don't wrap. Touchpads report points and are
+    /// left alone, as is Ctrl+wheel, which zooms.
+    pub(super) fn scale_wheel_scroll(&self, raw_input: &mut egui::RawInput) {
+        if self.row_height == 0.0 {
+            return;
+        }
+        let step = self.row_height * self.config.scroll_lines;
+        // egui-winit follows each wheel event with the scroll it makes.
+        let mut notches = None;
+        for event in &mut raw_input.events {
+            match event {
+                egui::Event::MouseWheel {
+                    unit: egui::MouseWheelUnit::Line,
+                    delta,
+                    modifiers,
+                } if !modifiers.ctrl && !modifiers.command => {
+                    notches = Some((*delta, modifiers.shift));
+                }
+                egui::Event::Scroll(scroll) => {
+                    if let Some((delta, shift)) = notches.take() {
+                        let delta = delta * step;
+                        *scroll = if shift {
+                            egui::vec2(delta.x + delta.y, 0.0)
+                        } else {
+                            delta
+                        };
+                    }
+                }
+                _ => notches = None,
+            }
+        }
+    }
+
+    /// A position saved on a monitor that's since been unplugged would
+    /// open the window off screen; winit then reports no monitor for it.
+    /// Brings it back, and shrinks a window larger than its monitor.
+    fn fit_window_on_screen(&self, ctx: &egui::Context, info: &egui::ViewportInfo) {
+        let Some(monitor) = info.monitor_size else {
+            if info.outer_rect.is_some() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::Pos2::ZERO));
+            }
+            return;
+        };
+        if let Some(inner) = info.inner_rect {
+            let fitted = inner.size().min(monitor);
+            if fitted != inner.size() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(fitted));
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! The message shown at startup. `<config>/banner` replaces the default
+//! "Terminal Ready"; in it `$NAME` and `${NAME}` become variables and
+//! `$(cmd)` the output of a command, so `$(uname -a)` or `$(fortune)` work.
+//! `\$` keeps a literal `$`.
+
+use std::fs;
+
+use crate::parse;
+use crate::paths::Profile;
+
+const DEFAULT: &str = "Terminal Ready";
+
+/// The banner template: the user's file, else the default.
+pub fn load(profile: &Profile) -> String {
+    profile
+        .config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join("banner")).ok())
+        .unwrap_or_else(|| DEFAULT.to_string())
+}
+
+/// Fills in `template`, returning its lines. Unlike on the command line,
+/// quotes are just text and values go in as they are.
+pub fn render(
+    template: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    mut run: impl FnMut(&str) -> String,
+) -> Result<Vec<String>, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('$')) => out.extend(chars.next()),
+            ('$', Some('(')) => {
+                chars.next();
+                let command = parse::take_parenthesized(&mut chars)?;
+                out.push_str(run(&command).trim_end_matches('\n'));
+            }
+            ('$', Some('{')) => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                if !parse::is_name(&name) {
+                    return Err(format!("bad substitution '${{{name}}}'"));
+                }
+                out.push_str(&lookup(&name).unwrap_or_default());
+            }
+            ('$', Some(&next)) if next.is_ascii_alphabetic() || next == '_' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                out.push_str(&lookup(&name).unwrap_or_default());
+            }
+            _ => out.push(c),
+        }
+    }
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_variables_and_commands() {
+        let lookup = |name: &str| (name == "USER").then(|| "ada".to_string());
+        let run = |command: &str| format!("<{command}>\n");
+        let lines = render(
+            "Hi $USER, it's ${USER}'s \\$5 $UNSET!\n$(uname -a)\n$ 1",
+            lookup,
+            run,
+        );
+        assert_eq!(
+            lines.unwrap(),
+            ["Hi ada, it's ada's $5 !", "<uname -a>", "$ 1"]
+        );
+        assert!(render("$(date", lookup, run).is_err());
+        assert!(render("${1x}", lookup, run).is_err());
+    }
+}
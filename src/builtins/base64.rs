@@ -0,0 +1,144 @@
+//! `base64 [-d] [FILE]`: standard base64 of a file or piped input, wrapped
+//! at 76 columns like coreutils, or decoded with `-d`.
+
+use std::fs;
+use std::path::Path;
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const WRAP: usize = 76;
+
+fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64, ignoring whitespace such as line breaks. The error
+/// says what was wrong.
+fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut count = 0;
+    let mut padding = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c == '=' {
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return Err("invalid input: data after padding".to_string());
+        }
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or_else(|| format!("invalid input: unexpected '{c}'"))?;
+        group = group << 6 | value as u32;
+        count += 1;
+        if count == 4 {
+            out.extend_from_slice(&group.to_be_bytes()[1..]);
+            group = 0;
+            count = 0;
+        }
+    }
+    match count {
+        0 => {}
+        2 => out.push((group >> 4) as u8),
+        3 => out.extend_from_slice(&((group >> 2) as u16).to_be_bytes()),
+        _ => return Err("invalid input: truncated".to_string()),
+    }
+    Ok(out)
+}
+
+pub fn run(args: &[String], stdin: Option<&str>, cwd: &Path) -> StageOutput {
+    let mut output = StageOutput::default();
+    let decoding = args.iter().any(|a| a == "-d" || a == "--decode");
+    let files: Vec<&String> = args.iter().filter(|a| !a.starts_with('-')).collect();
+    let input = match files.as_slice() {
+        [] => stdin.unwrap_or("").as_bytes().to_vec(),
+        [file] => match fs::read(cwd.join(file)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                output.errors.push(format!("base64: {file}: {e}"));
+                return output;
+            }
+        },
+        _ => {
+            output
+                .errors
+                .push("base64: usage: base64 [-d] [FILE]".to_string());
+            return output;
+        }
+    };
+
+    let text = if decoding {
+        // Output is text, and binary would be mangled into it, so refuse.
+        match decode(&String::from_utf8_lossy(&input)).and_then(|bytes| {
+            String::from_utf8(bytes).map_err(|_| {
+                "decoded data is binary; use the system base64 to write it to a file".to_string()
+            })
+        }) {
+            Ok(text) => text,
+            Err(message) => {
+                output.errors.push(format!("base64: {message}"));
+                return output;
+            }
+        }
+    } else {
+        let encoded = encode(&input);
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(WRAP)
+            .map(|line| std::str::from_utf8(line).unwrap_or_default())
+            .collect();
+        lines.join("\n")
+    };
+    output.lines = text
+        .lines()
+        .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+        .collect();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_with_padding() {
+        for (plain, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v")] {
+            assert_eq!(encode(plain.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(decode("Zm9v\nYmFy\n").unwrap(), b"foobar");
+        assert!(decode("Zm9v!").is_err());
+        assert!(decode("Zg==Zg").is_err());
+        assert!(decode("Z").is_err());
+    }
+
+    #[test]
+    fn binary_output_is_refused() {
+        let decode = |input: &str| run(&["-d".to_string()], Some(input), Path::new("."));
+        assert_eq!(decode("aGk=").text(), "hi\n");
+        let binary = decode("/wA=");
+        assert!(binary.lines.is_empty());
+        assert!(binary.errors[0].contains("binary"));
+    }
+}
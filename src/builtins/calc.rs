@@ -0,0 +1,351 @@
+//! `calc`: a small arithmetic evaluator, so quick sums don't need a
+//! separate calculator.
+//!
+//! Supports `+ - * / % ^`, parentheses, the constants `pi` and `e`, and a
+//! few common functions. `^` binds tighter than unary minus and is
+//! right-associative, so `-2^2` is -4 and `2^3^2` is 512.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => tokens.push(Token::Number(number(&mut chars)?)),
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name.to_ascii_lowercase()));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            _ => return Err(format!("unexpected '{c}'")),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A decimal number with an optional exponent: `3`, `.5`, `1.5e-3`.
+fn number(chars: &mut Peekable<Chars>) -> Result<f64, String> {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+        let exponent_sign = matches!(c, '+' | '-') && text.ends_with(['e', 'E']);
+        if c.is_ascii_digit() || c == '.' || matches!(c, 'e' | 'E') || exponent_sign {
+            text.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    text.parse().map_err(|_| format!("bad number '{text}'"))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(&Token::Op('+')) {
+                value += self.term()?;
+            } else if self.eat(&Token::Op('-')) {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(&Token::Op('*')) {
+                value *= self.unary()?;
+            } else if self.eat(&Token::Op('/')) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat(&Token::Op('%')) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat(&Token::Op('-')) {
+            Ok(-self.unary()?)
+        } else if self.eat(&Token::Op('+')) {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// power := primary ('^' unary)?
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.primary()?;
+        if self.eat(&Token::Op('^')) {
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// primary := number | name | name '(' args ')' | '(' expr ')'
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::Open) => {
+                let value = self.expr()?;
+                if !self.eat(&Token::Close) {
+                    return Err("missing ')'".to_string());
+                }
+                Ok(value)
+            }
+            Some(Token::Name(name)) => {
+                if !self.eat(&Token::Open) {
+                    return constant(&name);
+                }
+                let mut args = Vec::new();
+                if !self.eat(&Token::Close) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::Close) {
+                            break;
+                        }
+                        if !self.eat(&Token::Comma) {
+                            return Err("missing ')'".to_string());
+                        }
+                    }
+                }
+                call(&name, &args)
+            }
+            Some(Token::Op(op)) => Err(format!("unexpected '{op}'")),
+            Some(Token::Close) => Err("unexpected ')'".to_string()),
+            Some(Token::Comma) => Err("unexpected ','".to_string()),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn constant(name: &str) -> Result<f64, String> {
+    match name {
+        "pi" => Ok(std::f64::consts::PI),
+        "e" => Ok(std::f64::consts::E),
+        "tau" => Ok(std::f64::consts::TAU),
+        _ => Err(format!("unknown name '{name}'")),
+    }
+}
+
+const FUNCTIONS: &[&str] = &[
+    "abs", "acos", "asin", "atan", "ceil", "cos", "exp", "floor", "ln", "log", "log2", "max",
+    "min", "round", "sin", "sqrt", "tan",
+];
+
+fn call(name: &str, args: &[f64]) -> Result<f64, String> {
+    if matches!(name, "min" | "max") {
+        let fold = if name == "min" { f64::min } else { f64::max };
+        return args
+            .iter()
+            .copied()
+            .reduce(fold)
+            .ok_or_else(|| format!("{name}() needs at least one argument"));
+    }
+    if !FUNCTIONS.contains(&name) {
+        return Err(format!("unknown function '{name}'"));
+    }
+    let &[x] = args else {
+        return Err(format!("{name}() takes one argument"));
+    };
+    Ok(match name {
+        "abs" => x.abs(),
+        "acos" => x.acos(),
+        "asin" => x.asin(),
+        "atan" => x.atan(),
+        "ceil" => x.ceil(),
+        "cos" => x.cos(),
+        "exp" => x.exp(),
+        "floor" => x.floor(),
+        "ln" => x.ln(),
+        "log" => x.log10(),
+        "log2" => x.log2(),
+        "round" => x.round(),
+        "sin" => x.sin(),
+        "sqrt" => x.sqrt(),
+        _ => x.tan(),
+    })
+}
+
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("empty expression".to_string());
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(match token {
+            Token::Close => "unexpected ')'".to_string(),
+            _ => "expected an operator".to_string(),
+        });
+    }
+    if value.is_nan() {
+        Err("result is not a number".to_string())
+    } else if value.is_infinite() {
+        Err("result is too large".to_string())
+    } else {
+        Ok(value)
+    }
+}
+
+/// Whole numbers print without a fraction; others to at most ten decimal
+/// places, so `0.1 + 0.2` shows as `0.3`.
+pub fn format(value: f64) -> String {
+    let abs = value.abs();
+    if value.fract() == 0.0 && abs < 1e15 {
+        format!("{}", value as i64)
+    } else if !(1e-6..1e15).contains(&abs) {
+        format!("{value:e}")
+    } else {
+        let text = format!("{value:.10}");
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+/// Bare input like `12 * (3 + 4)`: digits, operators and parentheses only,
+/// with at least one operator. Anything with letters is left to the shell,
+/// as is anything starting with `.` or `/`, which reads as a path.
+pub fn looks_like_arithmetic(line: &str) -> bool {
+    line.starts_with(|c: char| c.is_ascii_digit() || c == '(')
+        && line.chars().any(|c| "+-*/%^".contains(c))
+        && line
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_whitespace() || "+-*/%^().".contains(c))
+}
+
+/// Runs `calc EXPRESSION...`; the arguments are joined, so the expression
+/// needn't be quoted.
+pub fn run(args: &[String]) -> StageOutput {
+    let mut output = StageOutput::default();
+    match evaluate(&args.join(" ")) {
+        Ok(value) => output
+            .lines
+            .push(TerminalLine::plain(format(value), OUTPUT_COLOR)),
+        Err(message) => output.errors.push(format!("calc: {message}")),
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_with_precedence() {
+        let eval = |e| evaluate(e).map(format);
+        assert_eq!(eval("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(eval("-2^2").unwrap(), "-4");
+        assert_eq!(eval("2^3^2").unwrap(), "512");
+        assert_eq!(eval("7 % 4 - 10 / 4").unwrap(), "0.5");
+        assert_eq!(eval("0.1 + 0.2").unwrap(), "0.3");
+        assert_eq!(eval("1.5e3").unwrap(), "1500");
+        assert_eq!(eval("sqrt(16) + max(1, 5, 3)").unwrap(), "9");
+        assert_eq!(eval("round(pi * 100)").unwrap(), "314");
+
+        assert_eq!(eval("1 / (2 - 2)").unwrap_err(), "division by zero");
+        assert_eq!(eval("(1 + 2").unwrap_err(), "missing ')'");
+        assert_eq!(eval("1 +").unwrap_err(), "unexpected end of expression");
+        assert_eq!(eval("2 3").unwrap_err(), "expected an operator");
+        assert_eq!(eval("foo(1)").unwrap_err(), "unknown function 'foo'");
+        assert_eq!(eval("sqrt(-1)").unwrap_err(), "result is not a number");
+
+        assert!(looks_like_arithmetic("12 * (3 + 4)"));
+        assert!(!looks_like_arithmetic("42"));
+        assert!(!looks_like_arithmetic("7z x archive.7z"));
+    }
+
+    #[test]
+    fn only_bare_arithmetic_is_taken_over() {
+        assert!(looks_like_arithmetic("12 * (3 + 4)"));
+        assert!(looks_like_arithmetic("(1+2)/3"));
+        assert!(looks_like_arithmetic("0.5 * 4"));
+        assert!(!looks_like_arithmetic("42"));
+        assert!(!looks_like_arithmetic("./configure"));
+        assert!(!looks_like_arithmetic("../2/3"));
+        assert!(!looks_like_arithmetic(".5 * 2"));
+        assert!(!looks_like_arithmetic("/1/2"));
+        assert!(!looks_like_arithmetic("7z x a.7z"));
+    }
+}
@@ -0,0 +1,268 @@
+//! `chmod [-R] MODE FILE...` with octal (`755`) and symbolic (`u+x,go-w`)
+//! modes. Each file's new permissions are printed, `ls -l` style, so the
+//! change can be checked at a glance. Other options go to the external
+//! `chmod`.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Octal(u32),
+    /// `(who mask, op, permission letters)` per clause; a who mask of 0
+    /// means none was given.
+    Symbolic(Vec<(u32, char, String)>),
+}
+
+fn parse_mode(text: &str) -> Option<Mode> {
+    if !text.is_empty() && text.chars().all(|c| c.is_digit(8)) {
+        return u32::from_str_radix(text, 8)
+            .ok()
+            .filter(|&mode| mode <= 0o7777)
+            .map(Mode::Octal);
+    }
+    let mut clauses = Vec::new();
+    for clause in text.split(',') {
+        let ops_start = clause.find(['+', '-', '='])?;
+        let mut who = 0;
+        for c in clause[..ops_start].chars() {
+            who |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return None,
+            };
+        }
+        // `u+x-w` is two actions on the same users.
+        let mut rest = &clause[ops_start..];
+        while let Some(op) = rest.chars().next() {
+            let perms_end = rest[1..]
+                .find(['+', '-', '='])
+                .map_or(rest.len(), |i| i + 1);
+            let perms = &rest[1..perms_end];
+            if !perms.chars().all(|c| "rwxXst".contains(c)) {
+                return None;
+            }
+            clauses.push((who, op, perms.to_string()));
+            rest = &rest[perms_end..];
+        }
+    }
+    Some(Mode::Symbolic(clauses))
+}
+
+/// The mode `mode` gives a file that currently has `old`. Clauses without
+/// users apply to all of them, minus the bits `umask` clears.
+fn apply(mode: &Mode, old: u32, is_dir: bool, umask: u32) -> u32 {
+    let clauses = match mode {
+        Mode::Octal(mode) => return *mode,
+        Mode::Symbolic(clauses) => clauses,
+    };
+    let mut new = old & 0o7777;
+    for (who, op, perms) in clauses {
+        let (who, mask) = if *who == 0 {
+            (0o7777, !umask)
+        } else {
+            (*who, 0o7777)
+        };
+        let mut bits = 0;
+        for c in perms.chars() {
+            bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                // Execute only for directories and files something can
+                // already execute.
+                'X' if is_dir || new & 0o111 != 0 => 0o111,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => 0,
+            };
+        }
+        let bits = bits & who & mask;
+        new = match op {
+            '+' => new | bits,
+            '-' => new & !bits,
+            _ => (new & !who) | bits,
+        };
+    }
+    new
+}
+
+/// `-rwxr-xr-x`, as `ls -l` shows it.
+fn permission_string(mode: u32, kind: char) -> String {
+    let mut text = String::from(kind);
+    for (shift, special, set, unset) in [
+        (6, 0o4000, 's', 'S'),
+        (3, 0o2000, 's', 'S'),
+        (0, 0o1000, 't', 'T'),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// The umask from before any threads started, for systems without
+/// `/proc`. Reading it means setting it, which would race with other
+/// threads creating files, so it's only done once.
+static STARTUP_UMASK: OnceLock<u32> = OnceLock::new();
+
+/// Records the umask while the process has a single thread; see `umask`.
+#[allow(clippy::useless_conversion)] // mode_t is narrower on macOS
+pub fn capture_umask() {
+    STARTUP_UMASK.get_or_init(|| {
+        // SAFETY: umask(2) can't fail and has no memory-safety preconditions.
+        let mask = unsafe { libc::umask(0) };
+        unsafe { libc::umask(mask) };
+        u32::from(mask) & 0o777
+    });
+}
+
+/// The process umask, from `/proc/self/status` where there is one.
+fn umask() -> u32 {
+    let from_proc = fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            let line = status
+                .lines()
+                .find_map(|line| line.strip_prefix("Umask:"))?;
+            u32::from_str_radix(line.trim(), 8).ok()
+        });
+    match from_proc {
+        Some(mask) => mask & 0o777,
+        None => {
+            capture_umask();
+            STARTUP_UMASK.get().copied().unwrap_or(0o022)
+        }
+    }
+}
+
+fn change(path: &Path, shown: &str, mode: &Mode, recursive: bool, output: &mut StageOutput) {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            output.errors.push(format!("chmod: {shown}: {e}"));
+            return;
+        }
+    };
+    let is_dir = metadata.is_dir();
+    let new = apply(mode, metadata.permissions().mode(), is_dir, umask());
+    if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(new)) {
+        output.errors.push(format!("chmod: {shown}: {e}"));
+        return;
+    }
+    let kind = if is_dir { 'd' } else { '-' };
+    output.lines.push(TerminalLine::plain(
+        format!("{}  {shown}", permission_string(new, kind)),
+        OUTPUT_COLOR,
+    ));
+    if !(recursive && is_dir) {
+        return;
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            output.errors.push(format!("chmod: {shown}: {e}"));
+            return;
+        }
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        // Like chmod -R, links met on the way down are left alone.
+        if entry.file_type().is_ok_and(|t| t.is_symlink()) {
+            continue;
+        }
+        let name = entry.file_name();
+        let shown = format!("{}/{}", shown.trim_end_matches('/'), name.to_string_lossy());
+        change(&entry.path(), &shown, mode, true, output);
+    }
+}
+
+pub fn run(args: &[String], cwd: &Path) -> Option<StageOutput> {
+    let mut recursive = false;
+    let mut mode = None;
+    let mut files = Vec::new();
+    let mut options_done = false;
+    for arg in args {
+        if !options_done && arg == "--" {
+            options_done = true;
+        } else if !options_done && arg == "-R" {
+            recursive = true;
+        } else if mode.is_none() {
+            // Symbolic modes such as `-w` look like options; anything else
+            // that does is an option this doesn't handle.
+            mode = Some(parse_mode(arg)?);
+        } else {
+            files.push(arg);
+        }
+    }
+    let mode = mode?;
+    if files.is_empty() {
+        return None;
+    }
+    let mut output = StageOutput::default();
+    for file in files {
+        change(&cwd.join(file), file, &mode, recursive, &mut output);
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chmod(mode: &str, old: u32, is_dir: bool) -> String {
+        let new = apply(&parse_mode(mode).unwrap(), old, is_dir, 0o022);
+        permission_string(new, if is_dir { 'd' } else { '-' })
+    }
+
+    #[test]
+    fn octal_and_symbolic_modes() {
+        assert_eq!(chmod("755", 0o644, false), "-rwxr-xr-x");
+        assert_eq!(chmod("u+x", 0o644, false), "-rwxr--r--");
+        assert_eq!(chmod("go-w", 0o666, false), "-rw-r--r--");
+        assert_eq!(chmod("+w", 0o444, false), "-rw-r--r--");
+        assert_eq!(chmod("a=r,u+w", 0o777, false), "-rw-r--r--");
+        assert_eq!(chmod("u+x-w", 0o644, false), "-r-xr--r--");
+        assert_eq!(chmod("a+X", 0o644, true), "drwxr-xr-x");
+        assert_eq!(chmod("a+X", 0o644, false), "-rw-r--r--");
+        assert_eq!(chmod("u+s,o+t", 0o755, false), "-rwsr-xr-t");
+        assert_eq!(chmod("g+s", 0o644, false), "-rw-r-Sr--");
+        assert!(parse_mode("8").is_none());
+        assert!(parse_mode("u+q").is_none());
+        assert!(parse_mode("-v").is_none());
+        assert!(parse_mode("u=g").is_none());
+    }
+
+    #[test]
+    fn recursive_after_the_mode() {
+        let dir = std::env::temp_dir().join(format!("chmod-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/file"), "").unwrap();
+        let args: Vec<String> = ["go-rwx", "-R", "sub"].map(String::from).to_vec();
+        let output = run(&args, &dir).unwrap();
+        let mode = fs::metadata(dir.join("sub/file"))
+            .unwrap()
+            .permissions()
+            .mode();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(output.errors.is_empty());
+        assert_eq!(output.lines.len(), 2);
+        assert_eq!(mode & 0o077, 0);
+        assert_eq!(umask() & !0o777, 0);
+    }
+}
@@ -0,0 +1,143 @@
+//! `cowsay [-W WIDTH] [TEXT]`: the text, or piped input, in a speech
+//! bubble sized to fit, said by an ASCII cow. `fortune`: a random quip
+//! from a bundled list.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+/// Quips separated by lines of just `%`, as fortune files are.
+const FORTUNES: &str = include_str!("fortunes.txt");
+
+/// Columns of text per bubble line unless `-W` says otherwise.
+const DEFAULT_WIDTH: usize = 40;
+
+const COW: &[&str] = &[
+    r"        \   ^__^",
+    r"         \  (oo)\_______",
+    r"            (__)\       )\/\",
+    r"                ||----w |",
+    r"                ||     ||",
+];
+
+/// `text` broken into lines of at most `width` columns, at spaces where
+/// possible. Line breaks in the text are kept; runs of spaces are not.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let needed = word.width() + usize::from(!line.is_empty());
+            if !line.is_empty() && line.width() + needed > width {
+                lines.push(std::mem::take(&mut line));
+            } else if !line.is_empty() {
+                line.push(' ');
+            }
+            // A word wider than the bubble is split wherever it overflows.
+            for c in word.chars() {
+                if line.width() + c.width().unwrap_or(0) > width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The speech bubble around `lines`, then the cow.
+fn render(lines: &[String]) -> Vec<String> {
+    let width = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+    let mut out = vec![format!(" {}", "_".repeat(width + 2))];
+    for (i, line) in lines.iter().enumerate() {
+        let (open, close) = match (i, lines.len()) {
+            (_, 1) => ('<', '>'),
+            (0, _) => ('/', '\\'),
+            (i, n) if i == n - 1 => ('\\', '/'),
+            _ => ('|', '|'),
+        };
+        let padding = " ".repeat(width - line.width());
+        out.push(format!("{open} {line}{padding} {close}"));
+    }
+    out.push(format!(" {}", "-".repeat(width + 2)));
+    out.extend(COW.iter().map(|line| line.to_string()));
+    out
+}
+
+pub fn cowsay(args: &[String], stdin: Option<&str>) -> StageOutput {
+    let mut output = StageOutput::default();
+    let mut width = DEFAULT_WIDTH;
+    let mut words = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-W" => match args.next().and_then(|n| n.parse().ok()).filter(|&n| n > 0) {
+                Some(n) => width = n,
+                None => {
+                    output.errors.push("cowsay: -W needs a width".to_string());
+                    return output;
+                }
+            },
+            _ => words.push(arg.as_str()),
+        }
+    }
+    let text = if words.is_empty() {
+        stdin.unwrap_or("").trim_end().to_string()
+    } else {
+        words.join(" ")
+    };
+    if text.is_empty() {
+        output
+            .errors
+            .push("cowsay: nothing to say (give TEXT or pipe some in)".to_string());
+        return output;
+    }
+    output.lines = render(&wrap(&text, width))
+        .into_iter()
+        .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+        .collect();
+    output
+}
+
+pub fn fortune() -> StageOutput {
+    let fortunes: Vec<&str> = FORTUNES
+        .split("\n%\n")
+        .map(str::trim)
+        .filter(|fortune| !fortune.is_empty())
+        .collect();
+    // A freshly seeded hasher is the standard library's only randomness.
+    let pick = RandomState::new().build_hasher().finish() as usize % fortunes.len();
+    StageOutput {
+        lines: fortunes[pick]
+            .lines()
+            .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+            .collect(),
+        ..StageOutput::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bubble_fits_wrapped_and_wide_text() {
+        assert_eq!(
+            render(&wrap("moo", 40))[..3],
+            [" _____", "< moo >", " -----"]
+        );
+
+        let lines = wrap("the quick brown fox\n日本語 ok\tgo", 10);
+        assert_eq!(lines, ["the quick", "brown fox", "日本語 ok", "go"]);
+        let bubble = render(&lines);
+        assert_eq!(bubble[1], "/ the quick \\");
+        assert_eq!(bubble[3], "| 日本語 ok |");
+        assert_eq!(bubble[4], "\\ go        /");
+        assert_eq!(wrap("abcdefgh", 3), ["abc", "def", "gh"]);
+    }
+}
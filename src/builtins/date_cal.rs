@@ -0,0 +1,132 @@
+//! `date [-u] [+FORMAT]` and `cal [MONTH YEAR]`, the same on every
+//! platform. Names of days and months are in English.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, Local, NaiveDate, Utc};
+
+use super::StageOutput;
+use crate::line::{
+    Segment, TerminalLine, HEADING_COLOR, MATCH_BACKGROUND, MATCH_COLOR, OUTPUT_COLOR,
+};
+
+/// What `date` prints without a format, as GNU date does in the C locale.
+const DEFAULT_FORMAT: &str = "%a %b %e %H:%M:%S %Z %Y";
+
+pub fn date(args: &[String]) -> Option<StageOutput> {
+    let mut utc = false;
+    let mut format = DEFAULT_FORMAT;
+    for arg in args {
+        match arg.as_str() {
+            "-u" | "--utc" => utc = true,
+            _ => format = arg.strip_prefix('+')?,
+        }
+    }
+    let mut output = StageOutput::default();
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        output
+            .errors
+            .push(format!("date: invalid format '{format}'"));
+        return Some(output);
+    }
+    let text = if utc {
+        Utc::now().format(format).to_string()
+    } else {
+        Local::now().format(format).to_string()
+    };
+    output.lines = text
+        .lines()
+        .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+        .collect();
+    Some(output)
+}
+
+/// A month laid out as `cal` does, weeks starting on Sunday, with `today`
+/// (a day of this month) highlighted.
+fn month_lines(year: i32, month: u32, today: Option<u32>) -> Option<Vec<TerminalLine>> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    let days = next.signed_duration_since(first).num_days() as u32;
+
+    let title = format!("{} {year}", first.format("%B"));
+    let mut lines = vec![
+        TerminalLine::plain(format!("{title:^20}").trim_end(), HEADING_COLOR),
+        TerminalLine::plain("Su Mo Tu We Th Fr Sa", HEADING_COLOR),
+    ];
+    let offset = first.weekday().num_days_from_sunday();
+    let mut week = vec![Segment::new("   ".repeat(offset as usize), OUTPUT_COLOR)];
+    for day in 1..=days {
+        let column = (offset + day - 1) % 7;
+        if column > 0 {
+            week.push(Segment::new(" ", OUTPUT_COLOR));
+        }
+        let text = format!("{day:>2}");
+        week.push(if today == Some(day) {
+            Segment::highlighted(text, MATCH_COLOR, MATCH_BACKGROUND)
+        } else {
+            Segment::new(text, OUTPUT_COLOR)
+        });
+        if column == 6 || day == days {
+            lines.push(TerminalLine::from_segments(std::mem::take(&mut week)));
+        }
+    }
+    Some(lines)
+}
+
+pub fn cal(args: &[String]) -> Option<StageOutput> {
+    let now = Local::now().date_naive();
+    let (month, year) = match args {
+        [] => (now.month(), now.year()),
+        [month, year] => (month.parse().ok()?, year.parse().ok()?),
+        _ => return None,
+    };
+    let mut output = StageOutput::default();
+    let today = (month == now.month() && year == now.year()).then(|| now.day());
+    match month_lines(year, month, today) {
+        Some(lines) if (1..=9999).contains(&year) => output.lines = lines,
+        _ => output
+            .errors
+            .push(format!("cal: invalid month or year '{month} {year}'")),
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_a_month() {
+        let lines: Vec<String> = month_lines(2026, 2, Some(14))
+            .unwrap()
+            .iter()
+            .map(TerminalLine::text)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "   February 2026",
+                "Su Mo Tu We Th Fr Sa",
+                " 1  2  3  4  5  6  7",
+                " 8  9 10 11 12 13 14",
+                "15 16 17 18 19 20 21",
+                "22 23 24 25 26 27 28",
+            ]
+        );
+        let march = month_lines(2026, 3, None).unwrap();
+        assert_eq!(march[2].text(), " 1  2  3  4  5  6  7");
+        assert_eq!(
+            month_lines(2024, 2, None).unwrap()[6].text(),
+            "25 26 27 28 29"
+        );
+        assert!(month_lines(2026, 13, None).is_none());
+
+        let args = |s: &str| vec![s.to_string()];
+        assert!(date(&args("-d")).is_none());
+        assert_eq!(date(&args("+%Q")).unwrap().errors.len(), 1);
+        assert_eq!(date(&args("+x%%y")).unwrap().text(), "x%y\n");
+    }
+}
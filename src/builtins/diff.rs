@@ -0,0 +1,361 @@
+//! Internal line-based `diff` with colored output.
+
+use std::fs;
+use std::path::Path;
+
+use super::StageOutput;
+use crate::line::{TerminalLine, DELETED_COLOR, HEADING_COLOR, INSERTED_COLOR, OUTPUT_COLOR};
+
+/// Beyond this many LCS table cells the external diff is used instead.
+const MAX_TABLE_CELLS: usize = 25_000_000;
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Edit script turning `a` into `b`, via a longest-common-subsequence table.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if a[i] == b[j] {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[at(i + 1, j)] >= lcs[at(i, j + 1)] {
+            ops.push(Op::Delete(i));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(j));
+            j += 1;
+        }
+    }
+    ops.extend((i..n).map(Op::Delete));
+    ops.extend((j..m).map(Op::Insert));
+    ops
+}
+
+/// Lines of `a` and `b` paired up to show side by side: common lines
+/// level with each other, and within a change the removed lines next to
+/// the added ones. Inputs too large to diff are paired by position.
+pub fn align(a: &[&str], b: &[&str]) -> Vec<(Option<usize>, Option<usize>)> {
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_TABLE_CELLS {
+        return (0..a.len().max(b.len()))
+            .map(|k| ((k < a.len()).then_some(k), (k < b.len()).then_some(k)))
+            .collect();
+    }
+    let mut rows = Vec::new();
+    let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+    let flush = |rows: &mut Vec<_>, deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+        for k in 0..deleted.len().max(inserted.len()) {
+            rows.push((deleted.get(k).copied(), inserted.get(k).copied()));
+        }
+        deleted.clear();
+        inserted.clear();
+    };
+    for op in diff_lines(a, b) {
+        match op {
+            Op::Equal(i, j) => {
+                flush(&mut rows, &mut deleted, &mut inserted);
+                rows.push((Some(i), Some(j)));
+            }
+            Op::Delete(i) => deleted.push(i),
+            Op::Insert(j) => inserted.push(j),
+        }
+    }
+    flush(&mut rows, &mut deleted, &mut inserted);
+    rows
+}
+
+/// `n` or `n,m` as used in normal-format change headers (1-based).
+fn range(start: usize, end: usize) -> String {
+    if end - start <= 1 {
+        (start + 1).to_string()
+    } else {
+        format!("{},{}", start + 1, end)
+    }
+}
+
+fn normal_format(ops: &[Op], a: &[&str], b: &[&str], out: &mut Vec<TerminalLine>) {
+    let mut k = 0;
+    while k < ops.len() {
+        let (i, j) = match ops[k] {
+            Op::Equal(..) => {
+                k += 1;
+                continue;
+            }
+            Op::Delete(i) => (i, insert_position(ops, k)),
+            Op::Insert(j) => (delete_position(ops, k), j),
+        };
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        while let Some(op) = ops.get(k) {
+            match *op {
+                Op::Delete(i) => deleted.push(i),
+                Op::Insert(j) => inserted.push(j),
+                Op::Equal(..) => break,
+            }
+            k += 1;
+        }
+
+        let header = match (deleted.is_empty(), inserted.is_empty()) {
+            (true, _) => format!("{i}a{}", range(j, j + inserted.len())),
+            (_, true) => format!("{}d{j}", range(i, i + deleted.len())),
+            _ => format!(
+                "{}c{}",
+                range(i, i + deleted.len()),
+                range(j, j + inserted.len())
+            ),
+        };
+        out.push(TerminalLine::plain(header, HEADING_COLOR));
+        for &d in &deleted {
+            out.push(TerminalLine::plain(format!("< {}", a[d]), DELETED_COLOR));
+        }
+        if !deleted.is_empty() && !inserted.is_empty() {
+            out.push(TerminalLine::plain("---", OUTPUT_COLOR));
+        }
+        for &n in &inserted {
+            out.push(TerminalLine::plain(format!("> {}", b[n]), INSERTED_COLOR));
+        }
+    }
+}
+
+/// Index into `b` that a deletion at `ops[k]` lines up with.
+fn insert_position(ops: &[Op], k: usize) -> usize {
+    ops[..k]
+        .iter()
+        .rev()
+        .find_map(|op| match *op {
+            Op::Equal(_, j) | Op::Insert(j) => Some(j + 1),
+            Op::Delete(_) => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Index into `a` that an insertion at `ops[k]` lines up with.
+fn delete_position(ops: &[Op], k: usize) -> usize {
+    ops[..k]
+        .iter()
+        .rev()
+        .find_map(|op| match *op {
+            Op::Equal(i, _) | Op::Delete(i) => Some(i + 1),
+            Op::Insert(_) => None,
+        })
+        .unwrap_or(0)
+}
+
+fn unified_format(
+    ops: &[Op],
+    a: &[&str],
+    b: &[&str],
+    names: (&str, &str),
+    out: &mut Vec<TerminalLine>,
+) {
+    out.push(TerminalLine::plain(
+        format!("--- {}", names.0),
+        DELETED_COLOR,
+    ));
+    out.push(TerminalLine::plain(
+        format!("+++ {}", names.1),
+        INSERTED_COLOR,
+    ));
+
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&k| !matches!(ops[k], Op::Equal(..)))
+        .collect();
+    let mut c = 0;
+    while c < changes.len() {
+        // Grow the hunk while at most 2*CONTEXT unchanged lines separate
+        // it from the next change, so the two contexts would touch.
+        let start = changes[c].saturating_sub(CONTEXT);
+        let mut last = changes[c];
+        while c + 1 < changes.len() && changes[c + 1] - last <= 2 * CONTEXT + 1 {
+            c += 1;
+            last = changes[c];
+        }
+        let end = (last + CONTEXT + 1).min(ops.len());
+        c += 1;
+
+        let hunk = &ops[start..end];
+        let a_start = hunk.iter().find_map(|op| match *op {
+            Op::Equal(i, _) | Op::Delete(i) => Some(i),
+            Op::Insert(_) => None,
+        });
+        let b_start = hunk.iter().find_map(|op| match *op {
+            Op::Equal(_, j) | Op::Insert(j) => Some(j),
+            Op::Delete(_) => None,
+        });
+        let a_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(_)))
+            .count();
+        let b_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(_)))
+            .count();
+        let a_from = a_start.map_or(delete_position(ops, start), |i| i + 1);
+        let b_from = b_start.map_or(insert_position(ops, start), |j| j + 1);
+        out.push(TerminalLine::plain(
+            format!("@@ -{a_from},{a_len} +{b_from},{b_len} @@"),
+            HEADING_COLOR,
+        ));
+
+        for op in hunk {
+            out.push(match *op {
+                Op::Equal(i, _) => TerminalLine::plain(format!(" {}", a[i]), OUTPUT_COLOR),
+                Op::Delete(i) => TerminalLine::plain(format!("-{}", a[i]), DELETED_COLOR),
+                Op::Insert(j) => TerminalLine::plain(format!("+{}", b[j]), INSERTED_COLOR),
+            });
+        }
+    }
+}
+
+/// Runs `diff [-u] A B`. Returns `None` for directories, other flags, or
+/// files too large for the in-memory table, deferring to the system diff.
+pub fn run(args: &[String], cwd: &Path) -> Option<StageOutput> {
+    let mut unified = false;
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-u" | "--unified" => unified = true,
+            flag if flag.starts_with('-') => return None,
+            file => files.push(file),
+        }
+    }
+    let [left, right] = files[..] else {
+        return None;
+    };
+    let (left_path, right_path) = (cwd.join(left), cwd.join(right));
+    if left_path.is_dir() || right_path.is_dir() {
+        return None;
+    }
+
+    let mut output = StageOutput::default();
+    let read = |path: &Path, name: &str| {
+        fs::read(path)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .map_err(|e| format!("diff: {name}: {e}"))
+    };
+    let (a_text, b_text) = match (read(&left_path, left), read(&right_path, right)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (a, b) => {
+            output.errors.extend(a.err());
+            output.errors.extend(b.err());
+            return Some(output);
+        }
+    };
+
+    let a: Vec<&str> = a_text.lines().collect();
+    let b: Vec<&str> = b_text.lines().collect();
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_TABLE_CELLS {
+        return None;
+    }
+    let ops = diff_lines(&a, &b);
+    if ops.iter().all(|op| matches!(op, Op::Equal(..))) {
+        return Some(output);
+    }
+    if unified {
+        unified_format(&ops, &a, &b, (left, right), &mut output.lines);
+    } else {
+        normal_format(&ops, &a, &b, &mut output.lines);
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[TerminalLine]) -> Vec<String> {
+        lines.iter().map(TerminalLine::text).collect()
+    }
+
+    #[test]
+    fn edit_script_keeps_common_lines() {
+        let ops = diff_lines(&["a", "b", "c"], &["a", "x", "c"]);
+        assert_eq!(
+            ops,
+            vec![
+                Op::Equal(0, 0),
+                Op::Delete(1),
+                Op::Insert(1),
+                Op::Equal(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn aligns_changes_side_by_side() {
+        let rows = align(&["a", "b", "c", "d"], &["a", "x", "y", "d", "e"]);
+        assert_eq!(
+            rows,
+            [
+                (Some(0), Some(0)),
+                (Some(1), Some(1)),
+                (Some(2), Some(2)),
+                (Some(3), Some(3)),
+                (None, Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn hunks_merge_when_their_contexts_touch() {
+        let hunks = |gap: usize| {
+            let mut a = vec!["x"];
+            a.extend(vec!["="; gap]);
+            a.push("y");
+            let mut b = vec!["X"];
+            b.extend(vec!["="; gap]);
+            b.push("Y");
+            let mut out = Vec::new();
+            unified_format(&diff_lines(&a, &b), &a, &b, ("a", "b"), &mut out);
+            texts(&out).iter().filter(|l| l.starts_with("@@")).count()
+        };
+        assert_eq!(hunks(2 * CONTEXT), 1);
+        assert_eq!(hunks(2 * CONTEXT + 1), 2);
+    }
+
+    #[test]
+    fn normal_format_headers() {
+        let a = ["a", "b", "c", "d"];
+        let b = ["a", "x", "c", "d", "e"];
+        let mut out = Vec::new();
+        normal_format(&diff_lines(&a, &b), &a, &b, &mut out);
+        assert_eq!(texts(&out), ["2c2", "< b", "---", "> x", "4a5", "> e"]);
+
+        let b = ["a", "d"];
+        let mut out = Vec::new();
+        normal_format(&diff_lines(&a, &b), &a, &b, &mut out);
+        assert_eq!(texts(&out), ["2,3d1", "< b", "< c"]);
+    }
+
+    #[test]
+    fn unified_format_hunk() {
+        let a = ["a", "b", "c"];
+        let b = ["a", "c", "d"];
+        let mut out = Vec::new();
+        unified_format(&diff_lines(&a, &b), &a, &b, ("l", "r"), &mut out);
+        assert_eq!(
+            texts(&out),
+            ["--- l", "+++ r", "@@ -1,3 +1,3 @@", " a", "-b", " c", "+d"]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! `echo [-neE] [ARG...]`, the same on every platform. Variables have
+//! already been expanded by the time the arguments get here.
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+/// Interprets backslash escapes as `echo -e` does. Returns the text and
+/// whether `\c` asked for output to stop there.
+fn unescape(text: &str) -> (String, bool) {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('a') => out.push('\u{7}'),
+            Some('b') => out.push('\u{8}'),
+            Some('c') => return (out, true),
+            Some('e' | 'E') => out.push('\u{1b}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('v') => out.push('\u{b}'),
+            Some(radix @ ('0' | 'x')) => {
+                let (base, max) = if radix == '0' { (8, 3) } else { (16, 2) };
+                let mut digits = String::new();
+                while digits.len() < max {
+                    match chars.peek() {
+                        Some(d) if d.is_digit(base) => digits.push(*d),
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                match u32::from_str_radix(&digits, base)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    Some(c) => out.push(c),
+                    None if radix == 'x' => out.push_str("\\x"),
+                    None => out.push('\0'),
+                }
+            }
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    (out, false)
+}
+
+pub fn run(args: &[String]) -> StageOutput {
+    let mut newline = true;
+    let mut escapes = false;
+    let mut words = args;
+    // Like bash, only words made entirely of known flags are options.
+    while let Some(flag) = words.first().and_then(|w| w.strip_prefix('-')) {
+        if flag.is_empty() || !flag.chars().all(|c| matches!(c, 'n' | 'e' | 'E')) {
+            break;
+        }
+        for c in flag.chars() {
+            match c {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        words = &words[1..];
+    }
+
+    let mut text = words.join(" ");
+    if escapes {
+        let (unescaped, stop) = unescape(&text);
+        text = unescaped;
+        newline &= !stop;
+    }
+    if newline {
+        text.push('\n');
+    }
+    let body = text.strip_suffix('\n').unwrap_or(&text);
+    let mut output = StageOutput::default();
+    if !text.is_empty() {
+        output.lines = body
+            .split('\n')
+            .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+            .collect();
+    }
+    output.no_newline = !text.is_empty() && !text.ends_with('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        run(&args).text()
+    }
+
+    #[test]
+    fn flags_and_escapes() {
+        assert_eq!(echo(&["a", "b"]), "a b\n");
+        assert_eq!(echo(&["-n", "a"]), "a");
+        assert_eq!(echo(&["-n"]), "");
+        assert_eq!(echo(&[]), "\n");
+        assert_eq!(echo(&["-e", "a\\tb\\nc"]), "a\tb\nc\n");
+        assert_eq!(echo(&["a\\tb"]), "a\\tb\n");
+        assert_eq!(echo(&["-ne", "x\\n"]), "x\n");
+        assert_eq!(echo(&["-e", "a\\cb"]), "a");
+        assert_eq!(echo(&["-e", "\\x41\\0102"]), "AB\n");
+        assert_eq!(echo(&["-x", "-n"]), "-x -n\n");
+    }
+}
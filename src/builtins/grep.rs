@@ -0,0 +1,397 @@
+//! Internal `grep` that highlights each match within its line.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::{Regex, RegexBuilder};
+
+use super::StageOutput;
+use crate::line::{
+    Segment, TerminalLine, LINE_NUMBER_COLOR, MATCH_BACKGROUND, MATCH_COLOR, OUTPUT_COLOR,
+    PATH_COLOR,
+};
+use crate::links::FileLink;
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum Syntax {
+    #[default]
+    Basic,
+    Extended,
+    Fixed,
+}
+
+#[derive(Default)]
+struct Options {
+    ignore_case: bool,
+    line_numbers: bool,
+    invert: bool,
+    recursive: bool,
+    /// `-R`: also follow symbolic links met while recursing, which `-r`
+    /// skips.
+    dereference: bool,
+    syntax: Syntax,
+}
+
+/// Parses `args` (without the leading `grep`). Returns `None` for any option
+/// this implementation doesn't understand.
+fn parse_args(args: &[String]) -> Option<(Options, String, Vec<String>)> {
+    let mut options = Options::default();
+    let mut pattern = None;
+    let mut paths = Vec::new();
+    let mut options_done = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if options_done || !arg.starts_with('-') || arg == "-" {
+            if pattern.is_none() {
+                pattern = Some(arg.clone());
+            } else {
+                paths.push(arg.clone());
+            }
+            continue;
+        }
+        match arg.as_str() {
+            "--" => options_done = true,
+            "--color" | "--colour" | "--color=auto" | "--color=always" | "--colour=auto"
+            | "--colour=always" => {}
+            "--ignore-case" => options.ignore_case = true,
+            "--line-number" => options.line_numbers = true,
+            "--invert-match" => options.invert = true,
+            "--recursive" => options.recursive = true,
+            "--dereference-recursive" => {
+                options.recursive = true;
+                options.dereference = true;
+            }
+            "-e" | "--regexp" => pattern = Some(iter.next()?.clone()),
+            flags if !flags.starts_with("--") => {
+                for flag in flags[1..].chars() {
+                    match flag {
+                        'i' => options.ignore_case = true,
+                        'n' => options.line_numbers = true,
+                        'v' => options.invert = true,
+                        'r' => options.recursive = true,
+                        'R' => {
+                            options.recursive = true;
+                            options.dereference = true;
+                        }
+                        'E' => options.syntax = Syntax::Extended,
+                        'F' => options.syntax = Syntax::Fixed,
+                        _ => return None,
+                    }
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some((options, pattern?, paths))
+}
+
+/// Translates a POSIX basic regular expression, where `+ ? | ( ) { }` are
+/// literal unless escaped, into the `regex` crate's syntax.
+pub(super) fn bre_to_regex(pattern: &str) -> String {
+    const SPECIAL: &[char] = &['+', '?', '|', '(', ')', '{', '}'];
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if SPECIAL.contains(&next) => out.push(next),
+                Some(next) => {
+                    out.push('\\');
+                    out.push(next);
+                }
+                None => out.push_str("\\\\"),
+            },
+            c if SPECIAL.contains(&c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn build_regex(pattern: &str, options: &Options) -> Result<Regex, regex::Error> {
+    let source = match options.syntax {
+        Syntax::Basic => bre_to_regex(pattern),
+        Syntax::Extended => pattern.to_string(),
+        Syntax::Fixed => regex::escape(pattern),
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(options.ignore_case)
+        .build()
+}
+
+/// Splits `text` into plain and highlighted segments at every match.
+pub fn highlight(text: &str, re: &Regex) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for m in re.find_iter(text).filter(|m| !m.is_empty()) {
+        if m.start() > last {
+            segments.push(Segment::new(&text[last..m.start()], OUTPUT_COLOR));
+        }
+        segments.push(Segment::highlighted(
+            m.as_str(),
+            MATCH_COLOR,
+            MATCH_BACKGROUND,
+        ));
+        last = m.end();
+    }
+    if last < text.len() || segments.is_empty() {
+        segments.push(Segment::new(&text[last..], OUTPUT_COLOR));
+    }
+    segments
+}
+
+/// Greps `text`, read from `file` if it came from one. The `name:line`
+/// prefix of each result then links to the match, so clicking it opens
+/// the editor there, even for names link detection would pass over.
+fn grep_text(
+    text: &str,
+    file: Option<(&Path, Option<&str>)>,
+    re: &Regex,
+    options: &Options,
+    out: &mut Vec<TerminalLine>,
+) {
+    let name = file.and_then(|(_, name)| name);
+    for (index, line) in text.lines().enumerate() {
+        if re.is_match(line) == options.invert {
+            continue;
+        }
+        let mut segments = Vec::new();
+        if let Some(name) = name {
+            segments.push(Segment::new(name, PATH_COLOR));
+            segments.push(Segment::new(":", OUTPUT_COLOR));
+        }
+        if options.line_numbers {
+            segments.push(Segment::new((index + 1).to_string(), LINE_NUMBER_COLOR));
+            segments.push(Segment::new(":", OUTPUT_COLOR));
+        }
+        let prefix: usize = segments.iter().map(|s| s.text.len()).sum();
+        if options.invert {
+            segments.push(Segment::new(line, OUTPUT_COLOR));
+        } else {
+            segments.extend(highlight(line, re));
+        }
+        let mut result = TerminalLine::from_segments(segments);
+        if let Some((path, _)) = file.filter(|_| prefix > 0) {
+            result.links.push(FileLink {
+                range: 0..prefix - 1,
+                path: path.to_path_buf(),
+                line: Some(index as u32 + 1),
+                column: None,
+            });
+        }
+        out.push(result);
+    }
+}
+
+/// Whether `args` (without the leading `grep`) search directories with
+/// `-r` or `-R`, which can take a while on a large tree.
+pub fn is_recursive(args: &[String]) -> bool {
+    parse_args(args).is_some_and(|(options, ..)| options.recursive)
+}
+
+/// Collects the files under `path`, in sorted order, for `-r`. `path`
+/// itself is followed if it's a link; links inside it only with
+/// `follow_links`, and `seen` stops those from looping.
+fn walk(
+    path: &Path,
+    display: &str,
+    follow_links: bool,
+    seen: &mut HashSet<PathBuf>,
+    files: &mut Vec<(PathBuf, String)>,
+) {
+    if !path.is_dir() {
+        files.push((path.to_path_buf(), display.to_string()));
+        return;
+    }
+    if !seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())) {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        if !follow_links && entry.file_type().is_ok_and(|kind| kind.is_symlink()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let child_display = if display.ends_with('/') {
+            format!("{display}{name}")
+        } else {
+            format!("{display}/{name}")
+        };
+        walk(&entry.path(), &child_display, follow_links, seen, files);
+    }
+}
+
+/// Runs `grep args`, reading `stdin` when no files are given.
+pub fn run(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    let (options, pattern, mut paths) = parse_args(args)?;
+    let mut output = StageOutput::default();
+
+    let re = match build_regex(&pattern, &options) {
+        Ok(re) => re,
+        Err(e) => {
+            output.errors.push(format!("grep: invalid pattern: {e}"));
+            return Some(output);
+        }
+    };
+
+    if paths.is_empty() {
+        if options.recursive {
+            paths.push(".".to_string());
+        } else {
+            grep_text(stdin.unwrap_or(""), None, &re, &options, &mut output.lines);
+            return Some(output);
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    for path in &paths {
+        let full = cwd.join(path);
+        if full.is_dir() && !options.recursive {
+            output.errors.push(format!("grep: {path}: Is a directory"));
+        } else {
+            walk(&full, path, options.dereference, &mut seen, &mut files);
+        }
+    }
+
+    let show_names = options.recursive || files.len() > 1;
+    for (file, display) in files {
+        match fs::read(&file) {
+            Ok(bytes) if bytes.contains(&0) => {
+                let text = String::from_utf8_lossy(&bytes);
+                if text.lines().any(|l| re.is_match(l) != options.invert) {
+                    output.lines.push(TerminalLine::plain(
+                        format!("Binary file {display} matches"),
+                        OUTPUT_COLOR,
+                    ));
+                }
+            }
+            Ok(bytes) => {
+                let text = String::from_utf8_lossy(&bytes);
+                let name = show_names.then_some(display.as_str());
+                let file = Some((file.as_path(), name));
+                grep_text(&text, file, &re, &options, &mut output.lines);
+            }
+            Err(e) => output.errors.push(format!("grep: {display}: {e}")),
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_link_to_their_line() {
+        let options = Options {
+            line_numbers: true,
+            ..Options::default()
+        };
+        let re = build_regex("b", &options).unwrap();
+        let mut out = Vec::new();
+        let file = Some((Path::new("/src/Makefile"), Some("Makefile")));
+        grep_text("a\nb\n", file, &re, &options, &mut out);
+        assert_eq!(out[0].text(), "Makefile:2:b");
+        let link = &out[0].links[0];
+        assert_eq!(link.range, 0..10);
+        assert_eq!(link.path, Path::new("/src/Makefile"));
+        assert_eq!(link.line, Some(2));
+
+        out.clear();
+        grep_text(
+            "b",
+            Some((Path::new("/src/Makefile"), None)),
+            &re,
+            &options,
+            &mut out,
+        );
+        assert_eq!(out[0].links[0].range, 0..1);
+
+        out.clear();
+        grep_text("b", None, &re, &options, &mut out);
+        assert!(out[0].links.is_empty());
+    }
+
+    fn grep(args: &str, stdin: &str) -> Option<String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        run(&args, Some(stdin), Path::new(".")).map(|output| output.text())
+    }
+
+    #[test]
+    fn options_filter_lines() {
+        let text = "Apple\nbanana\ncherry\n";
+        assert_eq!(grep("an", text).unwrap(), "banana\n");
+        assert_eq!(grep("-i apple", text).unwrap(), "Apple\n");
+        assert_eq!(grep("-v an", text).unwrap(), "Apple\ncherry\n");
+        assert_eq!(grep("-n err", text).unwrap(), "3:cherry\n");
+        assert_eq!(grep("-vin A", text).unwrap(), "3:cherry\n");
+        assert_eq!(grep("-e -x", "a-x\n").unwrap(), "a-x\n");
+        assert_eq!(grep("-- -x", "a-x\n").unwrap(), "a-x\n");
+    }
+
+    #[test]
+    fn basic_extended_and_fixed_syntax() {
+        let text = "a+b\naab\n";
+        assert_eq!(grep("a+b", text).unwrap(), "a+b\n");
+        assert_eq!(grep("-E a+b", text).unwrap(), "aab\n");
+        assert_eq!(grep("a\\+b", text).unwrap(), "aab\n");
+        assert_eq!(grep("-F a+b", text).unwrap(), "a+b\n");
+    }
+
+    #[test]
+    fn other_options_fall_back_to_the_system_grep() {
+        assert!(grep("-c x", "").is_none());
+        assert!(grep("-ix x", "").is_none());
+        assert!(grep("--include=*.rs x", "").is_none());
+        assert!(grep("-e", "").is_none());
+        assert!(grep("-i", "").is_none());
+    }
+
+    #[test]
+    fn recursive_search_names_files() {
+        let dir = std::env::temp_dir().join(format!("grep-r-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/notes.txt"), "todo: tests\ndone\n").unwrap();
+        let args = ["-r".to_string(), "todo".to_string()];
+        let output = run(&args, None, &dir).unwrap();
+        let plain = ["todo".to_string(), "sub".to_string()];
+        let directory = run(&plain, None, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output.text(), "./sub/notes.txt:todo: tests\n");
+        assert_eq!(directory.errors, ["grep: sub: Is a directory"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_capital_r_follows_links_and_never_loops() {
+        let dir = std::env::temp_dir().join(format!("grep-links-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "todo\n").unwrap();
+        let outside = dir.with_extension("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("notes.txt"), "todo\n").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("linked")).unwrap();
+        let search = |flag: &str| {
+            let args = [flag.to_string(), "todo".to_string()];
+            run(&args, None, &dir).unwrap().text()
+        };
+        let (r, capital_r) = (search("-r"), search("-R"));
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+        assert_eq!(r, "./src/main.rs:todo\n");
+        assert_eq!(capital_r, "./linked/notes.txt:todo\n./src/main.rs:todo\n");
+        assert!(is_recursive(&["-rn".to_string(), "x".to_string()]));
+        assert!(!is_recursive(&["-n".to_string(), "x".to_string()]));
+    }
+}
@@ -0,0 +1,211 @@
+//! `hash ALGO [FILE...]`: MD5, SHA-1 or SHA-256 checksums of files or
+//! piped input, printed like `sha256sum`, without needing coreutils.
+//! Piped input is hashed byte for byte as the program wrote it. SHA-1
+//! comes from the `sha1` crate; MD5 and SHA-256 are implemented here
+//! until `md-5` and `sha2` can be added as dependencies.
+
+use std::fs;
+use std::path::Path;
+
+use sha1::{Digest, Sha1};
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+const ALGORITHMS: &str = "md5, sha1, sha256";
+
+/// Pads `data` as MD5 and the SHA family do: a 1 bit, zeros, then the bit
+/// length, `big_endian` or not, filling out the last 64-byte block.
+fn pad(data: &[u8], big_endian: bool) -> Vec<u8> {
+    let bits = (data.len() as u64).wrapping_mul(8);
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    let length = if big_endian {
+        bits.to_be_bytes()
+    } else {
+        bits.to_le_bytes()
+    };
+    padded.extend_from_slice(&length);
+    padded
+}
+
+fn md5(data: &[u8]) -> Vec<u8> {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for block in pad(data, false).chunks(64) {
+        let words: Vec<u32> = block
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let shift = SHIFTS[(i / 16) * 4 + i % 4];
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g])
+                .rotate_left(shift);
+            (a, b, c, d) = (d, b.wrapping_add(rotated), b, c);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    state.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+fn sha1(data: &[u8]) -> Vec<u8> {
+    Sha1::digest(data).to_vec()
+}
+
+fn sha256(data: &[u8]) -> Vec<u8> {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    for block in pad(data, true).chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+            (h, g, f, e, d, c, b, a) = (
+                g,
+                f,
+                e,
+                d.wrapping_add(temp1),
+                c,
+                b,
+                a,
+                temp1.wrapping_add(temp2),
+            );
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    state.iter().flat_map(|s| s.to_be_bytes()).collect()
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn run(args: &[String], stdin: Option<Vec<u8>>, cwd: &Path) -> StageOutput {
+    let mut output = StageOutput::default();
+    let Some((algorithm, files)) = args.split_first() else {
+        output
+            .errors
+            .push(format!("hash: usage: hash ALGO [FILE...] ({ALGORITHMS})"));
+        return output;
+    };
+    let digest: fn(&[u8]) -> Vec<u8> = match algorithm.to_ascii_lowercase().as_str() {
+        "md5" => md5,
+        "sha1" => sha1,
+        "sha256" => sha256,
+        _ => {
+            output.errors.push(format!(
+                "hash: unknown algorithm '{algorithm}' (one of: {ALGORITHMS})"
+            ));
+            return output;
+        }
+    };
+    if files.is_empty() {
+        let sum = hex(&digest(&stdin.unwrap_or_default()));
+        output
+            .lines
+            .push(TerminalLine::plain(format!("{sum}  -"), OUTPUT_COLOR));
+        return output;
+    }
+    for file in files {
+        match fs::read(cwd.join(file)) {
+            Ok(bytes) => {
+                let sum = hex(&digest(&bytes));
+                output
+                    .lines
+                    .push(TerminalLine::plain(format!("{sum}  {file}"), OUTPUT_COLOR));
+            }
+            Err(e) => output.errors.push(format!("hash: {file}: {e}")),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            hex(&md5(b"The quick brown fox jumps over the lazy dog")),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks once padded.
+        let long = [b'a'; 100];
+        assert_eq!(
+            hex(&sha256(&long)),
+            "2816597888e4a0d3a36b82b83316ab32680eb8f00f8cd3b904d681246d285a0e"
+        );
+    }
+
+    #[test]
+    fn hashes_piped_bytes_as_they_are() {
+        let bytes = b"\xff\xfe progress 10%\rprogress 100%\r\n".to_vec();
+        let expected = hex(&sha256(&bytes));
+        let output = run(&["sha256".to_string()], Some(bytes), Path::new("."));
+        assert_eq!(output.lines[0].text(), format!("{expected}  -"));
+    }
+}
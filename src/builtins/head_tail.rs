@@ -0,0 +1,159 @@
+//! `head` and `tail`: the first or last lines of files or piped input.
+//!
+//! `tail -f` is started by the app rather than `run_stage`, since it keeps
+//! streaming new lines until interrupted.
+
+use std::fs;
+use std::path::Path;
+
+use super::StageOutput;
+use crate::line::{TerminalLine, HEADING_COLOR, OUTPUT_COLOR};
+
+const DEFAULT_COUNT: usize = 10;
+
+pub struct Options {
+    pub count: usize,
+    /// `tail -n +N`: everything from line N on, rather than the last N.
+    pub from_start: bool,
+    pub follow: bool,
+    pub files: Vec<String>,
+}
+
+/// Parses `args` (without the command name). Understands `-n N`, `-nN`,
+/// `--lines=N` and `-N`, plus `+N` and `-f` for tail. Returns `None` for
+/// anything else so the external program can handle it.
+pub fn parse(args: &[String], tail: bool) -> Option<Options> {
+    let mut options = Options {
+        count: DEFAULT_COUNT,
+        from_start: false,
+        follow: false,
+        files: Vec::new(),
+    };
+    let set_count = |text: &str, options: &mut Options| -> Option<()> {
+        match text.strip_prefix('+') {
+            Some(rest) if tail => {
+                options.count = rest.parse().ok()?;
+                options.from_start = true;
+            }
+            _ => options.count = text.parse().ok()?,
+        }
+        Some(())
+    };
+    let mut options_done = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if options_done || !arg.starts_with('-') || arg == "-" {
+            options.files.push(arg.clone());
+            continue;
+        }
+        match arg.as_str() {
+            "--" => options_done = true,
+            "-n" | "--lines" => set_count(iter.next()?, &mut options)?,
+            "-f" | "--follow" if tail => options.follow = true,
+            _ => {
+                if let Some(count) = arg
+                    .strip_prefix("--lines=")
+                    .or_else(|| arg.strip_prefix("-n"))
+                {
+                    set_count(count, &mut options)?;
+                } else if arg[1..].bytes().all(|b| b.is_ascii_digit()) {
+                    set_count(&arg[1..], &mut options)?;
+                } else {
+                    return None;
+                }
+            }
+        }
+    }
+    Some(options)
+}
+
+/// The lines `options` selects from `text`.
+pub fn select<'a>(text: &'a str, options: &Options, tail: bool) -> Vec<&'a str> {
+    let lines: Vec<&str> = text.lines().collect();
+    if !tail {
+        return lines.into_iter().take(options.count).collect();
+    }
+    let start = if options.from_start {
+        options.count.saturating_sub(1)
+    } else {
+        lines.len().saturating_sub(options.count)
+    };
+    lines[start.min(lines.len())..].to_vec()
+}
+
+fn run(name: &str, args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    let tail = name == "tail";
+    let options = parse(args, tail)?;
+    let mut output = StageOutput::default();
+    if options.files.is_empty() {
+        for line in select(stdin.unwrap_or(""), &options, tail) {
+            output.lines.push(TerminalLine::plain(line, OUTPUT_COLOR));
+        }
+        return Some(output);
+    }
+    let show_names = options.files.len() > 1;
+    for (index, file) in options.files.iter().enumerate() {
+        let read = if file == "-" {
+            Ok(stdin.unwrap_or("").as_bytes().to_vec())
+        } else {
+            fs::read(cwd.join(file))
+        };
+        let text = match read {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                output.errors.push(format!("{name}: {file}: {e}"));
+                continue;
+            }
+        };
+        if show_names {
+            if index > 0 {
+                output.lines.push(TerminalLine::plain("", OUTPUT_COLOR));
+            }
+            output.lines.push(TerminalLine::plain(
+                format!("==> {file} <=="),
+                HEADING_COLOR,
+            ));
+        }
+        for line in select(&text, &options, tail) {
+            output.lines.push(TerminalLine::plain(line, OUTPUT_COLOR));
+        }
+    }
+    Some(output)
+}
+
+pub fn head(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    run("head", args, stdin, cwd)
+}
+
+/// `tail` without `-f`; a follow request is left for the app to start.
+pub fn tail(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    if parse(args, true)?.follow {
+        return None;
+    }
+    run("tail", args, stdin, cwd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn selects_first_and_last_lines() {
+        let text = "1\n2\n3\n4\n5\n";
+        let head = parse(&args("-n 2"), false).unwrap();
+        assert_eq!(select(text, &head, false), ["1", "2"]);
+        let tail = parse(&args("-3 log.txt"), true).unwrap();
+        assert_eq!(select(text, &tail, true), ["3", "4", "5"]);
+        assert_eq!(tail.files, ["log.txt"]);
+        let from = parse(&args("-n +4"), true).unwrap();
+        assert_eq!(select(text, &from, true), ["4", "5"]);
+        assert!(parse(&args("-fn1 x"), true).is_none());
+        assert!(parse(&args("-f x"), true).unwrap().follow);
+        assert!(parse(&args("-f x"), false).is_none());
+        assert!(parse(&args("-c 5"), false).is_none());
+    }
+}
@@ -0,0 +1,52 @@
+//! `:hexdump`: raw bytes in the layout of `hexdump -C`, for output that
+//! isn't text.
+
+const WIDTH: usize = 16;
+
+/// Offset, hex bytes in two groups of eight, and the printable ASCII.
+pub fn lines(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(WIDTH)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut hex = String::new();
+            for i in 0..WIDTH {
+                if i == WIDTH / 2 {
+                    hex.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(byte) => hex.push_str(&format!("{byte:02x} ")),
+                    None => hex.push_str("   "),
+                }
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}  {hex} |{ascii}|", row * WIDTH)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_like_hexdump_c() {
+        let lines = lines(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0hi");
+        assert_eq!(
+            lines,
+            [
+                "00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|",
+                "00000010  68 69                                             |hi|",
+            ]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! `http [METHOD] URL [BODY]`: fetches a URL and shows the response laid
+//! out: the status colored by class, the headers, then the body, indented
+//! when it's JSON. Piped input is sent as the body. It runs on a worker
+//! thread (see `may_block`), so a slow server doesn't hold up the window.
+//!
+//! `http://` URLs are fetched in-process over HTTP/1.1. There's no TLS
+//! library in the build, so `https://` URLs are handed to `curl`.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui::Color32;
+
+use super::StageOutput;
+use crate::line::{
+    Segment, TerminalLine, ERROR_COLOR, HEADING_COLOR, INSERTED_COLOR, OUTPUT_COLOR,
+};
+
+const METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+/// How long a request may take altogether, as `curl --max-time`.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where an `http://` URL points.
+#[derive(Debug, PartialEq)]
+struct Target {
+    /// As written in the URL, brackets and all for IPv6, for `Host:`.
+    authority: String,
+    host: String,
+    port: u16,
+    /// Path and query, `/` when the URL has neither.
+    path: String,
+}
+
+/// Splits an `http://` URL, or one without a scheme, which `curl` also
+/// takes as http. `None` for any other scheme.
+fn parse_url(url: &str) -> Option<Result<Target, String>> {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    if !scheme.eq_ignore_ascii_case("http") {
+        return None;
+    }
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    };
+    let path = path.split('#').next().unwrap_or("");
+    let path = match path {
+        "" => "/".to_string(),
+        query if query.starts_with('?') => format!("/{query}"),
+        path => path.to_string(),
+    };
+    let host_port = authority.rsplit('@').next().unwrap_or(authority);
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => return Some(Err(format!("invalid port '{port}'"))),
+        },
+        _ => (host_port, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Some(Err(format!("no host in '{url}'")));
+    }
+    Some(Ok(Target {
+        authority: host_port.to_string(),
+        host: host.to_string(),
+        port,
+        path,
+    }))
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body. `None` if it's cut short.
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(body.len());
+    loop {
+        let end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[end + 2..];
+        if size == 0 {
+            return Some(out);
+        }
+        out.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+/// Sends the request over a plain TCP connection and returns the raw
+/// response, with a chunked body decoded so it reads like `curl -i`.
+fn fetch(method: &str, target: &Target, body: Option<&str>) -> Result<Vec<u8>, String> {
+    let deadline = Instant::now() + TIMEOUT;
+    let addresses = (target.host.as_str(), target.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("{}: {e}", target.host))?;
+    let mut last_error = format!("{}: no address", target.host);
+    let mut stream = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => last_error = format!("{address}: {e}"),
+        }
+    }
+    let mut stream = stream.ok_or(last_error)?;
+
+    let mut request = format!(
+        "{method} {} HTTP/1.1\r\nHost: {}\r\n",
+        target.path, target.authority
+    );
+    request.push_str("User-Agent: ai_terminal\r\nAccept: */*\r\nConnection: close\r\n");
+    if let Some(body) = body {
+        request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    request.push_str("\r\n");
+    request.push_str(body.unwrap_or(""));
+    stream
+        .set_write_timeout(Some(TIMEOUT))
+        .and_then(|()| stream.write_all(request.as_bytes()))
+        .map_err(|e| e.to_string())?;
+
+    let mut raw = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(format!("timed out after {} seconds", TIMEOUT.as_secs()));
+        }
+        stream
+            .set_read_timeout(Some(left))
+            .map_err(|e| e.to_string())?;
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => raw.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let Some(split) = raw.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return Ok(raw);
+    };
+    let head = String::from_utf8_lossy(&raw[..split]).to_ascii_lowercase();
+    let chunked = head.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim() == "transfer-encoding" && value.contains("chunked")
+        })
+    });
+    if chunked {
+        let body = dechunk(&raw[split + 4..]).ok_or("response ended mid-chunk")?;
+        raw.truncate(split + 4);
+        raw.extend_from_slice(&body);
+    }
+    Ok(raw)
+}
+
+/// Runs the request with `curl -i`, for URLs `fetch` can't handle.
+fn fetch_with_curl(method: &str, url: &str, body: Option<String>) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("curl");
+    command.args(["-sS", "-i", "--max-time", "30"]);
+    if method == "HEAD" {
+        command.arg("-I");
+    } else {
+        command.args(["-X", method]);
+    }
+    if body.is_some() {
+        command.args(["--data-binary", "@-"]);
+    }
+    // `--url` so that a URL starting with `-` isn't read as an option.
+    let mut child = command
+        .arg("--url")
+        .arg(url)
+        .stdin(if body.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("https needs curl, which didn't start: {e}"))?;
+    if let (Some(mut pipe), Some(body)) = (child.stdin.take(), body) {
+        thread::spawn(move || pipe.write_all(body.as_bytes()));
+    }
+    let result = child.wait_with_output().map_err(|e| format!("curl: {e}"))?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(stderr.trim().trim_start_matches("curl: ").to_string());
+    }
+    Ok(result.stdout)
+}
+
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: String,
+    code: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// Splits a raw response, as `curl -i` prints it, into its parts. Interim responses such as
+/// `100 Continue` come first, each with its own headers; only the last
+/// one is kept.
+fn parse_response(raw: &str) -> Option<Response> {
+    let mut rest = raw;
+    loop {
+        let (head, body) = rest
+            .split_once("\r\n\r\n")
+            .or_else(|| rest.split_once("\n\n"))
+            .unwrap_or((rest, ""));
+        if body.starts_with("HTTP/") {
+            rest = body;
+            continue;
+        }
+        let mut lines = head.lines();
+        let status = lines.next()?.trim().to_string();
+        let code = status.split_whitespace().nth(1)?.parse().ok()?;
+        let headers = lines
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect();
+        return Some(Response {
+            status,
+            code,
+            headers,
+            body: body.to_string(),
+        });
+    }
+}
+
+/// Re-indents JSON two spaces per level. `None` if `text` doesn't look
+/// like a JSON object or array, or its brackets and strings don't close.
+fn pretty_json(text: &str) -> Option<String> {
+    let text = text.trim();
+    if !(text.starts_with('{') || text.starts_with('[')) {
+        return None;
+    }
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut stack = Vec::new();
+    let mut chars = text.chars().peekable();
+    let newline = |out: &mut String, depth: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                loop {
+                    let c = chars.next()?;
+                    out.push(c);
+                    match c {
+                        '\\' => out.push(chars.next()?),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                let close = if c == '{' { '}' } else { ']' };
+                out.push(c);
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                if chars.peek() == Some(&close) {
+                    out.push(chars.next()?);
+                    continue;
+                }
+                stack.push(close);
+                newline(&mut out, stack.len());
+            }
+            '}' | ']' => {
+                if stack.pop()? != c {
+                    return None;
+                }
+                newline(&mut out, stack.len());
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, stack.len());
+            }
+            ':' => out.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    stack.is_empty().then_some(out)
+}
+
+fn status_color(code: u16) -> Color32 {
+    match code {
+        200..=299 => INSERTED_COLOR,
+        400..=599 => ERROR_COLOR,
+        _ => OUTPUT_COLOR,
+    }
+}
+
+fn render(response: &Response) -> Vec<TerminalLine> {
+    let mut lines = vec![TerminalLine::plain(
+        &response.status,
+        status_color(response.code),
+    )];
+    for (name, value) in &response.headers {
+        lines.push(TerminalLine::from_segments(vec![
+            Segment::new(format!("{name}: "), HEADING_COLOR),
+            Segment::new(value, OUTPUT_COLOR),
+        ]));
+    }
+    if response.body.is_empty() {
+        return lines;
+    }
+    lines.push(TerminalLine::plain("", OUTPUT_COLOR));
+    let is_json = response
+        .headers
+        .iter()
+        .any(|(name, value)| name.eq_ignore_ascii_case("content-type") && value.contains("json"));
+    let body = is_json
+        .then(|| pretty_json(&response.body))
+        .flatten()
+        .unwrap_or_else(|| response.body.clone());
+    lines.extend(
+        body.lines()
+            .map(|line| TerminalLine::plain(line, OUTPUT_COLOR)),
+    );
+    lines
+}
+
+pub fn run(args: &[String], stdin: Option<&str>) -> StageOutput {
+    let mut output = StageOutput::default();
+    let (method, rest) = match args.split_first() {
+        Some((method, rest)) if METHODS.contains(&method.to_ascii_uppercase().as_str()) => {
+            (method.to_ascii_uppercase(), rest)
+        }
+        _ => ("GET".to_string(), args),
+    };
+    let (url, body) = match rest {
+        [url] => (url, stdin.map(str::to_string)),
+        [url, body] => (url, Some(body.clone())),
+        _ => {
+            output
+                .errors
+                .push("http: usage: http [METHOD] URL [BODY]".to_string());
+            return output;
+        }
+    };
+
+    let raw = match parse_url(url) {
+        Some(Ok(target)) => fetch(&method, &target, body.as_deref()),
+        Some(Err(message)) => Err(message),
+        None => fetch_with_curl(&method, url, body),
+    };
+    match raw {
+        Ok(raw) => match parse_response(&String::from_utf8_lossy(&raw)) {
+            Some(response) => output.lines = render(&response),
+            None => output.errors.push("http: malformed response".to_string()),
+        },
+        Err(message) => output.errors.push(format!("http: {message}")),
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_indents_a_json_response() {
+        let raw = "HTTP/1.1 100 Continue\r\n\r\nHTTP/2 404 \r\ncontent-type: application/json\r\n\
+                   x-id: a:b\r\n\r\n{\"error\":{\"code\":404,\"tags\":[]},\"ok\":false}";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status, "HTTP/2 404");
+        assert_eq!(response.code, 404);
+        assert_eq!(response.headers[1], ("x-id".to_string(), "a:b".to_string()));
+        assert_eq!(
+            pretty_json(&response.body).unwrap(),
+            "{\n  \"error\": {\n    \"code\": 404,\n    \"tags\": []\n  },\n  \"ok\": false\n}"
+        );
+        assert_eq!(
+            pretty_json("{\"a\": \"}\\\"\"}").unwrap(),
+            "{\n  \"a\": \"}\\\"\"\n}"
+        );
+        assert_eq!(pretty_json("{\"a\": [1}"), None);
+        assert_eq!(pretty_json("plain"), None);
+    }
+
+    #[test]
+    fn splits_http_urls() {
+        let target = |url| parse_url(url).unwrap().unwrap();
+        assert_eq!(
+            target("http://example.com"),
+            Target {
+                authority: "example.com".to_string(),
+                host: "example.com".to_string(),
+                port: 80,
+                path: "/".to_string(),
+            }
+        );
+        let ipv6 = target("HTTP://[::1]:8080/a/b?q=1#top");
+        assert_eq!((ipv6.host.as_str(), ipv6.port), ("::1", 8080));
+        assert_eq!(
+            (ipv6.authority.as_str(), ipv6.path.as_str()),
+            ("[::1]:8080", "/a/b?q=1")
+        );
+        assert_eq!(target("http://h?x=1").path, "/?x=1");
+        assert_eq!(target("localhost:3000/api").port, 3000);
+        assert!(parse_url("http://h:port/").unwrap().is_err());
+        assert!(parse_url("https://example.com").is_none());
+        assert_eq!(
+            dechunk(b"4\r\nWiki\r\n5;x=y\r\npedia\r\n0\r\n\r\n").unwrap(),
+            b"Wikipedia"
+        );
+        assert_eq!(dechunk(b"9\r\nWiki"), None);
+    }
+
+    #[test]
+    fn fetches_in_process() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"hi") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(
+                    b"HTTP/1.1 201 Created\r\nContent-Type: application/json\r\n\
+                      Transfer-Encoding: chunked\r\n\r\n7\r\n{\"a\":1}\r\n0\r\n\r\n",
+                )
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let args = ["post".to_string(), format!("http://127.0.0.1:{port}/items")];
+        let output = run(&args, Some("hi"));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /items HTTP/1.1\r\n"), "{request}");
+        assert!(request.contains(&format!("Host: 127.0.0.1:{port}\r\n")));
+        assert!(request.contains("Content-Length: 2\r\n"));
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        let text: Vec<String> = output.lines.iter().map(|line| line.text()).collect();
+        assert_eq!(text[0], "HTTP/1.1 201 Created");
+        assert_eq!(text[3..], ["", "{", "  \"a\": 1", "}"]);
+    }
+}
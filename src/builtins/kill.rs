@@ -0,0 +1,163 @@
+//! Internal `kill` with named and numbered signals.
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+/// Signals accepted by name, with and without the `SIG` prefix.
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// Resolves `TERM`, `SIGTERM`, `term` or `15` to a signal number.
+fn parse_signal(spec: &str) -> Option<libc::c_int> {
+    if let Ok(number) = spec.parse::<libc::c_int>() {
+        return (0..=64).contains(&number).then_some(number);
+    }
+    let upper = spec.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS.iter().find(|(n, _)| *n == name).map(|(_, s)| *s)
+}
+
+/// What a `kill` command line asks for.
+#[derive(Debug, PartialEq)]
+enum Request {
+    List,
+    Send(libc::c_int, Vec<String>),
+}
+
+/// Reads the options. The signal comes first; after it, or after `--`,
+/// every argument is a PID, so `kill -9 -123` and `kill -- -123` signal
+/// process group 123.
+fn parse(args: &[String]) -> Result<Request, String> {
+    let mut signal = None;
+    let mut pids = Vec::new();
+    let mut options_done = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if options_done || !pids.is_empty() {
+            pids.push(arg.clone());
+            continue;
+        }
+        match arg.as_str() {
+            "--" => options_done = true,
+            _ if signal.is_some() => {
+                options_done = true;
+                pids.push(arg.clone());
+            }
+            "-l" | "-L" => return Ok(Request::List),
+            "-s" | "-n" => {
+                let spec = iter
+                    .next()
+                    .ok_or_else(|| format!("kill: {arg} requires a signal"))?;
+                let number =
+                    parse_signal(spec).ok_or_else(|| format!("kill: invalid signal '{spec}'"))?;
+                signal = Some(number);
+            }
+            spec if spec.starts_with('-') => {
+                let number = parse_signal(&spec[1..])
+                    .ok_or_else(|| format!("kill: invalid signal '{}'", &spec[1..]))?;
+                signal = Some(number);
+            }
+            pid => pids.push(pid.to_string()),
+        }
+    }
+
+    if pids.is_empty() {
+        return Err("kill: usage: kill [-s SIGNAL | -SIGNAL] [--] PID...".to_string());
+    }
+    Ok(Request::Send(signal.unwrap_or(libc::SIGTERM), pids))
+}
+
+/// Runs `kill [-SIGNAL | -s SIGNAL | -l] [--] PID...`. Bare `kill PID`
+/// sends TERM; a negative PID signals a process group.
+pub fn run(args: &[String]) -> StageOutput {
+    let mut output = StageOutput::default();
+    let (signal, pids) = match parse(args) {
+        Ok(Request::List) => {
+            let names: Vec<String> = SIGNALS
+                .iter()
+                .map(|(name, number)| format!("{number}) SIG{name}"))
+                .collect();
+            output
+                .lines
+                .push(TerminalLine::plain(names.join("  "), OUTPUT_COLOR));
+            return output;
+        }
+        Ok(Request::Send(signal, pids)) => (signal, pids),
+        Err(message) => {
+            output.errors.push(message);
+            return output;
+        }
+    };
+
+    for pid in pids {
+        let Ok(number) = pid.parse::<libc::pid_t>() else {
+            output
+                .errors
+                .push(format!("kill: {pid}: arguments must be process IDs"));
+            continue;
+        };
+        // SAFETY: kill(2) has no memory-safety preconditions.
+        if unsafe { libc::kill(number, signal) } != 0 {
+            let error = std::io::Error::last_os_error();
+            output.errors.push(format!("kill: ({pid}) - {error}"));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn send(signal: libc::c_int, pids: &[&str]) -> Result<Request, String> {
+        Ok(Request::Send(
+            signal,
+            pids.iter().map(|p| p.to_string()).collect(),
+        ))
+    }
+
+    #[test]
+    fn signals_by_name_and_number() {
+        assert_eq!(parse(&args("42")), send(libc::SIGTERM, &["42"]));
+        assert_eq!(parse(&args("-9 42")), send(libc::SIGKILL, &["42"]));
+        assert_eq!(
+            parse(&args("-s hup 42 43")),
+            send(libc::SIGHUP, &["42", "43"])
+        );
+        assert_eq!(parse(&args("-SIGINT 42")), send(libc::SIGINT, &["42"]));
+        assert_eq!(parse(&args("-l")), Ok(Request::List));
+        assert!(parse(&args("-BOGUS 42")).is_err());
+        assert!(parse(&args("-9")).is_err());
+    }
+
+    #[test]
+    fn negative_pids_after_the_signal_or_double_dash() {
+        assert_eq!(parse(&args("-- -42")), send(libc::SIGTERM, &["-42"]));
+        assert_eq!(parse(&args("-9 -42")), send(libc::SIGKILL, &["-42"]));
+        assert_eq!(
+            parse(&args("-s KILL -- -42")),
+            send(libc::SIGKILL, &["-42"])
+        );
+        assert_eq!(parse(&args("-9 -- -42")), send(libc::SIGKILL, &["-42"]));
+        assert_eq!(parse(&args("42 -43")), send(libc::SIGTERM, &["42", "-43"]));
+        assert_eq!(parse(&args("-- 42 --")), send(libc::SIGTERM, &["42", "--"]));
+    }
+}
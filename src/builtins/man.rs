@@ -0,0 +1,124 @@
+//! `man`: runs the system `man` formatted for the view's width and turns
+//! its backspace overstrike (`X\bX` bold, `_\bX` underline) into styled
+//! runs for the page viewer.
+
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Plain,
+    Bold,
+    Underline,
+}
+
+/// Splits one formatted line into styled runs.
+fn parse_overstrike(line: &str) -> Vec<(Style, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut runs: Vec<(Style, String)> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (style, c) = if chars.get(i + 1) == Some(&'\u{8}') && i + 2 < chars.len() {
+            let (first, second) = (chars[i], chars[i + 2]);
+            i += 3;
+            // Bold underlined chars repeat the overstrike; skip the extras.
+            while chars.get(i) == Some(&'\u{8}') && i + 1 < chars.len() {
+                i += 2;
+            }
+            if first == '_' && second != '_' {
+                (Style::Underline, second)
+            } else {
+                (Style::Bold, second)
+            }
+        } else {
+            i += 1;
+            (Style::Plain, chars[i - 1])
+        };
+        match runs.last_mut() {
+            Some((last, text)) if *last == style => text.push(c),
+            _ => runs.push((style, c.to_string())),
+        }
+    }
+    runs
+}
+
+/// One line of a formatted page.
+#[derive(Debug, PartialEq)]
+pub struct Line {
+    /// A section heading, such as `NAME` or `OPTIONS`.
+    pub heading: bool,
+    pub runs: Vec<(Style, String)>,
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        self.runs.iter().map(|(_, text)| text.as_str()).collect()
+    }
+}
+
+/// Section headings start in the first column; everything else is indented.
+fn parse_page(text: &str) -> Vec<Line> {
+    text.lines()
+        .map(|line| Line {
+            heading: !line.is_empty() && !line.starts_with(char::is_whitespace),
+            runs: parse_overstrike(line),
+        })
+        .collect()
+}
+
+/// Runs `man args` formatted to `columns`. The error is what `man` said
+/// when it printed no page, such as that there's no entry.
+pub fn fetch(args: &[String], columns: usize) -> Result<Vec<Line>, String> {
+    if args.is_empty() {
+        return Err("What manual page do you want?".to_string());
+    }
+    let result = Command::new("man")
+        .args(args)
+        .env("MANWIDTH", columns.to_string())
+        .env("MANPAGER", "cat")
+        .env("PAGER", "cat")
+        // Overstrike rather than SGR escapes, and keep it even though
+        // stdout isn't a terminal.
+        .env("MAN_KEEP_FORMATTING", "1")
+        .env("GROFF_NO_SGR", "1")
+        .output()
+        .map_err(|e| format!("man: {e}"))?;
+    if result.stdout.iter().all(u8::is_ascii_whitespace) {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("No manual entry for {}", args.join(" ")),
+            message => message.to_string(),
+        });
+    }
+    Ok(parse_page(&String::from_utf8_lossy(&result.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overstrike_becomes_styled_runs() {
+        let line = "     l\u{8}ls\u{8}s [_\u{8}f_\u{8}i_\u{8}l_\u{8}e]";
+        let runs = parse_overstrike(line);
+        let styles: Vec<Style> = runs.iter().map(|(s, _)| *s).collect();
+        let texts: Vec<&str> = runs.iter().map(|(_, t)| t.as_str()).collect();
+        assert_eq!(
+            styles,
+            [
+                Style::Plain,
+                Style::Bold,
+                Style::Plain,
+                Style::Underline,
+                Style::Plain
+            ]
+        );
+        assert_eq!(texts, ["     ", "ls", " [", "file", "]"]);
+
+        let lines = parse_page("N\u{8}NA\u{8}AM\u{8}ME\u{8}E\n       ls - list\n\n");
+        assert_eq!(lines[0].text(), "NAME");
+        assert!(lines[0].heading);
+        assert_eq!(lines[1].text(), "       ls - list");
+        assert!(!lines[1].heading && !lines[2].heading);
+        assert!(fetch(&[], 80).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Commands implemented in Rust rather than spawned as external processes.
+//!
+//! Pipeline-capable tools take the upstream stage's text as `stdin` and return
+//! `None` when they see arguments they don't support, so the caller can fall
+//! back to the external program of the same name.
+
+pub mod base64;
+pub mod calc;
+#[cfg(unix)]
+pub mod chmod;
+pub mod cowsay_fortune;
+pub mod date_cal;
+pub mod diff;
+pub mod echo;
+pub mod grep;
+pub mod hash;
+pub mod head_tail;
+pub mod hexdump;
+pub mod http;
+#[cfg(unix)]
+pub mod kill;
+pub mod man;
+pub mod ps;
+pub mod sed;
+pub mod sort_uniq_wc;
+pub mod tldr;
+pub mod trash;
+pub mod tree;
+
+use std::path::Path;
+
+use crate::line::TerminalLine;
+
+#[derive(Default)]
+pub struct StageOutput {
+    pub lines: Vec<TerminalLine>,
+    pub errors: Vec<String>,
+    /// The last line isn't newline-terminated, as after `echo -n`.
+    pub no_newline: bool,
+    /// Exactly what an external program wrote, which `lines` shows decoded
+    /// and with `\r` overwrites applied. `None` for internal tools.
+    pub stdout: Option<Vec<u8>>,
+}
+
+impl StageOutput {
+    /// Plain text handed to the next stage of a pipeline.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            text.push_str(&line.text());
+            text.push('\n');
+        }
+        if self.no_newline {
+            text.pop();
+        }
+        text
+    }
+
+    /// Raw input for the next stage: the program's own bytes where there
+    /// are some, else `text`.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.stdout
+            .clone()
+            .unwrap_or_else(|| self.text().into_bytes())
+    }
+}
+
+/// Internal tools that can take long enough, waiting on the network or
+/// walking a large tree, that a pipeline using them runs on a worker
+/// thread rather than holding up the window.
+pub fn may_block(args: &[String]) -> bool {
+    match args.first().map(String::as_str) {
+        Some("http" | "tldr" | "tree") => true,
+        Some("grep") => grep::is_recursive(&args[1..]),
+        _ => false,
+    }
+}
+
+/// Runs `args` as an internal pipeline stage fed `upstream`'s output, or
+/// returns `None` to have the caller spawn the external program instead.
+pub fn run_stage(
+    args: &[String],
+    upstream: Option<&StageOutput>,
+    cwd: &Path,
+) -> Option<StageOutput> {
+    let text = upstream.map(StageOutput::text);
+    let stdin = text.as_deref();
+    match args.first()?.as_str() {
+        "base64" => Some(base64::run(&args[1..], stdin, cwd)),
+        "cal" => date_cal::cal(&args[1..]),
+        "calc" => Some(calc::run(&args[1..])),
+        #[cfg(unix)]
+        "chmod" => chmod::run(&args[1..], cwd),
+        "cowsay" => Some(cowsay_fortune::cowsay(&args[1..], stdin)),
+        "date" => date_cal::date(&args[1..]),
+        "diff" => diff::run(&args[1..], cwd),
+        "echo" => Some(echo::run(&args[1..])),
+        "fortune" => Some(cowsay_fortune::fortune()),
+        "grep" => grep::run(&args[1..], stdin, cwd),
+        "hash" => Some(hash::run(&args[1..], upstream.map(StageOutput::bytes), cwd)),
+        "head" => head_tail::head(&args[1..], stdin, cwd),
+        "http" => Some(http::run(&args[1..], stdin)),
+        "ps" => ps::run(&args[1..]),
+        "sed" => sed::run(&args[1..], stdin, cwd),
+        "sort" => sort_uniq_wc::sort(&args[1..], stdin, cwd),
+        "tail" => head_tail::tail(&args[1..], stdin, cwd),
+        #[cfg(unix)]
+        "kill" => Some(kill::run(&args[1..])),
+        "tldr" => Some(tldr::run(&args[1..])),
+        "tree" => tree::run(&args[1..], cwd),
+        "uniq" => sort_uniq_wc::uniq(&args[1..], stdin, cwd),
+        "wc" => sort_uniq_wc::wc(&args[1..], stdin, cwd),
+        _ => None,
+    }
+}
@@ -0,0 +1,225 @@
+//! `ps --tree` and `ps --filter NAME`: processes (see `procs`) in colored
+//! columns, drawn as a parent/child tree or narrowed to names containing
+//! NAME. Plain `ps` and any other flags get the system `ps`.
+
+use std::collections::{HashMap, HashSet};
+
+use super::StageOutput;
+use crate::line::{
+    Segment, TerminalLine, HEADING_COLOR, LINE_NUMBER_COLOR, OUTPUT_COLOR, PATH_COLOR,
+    TIMESTAMP_COLOR,
+};
+use crate::procs::{self, Entry, Snapshot};
+
+fn format_time(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// A row. `prefix` is the tree drawing before the name; `dim` marks a
+/// process shown only as the ancestor of a match.
+fn row(entry: &Entry, prefix: &str, dim: bool) -> TerminalLine {
+    TerminalLine::from_segments(vec![
+        Segment::new(format!("{:>7} ", entry.pid), LINE_NUMBER_COLOR),
+        Segment::new(format!("{:>7} ", entry.ppid), TIMESTAMP_COLOR),
+        Segment::new(
+            format!(
+                "{:>9} {:>7}  ",
+                format_time(entry.seconds),
+                procs::format_memory(entry.memory)
+            ),
+            OUTPUT_COLOR,
+        ),
+        Segment::new(prefix, TIMESTAMP_COLOR),
+        Segment::new(&entry.name, if dim { TIMESTAMP_COLOR } else { PATH_COLOR }),
+    ])
+}
+
+/// Appends the children of `pid` that are in `shown`, drawn under `prefix`.
+fn render_children(
+    pid: u32,
+    entries: &[Entry],
+    children: &HashMap<u32, Vec<usize>>,
+    shown: &HashSet<u32>,
+    matched: &HashSet<u32>,
+    prefix: &str,
+    lines: &mut Vec<TerminalLine>,
+) {
+    let kids: Vec<&Entry> = children
+        .get(&pid)
+        .into_iter()
+        .flatten()
+        .map(|&i| &entries[i])
+        .filter(|entry| shown.contains(&entry.pid))
+        .collect();
+    for (i, entry) in kids.iter().enumerate() {
+        let last = i == kids.len() - 1;
+        let connector = if last { "└── " } else { "├── " };
+        lines.push(row(
+            entry,
+            &format!("{prefix}{connector}"),
+            !matched.contains(&entry.pid),
+        ));
+        let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+        render_children(entry.pid, entries, children, shown, matched, &prefix, lines);
+    }
+}
+
+/// The listing of `entries`, which are sorted by pid. With a filter, a
+/// tree keeps the ancestors of each match so its place stays visible.
+fn render(entries: &[Entry], filter: Option<&str>, tree: bool) -> Vec<TerminalLine> {
+    let mut lines = vec![TerminalLine::plain(
+        format!(
+            "{:>7} {:>7} {:>9} {:>7}  COMMAND",
+            "PID", "PPID", "TIME", "MEM"
+        ),
+        HEADING_COLOR,
+    )];
+    let filter = filter.map(str::to_lowercase);
+    let matched: HashSet<u32> = entries
+        .iter()
+        .filter(|entry| {
+            filter
+                .as_ref()
+                .is_none_or(|f| entry.name.to_lowercase().contains(f))
+        })
+        .map(|entry| entry.pid)
+        .collect();
+    if !tree {
+        lines.extend(
+            entries
+                .iter()
+                .filter(|entry| matched.contains(&entry.pid))
+                .map(|entry| row(entry, "", false)),
+        );
+        return lines;
+    }
+
+    let index: HashMap<u32, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (entry.pid, i))
+        .collect();
+    let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.ppid != entry.pid && index.contains_key(&entry.ppid) {
+            children.entry(entry.ppid).or_default().push(i);
+        } else {
+            roots.push(i);
+        }
+    }
+    let mut shown = HashSet::new();
+    for &pid in &matched {
+        let mut pid = pid;
+        // Stops at a pid already added, so a cycle in racing reads ends.
+        while shown.insert(pid) {
+            match index.get(&pid) {
+                Some(&i) => pid = entries[i].ppid,
+                None => break,
+            }
+        }
+    }
+    for i in roots {
+        let entry = &entries[i];
+        if shown.contains(&entry.pid) {
+            lines.push(row(entry, "", !matched.contains(&entry.pid)));
+            render_children(
+                entry.pid, entries, &children, &shown, &matched, "", &mut lines,
+            );
+        }
+    }
+    lines
+}
+
+/// Runs `ps args`, or `None` unless they ask for a tree or a filter and
+/// nothing else. Where processes can't be listed at all, says so.
+pub fn run(args: &[String]) -> Option<StageOutput> {
+    if args.is_empty() {
+        return None;
+    }
+    let mut tree = false;
+    let mut filter = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tree" => tree = true,
+            "--filter" => filter = Some(args.next()?.clone()),
+            arg => filter = Some(arg.strip_prefix("--filter=")?.to_string()),
+        }
+    }
+    let Some(snapshot) = Snapshot::take() else {
+        return Some(StageOutput {
+            errors: vec!["ps: --tree and --filter aren't supported on this system".to_string()],
+            ..StageOutput::default()
+        });
+    };
+    let entries = snapshot.entries();
+    Some(StageOutput {
+        lines: render(&entries, filter.as_deref(), tree),
+        ..StageOutput::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn draws_a_filtered_tree() {
+        let entry = |pid, ppid, name: &str| Entry {
+            pid,
+            ppid,
+            name: name.to_string(),
+            seconds: 3725,
+            memory: 2048,
+        };
+        let entries = [
+            entry(1, 0, "init"),
+            entry(10, 1, "sshd"),
+            entry(11, 10, "bash"),
+            entry(12, 11, "vim"),
+            entry(20, 1, "Cron"),
+        ];
+        let names = |lines: Vec<TerminalLine>| -> Vec<String> {
+            lines
+                .iter()
+                .skip(1)
+                .map(|line| line.text()[35..].to_string())
+                .collect()
+        };
+        assert_eq!(
+            names(render(&entries, None, true)),
+            [
+                "init",
+                "├── sshd",
+                "│   └── bash",
+                "│       └── vim",
+                "└── Cron"
+            ]
+        );
+        assert_eq!(
+            names(render(&entries, Some("VIM"), true)),
+            ["init", "└── sshd", "    └── bash", "        └── vim"]
+        );
+        assert_eq!(names(render(&entries, Some("cron"), false)), ["Cron"]);
+        assert_eq!(
+            render(&entries, None, false)[1].text(),
+            "      1       0   1:02:05    2.0K  init"
+        );
+
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(run(&[]).is_none());
+        assert!(run(&args(&["aux"])).is_none());
+        assert!(run(&args(&["--tree", "-e"])).is_none());
+        assert!(run(&args(&["--filter"])).is_none());
+        // Linux only; elsewhere even these go to the system ps.
+        let tree = run(&args(&["--tree"]));
+        assert_eq!(tree.is_some(), Path::new("/proc/stat").exists());
+    }
+}
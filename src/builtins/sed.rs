@@ -0,0 +1,276 @@
+//! Internal `sed` for the common scripts: `s/PATTERN/REPLACEMENT/[gi]`
+//! and `d`, each optionally after an address of a line number, `$` or
+//! `/PATTERN/`, separated by `;`. Anything else, and `-n` or `-i`, is left
+//! to the external `sed`.
+
+use std::fs;
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+
+use super::grep::bre_to_regex;
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+enum Address {
+    Line(usize),
+    Last,
+    Pattern(Regex),
+}
+
+enum Command {
+    Delete,
+    Substitute {
+        regex: Regex,
+        /// In the `regex` crate's `$1` syntax.
+        replacement: String,
+        global: bool,
+    },
+}
+
+struct Edit {
+    address: Option<Address>,
+    command: Command,
+}
+
+fn build_regex(pattern: &str, extended: bool, ignore_case: bool) -> Result<Regex, String> {
+    let source = if extended {
+        pattern.to_string()
+    } else {
+        bre_to_regex(pattern)
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| format!("sed: invalid pattern '{pattern}': {e}"))
+}
+
+/// Text up to the next unescaped `delimiter`, with `\delimiter` unescaped
+/// and other escapes kept. `None` if the delimiter never comes.
+fn take_delimited(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    delimiter: char,
+) -> Option<String> {
+    let mut out = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                next if next == delimiter => out.push(next),
+                next => {
+                    out.push('\\');
+                    out.push(next);
+                }
+            },
+            c if c == delimiter => return Some(out),
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+/// A sed replacement, where `&` is the match and `\1` a group, in the
+/// `regex` crate's syntax.
+fn convert_replacement(replacement: &str) -> String {
+    let mut out = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    out.push_str("${");
+                    out.push(digit);
+                    out.push('}');
+                }
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('$') => out.push_str("$$"),
+                Some(next) => out.push(next),
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parses a script. `Ok(None)` means it uses something only the real
+/// `sed` knows; `Err` is a pattern that doesn't compile.
+fn parse_script(script: &str, extended: bool) -> Result<Option<Vec<Edit>>, String> {
+    let mut edits = Vec::new();
+    let mut chars = script.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ';').is_some() {}
+        let Some(&c) = chars.peek() else {
+            break;
+        };
+        let address = match c {
+            '0'..='9' => {
+                let mut n = 0;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    n = n * 10 + digit as usize;
+                    chars.next();
+                }
+                Some(Address::Line(n))
+            }
+            '$' => {
+                chars.next();
+                Some(Address::Last)
+            }
+            '/' => {
+                chars.next();
+                let Some(pattern) = take_delimited(&mut chars, '/') else {
+                    return Ok(None);
+                };
+                Some(Address::Pattern(build_regex(&pattern, extended, false)?))
+            }
+            _ => None,
+        };
+        while chars.next_if(|c| *c == ' ').is_some() {}
+        let command = match chars.next() {
+            Some('d') => Command::Delete,
+            Some('s') => {
+                let Some(delimiter) = chars.next().filter(|c| *c != '\\' && *c != '\n') else {
+                    return Ok(None);
+                };
+                let pattern = take_delimited(&mut chars, delimiter);
+                let replacement = take_delimited(&mut chars, delimiter);
+                let (Some(pattern), Some(replacement)) = (pattern, replacement) else {
+                    return Ok(None);
+                };
+                let (mut global, mut ignore_case) = (false, false);
+                while let Some(flag) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                    match flag {
+                        'g' => global = true,
+                        'i' | 'I' => ignore_case = true,
+                        _ => return Ok(None),
+                    }
+                }
+                Command::Substitute {
+                    regex: build_regex(&pattern, extended, ignore_case)?,
+                    replacement: convert_replacement(&replacement),
+                    global,
+                }
+            }
+            _ => return Ok(None),
+        };
+        // Another command may only follow after a separator.
+        if chars
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && *c != ';')
+        {
+            return Ok(None);
+        }
+        edits.push(Edit { address, command });
+    }
+    Ok((!edits.is_empty()).then_some(edits))
+}
+
+fn apply(edits: &[Edit], text: &str) -> Vec<String> {
+    let count = text.lines().count();
+    let mut out = Vec::new();
+    'lines: for (i, line) in text.lines().enumerate() {
+        let mut line = line.to_string();
+        for edit in edits {
+            let selected = match &edit.address {
+                None => true,
+                Some(Address::Line(n)) => i + 1 == *n,
+                Some(Address::Last) => i + 1 == count,
+                Some(Address::Pattern(regex)) => regex.is_match(&line),
+            };
+            if !selected {
+                continue;
+            }
+            match &edit.command {
+                Command::Delete => continue 'lines,
+                Command::Substitute {
+                    regex,
+                    replacement,
+                    global,
+                } => {
+                    let limit = if *global { 0 } else { 1 };
+                    line = regex
+                        .replacen(&line, limit, replacement.as_str())
+                        .into_owned();
+                }
+            }
+        }
+        out.extend(line.split('\n').map(str::to_string));
+    }
+    out
+}
+
+pub fn run(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    let mut extended = false;
+    let mut scripts = Vec::new();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-E" | "-r" | "--regexp-extended" => extended = true,
+            "-e" | "--expression" => scripts.push(args.next()?.clone()),
+            flag if flag.starts_with('-') && flag != "-" => return None,
+            _ if scripts.is_empty() => scripts.push(arg.clone()),
+            _ => files.push(arg.clone()),
+        }
+    }
+    let script = scripts.join("\n");
+    let mut output = StageOutput::default();
+    let edits = match parse_script(&script, extended) {
+        Ok(Some(edits)) => edits,
+        Ok(None) => return None,
+        Err(message) => {
+            output.errors.push(message);
+            return Some(output);
+        }
+    };
+
+    // Like sed, the files read as one stream, so `$` is the last line of
+    // the last file.
+    let mut text = String::new();
+    if files.is_empty() {
+        text.push_str(stdin?);
+    }
+    for file in &files {
+        match fs::read(cwd.join(file)) {
+            Ok(bytes) => {
+                text.push_str(&String::from_utf8_lossy(&bytes));
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            Err(e) => output.errors.push(format!("sed: {file}: {e}")),
+        }
+    }
+    output.lines = apply(&edits, &text)
+        .into_iter()
+        .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+        .collect();
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sed(script: &str, text: &str) -> Option<Vec<String>> {
+        let edits = parse_script(script, false).unwrap()?;
+        Some(apply(&edits, text))
+    }
+
+    #[test]
+    fn substitutes_and_deletes_by_address() {
+        let text = "foo foo\nbar\n# note\nlast\n";
+        assert_eq!(
+            sed("s/foo/x/", text).unwrap(),
+            ["x foo", "bar", "# note", "last"]
+        );
+        assert_eq!(sed("s|o|0|g; /^#/d; $d", text).unwrap(), ["f00 f00", "bar"]);
+        assert_eq!(sed(r"2s/\(b\)\(a\)/\2\1[&]/", text).unwrap()[1], "ab[ba]r");
+        assert_eq!(sed("s/A+/$/gi", "aa+").unwrap(), ["a$"]);
+        assert!(sed("y/abc/xyz/", text).is_none());
+        assert!(sed("s/a/b/w out", text).is_none());
+        assert!(parse_script("s/(/x/", true).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+//! `sort`, `uniq` and `wc` over files or piped input, for the common flags
+//! only: `sort -rnu`, `uniq -c`, `wc -lwc`. Anything else is left to the
+//! external programs.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+use super::StageOutput;
+use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+/// Splits `args` into single-letter flags, all of which must be in
+/// `allowed`, and operands. `None` for any other option.
+fn parse(args: &[String], allowed: &str) -> Option<(String, Vec<String>)> {
+    let mut flags = String::new();
+    let mut files = Vec::new();
+    let mut options_done = false;
+    for arg in args {
+        if options_done || !arg.starts_with('-') || arg == "-" {
+            files.push(arg.clone());
+        } else if arg == "--" {
+            options_done = true;
+        } else if arg[1..].chars().all(|c| allowed.contains(c)) {
+            flags.push_str(&arg[1..]);
+        } else {
+            return None;
+        }
+    }
+    Some((flags, files))
+}
+
+/// Each operand's text and size in bytes, or `stdin` when there are none
+/// or for `-`. The size is of the file as read, before invalid UTF-8 is
+/// replaced.
+fn read_inputs(
+    name: &str,
+    files: &[String],
+    stdin: Option<&str>,
+    cwd: &Path,
+    output: &mut StageOutput,
+) -> Vec<(Option<String>, String, usize)> {
+    let stdin = stdin.unwrap_or("");
+    if files.is_empty() {
+        return vec![(None, stdin.to_string(), stdin.len())];
+    }
+    files
+        .iter()
+        .filter_map(|file| {
+            if file == "-" {
+                return Some((Some(file.clone()), stdin.to_string(), stdin.len()));
+            }
+            match fs::read(cwd.join(file)) {
+                Ok(bytes) => Some((
+                    Some(file.clone()),
+                    String::from_utf8_lossy(&bytes).into_owned(),
+                    bytes.len(),
+                )),
+                Err(e) => {
+                    output.errors.push(format!("{name}: {file}: {e}"));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The number a line starts with, after leading blanks, as `sort -n`
+/// reads it; lines without one sort as zero.
+fn leading_number(line: &str) -> f64 {
+    let line = line.trim_start();
+    let digits = |from: usize| {
+        line[from..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(line.len(), |i| from + i)
+    };
+    let mut end = digits(usize::from(line.starts_with('-')));
+    // One decimal point at most: `1.2.3` is 1.2.
+    if line[end..].starts_with('.') {
+        end = digits(end + 1);
+    }
+    line[..end].parse().unwrap_or(0.0)
+}
+
+pub fn sort(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    let (flags, files) = parse(args, "rnu")?;
+    let (reverse, numeric, unique) = (
+        flags.contains('r'),
+        flags.contains('n'),
+        flags.contains('u'),
+    );
+    let mut output = StageOutput::default();
+    let inputs = read_inputs("sort", &files, stdin, cwd, &mut output);
+    let mut lines: Vec<&str> = inputs
+        .iter()
+        .flat_map(|(_, text, _)| text.lines())
+        .collect();
+    let key = |a: &&str, b: &&str| {
+        if numeric {
+            leading_number(a).total_cmp(&leading_number(b))
+        } else {
+            a.cmp(b)
+        }
+    };
+    lines.sort_by(|a, b| {
+        // Lines with equal keys fall back to a plain comparison, except
+        // under -u, where they're duplicates.
+        let order = key(a, b).then_with(|| if unique { Ordering::Equal } else { a.cmp(b) });
+        if reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    if unique {
+        lines.dedup_by(|a, b| key(a, b) == Ordering::Equal);
+    }
+    output.lines = lines
+        .into_iter()
+        .map(|line| TerminalLine::plain(line, OUTPUT_COLOR))
+        .collect();
+    Some(output)
+}
+
+pub fn uniq(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    let (flags, files) = parse(args, "c")?;
+    // A second operand names an output file.
+    if files.len() > 1 {
+        return None;
+    }
+    let count = flags.contains('c');
+    let mut output = StageOutput::default();
+    let inputs = read_inputs("uniq", &files, stdin, cwd, &mut output);
+    let mut runs: Vec<(usize, &str)> = Vec::new();
+    for line in inputs.iter().flat_map(|(_, text, _)| text.lines()) {
+        match runs.last_mut() {
+            Some((n, last)) if *last == line => *n += 1,
+            _ => runs.push((1, line)),
+        }
+    }
+    output.lines = runs
+        .into_iter()
+        .map(|(n, line)| {
+            let text = if count {
+                format!("{n:>7} {line}")
+            } else {
+                line.to_string()
+            };
+            TerminalLine::plain(text, OUTPUT_COLOR)
+        })
+        .collect();
+    Some(output)
+}
+
+pub fn wc(args: &[String], stdin: Option<&str>, cwd: &Path) -> Option<StageOutput> {
+    let (flags, files) = parse(args, "lwc")?;
+    let selected = if flags.is_empty() { "lwc" } else { &flags };
+    let shown: Vec<usize> = [('l', 0), ('w', 1), ('c', 2)]
+        .iter()
+        .filter(|(flag, _)| selected.contains(*flag))
+        .map(|&(_, index)| index)
+        .collect();
+
+    let mut output = StageOutput::default();
+    let inputs = read_inputs("wc", &files, stdin, cwd, &mut output);
+    let mut rows: Vec<([usize; 3], Option<String>)> = inputs
+        .iter()
+        .map(|(name, text, bytes)| {
+            let counts = [
+                text.matches('\n').count(),
+                text.split_whitespace().count(),
+                *bytes,
+            ];
+            (counts, name.clone())
+        })
+        .collect();
+    if rows.len() > 1 {
+        let mut total = [0; 3];
+        for (counts, _) in &rows {
+            for (sum, n) in total.iter_mut().zip(counts) {
+                *sum += n;
+            }
+        }
+        rows.push((total, Some("total".to_string())));
+    }
+
+    // Like GNU wc: a lone count from stdin is bare, otherwise the columns
+    // line up.
+    let largest = rows
+        .iter()
+        .flat_map(|(counts, _)| shown.iter().map(|&i| counts[i]))
+        .max()
+        .unwrap_or(0);
+    let width = match (files.is_empty(), shown.len()) {
+        (true, 1) => 1,
+        (true, _) => 7,
+        _ => largest.to_string().len(),
+    };
+    output.lines = rows
+        .into_iter()
+        .map(|(counts, name)| {
+            let mut columns: Vec<String> = shown
+                .iter()
+                .map(|&i| format!("{:>width$}", counts[i]))
+                .collect();
+            columns.extend(name);
+            TerminalLine::plain(columns.join(" "), OUTPUT_COLOR)
+        })
+        .collect();
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(
+        tool: fn(&[String], Option<&str>, &Path) -> Option<StageOutput>,
+        args: &str,
+        stdin: &str,
+    ) -> Option<String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        tool(&args, Some(stdin), Path::new(".")).map(|output| output.text())
+    }
+
+    #[test]
+    fn sorts_dedups_and_counts() {
+        let text = "b\n10 x\n9 y\na\nb\n";
+        assert_eq!(run(sort, "", text).unwrap(), "10 x\n9 y\na\nb\nb\n");
+        assert_eq!(run(sort, "-n", text).unwrap(), "a\nb\nb\n9 y\n10 x\n");
+        assert_eq!(run(sort, "-ru", text).unwrap(), "b\na\n9 y\n10 x\n");
+        assert!(run(sort, "-k2", text).is_none());
+
+        assert_eq!(run(uniq, "", "a\na\nb\na\n").unwrap(), "a\nb\na\n");
+        assert_eq!(
+            run(uniq, "-c", "a\na\nb\n").unwrap(),
+            "      2 a\n      1 b\n"
+        );
+
+        assert_eq!(run(wc, "-l", "one two\nthree\n").unwrap(), "2\n");
+        assert_eq!(
+            run(wc, "", "one two\nthree\n").unwrap(),
+            "      2       3      14\n"
+        );
+        assert!(run(wc, "-m", "").is_none());
+    }
+
+    #[test]
+    fn numbers_stop_at_a_second_point() {
+        assert_eq!(leading_number("1.2.3 x"), 1.2);
+        assert_eq!(leading_number("  -4.5kB"), -4.5);
+        assert_eq!(leading_number("10-2"), 10.0);
+        assert_eq!(leading_number("v1"), 0.0);
+        assert_eq!(
+            run(sort, "-n", "1.10.0\n1.9.2\n1.2.3\n").unwrap(),
+            "1.10.0\n1.2.3\n1.9.2\n"
+        );
+    }
+
+    #[test]
+    fn wc_counts_the_bytes_of_the_file() {
+        let dir = std::env::temp_dir().join(format!("wc-bytes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("latin1"), b"caf\xe9\n").unwrap();
+        let args = ["-c".to_string(), "latin1".to_string()];
+        let output = wc(&args, None, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output.text(), "5 latin1\n");
+    }
+}
@@ -0,0 +1,225 @@
+//! `tldr`: short, example-first usage notes for common commands.
+//!
+//! Pages use the tldr-pages markdown format. A handful ship with the
+//! terminal; others are fetched from the tldr-pages project with `curl` the
+//! first time they're asked for and cached for offline use. The fetch runs
+//! on a worker thread (see `may_block`), and a page the project doesn't
+//! have is remembered for a day rather than asked for again.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use super::StageOutput;
+use crate::line::{Segment, TerminalLine, HEADING_COLOR, INPUT_COLOR, OUTPUT_COLOR, PATH_COLOR};
+use crate::paths;
+
+const BUNDLED: &str = include_str!("tldr_pages.md");
+const PAGE_URL: &str = "https://raw.githubusercontent.com/tldr-pages/tldr/main/pages";
+
+/// How long a page the project doesn't have stays unasked for, so one
+/// added upstream still turns up.
+const MISS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// curl's exit status when the server answered with an HTTP error, as
+/// opposed to the network failing.
+const CURL_HTTP_ERROR: i32 = 22;
+
+/// The bundled pages as `(name, markdown)`, split on their `# name` titles.
+fn bundled() -> impl Iterator<Item = (&'static str, &'static str)> {
+    let starts: Vec<usize> = BUNDLED
+        .match_indices("# ")
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || BUNDLED.as_bytes()[i - 1] == b'\n')
+        .collect();
+    let ends: Vec<usize> = starts[1..].iter().copied().chain([BUNDLED.len()]).collect();
+    starts.into_iter().zip(ends).map(|(start, end)| {
+        let page = &BUNDLED[start..end];
+        let name = page[2..].lines().next().unwrap_or("").trim();
+        (name, page)
+    })
+}
+
+fn cache_path(name: &str) -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("tldr").join(format!("{name}.md")))
+}
+
+/// Marks a page the project doesn't have, by when it was last asked for.
+fn miss_path(name: &str) -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("tldr").join(format!("{name}.missing")))
+}
+
+fn recently_missed(name: &str) -> bool {
+    let modified = miss_path(name)
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok());
+    modified
+        .and_then(|at| SystemTime::now().duration_since(at).ok())
+        .is_some_and(|age| age < MISS_TTL)
+}
+
+enum Fetched {
+    Page(String),
+    /// Every section answered that there's no such page.
+    Missing,
+    /// curl isn't there or the network failed; worth trying again later.
+    Failed,
+}
+
+/// Downloads a page, trying the cross-platform section before this OS's.
+fn fetch(name: &str) -> Fetched {
+    let platform = if cfg!(target_os = "macos") {
+        "osx"
+    } else if cfg!(windows) {
+        "windows"
+    } else {
+        "linux"
+    };
+    let mut missing = true;
+    for section in ["common", platform] {
+        let output = Command::new("curl")
+            .args(["-fsSL", "--max-time", "5", "--url"])
+            .arg(format!("{PAGE_URL}/{section}/{name}.md"))
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                return Fetched::Page(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+            Ok(output) if output.status.code() == Some(CURL_HTTP_ERROR) => {}
+            _ => missing = false,
+        }
+    }
+    if missing {
+        Fetched::Missing
+    } else {
+        Fetched::Failed
+    }
+}
+
+fn save(path: Option<PathBuf>, text: &str) {
+    if let Some(path) = path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, text);
+    }
+}
+
+/// Finds a page: bundled, then cached, then fetched (and cached).
+fn page(name: &str) -> Option<String> {
+    if let Some((_, page)) = bundled().find(|(n, _)| *n == name) {
+        return Some(page.to_string());
+    }
+    let cache = cache_path(name);
+    if let Some(page) = cache.as_ref().and_then(|p| fs::read_to_string(p).ok()) {
+        return Some(page);
+    }
+    if recently_missed(name) {
+        return None;
+    }
+    match fetch(name) {
+        Fetched::Page(page) => {
+            save(cache, &page);
+            Some(page)
+        }
+        Fetched::Missing => {
+            save(miss_path(name), "");
+            None
+        }
+        Fetched::Failed => None,
+    }
+}
+
+/// Splits an example command into segments, coloring `{{placeholders}}`.
+fn example_line(command: &str) -> TerminalLine {
+    let mut segments = vec![Segment::new("    ", OUTPUT_COLOR)];
+    let mut rest = command;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open + 2..].find("}}") else {
+            break;
+        };
+        if open > 0 {
+            segments.push(Segment::new(&rest[..open], INPUT_COLOR));
+        }
+        segments.push(Segment::new(&rest[open + 2..open + 2 + close], PATH_COLOR));
+        rest = &rest[open + 2 + close + 2..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::new(rest, INPUT_COLOR));
+    }
+    TerminalLine::from_segments(segments)
+}
+
+fn render(page: &str) -> Vec<TerminalLine> {
+    let mut lines = Vec::new();
+    for line in page.lines() {
+        let line = line.trim_end();
+        if let Some(title) = line.strip_prefix("# ") {
+            lines.push(TerminalLine::plain(title, HEADING_COLOR));
+        } else if let Some(text) = line.strip_prefix("> ") {
+            lines.push(TerminalLine::plain(format!("  {text}"), OUTPUT_COLOR));
+        } else if let Some(text) = line.strip_prefix("- ") {
+            lines.push(TerminalLine::plain("", OUTPUT_COLOR));
+            lines.push(TerminalLine::plain(format!("  {text}"), OUTPUT_COLOR));
+        } else if let Some(command) = line.strip_prefix('`').and_then(|l| l.strip_suffix('`')) {
+            lines.push(example_line(command));
+        }
+    }
+    lines
+}
+
+/// Runs `tldr COMMAND`, or `tldr` alone to list the bundled pages.
+pub fn run(args: &[String]) -> StageOutput {
+    let mut output = StageOutput::default();
+    let Some(name) = args.first() else {
+        let names: Vec<&str> = bundled().map(|(name, _)| name).collect();
+        output.lines.push(TerminalLine::plain(
+            format!("Bundled pages: {}", names.join(", ")),
+            OUTPUT_COLOR,
+        ));
+        output.lines.push(TerminalLine::plain(
+            "Other commands are downloaded from tldr-pages on first use.",
+            OUTPUT_COLOR,
+        ));
+        return output;
+    };
+    let name = name.to_ascii_lowercase();
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'));
+    let page = if valid { page(&name) } else { None };
+    match page {
+        Some(page) => output.lines = render(&page),
+        None => output.errors.push(format!("tldr: no page for '{name}'")),
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_pages_render_examples() {
+        let (name, page) = bundled().find(|(n, _)| *n == "tar").unwrap();
+        assert_eq!(name, "tar");
+        assert!(!page.contains("# find"));
+
+        let lines = render(page);
+        assert_eq!(lines[0].text(), "tar");
+        let example = example_line("tar xf {{archive.tar}} -C {{dir}}");
+        let colors: Vec<_> = example.segments.iter().map(|s| s.color).collect();
+        assert_eq!(
+            colors,
+            [
+                OUTPUT_COLOR,
+                INPUT_COLOR,
+                PATH_COLOR,
+                INPUT_COLOR,
+                PATH_COLOR
+            ]
+        );
+        assert_eq!(example.text(), "    tar xf archive.tar -C dir");
+    }
+}
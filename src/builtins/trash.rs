@@ -0,0 +1,342 @@
+//! `rm` that moves files to the freedesktop.org trash, and `trash-restore`.
+//!
+//! Trashed files go to `$XDG_DATA_HOME/Trash/files` with a matching
+//! `info/<name>.trashinfo` recording the original path and deletion time, so
+//! file managers see and can restore them too.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::StageOutput;
+use crate::line::{Segment, TerminalLine, LINE_NUMBER_COLOR, OUTPUT_COLOR, PATH_COLOR};
+use crate::paths;
+
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+fn trash_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| paths::home_dir().map(|home| home.join(".local/share")))
+        .map(|dir| dir.join("Trash"))
+}
+
+/// Percent-encodes everything but unreserved characters and `/`, as the
+/// trash spec requires for the `Path=` key.
+fn encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+fn decode_path(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+struct TrashEntry {
+    /// File name under `Trash/files`.
+    name: String,
+    original: PathBuf,
+    deleted: String,
+}
+
+fn parse_info(name: String, text: &str) -> Option<TrashEntry> {
+    let mut original = None;
+    let mut deleted = String::new();
+    for line in text.lines() {
+        if let Some(path) = line.strip_prefix("Path=") {
+            original = Some(PathBuf::from(decode_path(path)));
+        } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+            deleted = date.to_string();
+        }
+    }
+    Some(TrashEntry {
+        name,
+        original: original?,
+        deleted,
+    })
+}
+
+/// Every trashed item with a readable info file, oldest first.
+fn entries(trash: &Path) -> Vec<TrashEntry> {
+    let Ok(infos) = fs::read_dir(trash.join("info")) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<TrashEntry> = infos
+        .flatten()
+        .filter_map(|info| {
+            let file_name = info.file_name().to_string_lossy().into_owned();
+            let name = file_name.strip_suffix(".trashinfo")?.to_string();
+            let text = fs::read_to_string(info.path()).ok()?;
+            parse_info(name, &text)
+        })
+        .collect();
+    entries.sort_by(|a, b| a.deleted.cmp(&b.deleted));
+    entries
+}
+
+/// Moves `from` to `to`, copying and deleting when they're on different
+/// filesystems (e.g. trashing from a tmpfs `/tmp`).
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    if meta.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if meta.file_type().is_symlink() {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+        #[cfg(not(unix))]
+        return fs::copy(from, to).map(|_| ());
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Moves `path` into the trash, picking a free name under `files/` and
+/// writing its info file first so a crash never leaves an orphan.
+fn trash_path(trash: &Path, path: &Path) -> io::Result<()> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let base = path
+        .file_name()
+        .map_or("unnamed".into(), |n| n.to_string_lossy().into_owned());
+    let mut name = base.clone();
+    let mut n = 1;
+    let info_path = loop {
+        let candidate = info.join(format!("{name}.trashinfo"));
+        if !candidate.exists() && fs::symlink_metadata(files.join(&name)).is_err() {
+            break candidate;
+        }
+        n += 1;
+        name = format!("{base}.{n}");
+    };
+
+    let date = chrono::Local::now().format(DATE_FORMAT);
+    fs::write(
+        &info_path,
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={date}\n",
+            encode_path(&path.to_string_lossy())
+        ),
+    )?;
+    move_path(path, &files.join(&name)).inspect_err(|_| {
+        let _ = fs::remove_file(&info_path);
+    })
+}
+
+/// Runs `rm [-r] [-f] [--force-permanent] FILE...`, trashing instead of
+/// deleting unless `--force-permanent` is given.
+pub fn rm(args: &[String], cwd: &Path) -> StageOutput {
+    let mut output = StageOutput::default();
+    let (mut recursive, mut force, mut permanent) = (false, false, false);
+    let mut targets = Vec::new();
+    let mut options_done = false;
+    for arg in args {
+        match arg.as_str() {
+            "--" if !options_done => options_done = true,
+            "--recursive" if !options_done => recursive = true,
+            "--force" if !options_done => force = true,
+            "--force-permanent" if !options_done => permanent = true,
+            flag if !options_done && flag.starts_with("--") => {
+                output.errors.push(format!(
+                    "rm: unsupported option '{flag}' (trash is on; see help trash)"
+                ));
+                return output;
+            }
+            flag if !options_done && flag.starts_with('-') && flag.len() > 1 => {
+                for c in flag[1..].chars() {
+                    match c {
+                        'r' | 'R' => recursive = true,
+                        'f' => force = true,
+                        _ => {
+                            output.errors.push(format!(
+                                "rm: unsupported option '-{c}' (trash is on; see help trash)"
+                            ));
+                            return output;
+                        }
+                    }
+                }
+            }
+            target => targets.push(target),
+        }
+    }
+    if permanent && !force {
+        output
+            .errors
+            .push("rm: --force-permanent also needs -f".to_string());
+        return output;
+    }
+    if targets.is_empty() && !force {
+        output.errors.push("rm: missing operand".to_string());
+        return output;
+    }
+
+    let Some(trash) = trash_dir() else {
+        output.errors.push("rm: no trash directory".to_string());
+        return output;
+    };
+    for target in targets {
+        let path = paths::resolve(cwd, target);
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            if !force {
+                output.errors.push(format!(
+                    "rm: cannot remove '{target}': No such file or directory"
+                ));
+            }
+            continue;
+        };
+        if meta.is_dir() && !recursive {
+            output
+                .errors
+                .push(format!("rm: cannot remove '{target}': Is a directory"));
+            continue;
+        }
+        let result = if permanent && meta.is_dir() {
+            fs::remove_dir_all(&path)
+        } else if permanent {
+            fs::remove_file(&path)
+        } else {
+            trash_path(&trash, &path)
+        };
+        if let Err(e) = result {
+            output
+                .errors
+                .push(format!("rm: cannot remove '{target}': {e}"));
+        }
+    }
+    output
+}
+
+/// Runs `trash-restore [NAME | PATH]`. Without arguments, lists the trash;
+/// otherwise restores the most recently trashed item whose trash name or
+/// original path matches.
+pub fn restore(args: &[String], cwd: &Path) -> StageOutput {
+    let mut output = StageOutput::default();
+    let Some(trash) = trash_dir() else {
+        output
+            .errors
+            .push("trash-restore: no trash directory".to_string());
+        return output;
+    };
+    let entries = entries(&trash);
+
+    let Some(target) = args.first() else {
+        if entries.is_empty() {
+            output
+                .lines
+                .push(TerminalLine::plain("Trash is empty", OUTPUT_COLOR));
+        }
+        for entry in &entries {
+            output.lines.push(TerminalLine::from_segments(vec![
+                Segment::new(format!("{}  ", entry.deleted), LINE_NUMBER_COLOR),
+                Segment::new(format!("{}  ", entry.name), OUTPUT_COLOR),
+                Segment::new(entry.original.display().to_string(), PATH_COLOR),
+            ]));
+        }
+        return output;
+    };
+
+    let wanted = paths::resolve(cwd, target);
+    let Some(entry) = entries
+        .iter()
+        .rev()
+        .find(|e| e.name == *target || e.original == wanted)
+    else {
+        output
+            .errors
+            .push(format!("trash-restore: '{target}' is not in the trash"));
+        return output;
+    };
+    if fs::symlink_metadata(&entry.original).is_ok() {
+        output.errors.push(format!(
+            "trash-restore: '{}' already exists",
+            entry.original.display()
+        ));
+        return output;
+    }
+
+    let restored = entry
+        .original
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| move_path(&trash.join("files").join(&entry.name), &entry.original));
+    match restored {
+        Ok(()) => {
+            let _ = fs::remove_file(trash.join("info").join(format!("{}.trashinfo", entry.name)));
+            output.lines.push(TerminalLine::plain(
+                format!("restored {}", entry.original.display()),
+                OUTPUT_COLOR,
+            ));
+        }
+        Err(e) => output.errors.push(format!(
+            "trash-restore: cannot restore '{}': {e}",
+            entry.original.display()
+        )),
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_path_round_trips() {
+        let path = "/home/me/my file%.txt";
+        let encoded = encode_path(path);
+        assert_eq!(encoded, "/home/me/my%20file%25.txt");
+        assert_eq!(decode_path(&encoded), path);
+
+        let entry = parse_info(
+            "x".to_string(),
+            "[Trash Info]\nPath=/tmp/a%20b\nDeletionDate=2024-01-02T03:04:05\n",
+        )
+        .unwrap();
+        assert_eq!(entry.original, PathBuf::from("/tmp/a b"));
+        assert_eq!(entry.deleted, "2024-01-02T03:04:05");
+    }
+}
@@ -0,0 +1,230 @@
+//! `tree [-a] [-L DEPTH] [DIR]`: a directory drawn as an indented tree.
+//! The walk stops after `MAX_ENTRIES`, so a huge tree ends in a summary
+//! rather than a flood of lines; it runs on a worker thread meanwhile.
+
+use std::fs;
+use std::path::Path;
+
+use super::StageOutput;
+use crate::line::{Segment, TerminalLine, OUTPUT_COLOR, PATH_COLOR, TIMESTAMP_COLOR};
+use crate::paths;
+
+const MAX_ENTRIES: usize = 5_000;
+
+#[derive(Debug)]
+enum Kind {
+    File,
+    Dir(Vec<Node>),
+    Link(String),
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    kind: Kind,
+}
+
+struct Walk {
+    all: bool,
+    max_depth: Option<usize>,
+    /// Entries left before the walk gives up.
+    budget: usize,
+}
+
+impl Walk {
+    fn read(&mut self, dir: &Path, depth: usize) -> Vec<Node> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        // Only as many as the budget allows are read, so a directory of a
+        // million files stops early too.
+        let mut entries: Vec<_> = entries
+            .flatten()
+            .filter(|e| self.all || !e.file_name().to_string_lossy().starts_with('.'))
+            .take(self.budget)
+            .collect();
+        entries.sort_by_key(|e| e.file_name());
+        let mut nodes = Vec::new();
+        for entry in entries {
+            if self.budget == 0 {
+                break;
+            }
+            self.budget -= 1;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            let kind = match entry.file_type() {
+                Ok(t) if t.is_symlink() => Kind::Link(
+                    fs::read_link(&path)
+                        .map(|target| target.display().to_string())
+                        .unwrap_or_default(),
+                ),
+                Ok(t) if t.is_dir() => {
+                    let deeper = self.max_depth.is_none_or(|max| depth + 1 < max);
+                    Kind::Dir(if deeper {
+                        self.read(&path, depth + 1)
+                    } else {
+                        Vec::new()
+                    })
+                }
+                _ => Kind::File,
+            };
+            nodes.push(Node { name, kind });
+        }
+        nodes
+    }
+}
+
+/// Appends `nodes` under `prefix`, counting directories and files.
+fn render(
+    nodes: &[Node],
+    prefix: &str,
+    lines: &mut Vec<TerminalLine>,
+    counts: &mut (usize, usize),
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i == nodes.len() - 1;
+        let connector = if last { "└── " } else { "├── " };
+        let mut segments = vec![Segment::new(
+            format!("{prefix}{connector}"),
+            TIMESTAMP_COLOR,
+        )];
+        match &node.kind {
+            Kind::File => {
+                counts.1 += 1;
+                segments.push(Segment::new(&node.name, OUTPUT_COLOR));
+            }
+            Kind::Link(target) => {
+                counts.1 += 1;
+                segments.push(Segment::new(
+                    format!("{} -> {target}", node.name),
+                    OUTPUT_COLOR,
+                ));
+            }
+            Kind::Dir(_) => {
+                counts.0 += 1;
+                segments.push(Segment::new(&node.name, PATH_COLOR));
+            }
+        }
+        lines.push(TerminalLine::from_segments(segments));
+        if let Kind::Dir(children) = &node.kind {
+            let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            render(children, &prefix, lines, counts);
+        }
+    }
+}
+
+pub fn run(args: &[String], cwd: &Path) -> Option<StageOutput> {
+    let mut walk = Walk {
+        all: false,
+        max_depth: None,
+        budget: MAX_ENTRIES,
+    };
+    let mut dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-a" => walk.all = true,
+            "-L" => walk.max_depth = Some(args.next()?.parse().ok().filter(|&n| n > 0)?),
+            flag if flag.starts_with('-') => return None,
+            _ if dir.is_some() => return None,
+            path => dir = Some(path),
+        }
+    }
+    let shown = dir.unwrap_or(".");
+    let path = paths::resolve(cwd, shown);
+    let mut output = StageOutput::default();
+    if !path.is_dir() {
+        output
+            .errors
+            .push(format!("tree: {shown}: not a directory"));
+        return Some(output);
+    }
+
+    let nodes = walk.read(&path, 0);
+    output.lines.push(TerminalLine::plain(shown, PATH_COLOR));
+    let mut counts = (0, 0);
+    render(&nodes, "", &mut output.lines, &mut counts);
+    if walk.budget == 0 {
+        output.lines.push(TerminalLine::plain(
+            format!("… stopped after {MAX_ENTRIES} entries"),
+            TIMESTAMP_COLOR,
+        ));
+    }
+    let dirs = match counts.0 {
+        1 => "1 directory".to_string(),
+        n => format!("{n} directories"),
+    };
+    let files = match counts.1 {
+        1 => "1 file".to_string(),
+        n => format!("{n} files"),
+    };
+    output.lines.push(TerminalLine::plain("", OUTPUT_COLOR));
+    output.lines.push(TerminalLine::plain(
+        format!("{dirs}, {files}"),
+        OUTPUT_COLOR,
+    ));
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, kind: Kind) -> Node {
+        Node {
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn draws_connectors() {
+        let nodes = vec![
+            node(
+                "src",
+                Kind::Dir(vec![
+                    node("app", Kind::Dir(vec![node("exec.rs", Kind::File)])),
+                    node("main.rs", Kind::File),
+                ]),
+            ),
+            node("README", Kind::Link("docs/README".to_string())),
+        ];
+        let mut lines = Vec::new();
+        let mut counts = (0, 0);
+        render(&nodes, "", &mut lines, &mut counts);
+        let text: Vec<String> = lines.iter().map(TerminalLine::text).collect();
+        assert_eq!(
+            text,
+            [
+                "├── src",
+                "│   ├── app",
+                "│   │   └── exec.rs",
+                "│   └── main.rs",
+                "└── README -> docs/README",
+            ]
+        );
+        assert_eq!(counts, (2, 3));
+        assert!(run(&["-x".to_string()], Path::new(".")).is_none());
+    }
+
+    #[test]
+    fn stops_reading_at_the_budget() {
+        let dir = std::env::temp_dir().join(format!("tree-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a", "b", "sub/c", "sub/d"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let mut walk = Walk {
+            all: false,
+            max_depth: None,
+            budget: 3,
+        };
+        let nodes = walk.read(&dir, 0);
+        fs::remove_dir_all(&dir).unwrap();
+        let (mut lines, mut counts) = (Vec::new(), (0, 0));
+        render(&nodes, "", &mut lines, &mut counts);
+        assert_eq!(walk.budget, 0);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(counts, (1, 2));
+    }
+}
@@ -0,0 +1,73 @@
+//! Command-line flags accepted at launch.
+
+use std::path::PathBuf;
+
+use crate::paths::Profile;
+use crate::theme::Theme;
+
+pub const USAGE: &str = "Usage: ai_terminal [--profile NAME] [--log-file PATH] \
+     [--theme dark|light] [--font PATH|NAME] [--keep-open COMMAND] [--no-git]";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Append every command and line of output to this file.
+    pub log_file: Option<PathBuf>,
+    /// Theme for this session, overriding the config and system preference.
+    pub theme: Option<Theme>,
+    /// Font file or installed font name for this session, overriding the
+    /// config.
+    pub font: Option<String>,
+    /// Config, history and directory database to use.
+    pub profile: Profile,
+    /// Run at startup as if typed at the prompt, leaving the window open
+    /// afterwards; for launchers and file managers.
+    pub command: Option<String>,
+    /// Don't run git for the header's branch this session.
+    pub no_git: bool,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{flag} requires a value"))
+            };
+            match arg.as_str() {
+                "--log-file" => parsed.log_file = Some(PathBuf::from(value("--log-file")?)),
+                "--theme" => parsed.theme = Some(parse_theme(&value("--theme")?)?),
+                "--font" => parsed.font = Some(value("--font")?),
+                "--profile" => parsed.profile = Profile::new(&value("--profile")?)?,
+                "--keep-open" => parsed.command = Some(value("--keep-open")?),
+                "--no-git" => parsed.no_git = true,
+                flag => {
+                    if let Some(path) = flag.strip_prefix("--log-file=") {
+                        parsed.log_file = Some(PathBuf::from(path));
+                    } else if let Some(name) = flag.strip_prefix("--theme=") {
+                        parsed.theme = Some(parse_theme(name)?);
+                    } else if let Some(font) = flag.strip_prefix("--font=") {
+                        parsed.font = Some(font.to_string());
+                    } else if let Some(name) = flag.strip_prefix("--profile=") {
+                        parsed.profile = Profile::new(name)?;
+                    } else if let Some(command) = flag.strip_prefix("--keep-open=") {
+                        parsed.command = Some(command.to_string());
+                    } else {
+                        return Err(format!("unknown option '{flag}'"));
+                    }
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_theme(name: &str) -> Result<Theme, String> {
+    Theme::parse(name).ok_or_else(|| {
+        format!(
+            "unknown theme '{name}' (one of: {})",
+            Theme::NAMES.join(", ")
+        )
+    })
+}
@@ -0,0 +1,106 @@
+//! The form behind `:build`: a command's flags from its completion spec,
+//! each toggled on or off and given a value, assembled into a command line
+//! to review at the prompt.
+
+use crate::completion_spec::{Arg, Specs};
+use crate::shell;
+
+pub struct Field {
+    pub flag: String,
+    pub description: String,
+    /// What the flag's value is, if it takes one.
+    pub arg: Option<Arg>,
+    pub on: bool,
+    pub value: String,
+}
+
+pub struct CommandForm {
+    /// The command path, e.g. `docker run`.
+    pub command: String,
+    pub fields: Vec<Field>,
+    /// Positional arguments, as typed on the command line.
+    pub args: String,
+}
+
+impl CommandForm {
+    /// A form for `command`, or `None` if its spec has no flags.
+    pub fn new(specs: &Specs, command: &str) -> Option<Self> {
+        let command = command.split_whitespace().collect::<Vec<_>>().join(" ");
+        let fields: Vec<Field> = specs
+            .flags(&command)?
+            .into_iter()
+            .map(|entry| Field {
+                flag: entry.word.clone(),
+                description: entry.description.clone(),
+                arg: entry.arg,
+                on: false,
+                value: String::new(),
+            })
+            .collect();
+        (!fields.is_empty()).then_some(Self {
+            command,
+            fields,
+            args: String::new(),
+        })
+    }
+
+    /// The command line so far. Values are quoted; a flag whose value is
+    /// still empty goes in bare, for the user to finish at the prompt.
+    pub fn line(&self) -> String {
+        let mut flags = Vec::new();
+        for field in self.fields.iter().filter(|f| f.on) {
+            flags.push(field.flag.clone());
+            if field.arg.is_some() && !field.value.is_empty() {
+                flags.push(shell::quote(&field.value));
+            }
+        }
+        let args = Some(self.args.trim()).filter(|a| !a.is_empty());
+        let mut parts = vec![self.command.clone()];
+        // find reads its paths before its expression.
+        if self.command == "find" {
+            parts.extend(args.map(str::to_string));
+            parts.extend(flags);
+        } else {
+            parts.extend(flags);
+            parts.extend(args.map(str::to_string));
+        }
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assembles_the_checked_flags() {
+        let specs = Specs::bundled();
+        assert!(CommandForm::new(&specs, "frobnicate").is_none());
+
+        let mut form = CommandForm::new(&specs, "docker  run").unwrap();
+        assert_eq!(form.line(), "docker run");
+        for field in &mut form.fields {
+            match field.flag.as_str() {
+                "--rm" | "-p" => field.on = true,
+                "-e" => {
+                    field.on = true;
+                    field.value = "GREETING=hi there".to_string();
+                }
+                "--name" => field.value = "ignored while off".to_string(),
+                _ => {}
+            }
+        }
+        form.args = " nginx ".to_string();
+        assert_eq!(
+            form.line(),
+            "docker run -e 'GREETING=hi there' -p --rm nginx"
+        );
+
+        let mut form = CommandForm::new(&specs, "find").unwrap();
+        let name = form.fields.iter_mut().find(|f| f.flag == "-name").unwrap();
+        name.on = true;
+        name.value = "*.rs".to_string();
+        form.args = "src".to_string();
+        assert_eq!(form.line(), "find src -name '*.rs'");
+    }
+}
@@ -0,0 +1,301 @@
+//! Candidate generation for Tab completion.
+
+use std::fs;
+use std::path::Path;
+
+use crate::{find, input, paths};
+
+/// The word being completed: its starting char index and its text up to
+/// the cursor. Words are split on unescaped whitespace.
+pub fn word_at_cursor(buf: &str, cursor: usize) -> (usize, String) {
+    let before: Vec<char> = buf.chars().take(cursor).collect();
+    let mut start = before.len();
+    while start > 0 {
+        let boundary = before[start - 1].is_whitespace() && !escaped(&before, start - 1);
+        if boundary {
+            break;
+        }
+        start -= 1;
+    }
+    (start, unescape(&before[start..].iter().collect::<String>()))
+}
+
+/// True if the char at `index` is preceded by an odd number of backslashes.
+fn escaped(chars: &[char], index: usize) -> bool {
+    chars[..index]
+        .iter()
+        .rev()
+        .take_while(|&&c| c == '\\')
+        .count()
+        % 2
+        == 1
+}
+
+fn unescape(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes a completion so it survives `parse::split_args` as one word.
+pub fn escape(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    for c in word.chars() {
+        if c.is_whitespace() || matches!(c, '\'' | '"' | '\\' | '|' | '&' | ';') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// True if the cursor is in the first word, where command names complete.
+pub fn is_command_position(buf: &str, word_start: usize) -> bool {
+    let before = &buf[..input::byte_offset(buf, word_start)];
+    let trimmed = before.trim_end();
+    trimmed.is_empty() || trimmed.ends_with('|')
+}
+
+pub fn complete_command<'a>(
+    prefix: &str,
+    commands: impl IntoIterator<Item = &'a str>,
+) -> Vec<String> {
+    let mut matches: Vec<String> = commands
+        .into_iter()
+        .filter(|c| c.starts_with(prefix))
+        .map(String::from)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Files and directories matching `prefix`, relative to `cwd`. Directories
+/// get a trailing `/` so completion can continue into them.
+pub fn complete_path(prefix: &str, cwd: &Path) -> Vec<String> {
+    let (dir, name_prefix) = match prefix.rfind('/') {
+        Some(slash) => prefix.split_at(slash + 1),
+        None => ("", prefix),
+    };
+    let search_dir = if dir.is_empty() {
+        cwd.to_path_buf()
+    } else {
+        paths::resolve(cwd, dir)
+    };
+    let Ok(entries) = fs::read_dir(search_dir) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(name_prefix)
+                || (name.starts_with('.') && !name_prefix.starts_with('.'))
+            {
+                return None;
+            }
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{suffix}"))
+        })
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// `find` arguments: its flags when the word starts with `-`, and file
+/// types after `-type`, as `(value, "value  hint")` pairs. `None` where a
+/// path is expected instead.
+pub fn complete_find(previous: &str, word: &str) -> Option<Vec<(String, String)>> {
+    let options = if previous == "-type" {
+        find::TYPES
+    } else if word.starts_with('-') {
+        find::FLAGS
+    } else {
+        return None;
+    };
+    let width = options
+        .iter()
+        .map(|(value, _)| value.len())
+        .max()
+        .unwrap_or(0);
+    Some(
+        options
+            .iter()
+            .filter(|(value, _)| value.starts_with(word))
+            .map(|(value, hint)| (value.to_string(), format!("{value:<width$}  {hint}")))
+            .collect(),
+    )
+}
+
+/// Running processes whose name or pid starts with `prefix`, as
+/// `(pid, "  pid  name")` pairs sorted by name.
+pub fn complete_process(prefix: &str) -> Vec<(String, String)> {
+    matching_processes(prefix, processes(), std::process::id())
+}
+
+/// `complete_process` over a given process list, leaving out `own`.
+fn matching_processes(
+    prefix: &str,
+    processes: Vec<(u32, String)>,
+    own: u32,
+) -> Vec<(String, String)> {
+    let lower = prefix.to_lowercase();
+    let mut matches: Vec<(u32, String)> = processes
+        .into_iter()
+        .filter(|(pid, name)| {
+            *pid != own
+                && (name.to_lowercase().starts_with(&lower) || pid.to_string().starts_with(prefix))
+        })
+        .collect();
+    matches.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+    matches
+        .into_iter()
+        .map(|(pid, name)| (pid.to_string(), format!("{pid:>7}  {name}")))
+        .collect()
+}
+
+/// `(pid, command name)` for every process we can see.
+#[cfg(target_os = "linux")]
+fn processes() -> Vec<(u32, String)> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let name = fs::read_to_string(entry.path().join("comm")).ok()?;
+            Some((pid, name.trim_end().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn processes() -> Vec<(u32, String)> {
+    let Ok(output) = std::process::Command::new("ps")
+        .args(["-axo", "pid=,comm="])
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, name) = line.trim().split_once(char::is_whitespace)?;
+            let name = name.trim().rsplit('/').next()?;
+            Some((pid.parse().ok()?, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn processes() -> Vec<(u32, String)> {
+    Vec::new()
+}
+
+/// Longest prefix shared by every candidate.
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_split_on_unescaped_spaces() {
+        assert_eq!(word_at_cursor("ls src/ma", 9), (3, "src/ma".to_string()));
+        assert_eq!(word_at_cursor("cat my\\ fi", 11), (4, "my fi".to_string()));
+        assert_eq!(word_at_cursor("ls ", 3), (3, String::new()));
+        assert_eq!(word_at_cursor("git sta more", 7), (4, "sta".to_string()));
+        assert_eq!(escape("my file's"), "my\\ file\\'s");
+    }
+
+    #[test]
+    fn commands_complete_at_the_start_and_after_pipes() {
+        assert!(is_command_position("gi", 0));
+        assert!(is_command_position("ls | so", 5));
+        assert!(!is_command_position("ls sr", 3));
+        assert_eq!(
+            complete_command("gi", ["git", "grep", "gitk", "git"]),
+            ["git", "gitk"]
+        );
+        assert_eq!(common_prefix(&["gitk".into(), "git".into()]), "git");
+        assert_eq!(common_prefix(&["été".into(), "étui".into()]), "ét");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn paths_mark_directories_and_hide_dot_files() {
+        let dir = std::env::temp_dir().join(format!("complete-path-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("setup.sh"), "").unwrap();
+        fs::write(dir.join(".secret"), "").unwrap();
+        fs::write(dir.join("src/main.rs"), "").unwrap();
+        assert_eq!(complete_path("s", &dir), ["setup.sh", "src/"]);
+        assert_eq!(complete_path("src/m", &dir), ["src/main.rs"]);
+        assert_eq!(complete_path("", &dir), ["setup.sh", "src/"]);
+        assert_eq!(complete_path(".s", &dir), [".secret"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn processes_match_by_name_or_pid() {
+        let list = || {
+            vec![
+                (300, "sshd".to_string()),
+                (31, "Xorg".to_string()),
+                (7, "ssh-agent".to_string()),
+                (42, "terminal-app".to_string()),
+            ]
+        };
+        let pids = |prefix| -> Vec<String> {
+            matching_processes(prefix, list(), 42)
+                .into_iter()
+                .map(|(pid, _)| pid)
+                .collect()
+        };
+        assert_eq!(pids("ssh"), ["7", "300"]);
+        assert_eq!(pids("xo"), ["31"]);
+        assert_eq!(pids("3"), ["31", "300"]);
+        assert_eq!(pids("term"), Vec::<String>::new());
+        assert_eq!(pids("").len(), 3);
+        assert_eq!(matching_processes("x", list(), 42)[0].1, "     31  Xorg");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finds_a_running_process() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        let pid = child.id().to_string();
+        let found = complete_process(&pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(found
+            .iter()
+            .any(|(p, label)| *p == pid && label.ends_with("sleep")));
+    }
+}
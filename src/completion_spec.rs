@@ -0,0 +1,268 @@
+//! Argument completion from declarative specs: a command's subcommands,
+//! its flags, and what kind of argument each one takes.
+//!
+//! A spec file holds sections headed `[command]` or `[command subcommand]`.
+//! Each line in a section is a word, optionally followed by `<file>`,
+//! `<dir>` or `<value>` for the argument it takes, then a description.
+//! Words starting with `-` are flags, others subcommands. A line that is
+//! just `<file>`, `<dir>` or `<value>` says what the other arguments are.
+//! `#` starts a comment. A file in `<config>/completions/` named after a
+//! command may leave out the header for that command's own section.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::paths::Profile;
+
+const BUNDLED: &str = include_str!("completion_specs.txt");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arg {
+    File,
+    Dir,
+    /// Free text, with nothing to suggest.
+    Value,
+}
+
+impl Arg {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "<file>" => Some(Self::File),
+            "<dir>" => Some(Self::Dir),
+            "<value>" => Some(Self::Value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub word: String,
+    pub arg: Option<Arg>,
+    pub description: String,
+}
+
+#[derive(Debug, Default)]
+struct Spec {
+    entries: Vec<Entry>,
+    /// What positional arguments are; paths when unset.
+    args: Option<Arg>,
+}
+
+/// What to offer for the word being completed.
+#[derive(Debug, PartialEq)]
+pub enum Completion {
+    /// `(word, "word  description")` pairs.
+    Words(Vec<(String, String)>),
+    Paths {
+        dirs_only: bool,
+    },
+    Nothing,
+}
+
+/// Specs by command path, e.g. `docker run`.
+#[derive(Debug, Default)]
+pub struct Specs {
+    specs: BTreeMap<String, Spec>,
+}
+
+impl Specs {
+    /// The bundled specs, then the user's, which replace bundled sections
+    /// of the same name.
+    pub fn load(profile: &Profile) -> Self {
+        let mut specs = Self::bundled();
+        let dir = profile.config_dir().map(|dir| dir.join("completions"));
+        let Some(Ok(files)) = dir.map(fs::read_dir) else {
+            return specs;
+        };
+        let mut files: Vec<_> = files.flatten().map(|f| f.path()).collect();
+        files.sort();
+        for path in files {
+            let (Some(name), Ok(text)) = (
+                path.file_name().and_then(|n| n.to_str()),
+                fs::read_to_string(&path),
+            ) else {
+                continue;
+            };
+            specs.add(&text, Some(name));
+        }
+        specs
+    }
+
+    pub fn bundled() -> Self {
+        let mut specs = Self::default();
+        specs.add(BUNDLED, None);
+        specs
+    }
+
+    /// The flags of `command`, e.g. `docker run`, in spec order. `None`
+    /// when there's no spec for it.
+    pub fn flags(&self, command: &str) -> Option<Vec<&Entry>> {
+        let spec = self.specs.get(command)?;
+        Some(
+            spec.entries
+                .iter()
+                .filter(|e| e.word.starts_with('-'))
+                .collect(),
+        )
+    }
+
+    /// Adds the sections in `text`; lines before the first header belong
+    /// to `command`, or are ignored without one.
+    fn add(&mut self, text: &str, command: Option<&str>) {
+        let mut replaced = Vec::new();
+        let mut current = command.map(String::from);
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                current = Some(header.split_whitespace().collect::<Vec<_>>().join(" "));
+                continue;
+            }
+            let (Some(name), false) = (&current, line.is_empty()) else {
+                continue;
+            };
+            if !replaced.contains(name) {
+                self.specs.insert(name.clone(), Spec::default());
+                replaced.push(name.clone());
+            }
+            let spec = self.specs.entry(name.clone()).or_default();
+            let mut words = line.split_whitespace();
+            let word = words.next().unwrap_or("");
+            if let Some(arg) = Arg::parse(word) {
+                spec.args = Some(arg);
+                continue;
+            }
+            let rest = line[word.len()..].trim_start();
+            let (arg, description) = match rest.split_once(char::is_whitespace) {
+                Some((first, after)) if Arg::parse(first).is_some() => {
+                    (Arg::parse(first), after.trim_start())
+                }
+                _ if Arg::parse(rest).is_some() => (Arg::parse(rest), ""),
+                _ => (None, rest),
+            };
+            spec.entries.push(Entry {
+                word: word.to_string(),
+                arg,
+                description: description.to_string(),
+            });
+        }
+    }
+
+    /// Completions for `word`, given the words before it on the line
+    /// (command first). `None` when there's no spec for the command.
+    pub fn complete(&self, before: &[&str], word: &str) -> Option<Completion> {
+        let (command, args) = before.split_first()?;
+        let mut path = command.to_string();
+        let mut spec = self.specs.get(&path)?;
+        let mut takes_value = None;
+        for arg in args {
+            if takes_value.take().is_some() {
+                continue;
+            }
+            if arg.starts_with('-') {
+                takes_value = spec.entry(arg).and_then(|e| e.arg);
+                continue;
+            }
+            let deeper = format!("{path} {arg}");
+            if let Some(sub) = self.specs.get(&deeper) {
+                path = deeper;
+                spec = sub;
+            }
+        }
+        let kind = match takes_value {
+            Some(arg) => arg,
+            None if word.starts_with('-') => {
+                return Some(Completion::Words(spec.matching(word, true)));
+            }
+            None => {
+                let subcommands = spec.matching(word, false);
+                if !subcommands.is_empty() {
+                    return Some(Completion::Words(subcommands));
+                }
+                spec.args.unwrap_or(Arg::File)
+            }
+        };
+        Some(match kind {
+            Arg::File => Completion::Paths { dirs_only: false },
+            Arg::Dir => Completion::Paths { dirs_only: true },
+            Arg::Value => Completion::Nothing,
+        })
+    }
+}
+
+impl Spec {
+    fn entry(&self, word: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.word == word)
+    }
+
+    /// Flags or subcommands starting with `prefix`, labelled with their
+    /// descriptions.
+    fn matching(&self, prefix: &str, flags: bool) -> Vec<(String, String)> {
+        let matches: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|e| e.word.starts_with('-') == flags && e.word.starts_with(prefix))
+            .collect();
+        let width = matches.iter().map(|e| e.word.len()).max().unwrap_or(0);
+        matches
+            .into_iter()
+            .map(|e| {
+                let label = format!("{:<width$}  {}", e.word, e.description);
+                (e.word.clone(), label.trim_end().to_string())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(completion: Option<Completion>) -> Vec<String> {
+        match completion {
+            Some(Completion::Words(words)) => words.into_iter().map(|(w, _)| w).collect(),
+            other => panic!("expected words, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn completes_subcommands_flags_and_arguments() {
+        let mut specs = Specs::default();
+        specs.add(
+            "[docker]\nrun  Run a container\nrm  Remove\n\
+             [docker run]\n--name <value>  Name\n--rm  Remove after\n-v <dir>\n<value>",
+            None,
+        );
+        assert_eq!(words(specs.complete(&["docker"], "r")), ["run", "rm"]);
+        assert_eq!(
+            words(specs.complete(&["docker", "run"], "--")),
+            ["--name", "--rm"]
+        );
+        assert_eq!(
+            specs.complete(&["docker", "run", "--name"], ""),
+            Some(Completion::Nothing)
+        );
+        assert_eq!(
+            specs.complete(&["docker", "run", "-v"], ""),
+            Some(Completion::Paths { dirs_only: true })
+        );
+        assert_eq!(
+            specs.complete(&["docker", "run", "--name", "web"], "ng"),
+            Some(Completion::Nothing)
+        );
+        assert!(specs.complete(&["podman"], "").is_none());
+
+        // A user file named after the command needs no header, and
+        // replaces the bundled section.
+        specs.add("start  Start\n", Some("docker"));
+        assert_eq!(words(specs.complete(&["docker"], "")), ["start"]);
+        assert!(specs.complete(&["docker", "run"], "--").is_some());
+
+        let specs = Specs::bundled();
+        assert!(words(specs.complete(&["cargo"], "b")).contains(&"build".to_string()));
+        assert_eq!(
+            specs.complete(&["cargo", "build", "--manifest-path"], ""),
+            Some(Completion::Paths { dirs_only: false })
+        );
+    }
+}
@@ -0,0 +1,496 @@
+//! Runtime options, read from and written to `~/.config/ai_terminal/config`.
+//!
+//! The file is plain `key = value` lines; `#` starts a comment. Keys use the
+//! same names as `:set`, so anything settable at runtime can be persisted.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use eframe::egui::Key;
+
+use crate::paths::Profile;
+use crate::shell::ExecMode;
+use crate::snapshot;
+use crate::theme::Theme;
+use crate::tty::{self, TtyFallback};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorStyle {
+    Block,
+    Underline,
+    Bar,
+}
+
+impl CursorStyle {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(Self::Block),
+            "underline" => Some(Self::Underline),
+            "bar" => Some(Self::Bar),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Underline => "underline",
+            Self::Bar => "bar",
+        }
+    }
+}
+
+/// What Enter does while a foreground command is still running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusyInput {
+    /// Run the line once the command finishes.
+    Queue,
+    /// Leave the line in the input.
+    Ignore,
+    /// Send the line to the command's stdin.
+    Stdin,
+}
+
+impl BusyInput {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "queue" => Some(Self::Queue),
+            "ignore" => Some(Self::Ignore),
+            "stdin" => Some(Self::Stdin),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Queue => "queue",
+            Self::Ignore => "ignore",
+            Self::Stdin => "stdin",
+        }
+    }
+}
+
+/// What starting a background job does once `max-jobs` are running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobPolicy {
+    /// Start it when a running job finishes.
+    Queue,
+    /// Refuse it with an error.
+    Reject,
+}
+
+impl JobPolicy {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "queue" => Some(Self::Queue),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Queue => "queue",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub font_size: f32,
+    /// A TTF/OTF file, or the name of an installed font, for the text;
+    /// empty for egui's own.
+    pub font: String,
+    /// Pixels between scrollback lines.
+    pub line_spacing: f32,
+    /// Pixels between the window edge and the text.
+    pub inner_margin: f32,
+    /// Lines a mouse-wheel notch scrolls.
+    pub scroll_lines: f32,
+    /// `None` follows the system's dark/light preference.
+    pub theme: Option<Theme>,
+    /// Maximum number of lines kept in the scrollback.
+    pub scrollback: usize,
+    pub cursor_style: CursorStyle,
+    /// When false, all output renders in the theme's foreground color.
+    pub color: bool,
+    /// Soft-wrap long lines to the window width; off scrolls horizontally.
+    pub wrap: bool,
+    /// Columns between tab stops when output tabs are expanded.
+    pub tab_width: usize,
+    /// Makes `rm` move files to the trash instead of deleting them.
+    pub trash: bool,
+    /// Regex `:next-error` and F8 look for in the scrollback.
+    pub error_pattern: String,
+    /// strftime-style format for `--log-file` timestamps.
+    pub log_timestamp_format: String,
+    /// Command that opens clicked file paths; empty uses `$VISUAL`/`$EDITOR`.
+    pub editor: String,
+    /// Background jobs allowed to run at once; 0 is no limit.
+    pub max_jobs: usize,
+    pub max_jobs_policy: JobPolicy,
+    /// Show a desktop notification when a background job finishes.
+    pub notify: bool,
+    /// Only notify for jobs that ran at least this many seconds.
+    pub notify_after: u64,
+    /// Seconds without input before the window idles; 0 never idles.
+    pub idle_timeout: u64,
+    /// Dim the window while idle.
+    pub idle_dim: bool,
+    /// Show when each command ran and how long it took, and let clicking
+    /// the command fold its output.
+    pub command_headers: bool,
+    /// Prefix each output line with the time it arrived.
+    pub timestamps: bool,
+    /// Keep those times in copied text.
+    pub copy_timestamps: bool,
+    /// Show the startup banner.
+    pub banner: bool,
+    /// Show the bar with the directory and git branch above the scrollback.
+    pub prompt_header: bool,
+    /// Draw that bar's directory and branch as solid blocks joined by
+    /// Powerline arrows.
+    pub powerline: bool,
+    /// Show the git branch in that bar, which means running git as
+    /// directories change.
+    pub git_prompt: bool,
+    /// Typing an opening bracket or quote also inserts its closer.
+    pub auto_pair: bool,
+    /// A line that is just a directory changes into it, like zsh's AUTO_CD.
+    pub auto_cd: bool,
+    /// Clear the scrollback, but not history, on entering another
+    /// directory.
+    pub clear_on_cd: bool,
+    /// `cd` into a mistyped directory goes to the one sibling it's nearest
+    /// to instead, like zsh's CORRECT, rather than only suggesting it.
+    pub cd_correct: bool,
+    /// Drawn flush right on the input line; `{time}`, `{branch}` and the
+    /// like are filled in. Empty draws nothing.
+    pub rprompt: String,
+    /// Key that replays the macro recorded with Ctrl+Shift+M.
+    pub macro_key: String,
+    /// Programs that need a real terminal, comma-separated.
+    pub tty_commands: String,
+    pub tty_fallback: TtyFallback,
+    /// After commands from `watch_fs_commands`, list the files they
+    /// created, deleted or modified in the current directory.
+    pub watch_fs: bool,
+    /// Programs `watch-fs` applies to, comma-separated.
+    pub watch_fs_commands: String,
+    /// Whether external commands run directly or through `$SHELL -lc`.
+    pub exec_mode: ExecMode,
+    /// What Enter does while a foreground command runs.
+    pub busy_input: BusyInput,
+    /// Command prefix that runs a program in a new terminal window, e.g.
+    /// `xterm -e`; empty picks one automatically.
+    pub terminal: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            font: String::new(),
+            line_spacing: 3.0,
+            scroll_lines: 3.0,
+            inner_margin: 12.0,
+            theme: None,
+            scrollback: 10_000,
+            cursor_style: CursorStyle::Block,
+            color: true,
+            wrap: true,
+            tab_width: 8,
+            trash: false,
+            error_pattern: "error|warning:".to_string(),
+            log_timestamp_format: "%Y-%m-%d %H:%M:%S".to_string(),
+            editor: String::new(),
+            max_jobs: 0,
+            max_jobs_policy: JobPolicy::Queue,
+            notify: true,
+            notify_after: 10,
+            idle_timeout: 0,
+            idle_dim: true,
+            command_headers: true,
+            timestamps: false,
+            copy_timestamps: false,
+            banner: true,
+            prompt_header: true,
+            powerline: false,
+            git_prompt: true,
+            auto_pair: true,
+            auto_cd: false,
+            clear_on_cd: false,
+            cd_correct: false,
+            macro_key: "F9".to_string(),
+            rprompt: String::new(),
+            tty_commands: tty::DEFAULT_COMMANDS.to_string(),
+            tty_fallback: if cfg!(unix) {
+                TtyFallback::Pty
+            } else {
+                TtyFallback::External
+            },
+            watch_fs: false,
+            watch_fs_commands: snapshot::DEFAULT_COMMANDS.to_string(),
+            exec_mode: ExecMode::Direct,
+            busy_input: BusyInput::Queue,
+            terminal: String::new(),
+        }
+    }
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Some(true),
+        "off" | "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn on_off(value: bool) -> String {
+    if value { "on" } else { "off" }.to_string()
+}
+
+impl Config {
+    pub fn path(profile: &Profile) -> Option<PathBuf> {
+        profile.config_dir().map(|dir| dir.join("config"))
+    }
+
+    /// Loads the config file, ignoring unknown keys and invalid values so an
+    /// old or hand-edited file never prevents startup.
+    pub fn load(profile: &Profile) -> Self {
+        let mut config = Self::default();
+        let Some(text) = Self::path(profile).and_then(|path| fs::read_to_string(path).ok()) else {
+            return config;
+        };
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let _ = config.set(key.trim(), value.trim());
+            }
+        }
+        config
+    }
+
+    pub fn save(&self, profile: &Profile) -> io::Result<()> {
+        let path = Self::path(profile)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = String::new();
+        for (key, value) in self.entries() {
+            text.push_str(&format!("{key} = {value}\n"));
+        }
+        fs::write(path, text)
+    }
+
+    /// Current values of every settable key, in display order.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("font-size", self.font_size.to_string()),
+            ("font", self.font.clone()),
+            ("line-spacing", self.line_spacing.to_string()),
+            ("inner-margin", self.inner_margin.to_string()),
+            ("scroll-lines", self.scroll_lines.to_string()),
+            ("theme", self.theme.map_or("auto", Theme::name).to_string()),
+            ("scrollback", self.scrollback.to_string()),
+            ("cursor", self.cursor_style.name().to_string()),
+            ("color", on_off(self.color)),
+            ("wrap", on_off(self.wrap)),
+            ("tab-width", self.tab_width.to_string()),
+            ("trash", on_off(self.trash)),
+            ("error-pattern", self.error_pattern.clone()),
+            ("log-timestamp-format", self.log_timestamp_format.clone()),
+            ("editor", self.editor.clone()),
+            ("max-jobs", self.max_jobs.to_string()),
+            ("max-jobs-policy", self.max_jobs_policy.name().to_string()),
+            ("notify", on_off(self.notify)),
+            ("notify-after", self.notify_after.to_string()),
+            ("idle-timeout", self.idle_timeout.to_string()),
+            ("idle-dim", on_off(self.idle_dim)),
+            ("command-headers", on_off(self.command_headers)),
+            ("timestamps", on_off(self.timestamps)),
+            ("copy-timestamps", on_off(self.copy_timestamps)),
+            ("banner", on_off(self.banner)),
+            ("prompt-header", on_off(self.prompt_header)),
+            ("powerline", on_off(self.powerline)),
+            ("git-prompt", on_off(self.git_prompt)),
+            ("auto-pair", on_off(self.auto_pair)),
+            ("auto-cd", on_off(self.auto_cd)),
+            ("clear-on-cd", on_off(self.clear_on_cd)),
+            ("cd-correct", on_off(self.cd_correct)),
+            ("macro-key", self.macro_key.clone()),
+            ("rprompt", self.rprompt.clone()),
+            ("tty-commands", self.tty_commands.clone()),
+            ("tty-fallback", self.tty_fallback.name().to_string()),
+            ("watch-fs", on_off(self.watch_fs)),
+            ("watch-fs-commands", self.watch_fs_commands.clone()),
+            ("exec", self.exec_mode.name().to_string()),
+            ("busy-input", self.busy_input.name().to_string()),
+            ("terminal", self.terminal.clone()),
+        ]
+    }
+
+    /// Applies `key = value`, returning a user-facing message on bad input.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value '{value}' for '{key}'");
+        match key {
+            "font-size" => {
+                let size: f32 = value.parse().map_err(|_| invalid())?;
+                if !(6.0..=48.0).contains(&size) {
+                    return Err("font-size must be between 6 and 48".to_string());
+                }
+                self.font_size = size;
+            }
+            "font" => self.font = value.to_string(),
+            "line-spacing" => {
+                let spacing: f32 = value.parse().map_err(|_| invalid())?;
+                if !(0.0..=20.0).contains(&spacing) {
+                    return Err("line-spacing must be between 0 and 20".to_string());
+                }
+                self.line_spacing = spacing;
+            }
+            "inner-margin" => {
+                let margin: f32 = value.parse().map_err(|_| invalid())?;
+                if !(0.0..=64.0).contains(&margin) {
+                    return Err("inner-margin must be between 0 and 64".to_string());
+                }
+                self.inner_margin = margin;
+            }
+            "scroll-lines" => {
+                let lines: f32 = value.parse().map_err(|_| invalid())?;
+                if !(0.5..=50.0).contains(&lines) {
+                    return Err("scroll-lines must be between 0.5 and 50".to_string());
+                }
+                self.scroll_lines = lines;
+            }
+            "theme" if value == "auto" => self.theme = None,
+            "theme" => {
+                self.theme = Some(Theme::parse(value).ok_or_else(|| {
+                    format!(
+                        "unknown theme '{value}' (one of: auto, {})",
+                        Theme::NAMES.join(", ")
+                    )
+                })?);
+            }
+            "scrollback" => {
+                let lines: usize = value.parse().map_err(|_| invalid())?;
+                if lines < 100 {
+                    return Err("scrollback must be at least 100 lines".to_string());
+                }
+                self.scrollback = lines;
+            }
+            "cursor" => {
+                self.cursor_style = CursorStyle::parse(value)
+                    .ok_or_else(|| "cursor must be block, underline or bar".to_string())?;
+            }
+            "color" => self.color = parse_bool(value).ok_or_else(invalid)?,
+            "wrap" => self.wrap = parse_bool(value).ok_or_else(invalid)?,
+            "tab-width" => {
+                let width: usize = value.parse().map_err(|_| invalid())?;
+                if !(1..=16).contains(&width) {
+                    return Err("tab-width must be between 1 and 16".to_string());
+                }
+                self.tab_width = width;
+            }
+            "trash" => self.trash = parse_bool(value).ok_or_else(invalid)?,
+            "error-pattern" => {
+                regex::Regex::new(value).map_err(|e| format!("invalid regex: {e}"))?;
+                self.error_pattern = value.to_string();
+            }
+            "log-timestamp-format" => {
+                let mut items = chrono::format::StrftimeItems::new(value);
+                if items.any(|item| matches!(item, chrono::format::Item::Error)) {
+                    return Err(format!("invalid timestamp format '{value}'"));
+                }
+                self.log_timestamp_format = value.to_string();
+            }
+            "editor" => self.editor = value.to_string(),
+            "max-jobs" => self.max_jobs = value.parse().map_err(|_| invalid())?,
+            "max-jobs-policy" => {
+                self.max_jobs_policy = JobPolicy::parse(value)
+                    .ok_or_else(|| "max-jobs-policy must be queue or reject".to_string())?;
+            }
+            "notify" => self.notify = parse_bool(value).ok_or_else(invalid)?,
+            "notify-after" => self.notify_after = value.parse().map_err(|_| invalid())?,
+            "idle-timeout" => self.idle_timeout = value.parse().map_err(|_| invalid())?,
+            "idle-dim" => self.idle_dim = parse_bool(value).ok_or_else(invalid)?,
+            "command-headers" => self.command_headers = parse_bool(value).ok_or_else(invalid)?,
+            // Its first name, kept for config files that use it.
+            "timestamps" | "line-timestamps" => {
+                self.timestamps = parse_bool(value).ok_or_else(invalid)?
+            }
+            "copy-timestamps" => self.copy_timestamps = parse_bool(value).ok_or_else(invalid)?,
+            "banner" => self.banner = parse_bool(value).ok_or_else(invalid)?,
+            "prompt-header" => self.prompt_header = parse_bool(value).ok_or_else(invalid)?,
+            "powerline" => self.powerline = parse_bool(value).ok_or_else(invalid)?,
+            "git-prompt" => self.git_prompt = parse_bool(value).ok_or_else(invalid)?,
+            "auto-pair" => self.auto_pair = parse_bool(value).ok_or_else(invalid)?,
+            "auto-cd" => self.auto_cd = parse_bool(value).ok_or_else(invalid)?,
+            "clear-on-cd" => self.clear_on_cd = parse_bool(value).ok_or_else(invalid)?,
+            "cd-correct" => self.cd_correct = parse_bool(value).ok_or_else(invalid)?,
+            "rprompt" => self.rprompt = value.to_string(),
+            "macro-key" => {
+                Key::from_name(value).ok_or_else(invalid)?;
+                self.macro_key = value.to_string();
+            }
+            "tty-commands" => self.tty_commands = value.to_string(),
+            "tty-fallback" => {
+                self.tty_fallback = TtyFallback::parse(value)
+                    .ok_or_else(|| "tty-fallback must be pty, external or message".to_string())?;
+            }
+            "terminal" => self.terminal = value.to_string(),
+            "watch-fs" => self.watch_fs = parse_bool(value).ok_or_else(invalid)?,
+            "watch-fs-commands" => self.watch_fs_commands = value.to_string(),
+            "exec" => {
+                self.exec_mode = ExecMode::parse(value)
+                    .ok_or_else(|| "exec must be direct or login".to_string())?;
+            }
+            "busy-input" => {
+                self.busy_input = BusyInput::parse(value)
+                    .ok_or_else(|| "busy-input must be queue, ignore or stdin".to_string())?;
+            }
+            _ => return Err(format!("unknown option '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_checks_values_like_tab_width() {
+        let mut config = Config::default();
+        assert_eq!(config.set("scrollback", "500"), Ok(()));
+        assert_eq!(config.scrollback, 500);
+        assert!(config.set("scrollback", "50").is_err());
+        assert!(config.set("scrollback", "lots").is_err());
+        assert_eq!(config.scrollback, 500);
+        assert!(config.set("tab-width", "0").is_err());
+        assert_eq!(config.set("color", "off"), Ok(()));
+        assert!(!config.color);
+        assert!(config.set("color", "maybe").is_err());
+        assert!(config.set("no-such-option", "1").is_err());
+    }
+
+    #[test]
+    fn every_listed_value_can_be_set_back() {
+        let mut config = Config::default();
+        config.set("scrollback", "2000").unwrap();
+        config.set("theme", Theme::NAMES[0]).unwrap();
+        config.set("line-timestamps", "on").unwrap();
+        assert!(config.timestamps);
+        let mut copy = Config::default();
+        for (key, value) in config.entries() {
+            assert_eq!(copy.set(key, &value), Ok(()), "{key} = {value}");
+        }
+        assert_eq!(copy.entries(), config.entries());
+    }
+}
@@ -0,0 +1,306 @@
+//! The subset of `find` that's safe to evaluate while it's still being
+//! typed, for the match count shown in the header. Anything with side
+//! effects (`-exec`, `-delete`) or operators isn't previewed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Flags offered when completing a `find` argument, with a short hint.
+pub const FLAGS: &[(&str, &str)] = &[
+    ("-delete", "remove matches"),
+    ("-empty", "empty files and directories"),
+    ("-exec", "run a command on each match"),
+    ("-iname", "name glob, ignoring case"),
+    ("-maxdepth", "descend at most N levels"),
+    ("-mindepth", "skip the first N levels"),
+    ("-mtime", "modified N days ago (+N older, -N newer)"),
+    ("-name", "name glob, e.g. '*.rs'"),
+    ("-newer", "modified after FILE"),
+    ("-path", "path glob"),
+    ("-print", "print matches"),
+    ("-size", "size, e.g. +1M"),
+    ("-type", "f file, d directory, l symlink"),
+];
+
+/// Values offered after `-type`.
+pub const TYPES: &[(&str, &str)] = &[("d", "directory"), ("f", "file"), ("l", "symlink")];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Days {
+    Exactly(u64),
+    MoreThan(u64),
+    LessThan(u64),
+}
+
+#[derive(Debug, PartialEq)]
+enum Test {
+    Name(String),
+    IName(String),
+    Path(String),
+    Type(Kind),
+    Mtime(Days),
+    Empty,
+}
+
+#[derive(Debug)]
+pub struct Query {
+    roots: Vec<String>,
+    tests: Vec<Test>,
+    min_depth: usize,
+    max_depth: usize,
+}
+
+impl Query {
+    /// Parses `find` arguments (without the command name). `None` for
+    /// anything outside the previewable subset or not yet complete.
+    pub fn parse(args: &[String]) -> Option<Query> {
+        let split = args
+            .iter()
+            .position(|a| a.starts_with('-'))
+            .unwrap_or(args.len());
+        let mut query = Query {
+            roots: args[..split].to_vec(),
+            tests: Vec::new(),
+            min_depth: 0,
+            max_depth: usize::MAX,
+        };
+        if query.roots.is_empty() {
+            query.roots.push(".".to_string());
+        }
+        let mut rest = args[split..].iter();
+        while let Some(flag) = rest.next() {
+            let test = match flag.as_str() {
+                "-print" => continue,
+                "-empty" => Test::Empty,
+                "-name" => Test::Name(rest.next()?.clone()),
+                "-iname" => Test::IName(rest.next()?.to_lowercase()),
+                "-path" | "-wholename" => Test::Path(rest.next()?.clone()),
+                "-type" => Test::Type(match rest.next()?.as_str() {
+                    "f" => Kind::File,
+                    "d" => Kind::Dir,
+                    "l" => Kind::Symlink,
+                    _ => return None,
+                }),
+                "-mtime" => {
+                    let value = rest.next()?;
+                    Test::Mtime(if let Some(n) = value.strip_prefix('+') {
+                        Days::MoreThan(n.parse().ok()?)
+                    } else if let Some(n) = value.strip_prefix('-') {
+                        Days::LessThan(n.parse().ok()?)
+                    } else {
+                        Days::Exactly(value.parse().ok()?)
+                    })
+                }
+                "-maxdepth" => {
+                    query.max_depth = rest.next()?.parse().ok()?;
+                    continue;
+                }
+                "-mindepth" => {
+                    query.min_depth = rest.next()?.parse().ok()?;
+                    continue;
+                }
+                _ => return None,
+            };
+            query.tests.push(test);
+        }
+        Some(query)
+    }
+
+    /// `shown` is the path as find prints it, `path` the one on disk.
+    fn matches(&self, shown: &str, path: &Path, metadata: &fs::Metadata) -> bool {
+        let name = shown
+            .rsplit('/')
+            .find(|part| !part.is_empty())
+            .unwrap_or(shown);
+        self.tests.iter().all(|test| match test {
+            Test::Name(pattern) => glob_match(pattern, name),
+            Test::IName(pattern) => glob_match(pattern, &name.to_lowercase()),
+            Test::Path(pattern) => glob_match(pattern, shown),
+            Test::Type(kind) => {
+                let file_type = metadata.file_type();
+                match kind {
+                    Kind::File => file_type.is_file(),
+                    Kind::Dir => file_type.is_dir(),
+                    Kind::Symlink => file_type.is_symlink(),
+                }
+            }
+            Test::Mtime(days) => {
+                let age = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| SystemTime::now().duration_since(time).ok())
+                    .unwrap_or(Duration::ZERO);
+                let age = age.as_secs() / 86_400;
+                match *days {
+                    Days::Exactly(n) => age == n,
+                    Days::MoreThan(n) => age > n,
+                    Days::LessThan(n) => age < n,
+                }
+            }
+            Test::Empty => {
+                if metadata.is_dir() {
+                    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+                } else {
+                    metadata.is_file() && metadata.len() == 0
+                }
+            }
+        })
+    }
+
+    /// Counts matches under the roots, relative to `cwd`, without following
+    /// symlinks. Gives up after visiting `limit` entries or when `stop` is
+    /// set; the bool is true if the count is complete.
+    pub fn count(&self, cwd: &Path, limit: usize, stop: &AtomicBool) -> (usize, bool) {
+        let mut found = 0;
+        let mut visited = 0;
+        // (path as find prints it, path on disk, depth)
+        let mut pending: Vec<(String, PathBuf, usize)> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|root| (root.clone(), cwd.join(root), 0))
+            .collect();
+        while let Some((shown, path, depth)) = pending.pop() {
+            visited += 1;
+            if visited > limit || stop.load(Ordering::Relaxed) {
+                return (found, false);
+            }
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if depth >= self.min_depth && self.matches(&shown, &path, &metadata) {
+                found += 1;
+            }
+            if !metadata.is_dir() || depth >= self.max_depth {
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let child = format!("{}/{}", shown.trim_end_matches('/'), name.to_string_lossy());
+                pending.push((child, entry.path(), depth + 1));
+            }
+        }
+        (found, true)
+    }
+}
+
+/// Shell-style glob: `*`, `?` and `[...]` classes (with `!` or `^` to
+/// negate and `a-z` ranges). `*` matches `/` too, as in `find -path`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*`: (pattern index, text index).
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            Some('[') => {
+                if let Some((matched, len)) = match_class(&pattern[p..], text[t]) {
+                    if matched {
+                        p += len;
+                        t += 1;
+                        continue;
+                    }
+                } else if text[t] == '[' {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+            }
+            Some(&c) if c == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star_p, star_t)) => {
+                p = star_p;
+                t = star_t + 1;
+                backtrack = Some((star_p, star_t + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the class at the start of `pattern`, returning
+/// whether it matched and the class's length, or `None` if it's unclosed.
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = matches!(pattern.get(i), Some('!' | '^'));
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while let Some(&start) = pattern.get(i) {
+        if start == ']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&e| e != ']') {
+            matched |= (start..=pattern[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= start == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_previewable_queries_and_globs() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "main.rsx"));
+        assert!(glob_match("test_?.[ch]", "test_1.h"));
+        assert!(!glob_match("[!a-c]*", "build"));
+        assert!(glob_match("./src/*/mod.rs", "./src/app/mod.rs"));
+
+        let query = Query::parse(&args(&["src", "-name", "*.rs", "-type", "f"])).unwrap();
+        assert_eq!(query.roots, ["src"]);
+        assert_eq!(
+            query.tests,
+            [Test::Name("*.rs".to_string()), Test::Type(Kind::File)]
+        );
+        assert_eq!(
+            Query::parse(&args(&["-mtime", "-2"])).unwrap().tests,
+            [Test::Mtime(Days::LessThan(2))]
+        );
+        assert!(Query::parse(&args(&[".", "-name"])).is_none());
+        assert!(Query::parse(&args(&[".", "-name", "*.o", "-delete"])).is_none());
+    }
+}
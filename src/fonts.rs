@@ -0,0 +1,160 @@
+//! A user font for the terminal text, from `--font` or the `font` option:
+//! the path of a TTF/OTF file, or the name of one installed in the usual
+//! font directories, such as `JetBrains Mono` or `FiraCode-Retina`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{FontData, FontDefinitions, FontFamily};
+
+use crate::paths;
+
+/// Powerline's solid arrows, as patched into Nerd Fonts: pointing right
+/// between the header's segments, and left for the right prompt's `{sep}`.
+pub const POWERLINE_RIGHT: char = '\u{e0b0}';
+pub const POWERLINE_LEFT: char = '\u{e0b2}';
+
+const EXTENSIONS: &[&str] = &["ttf", "otf", "ttc"];
+
+/// How deep to look below each font directory; distributions nest fonts
+/// by format and then family.
+const MAX_DEPTH: usize = 4;
+
+/// egui's fonts with `name` in front for monospace text and behind the
+/// defaults for the rest, so its extra glyphs are there for the UI too.
+/// An empty name is the defaults.
+pub fn definitions(name: &str) -> Result<FontDefinitions, String> {
+    let mut definitions = FontDefinitions::default();
+    if name.is_empty() {
+        return Ok(definitions);
+    }
+    let path = resolve(name).ok_or_else(|| format!("no font file or installed font '{name}'"))?;
+    let bytes = fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    // egui can't tell us whether the bytes are a font without panicking
+    // later, so at least check for one of the font file signatures.
+    if !is_font(&bytes) {
+        return Err(format!(
+            "{}: not a TrueType or OpenType font",
+            path.display()
+        ));
+    }
+    definitions
+        .font_data
+        .insert("user".to_string(), FontData::from_owned(bytes));
+    let families = &mut definitions.families;
+    families
+        .entry(FontFamily::Monospace)
+        .or_default()
+        .insert(0, "user".to_string());
+    families
+        .entry(FontFamily::Proportional)
+        .or_default()
+        .push("user".to_string());
+    Ok(definitions)
+}
+
+fn is_font(bytes: &[u8]) -> bool {
+    matches!(
+        bytes.get(..4),
+        Some(b"\x00\x01\x00\x00" | b"OTTO" | b"true" | b"ttcf")
+    )
+}
+
+/// `name` as a path to a font file, or else the best match for it among
+/// the installed fonts.
+fn resolve(name: &str) -> Option<PathBuf> {
+    let path = paths::resolve(Path::new("."), name);
+    if path.is_file() {
+        return Some(path);
+    }
+    let mut best: Option<(u8, PathBuf)> = None;
+    for dir in font_dirs() {
+        search(&dir, name, MAX_DEPTH, &mut best);
+    }
+    best.map(|(_, path)| path)
+}
+
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = paths::home_dir() {
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join("Library/Fonts"));
+    }
+    if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+        dirs.push(PathBuf::from(local).join("Microsoft/Windows/Fonts"));
+    }
+    for dir in [
+        "/usr/share/fonts",
+        "/usr/local/share/fonts",
+        "/Library/Fonts",
+        "/System/Library/Fonts",
+        "C:\\Windows\\Fonts",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+fn search(dir: &Path, name: &str, depth: usize, best: &mut Option<(u8, PathBuf)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                search(&path, name, depth - 1, best);
+            }
+            continue;
+        }
+        let Some(rank) = rank(&path, name) else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|(best, _)| rank < *best) {
+            *best = Some((rank, path));
+        }
+    }
+}
+
+/// How well a font file's name matches what was asked for, lower being
+/// better, ignoring case, spaces and punctuation: the exact name, then its
+/// regular style, then any style of it.
+fn rank(path: &Path, name: &str) -> Option<u8> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if !EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let stem = squash(path.file_stem()?.to_str()?);
+    let name = squash(name);
+    let style = stem.strip_prefix(&name)?;
+    match style {
+        "" => Some(0),
+        "regular" => Some(1),
+        _ => Some(2),
+    }
+}
+
+fn squash(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_file_names() {
+        let rank = |file: &str| rank(Path::new(file), "JetBrains Mono");
+        assert_eq!(rank("/f/jetbrainsmono.ttf"), Some(0));
+        assert_eq!(rank("/f/JetBrainsMono-Regular.TTF"), Some(1));
+        assert_eq!(rank("/f/JetBrains_Mono-BoldItalic.otf"), Some(2));
+        assert_eq!(rank("/f/JetBrainsMono-Regular.woff2"), None);
+        assert_eq!(rank("/f/DejaVuSansMono.ttf"), None);
+        assert!(is_font(b"OTTO\x00\x0a"));
+        assert!(!is_font(b"<html>"));
+    }
+}
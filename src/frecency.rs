@@ -0,0 +1,205 @@
+//! Visited-directory database behind `z`, ranked by frequency and recency.
+//!
+//! Stored as `path|visits|last-visit-unix-seconds` lines, one per directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::paths::Profile;
+
+/// Entries beyond this are dropped, lowest score first.
+const MAX_ENTRIES: usize = 500;
+
+struct Entry {
+    path: PathBuf,
+    visits: u32,
+    last_visit: u64,
+}
+
+impl Entry {
+    /// Visit count weighted by how recently the directory was used, the
+    /// same buckets `z` uses.
+    fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_visit);
+        let weight = match age {
+            0..=3_599 => 4.0,
+            3_600..=86_399 => 2.0,
+            86_400..=604_799 => 0.5,
+            _ => 0.25,
+        };
+        f64::from(self.visits) * weight
+    }
+}
+
+#[derive(Default)]
+pub struct DirHistory {
+    entries: Vec<Entry>,
+    /// File the database was loaded from and is saved back to.
+    path: Option<PathBuf>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The entries of a saved database, skipping malformed lines. Fields are
+/// split from the right, so paths may contain `|`.
+fn parse(text: &str) -> Vec<Entry> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.rsplitn(3, '|');
+            let last_visit = fields.next()?.parse().ok()?;
+            let visits = fields.next()?.parse().ok()?;
+            let path = PathBuf::from(fields.next()?);
+            Some(Entry {
+                path,
+                visits,
+                last_visit,
+            })
+        })
+        .collect()
+}
+
+impl DirHistory {
+    pub fn load(profile: &Profile) -> Self {
+        let path = profile.data_dir().map(|dir| dir.join("dirs"));
+        let Some(text) = path.as_ref().and_then(|p| fs::read_to_string(p).ok()) else {
+            return Self {
+                entries: Vec::new(),
+                path,
+            };
+        };
+        Self {
+            entries: parse(&text),
+            path,
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let text: String = self
+            .entries
+            .iter()
+            .map(|e| format!("{}|{}|{}\n", e.path.display(), e.visits, e.last_visit))
+            .collect();
+        let _ = fs::write(path, text);
+    }
+
+    /// Records a visit to `dir` and persists the database.
+    pub fn visit(&mut self, dir: &Path) {
+        let now = now();
+        match self.entries.iter_mut().find(|e| e.path == dir) {
+            Some(entry) => {
+                entry.visits += 1;
+                entry.last_visit = now;
+            }
+            None => self.entries.push(Entry {
+                path: dir.to_path_buf(),
+                visits: 1,
+                last_visit: now,
+            }),
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries
+                .sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+            self.entries.truncate(MAX_ENTRIES);
+        }
+        self.save();
+    }
+
+    /// Directories whose path contains every term, in order and ignoring
+    /// case, best first. Directories that no longer exist are skipped.
+    pub fn matches(&self, terms: &[&str]) -> Vec<PathBuf> {
+        let now = now();
+        let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+        let mut found: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|e| {
+                let path = e.path.to_string_lossy().to_lowercase();
+                let mut rest = path.as_str();
+                terms.iter().all(|term| match rest.find(term.as_str()) {
+                    Some(at) => {
+                        rest = &rest[at + term.len()..];
+                        true
+                    }
+                    None => false,
+                })
+            })
+            .filter(|e| e.path.is_dir())
+            .collect();
+        found.sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+        found.into_iter().map(|e| e.path.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &Path, visits: u32, age: u64) -> Entry {
+        Entry {
+            path: path.to_path_buf(),
+            visits,
+            last_visit: now() - age,
+        }
+    }
+
+    #[test]
+    fn recent_visits_outweigh_old_ones() {
+        let now = now();
+        let old = entry(Path::new("/old"), 10, 30 * 86_400);
+        let recent = entry(Path::new("/recent"), 2, 60);
+        assert_eq!(old.score(now), 2.5);
+        assert_eq!(recent.score(now), 8.0);
+        assert_eq!(entry(Path::new("/day"), 1, 7_200).score(now), 2.0);
+    }
+
+    #[test]
+    fn matches_terms_in_order_and_ranks_them() {
+        let root = std::env::temp_dir().join(format!("frecency-{}", std::process::id()));
+        let (api, app, gone) = (
+            root.join("src/Api"),
+            root.join("app/src"),
+            root.join("gone-src"),
+        );
+        fs::create_dir_all(&api).unwrap();
+        fs::create_dir_all(&app).unwrap();
+        let history = DirHistory {
+            entries: vec![entry(&api, 3, 60), entry(&app, 9, 60), entry(&gone, 50, 60)],
+            path: None,
+        };
+        assert_eq!(history.matches(&["src"]), [app, api.clone()]);
+        assert_eq!(history.matches(&["SRC", "api"]), [api]);
+        assert_eq!(history.matches(&["api", "src"]), Vec::<PathBuf>::new());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn visits_are_saved_and_read_back() {
+        let file = std::env::temp_dir().join(format!("frecency-db-{}", std::process::id()));
+        let mut history = DirHistory {
+            entries: Vec::new(),
+            path: Some(file.clone()),
+        };
+        history.visit(Path::new("/tmp/a|b"));
+        history.visit(Path::new("/tmp/a|b"));
+        history.visit(Path::new("/usr"));
+        let text = fs::read_to_string(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+        let entries = parse(&format!("{text}garbage\n/x|notanumber|1\n"));
+        let read: Vec<(&Path, u32)> = entries
+            .iter()
+            .map(|e| (e.path.as_path(), e.visits))
+            .collect();
+        assert_eq!(read, [(Path::new("/tmp/a|b"), 2), (Path::new("/usr"), 1)]);
+    }
+}
@@ -0,0 +1,169 @@
+//! Just enough git to show and switch the current branch, and to correct
+//! mistyped subcommands.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::suggest;
+
+/// Git's own subcommands, for when its output doesn't suggest one.
+const SUBCOMMANDS: &[&str] = &[
+    "add",
+    "am",
+    "archive",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "clone",
+    "commit",
+    "config",
+    "describe",
+    "diff",
+    "fetch",
+    "grep",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reflog",
+    "remote",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "show",
+    "stash",
+    "status",
+    "submodule",
+    "switch",
+    "tag",
+    "worktree",
+];
+
+/// The `.git` directory for the repository containing `dir`, following the
+/// `gitdir:` pointer that worktrees and submodules use.
+fn git_dir(dir: &Path) -> Option<PathBuf> {
+    let dot_git = dir
+        .ancestors()
+        .map(|d| d.join(".git"))
+        .find(|p| p.exists())?;
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let pointer = fs::read_to_string(&dot_git).ok()?;
+    let target = pointer.trim().strip_prefix("gitdir:")?.trim();
+    Some(dot_git.parent()?.join(target))
+}
+
+/// The branch named by a HEAD file, or the short commit hash when HEAD is
+/// detached.
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            )
+        }
+        None if head.len() >= 7 => Some(head[..7].to_string()),
+        None => None,
+    }
+}
+
+/// The checked-out branch for `dir`, read from HEAD without spawning git so
+/// it's cheap enough to poll.
+pub fn current_branch(dir: &Path) -> Option<String> {
+    parse_head(&fs::read_to_string(git_dir(dir)?.join("HEAD")).ok()?)
+}
+
+/// Whether tracked files in `dir`'s repository have uncommitted changes.
+/// Spawns git, so callers keep it off the per-frame path.
+pub fn is_dirty(dir: &Path) -> bool {
+    Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .current_dir(dir)
+        .output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+}
+
+/// Local branch names, as `git branch` lists them.
+pub fn local_branches(dir: &Path) -> Vec<String> {
+    let Ok(output) = Command::new("git")
+        .args(["branch", "--format=%(refname:short)"])
+        .current_dir(dir)
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// For output where git rejected a subcommand, the word it rejected and
+/// the one meant: the first git suggested, or else the nearest known one.
+pub fn typo_correction(output: &[String]) -> Option<(String, String)> {
+    let (index, typo) = output.iter().enumerate().find_map(|(i, line)| {
+        let rest = line.strip_prefix("git: '")?;
+        let (typo, _) = rest.split_once("' is not a git command")?;
+        Some((i, typo.to_string()))
+    })?;
+    let suggested = output[index + 1..]
+        .iter()
+        .skip_while(|line| !line.starts_with("The most similar command"))
+        .nth(1)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty());
+    let meant = suggested.or_else(|| {
+        suggest::closest(&typo, SUBCOMMANDS.iter().copied(), 2, 1)
+            .into_iter()
+            .next()
+    })?;
+    Some((typo, meant))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_names_branch_or_commit() {
+        assert_eq!(
+            parse_head("ref: refs/heads/feature/x\n").as_deref(),
+            Some("feature/x")
+        );
+        assert_eq!(
+            parse_head("89fcf58d0a1b2c3d4e5f\n").as_deref(),
+            Some("89fcf58")
+        );
+        assert_eq!(parse_head(""), None);
+    }
+
+    #[test]
+    fn corrects_subcommand_typos() {
+        let lines = |text: &str| text.lines().map(String::from).collect::<Vec<_>>();
+        let output = lines(
+            "git: 'stauts' is not a git command. See 'git --help'.\n\n\
+             The most similar commands are\n\tstatus\n\tstash",
+        );
+        assert_eq!(
+            typo_correction(&output),
+            Some(("stauts".to_string(), "status".to_string()))
+        );
+        let output = lines("git: 'comit' is not a git command. See 'git --help'.");
+        assert_eq!(typo_correction(&output).unwrap().1, "commit");
+        assert!(typo_correction(&lines("fatal: not a git repository")).is_none());
+    }
+}
@@ -0,0 +1,675 @@
+//! Topics shown by the `help` built-in.
+
+use crate::input;
+use crate::line::{Segment, TerminalLine, ERROR_COLOR, HEADING_COLOR, INPUT_COLOR, OUTPUT_COLOR};
+
+pub struct Section {
+    pub heading: &'static str,
+    /// `(what you type, what it does)` pairs, rendered as an aligned table.
+    pub entries: &'static [(&'static str, &'static str)],
+}
+
+struct Topic {
+    name: &'static str,
+    summary: &'static str,
+    sections: &'static [Section],
+}
+
+static TOPICS: &[Topic] = &[
+    Topic {
+        name: "builtins",
+        summary: "Commands handled by the terminal itself",
+        sections: &[
+            Section {
+                heading: "Session",
+                entries: &[
+                    ("cd [dir]", "Change directory (defaults to ~)"),
+                    ("cd - / cd --back", "Previous directory / undo the last cd"),
+                    (
+                        "pushd DIR / popd",
+                        "Change directory saving / restoring the old one",
+                    ),
+                    ("dirs [-v]", "Show the pushd stack"),
+                    ("z [terms...]", "Jump to the most-used matching directory"),
+                    ("clear", "Clear the scrollback"),
+                    (
+                        "exit",
+                        "Quit the terminal; with jobs running, asks first and hangs them up",
+                    ),
+                    ("help [topic]", "Show this help, or a single topic"),
+                    (
+                        "history [PATTERN]",
+                        "List previous commands, or those containing PATTERN",
+                    ),
+                    (
+                        "!! !N !-N",
+                        "The last command, entry N of history, the Nth previous",
+                    ),
+                    ("sudo!!", "Run the last command again with sudo"),
+                    ("cmd &", "Run cmd in the background as a numbered job"),
+                    (
+                        "NAME=VALUE [cmd]",
+                        "Set a variable for the session, or for cmd's environment only",
+                    ),
+                    (
+                        "export [NAME[=VALUE]]",
+                        "Pass a variable to every command from now on; alone, list them",
+                    ),
+                    ("unset NAME", "Forget a variable"),
+                    (
+                        "alias NAME='CMD $1'",
+                        "Define an alias; $1..$N and $@ take its arguments, others go after. It may run several commands split by ; or &&",
+                    ),
+                    ("unalias NAME", "Forget an alias; -a forgets them all"),
+                    (
+                        "for X in A B; do cmd $X; done",
+                        "Run cmd once per word; Enter before done starts a new line",
+                    ),
+                    ("jobs", "List background jobs"),
+                    (":keybindings", "Show the keybindings in a window"),
+                    (
+                        ":search [-b] [PATTERN]",
+                        "Jump to the next output line matching a regex; alone repeats",
+                    ),
+                    (
+                        ":macro [save|run|rm NAME]",
+                        "List saved macros, keep the last recording, replay or forget one",
+                    ),
+                    (
+                        ":compare A ||| B",
+                        "Show two commands' outputs side by side, differing lines colored",
+                    ),
+                    (
+                        ":build COMMAND",
+                        "Pick flags for find, tar, docker run... in a form; Use fills the prompt",
+                    ),
+                    (
+                        ":copy-last",
+                        "Copy the previous command's output to the clipboard",
+                    ),
+                    (
+                        ":errors-only",
+                        "Hide all output but errors and stderr, counting what's hidden; again to undo",
+                    ),
+                    (
+                        ":export-history [--zsh] FILE",
+                        "Write history for bash or zsh",
+                    ),
+                    (
+                        ":export-html FILE",
+                        "Write the scrollback as HTML, colors included",
+                    ),
+                    (
+                        ":hexdump",
+                        "Show the last command's output as hex, e.g. after binary output",
+                    ),
+                    (
+                        ":highlight [REGEX COLOR | clear]",
+                        "Color matching output text; alone, list the rules",
+                    ),
+                    (
+                        ":pin / :unpin [N]",
+                        "Keep the highlighted or last output line above the scrollback",
+                    ),
+                    (
+                        ":set [key [value]]",
+                        "List or change options (help settings)",
+                    ),
+                ],
+            },
+            Section {
+                heading: "Tools",
+                entries: &[
+                    (
+                        "base64 [-d] [FILE]",
+                        "Encode a file or piped input as base64, or decode it",
+                    ),
+                    (
+                        "bench N COMMAND...",
+                        "Time N runs of a command or pipeline: min/max/mean/median/stddev",
+                    ),
+                    ("cal [MONTH YEAR]", "Month calendar with today highlighted"),
+                    (
+                        "calc EXPR",
+                        "Arithmetic with + - * / % ^, sqrt(), pi...; bare 2*(3+4) works too",
+                    ),
+                    (
+                        "chmod [-R] MODE FILE...",
+                        "Set permissions (755, u+x, go-w) and show the result",
+                    ),
+                    ("cowsay [-W N] TEXT", "TEXT, or piped input, said by an ASCII cow"),
+                    (
+                        "date [-u] [+FORMAT]",
+                        "Current date and time; FORMAT as strftime, e.g. +%F",
+                    ),
+                    ("diff [-u] A B", "Compare two files with colored changes"),
+                    (
+                        "echo [-n] [-e] ARG...",
+                        "Print arguments; -n drops the newline, -e reads \\n \\t escapes",
+                    ),
+                    ("fortune", "A random quip"),
+                    ("grep", "Search text with highlighted matches (help grep)"),
+                    (
+                        "hash ALGO [FILE...]",
+                        "md5, sha1 or sha256 checksums of files or piped input",
+                    ),
+                    (
+                        "head [-n N] [FILE...]",
+                        "First lines of files or piped input",
+                    ),
+                    (
+                        "http [METHOD] URL [BODY]",
+                        "Fetch in-process (https via curl): colored status, headers, indented JSON",
+                    ),
+                    (
+                        "kill [-SIGNAL] PID...",
+                        "Send a signal (default TERM); -l lists names",
+                    ),
+                    ("man PAGE", "Manual page in its own view, with find; Escape closes"),
+                    (
+                        "open PATH|URL",
+                        "Open with the default application; with flags, the system open",
+                    ),
+                    (
+                        "ps --tree | --filter NAME",
+                        "Processes as a tree or only matching names (Unix); plain ps is the system one",
+                    ),
+                    ("rm [-rf] FILE...", "Move files to the trash (help trash)"),
+                    (
+                        "sed [-E] 's/RE/TEXT/[gi]; /RE/d' [FILE...]",
+                        "Substitute and delete lines; other scripts run the real sed",
+                    ),
+                    (
+                        "sort [-rnu] [FILE...]",
+                        "Sort lines: reverse, numeric, unique",
+                    ),
+                    (
+                        "tail [-n N|+N] [-f] FILE",
+                        "Last lines; -f keeps printing new ones until Ctrl+C",
+                    ),
+                    ("tldr COMMAND", "Short usage examples for a command"),
+                    (
+                        "top",
+                        "Live table of the busiest processes; q quits; with flags, the system top",
+                    ),
+                    (
+                        "trap 'CMD' EVENT",
+                        "Run CMD on start, precmd or preexec (help hooks)",
+                    ),
+                    ("trash-restore [FILE]", "List the trash, or restore a file"),
+                    (
+                        "tree [-a] [-L N] [DIR]",
+                        "Directory tree; -a shows hidden files, -L limits depth",
+                    ),
+                    (
+                        "uniq [-c] [FILE]",
+                        "Drop repeated adjacent lines; -c counts them",
+                    ),
+                    ("wc [-lwc] [FILE...]", "Count lines, words and bytes"),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "autocomplete",
+        summary: "Completing commands and paths with Tab",
+        sections: &[
+            Section {
+                heading: "Keys",
+                entries: &[
+                    ("Tab", "Complete the word under the cursor"),
+                    ("Tab again", "Cycle through the suggestion popup"),
+                    (
+                        "Up / Down",
+                        "Move through the open popup; Enter takes the highlighted one",
+                    ),
+                    ("Esc", "Close the popup"),
+                    ("Click", "Accept a suggestion from the popup"),
+                ],
+            },
+            Section {
+                heading: "Sources",
+                entries: &[
+                    ("first word", "Built-ins and common command names"),
+                    ("after z", "Previously visited directories"),
+                    (
+                        "after kill",
+                        "Running processes by name or pid; inserts the pid",
+                    ),
+                    (
+                        "after find",
+                        "Flags such as -name and -type, and the types after -type",
+                    ),
+                    (
+                        "after cargo, git, …",
+                        "Subcommands and flags from a completion spec",
+                    ),
+                    ("other words", "Files and directories, relative to the cwd"),
+                ],
+            },
+            Section {
+                heading: "Specs",
+                entries: &[
+                    (
+                        "<config>/completions/CMD",
+                        "Specs for CMD, added to the bundled cargo, git, docker and npm",
+                    ),
+                    ("[CMD SUB]", "Starts the section for a subcommand"),
+                    ("sub  text", "A subcommand and its description"),
+                    ("--flag  text", "A flag; words starting with - are flags"),
+                    (
+                        "--flag <file>  text",
+                        "A flag taking a path; also <dir>, or <value> for free text",
+                    ),
+                    ("<file>", "Alone on a line: what other arguments are"),
+                    ("# ...", "A comment"),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "keybindings",
+        summary: "Keys for editing and running commands",
+        sections: &[
+            Section {
+                heading: "Running",
+                entries: &[
+                    ("Enter", "Run the current line"),
+                    ("Tab", "Complete a command or path (help autocomplete)"),
+                    (
+                        "Up / Down",
+                        "Step through history; with text typed, only entries starting with it",
+                    ),
+                    ("Ctrl+L", "Clear the scrollback"),
+                    ("Ctrl+P", "Run the last command again"),
+                    (
+                        "Paste a URL",
+                        "Offers open, download here, or insert; Enter inserts, Esc drops it",
+                    ),
+                    ("Ctrl+Shift+M", "Start or stop recording a macro"),
+                    ("F9", "Replay the recorded macro (set macro-key)"),
+                    ("?", "On an empty line, show these keys in a window"),
+                    ("Esc", "Close the keybindings window"),
+                    (
+                        "Ctrl+C",
+                        "Stop the running command or tail -f; kill, jobs and : commands never queue",
+                    ),
+                    (
+                        "Ctrl+D",
+                        "Close a running command's input (busy-input stdin), else exit",
+                    ),
+                    (
+                        "Ctrl+Shift+C",
+                        "Copy the whole scrollback, minus folded output",
+                    ),
+                    ("F8 / Shift+F8", "Jump to the next / previous error line"),
+                    ("Ctrl+Shift+E", "Show only errors and stderr, or everything again"),
+                    (
+                        "Ctrl+Up / Ctrl+Down",
+                        "Jump to the previous / next command in the scrollback",
+                    ),
+                    (
+                        "Click a path",
+                        "Open file:line:col from output in the editor",
+                    ),
+                    ("Right-click an error", "Copy it, or search for it online"),
+                ],
+            },
+            Section {
+                heading: "Moving",
+                entries: &[
+                    ("Left / Right", "Move one character"),
+                    ("Right at end of line", "Accept the gray history suggestion"),
+                    ("Ctrl+Left / Ctrl+Right", "Move one word"),
+                    ("Home / Ctrl+A", "Jump to the start of the line"),
+                    ("End / Ctrl+E", "Jump to the end of the line"),
+                ],
+            },
+            Section {
+                heading: "Deleting",
+                entries: &[
+                    ("Backspace / Delete", "Delete before / under the cursor"),
+                    ("Ctrl+Backspace", "Delete the word before the cursor"),
+                    ("Ctrl+U", "Delete to the start of the line"),
+                    ("Ctrl+K", "Delete to the end of the line"),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "settings",
+        summary: "Runtime options changed with :set",
+        sections: &[
+            Section {
+                heading: "Usage",
+                entries: &[
+                    (":set", "List every option and its value"),
+                    (":set key value", "Change an option; saved on exit"),
+                ],
+            },
+            Section {
+                heading: "Options",
+                entries: &[
+                    ("font-size", "Text size in points (6-48)"),
+                    (
+                        "font",
+                        "A .ttf/.otf path or installed font name, e.g. 'JetBrains Mono'; empty for the default",
+                    ),
+                    ("line-spacing", "Pixels between lines (0-20, default 3)"),
+                    ("inner-margin", "Pixels around the text (0-64, default 12)"),
+                    (
+                        "scroll-lines",
+                        "Lines a mouse-wheel notch scrolls (0.5-50, default 3)",
+                    ),
+                    ("theme", "dark, light, or auto to follow the system"),
+                    ("scrollback", "Lines kept before the oldest are dropped"),
+                    ("cursor", "block, underline or bar"),
+                    ("color", "on or off"),
+                    ("wrap", "on wraps long lines, off scrolls sideways"),
+                    (
+                        "tab-width",
+                        "Columns per tab stop in output (1-16, default 8)",
+                    ),
+                    ("trash", "on makes rm move files to the trash"),
+                    (
+                        "max-jobs",
+                        "Background jobs that may run at once; 0 is no limit",
+                    ),
+                    (
+                        "max-jobs-policy",
+                        "queue starts extra jobs as others finish; reject refuses them",
+                    ),
+                    ("notify", "Desktop notification when a background job ends"),
+                    ("notify-after", "Seconds a job must run before it notifies"),
+                    (
+                        "idle-timeout",
+                        "Seconds without input before repaints slow down; 0 is off",
+                    ),
+                    ("idle-dim", "on dims the window while it's idle"),
+                    (
+                        "command-headers",
+                        "on shows when each command ran; click the command to fold its output",
+                    ),
+                    (
+                        "timestamps",
+                        "on prefixes each output line with the time it arrived",
+                    ),
+                    ("copy-timestamps", "on keeps those times in copied output"),
+                    (
+                        "banner",
+                        "off skips the startup message; ~/.config/ai_terminal/banner sets it, $VAR and $(cmd) allowed",
+                    ),
+                    (
+                        "prompt-header",
+                        "off hides the directory and branch bar, leaving the > prompt",
+                    ),
+                    (
+                        "powerline",
+                        "on draws the bar as blocks joined by Powerline arrows; > where the font lacks them (see font)",
+                    ),
+                    (
+                        "git-prompt",
+                        "off leaves the branch out of the bar and never runs git for it",
+                    ),
+                    (
+                        "rprompt",
+                        "Right of the input: {time} {date} {branch} {dirty} {status} {sep}, e.g. '{status} {branch}{dirty} {sep} {time}'",
+                    ),
+                    (
+                        "auto-pair",
+                        "on closes brackets and quotes as you type them",
+                    ),
+                    (
+                        "auto-cd",
+                        "on makes a line that's only a directory cd into it",
+                    ),
+                    (
+                        "cd-correct",
+                        "on makes cd into a mistyped directory enter the one close match instead of suggesting it",
+                    ),
+                    (
+                        "clear-on-cd",
+                        "on clears the scrollback (not history) on changing directory",
+                    ),
+                    ("macro-key", "Key that replays the recorded macro (default F9)"),
+                    ("error-pattern", "Regex used by :next-error and F8"),
+                    (
+                        "watch-fs",
+                        "on lists files a watched command created, deleted or changed",
+                    ),
+                    (
+                        "watch-fs-commands",
+                        "Programs watch-fs applies to, e.g. git,make",
+                    ),
+                    (
+                        "exec",
+                        "direct spawns programs; login runs them via $SHELL -lc",
+                    ),
+                    (
+                        "busy-input",
+                        "Enter while a command runs: queue, ignore, or stdin to type to it",
+                    ),
+                    (
+                        "tty-commands",
+                        "Programs that need a real terminal, e.g. vim,less,top",
+                    ),
+                    (
+                        "tty-fallback",
+                        "pty runs them here; external opens a terminal window; message refuses",
+                    ),
+                    (
+                        "terminal",
+                        "Emulator prefix such as 'xterm -e'; empty detects one",
+                    ),
+                    (
+                        "editor",
+                        "Opens clicked paths; {file} {line} {col}, else +LINE FILE",
+                    ),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "pipelines",
+        summary: "Chaining commands with |",
+        sections: &[Section {
+            heading: "Usage",
+            entries: &[
+                ("a | b", "Feed the output of a into b"),
+                ("'x | y'", "Quote a | to pass it as a literal argument"),
+                ("$(cmd) / `cmd`", "Substitute the output of cmd"),
+                ("\\$(cmd) / '$(cmd)'", "Keep $(cmd) as literal text"),
+                (
+                    "internal tools",
+                    "grep runs in-process when its options are supported",
+                ),
+            ],
+        }],
+    },
+    Topic {
+        name: "grep",
+        summary: "The internal grep and its options",
+        sections: &[
+            Section {
+                heading: "Usage",
+                entries: &[
+                    ("grep PATTERN [FILE...]", "Search files, or piped input"),
+                    ("cmd | grep PATTERN", "Search the output of cmd"),
+                ],
+            },
+            Section {
+                heading: "Options",
+                entries: &[
+                    ("-i", "Ignore case"),
+                    ("-n", "Prefix each match with its line number"),
+                    ("-v", "Show lines that do not match"),
+                    ("-r", "Search directories recursively, skipping symlinks"),
+                    ("-R", "Search recursively, following symlinks"),
+                    ("-E / -F", "Extended regex / fixed string"),
+                ],
+            },
+            Section {
+                heading: "Fallback",
+                entries: &[("other options", "Run the system grep instead")],
+            },
+        ],
+    },
+    Topic {
+        name: "git",
+        summary: "Branch and status in the prompt, and git typo fixes",
+        sections: &[
+            Section {
+                heading: "Prompt",
+                entries: &[
+                    ("bar", "Shows the current branch inside a repository"),
+                    (
+                        "{branch} {dirty}",
+                        "rprompt fields: the branch, and * with uncommitted changes",
+                    ),
+                    ("git-prompt off", "Never run git for the prompt"),
+                    ("--no-git", "The same, for one session"),
+                ],
+            },
+            Section {
+                heading: "Commands",
+                entries: &[
+                    (
+                        "git TYPO",
+                        "When git suggests a command, click the hint to run it",
+                    ),
+                    (
+                        "git <Tab>",
+                        "Completes subcommands and flags (help autocomplete)",
+                    ),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "trash",
+        summary: "Recoverable rm, enabled with :set trash on",
+        sections: &[
+            Section {
+                heading: "Usage",
+                entries: &[
+                    ("rm [-r] [-f] FILE...", "Move files to ~/.local/share/Trash"),
+                    ("rm -f --force-permanent FILE...", "Delete for real"),
+                    ("trash-restore", "List trashed files, oldest first"),
+                    (
+                        "trash-restore NAME|PATH",
+                        "Put the latest matching file back where it was",
+                    ),
+                ],
+            },
+            Section {
+                heading: "Notes",
+                entries: &[
+                    ("trash off", "rm runs the system rm as usual"),
+                    (
+                        "other options",
+                        "Refused while trash is on, never passed through",
+                    ),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "hooks",
+        summary: "Commands run on session events, registered with trap",
+        sections: &[
+            Section {
+                heading: "Usage",
+                entries: &[
+                    ("trap", "List registered hooks"),
+                    ("trap 'COMMAND' EVENT...", "Run COMMAND on each EVENT"),
+                    ("trap - EVENT...", "Remove every hook for EVENT"),
+                    ("trap -l", "List event names"),
+                ],
+            },
+            Section {
+                heading: "Events",
+                entries: &[
+                    ("start", "Once, after the rc file has run"),
+                    ("precmd", "Before each prompt, once the command finishes"),
+                    ("preexec", "Before each command typed at the prompt"),
+                ],
+            },
+            Section {
+                heading: "Notes",
+                entries: &[
+                    (
+                        "rc file",
+                        "~/.config/ai_terminal/rc runs at startup, one command per line",
+                    ),
+                    ("failing hooks", "A hook that errors or exits non-zero is removed"),
+                ],
+            },
+        ],
+    },
+];
+
+/// Renders `help` (topic index) or `help <topic>`.
+pub fn render(topic: Option<&str>) -> Vec<TerminalLine> {
+    let Some(name) = topic else {
+        return render_index();
+    };
+    match TOPICS.iter().find(|t| t.name.eq_ignore_ascii_case(name)) {
+        Some(topic) => render_topic(topic),
+        None => {
+            let names: Vec<&str> = TOPICS.iter().map(|t| t.name).collect();
+            vec![TerminalLine::plain(
+                format!("help: no topic '{name}'. Topics: {}", names.join(", ")),
+                ERROR_COLOR,
+            )]
+        }
+    }
+}
+
+/// A topic's sections, for showing them outside the scrollback.
+pub fn sections(topic: &str) -> &'static [Section] {
+    TOPICS
+        .iter()
+        .find(|t| t.name == topic)
+        .map_or(&[], |t| t.sections)
+}
+
+fn render_index() -> Vec<TerminalLine> {
+    let width = TOPICS
+        .iter()
+        .map(|t| input::display_width(t.name))
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![TerminalLine::plain("Help topics:", HEADING_COLOR)];
+    lines.extend(TOPICS.iter().map(|t| entry_line(t.name, t.summary, width)));
+    lines.push(TerminalLine::plain(
+        "Type 'help <topic>' for details.",
+        OUTPUT_COLOR,
+    ));
+    lines
+}
+
+fn render_topic(topic: &Topic) -> Vec<TerminalLine> {
+    let mut lines = vec![TerminalLine::plain(
+        format!("{} — {}", topic.name, topic.summary),
+        HEADING_COLOR,
+    )];
+    for section in topic.sections {
+        let width = section
+            .entries
+            .iter()
+            .map(|(k, _)| input::display_width(k))
+            .max()
+            .unwrap_or(0);
+        lines.push(TerminalLine::plain("", OUTPUT_COLOR));
+        lines.push(TerminalLine::plain(section.heading, HEADING_COLOR));
+        lines.extend(section.entries.iter().map(|(k, v)| entry_line(k, v, width)));
+    }
+    lines
+}
+
+fn entry_line(key: &str, description: &str, width: usize) -> TerminalLine {
+    TerminalLine::from_segments(vec![
+        Segment::new(format!("  {}  ", input::pad(key, width)), INPUT_COLOR),
+        Segment::new(description, OUTPUT_COLOR),
+    ])
+}
@@ -0,0 +1,168 @@
+//! User highlight rules: text in output matching a regex is drawn in a
+//! color, e.g. `:highlight '\bFAIL(ED)?\b' red`. Rules are kept one per
+//! line as `COLOR REGEX` in `<config>/highlights`. Where rules overlap, the
+//! one added first wins.
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use eframe::egui::text::LayoutJob;
+use eframe::egui::Color32;
+use regex::Regex;
+
+use crate::line;
+use crate::paths::Profile;
+use crate::theme::Theme;
+
+const COLORS: &[(&str, Color32)] = &[
+    ("red", Color32::from_rgb(255, 100, 100)),
+    ("green", Color32::from_rgb(110, 210, 110)),
+    ("yellow", Color32::from_rgb(230, 200, 90)),
+    ("blue", Color32::from_rgb(120, 170, 255)),
+    ("magenta", Color32::from_rgb(200, 120, 220)),
+    ("cyan", Color32::from_rgb(90, 200, 210)),
+    ("orange", Color32::from_rgb(240, 160, 80)),
+    ("gray", Color32::from_rgb(130, 130, 130)),
+    ("white", Color32::from_rgb(240, 240, 240)),
+];
+
+/// A color name from `COLORS`, or `#rrggbb`.
+fn parse_color(spec: &str) -> Option<Color32> {
+    if let Some(hex) = spec.strip_prefix('#').filter(|hex| hex.len() == 6) {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(spec))
+        .map(|&(_, color)| color)
+}
+
+pub struct Rule {
+    pub pattern: String,
+    /// As typed, for listing and saving.
+    pub color_name: String,
+    regex: Regex,
+    color: Color32,
+}
+
+#[derive(Default)]
+pub struct Highlights {
+    pub rules: Vec<Rule>,
+    path: Option<PathBuf>,
+}
+
+impl Highlights {
+    /// Reads the saved rules, skipping any that no longer parse.
+    pub fn load(profile: &Profile) -> Self {
+        let path = profile.config_dir().map(|dir| dir.join("highlights"));
+        let mut highlights = Self {
+            rules: Vec::new(),
+            path,
+        };
+        let text = highlights
+            .path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        for line in text.lines() {
+            if let Some((color, pattern)) = line.trim().split_once(' ') {
+                let _ = highlights.add(pattern, color);
+            }
+        }
+        highlights
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .rules
+            .iter()
+            .map(|rule| format!("{} {}\n", rule.color_name, rule.pattern))
+            .collect();
+        fs::write(path, text)
+    }
+
+    /// Adds a rule after the others, returning a user-facing message if
+    /// the regex or color is invalid.
+    pub fn add(&mut self, pattern: &str, color: &str) -> Result<(), String> {
+        let regex = Regex::new(pattern).map_err(|e| format!("invalid regex: {e}"))?;
+        let names: Vec<&str> = COLORS.iter().map(|(name, _)| *name).collect();
+        let parsed = parse_color(color).ok_or_else(|| {
+            format!(
+                "unknown color '{color}' (#rrggbb or one of: {})",
+                names.join(", ")
+            )
+        })?;
+        self.rules.push(Rule {
+            pattern: pattern.to_string(),
+            color_name: color.to_string(),
+            regex,
+            color: parsed,
+        });
+        Ok(())
+    }
+
+    /// What the rules match in `text`, with their colors, in the order to
+    /// paint them: last rule to first, so the first ends up on top.
+    pub fn find(&self, text: &str) -> Vec<(Range<usize>, Color32)> {
+        self.rules
+            .iter()
+            .rev()
+            .flat_map(|rule| {
+                rule.regex
+                    .find_iter(text)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (m.range(), rule.color))
+            })
+            .collect()
+    }
+}
+
+/// Colors the `found` ranges of a line's text, which starts `shift` bytes
+/// into `job`.
+pub fn apply(job: &mut LayoutJob, found: &[(Range<usize>, Color32)], shift: usize, theme: Theme) {
+    for (range, color) in found {
+        let color = theme.adapt(*color);
+        let range = range.start + shift..range.end + shift;
+        line::restyle(job, range, |format| format.color = color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::FontId;
+
+    use crate::line::{TerminalLine, OUTPUT_COLOR};
+
+    #[test]
+    fn first_rule_wins_where_matches_overlap() {
+        let mut highlights = Highlights::default();
+        highlights.add("FAIL", "red").unwrap();
+        highlights.add("[A-Z]+", "#0000ff").unwrap();
+        assert!(highlights.add("(", "red").is_err());
+        assert!(highlights.add("x", "mauve").is_err());
+
+        let text = "ok FAILED";
+        let line = TerminalLine::plain(text, OUTPUT_COLOR);
+        let mut job = line.layout(&FontId::monospace(12.0), Theme::Dark, true);
+        apply(&mut job, &highlights.find(text), 0, Theme::Dark);
+        let colors: Vec<(&str, Color32)> = job
+            .sections
+            .iter()
+            .map(|s| (&text[s.byte_range.clone()], s.format.color))
+            .collect();
+        let red = Theme::Dark.adapt(COLORS[0].1);
+        let blue = Theme::Dark.adapt(Color32::from_rgb(0, 0, 255));
+        assert_eq!(colors[1..], [("FAIL", red), ("ED", blue)]);
+    }
+}
@@ -0,0 +1,243 @@
+//! Command history, kept in memory and persisted to the data directory.
+//!
+//! The file holds one `unix-seconds<TAB>command` line per entry, with
+//! newlines inside a command escaped as `\n` and backslashes as `\\`.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::paths::Profile;
+
+/// Oldest entries are dropped past this many.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub command: String,
+    /// Seconds since the Unix epoch when the command was run.
+    pub timestamp: u64,
+}
+
+#[derive(Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    /// File the history was loaded from and is saved back to.
+    path: Option<PathBuf>,
+}
+
+fn escape(command: &str) -> String {
+    command.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+impl History {
+    pub fn load(profile: &Profile) -> Self {
+        let path = profile.data_dir().map(|dir| dir.join("history"));
+        let Some(text) = path.as_ref().and_then(|p| fs::read_to_string(p).ok()) else {
+            return Self {
+                entries: Vec::new(),
+                path,
+            };
+        };
+        let entries = text
+            .lines()
+            .filter_map(|line| {
+                let (timestamp, command) = line.split_once('\t')?;
+                Some(HistoryEntry {
+                    command: unescape(command),
+                    timestamp: timestamp.parse().ok()?,
+                })
+            })
+            .collect();
+        Self { entries, path }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .entries
+            .iter()
+            .map(|e| format!("{}\t{}\n", e.timestamp, escape(&e.command)))
+            .collect();
+        fs::write(path, text)
+    }
+
+    /// Records `command`, skipping blanks and immediate repeats.
+    pub fn push(&mut self, command: &str) {
+        if command.trim().is_empty() || self.entries.last().is_some_and(|e| e.command == command) {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.entries.push(HistoryEntry {
+            command: command.to_string(),
+            timestamp,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..excess);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Expands `!!` (the previous command), `!N` (entry N as numbered by
+    /// `history`) and `!-N` (the Nth previous command) outside single
+    /// quotes; `\\!` stays literal. `sudo!!` is read as `sudo !!`. Returns
+    /// `None` when there's nothing to expand.
+    pub fn expand(&self, line: &str) -> Result<Option<String>, String> {
+        let line = match line.strip_prefix("sudo!!") {
+            Some(rest) => format!("sudo !!{rest}"),
+            None => line.to_string(),
+        };
+        let mut out = String::with_capacity(line.len());
+        let mut expanded = false;
+        let mut in_single = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if !in_single => {
+                    out.push(c);
+                    out.extend(chars.next());
+                }
+                '\'' => {
+                    in_single = !in_single;
+                    out.push(c);
+                }
+                '!' if !in_single => {
+                    let mut event = String::new();
+                    if chars.peek() == Some(&'!') {
+                        event.push('!');
+                        chars.next();
+                    } else {
+                        if chars.peek() == Some(&'-') {
+                            event.push('-');
+                            chars.next();
+                        }
+                        while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                            event.push(d);
+                            chars.next();
+                        }
+                    }
+                    let index = match event.as_str() {
+                        "" | "-" => {
+                            out.push('!');
+                            out.push_str(&event);
+                            continue;
+                        }
+                        "!" => self.len().checked_sub(1),
+                        _ => match event.strip_prefix('-') {
+                            Some(back) => back
+                                .parse::<usize>()
+                                .ok()
+                                .and_then(|n| self.len().checked_sub(n)),
+                            None => event.parse::<usize>().ok().and_then(|n| n.checked_sub(1)),
+                        },
+                    };
+                    let entry = index.and_then(|i| self.entries.get(i));
+                    let Some(entry) = entry else {
+                        return Err(format!("!{event}: event not found"));
+                    };
+                    out.push_str(&entry.command);
+                    expanded = true;
+                }
+                _ => out.push(c),
+            }
+        }
+        Ok(expanded.then_some(out))
+    }
+
+    /// The newest entry before `before` that starts with `prefix`, skipping
+    /// copies of `current` so repeated commands are only visited once.
+    pub fn search_back(&self, before: usize, prefix: &str, current: &str) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|e| e.command.starts_with(prefix) && e.command != current)
+    }
+
+    /// The oldest entry after `after` that starts with `prefix`, skipping
+    /// copies of `current`.
+    pub fn search_forward(&self, after: usize, prefix: &str, current: &str) -> Option<usize> {
+        (after + 1..self.entries.len()).find(|&i| {
+            let command = &self.entries[i].command;
+            command.starts_with(prefix) && command != current
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_by_prefix() {
+        let mut history = History::default();
+        for command in ["git status", "ls", "git log", "git status", "make"] {
+            history.push(command);
+        }
+        assert_eq!(history.search_back(5, "git", ""), Some(3));
+        assert_eq!(history.search_back(3, "git", "git status"), Some(2));
+        assert_eq!(history.search_back(2, "git", "git log"), Some(0));
+        assert_eq!(history.search_back(0, "git", ""), None);
+        assert_eq!(history.search_forward(0, "git", "git status"), Some(2));
+        assert_eq!(history.search_forward(3, "git", ""), None);
+    }
+
+    #[test]
+    fn expands_history_events() {
+        let mut history = History::default();
+        for command in ["ls", "apt install vim"] {
+            history.push(command);
+        }
+        let expand = |line| history.expand(line);
+        assert_eq!(
+            expand("sudo!!"),
+            Ok(Some("sudo apt install vim".to_string()))
+        );
+        assert_eq!(expand("!1 -l"), Ok(Some("ls -l".to_string())));
+        assert_eq!(expand("echo !-2"), Ok(Some("echo ls".to_string())));
+        assert_eq!(expand("echo '!!' \\!! hi!"), Ok(None));
+        assert!(expand("!9").is_err());
+    }
+}
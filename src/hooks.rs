@@ -0,0 +1,127 @@
+//! `trap`: commands run on session events. Hooks are registered at the
+//! prompt or from the rc file and last for the session.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Once, after the rc file has run.
+    Start,
+    /// Before each prompt, i.e. once the previous command has finished.
+    Precmd,
+    /// Before each command typed at the prompt.
+    Preexec,
+}
+
+impl Event {
+    pub const ALL: [Event; 3] = [Event::Start, Event::Precmd, Event::Preexec];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Start => "start",
+            Event::Precmd => "precmd",
+            Event::Preexec => "preexec",
+        }
+    }
+}
+
+/// Registered hooks, run in the order they were added.
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<(Event, String)>,
+}
+
+impl Hooks {
+    /// Adds `command` for `event`, unless it's already there.
+    pub fn add(&mut self, event: Event, command: &str) {
+        if !self.hooks.iter().any(|(e, c)| *e == event && c == command) {
+            self.hooks.push((event, command.to_string()));
+        }
+    }
+
+    pub fn remove(&mut self, event: Event, command: &str) {
+        self.hooks.retain(|(e, c)| !(*e == event && c == command));
+    }
+
+    pub fn clear(&mut self, event: Event) {
+        self.hooks.retain(|(e, _)| *e != event);
+    }
+
+    pub fn commands(&self, event: Event) -> Vec<String> {
+        self.hooks
+            .iter()
+            .filter(|(e, _)| *e == event)
+            .map(|(_, command)| command.clone())
+            .collect()
+    }
+
+    pub fn entries(&self) -> &[(Event, String)] {
+        &self.hooks
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Trap {
+    /// `trap`: show every hook.
+    List,
+    /// `trap -l`: show the event names.
+    ListEvents,
+    /// `trap COMMAND EVENT...`
+    Add(String, Vec<Event>),
+    /// `trap - EVENT...`
+    Clear(Vec<Event>),
+}
+
+/// Parses `trap` arguments (without the command name).
+pub fn parse_trap(args: &[String]) -> Result<Trap, String> {
+    let Some((first, rest)) = args.split_first() else {
+        return Ok(Trap::List);
+    };
+    if first == "-l" && rest.is_empty() {
+        return Ok(Trap::ListEvents);
+    }
+    if rest.is_empty() {
+        return Err("usage: trap COMMAND EVENT... | trap - EVENT... | trap -l".to_string());
+    }
+    let events = rest
+        .iter()
+        .map(|name| Event::parse(name).ok_or_else(|| format!("unknown event '{name}'")))
+        .collect::<Result<Vec<_>, _>>()?;
+    if first == "-" {
+        Ok(Trap::Clear(events))
+    } else {
+        Ok(Trap::Add(first.clone(), events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_trap_forms() {
+        assert_eq!(parse_trap(&[]), Ok(Trap::List));
+        assert_eq!(parse_trap(&args(&["-l"])), Ok(Trap::ListEvents));
+        assert_eq!(
+            parse_trap(&args(&["date", "PRECMD", "start"])),
+            Ok(Trap::Add(
+                "date".to_string(),
+                vec![Event::Precmd, Event::Start]
+            ))
+        );
+        assert_eq!(
+            parse_trap(&args(&["-", "preexec"])),
+            Ok(Trap::Clear(vec![Event::Preexec]))
+        );
+        assert!(parse_trap(&args(&["date", "exit"])).is_err());
+        assert!(parse_trap(&args(&["date"])).is_err());
+    }
+}
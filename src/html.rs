@@ -0,0 +1,78 @@
+//! `:export-html`: the scrollback as a standalone HTML page, each styled
+//! segment an inline-styled span.
+
+use eframe::egui::Color32;
+
+use crate::line::TerminalLine;
+use crate::theme::Theme;
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn css(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// A page showing `lines` in `theme`'s colors, or in its plain foreground
+/// with `color` off, as on screen.
+pub fn render(lines: &[TerminalLine], theme: Theme, color: bool) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Terminal session</title>\n</head>\n\
+         <body style=\"margin:0\">\n<pre style=\"background:{};color:{};\
+         padding:12px;margin:0;font-family:monospace\">\n",
+        css(theme.background()),
+        css(theme.foreground()),
+    );
+    for line in lines {
+        for segment in &line.segments {
+            if !color {
+                html.push_str(&escape(&segment.text));
+                continue;
+            }
+            let mut style = format!("color:{}", css(theme.adapt(segment.color)));
+            let background = theme.adapt_background(segment.background);
+            if background != Color32::TRANSPARENT {
+                style.push_str(&format!(";background:{}", css(background)));
+            }
+            html.push_str(&format!(
+                "<span style=\"{style}\">{}</span>",
+                escape(&segment.text)
+            ));
+        }
+        html.push('\n');
+    }
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::{Segment, MATCH_BACKGROUND, OUTPUT_COLOR};
+
+    #[test]
+    fn escapes_text_and_keeps_colors() {
+        let lines = [TerminalLine::from_segments(vec![
+            Segment::new("<a href='x'> & ", OUTPUT_COLOR),
+            Segment::highlighted("hit", OUTPUT_COLOR, MATCH_BACKGROUND),
+        ])];
+        let html = render(&lines, Theme::Dark, true);
+        assert!(html.contains("&lt;a href=&#39;x&#39;&gt; &amp; </span>"));
+        assert!(html.contains(";background:#461e1e\">hit</span>"));
+        let plain = render(&lines, Theme::Dark, false);
+        assert!(plain.contains("\n&lt;a href=&#39;x&#39;&gt; &amp; hit\n"));
+    }
+}
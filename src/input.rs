@@ -0,0 +1,322 @@
+//! Line-editing helpers for the prompt's input buffer.
+//!
+//! `cursor_pos` is always a char index into the buffer, never a byte offset.
+//! Every helper converts to a byte offset only at the moment it indexes the
+//! `String`, so multibyte UTF-8 input can't be split mid code point.
+//!
+//! The cursor also never rests inside a cell: zero-width chars (combining
+//! accents, variation selectors) and zero-width-joined emoji move and
+//! delete together with the char they attach to.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Number of chars in `s`; the upper bound for a cursor position.
+pub fn char_count(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Byte offset of the char at `char_idx`, or `s.len()` past the end.
+pub fn byte_offset(s: &str, char_idx: usize) -> usize {
+    s.char_indices().nth(char_idx).map_or(s.len(), |(i, _)| i)
+}
+
+/// Terminal columns `s` occupies: 2 for CJK and emoji, 0 for combining
+/// marks.
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// `s` followed by enough spaces to fill `width` columns.
+pub fn pad(s: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(s));
+    format!("{s}{}", " ".repeat(fill))
+}
+
+/// True if the char at `i` draws in the same cell as the one before it.
+fn joins_previous(chars: &[char], i: usize) -> bool {
+    i > 0 && (chars[i - 1] == ZERO_WIDTH_JOINER || chars[i].width() == Some(0))
+}
+
+/// Char index of the next cell boundary after `cursor` (ArrowRight).
+pub fn next_cell(buf: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = buf.chars().collect();
+    let mut i = (cursor + 1).min(chars.len());
+    while i < chars.len() && joins_previous(&chars, i) {
+        i += 1;
+    }
+    i
+}
+
+/// Char index of the cell boundary before `cursor` (ArrowLeft).
+pub fn prev_cell(buf: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = buf.chars().take(cursor).collect();
+    let mut i = chars.len().saturating_sub(1);
+    while i > 0 && joins_previous(&chars, i) {
+        i -= 1;
+    }
+    i
+}
+
+/// Splits the buffer into the text before and after the cursor.
+pub fn split_at_cursor(buf: &str, cursor: usize) -> (&str, &str) {
+    buf.split_at(byte_offset(buf, cursor))
+}
+
+/// Inserts `text` at the cursor and advances the cursor past it.
+pub fn insert_str(buf: &mut String, cursor: &mut usize, text: &str) {
+    let at = byte_offset(buf, *cursor);
+    buf.insert_str(at, text);
+    *cursor += char_count(text);
+}
+
+/// Backspace: removes the cell before the cursor. Returns false at the start.
+pub fn delete_before(buf: &mut String, cursor: &mut usize) -> bool {
+    if *cursor == 0 {
+        return false;
+    }
+    let end = *cursor;
+    let start = prev_cell(buf, end);
+    delete_range(buf, cursor, start, end);
+    true
+}
+
+/// Delete: removes the cell under the cursor. Returns false at the end.
+pub fn delete_at(buf: &mut String, cursor: usize) -> bool {
+    if cursor >= char_count(buf) {
+        return false;
+    }
+    let end = next_cell(buf, cursor);
+    buf.replace_range(byte_offset(buf, cursor)..byte_offset(buf, end), "");
+    true
+}
+
+const PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+];
+
+/// The quote the cursor is inside, if any, following shell rules:
+/// backslash escapes outside single quotes, nothing escapes inside them.
+fn open_quote(before: &[char]) -> Option<char> {
+    let mut quote = None;
+    let mut escaped = false;
+    for &c in before {
+        match (quote, c) {
+            (_, _) if escaped => escaped = false,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    quote
+}
+
+/// Types `c` with bracket and quote pairing: an opener also inserts its
+/// closer after the cursor, and typing a closer that's already under the
+/// cursor steps over it. Nothing pairs inside quotes or after a backslash,
+/// and quotes don't pair mid-word, so `don't` types normally.
+pub fn insert_paired(buf: &mut String, cursor: &mut usize, c: char) {
+    let chars: Vec<char> = buf.chars().collect();
+    let at = (*cursor).min(chars.len());
+    let (prev, next) = (at.checked_sub(1).map(|i| chars[i]), chars.get(at).copied());
+    let quote = open_quote(&chars[..at]);
+    let escaped = prev == Some('\\') && quote != Some('\'');
+
+    let is_closer = PAIRS.iter().any(|&(_, close)| close == c);
+    if is_closer && next == Some(c) && !escaped && (quote.is_none() || quote == Some(c)) {
+        *cursor += 1;
+        return;
+    }
+    let closer = PAIRS
+        .iter()
+        .find(|&&(open, _)| open == c)
+        .map(|&(_, close)| close);
+    let free_after = next.is_none_or(|n| n.is_whitespace() || PAIRS.iter().any(|&(_, cl)| cl == n));
+    let free_before =
+        prev.is_none_or(|p| p.is_whitespace() || PAIRS.iter().any(|&(op, _)| op == p));
+    let is_quote = matches!(c, '"' | '\'' | '`');
+    match closer {
+        Some(closer) if quote.is_none() && !escaped && free_after && (!is_quote || free_before) => {
+            insert_str(buf, cursor, &format!("{c}{closer}"));
+            *cursor -= 1;
+        }
+        _ => insert_str(buf, cursor, &c.to_string()),
+    }
+}
+
+/// Backspace between an empty pair such as `(|)` removes both halves.
+/// Returns false, changing nothing, anywhere else.
+pub fn delete_pair(buf: &mut String, cursor: &mut usize) -> bool {
+    let chars: Vec<char> = buf.chars().collect();
+    let at = *cursor;
+    if at == 0 || at >= chars.len() || !PAIRS.contains(&(chars[at - 1], chars[at])) {
+        return false;
+    }
+    delete_range(buf, cursor, at - 1, at + 1);
+    true
+}
+
+/// Char index of the start of the word before the cursor (Ctrl+Left / Ctrl+W).
+pub fn word_start_before(buf: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = buf.chars().take(cursor).collect();
+    let mut i = chars.len();
+    while i > 0 && chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    while i > 0 && !chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    i
+}
+
+/// Char index just past the end of the word after the cursor (Ctrl+Right).
+pub fn word_end_after(buf: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = buf.chars().collect();
+    let mut i = cursor.min(chars.len());
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    while i < chars.len() && !chars[i].is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Removes the chars in `start..end` (char indices) and leaves the cursor at `start`.
+pub fn delete_range(buf: &mut String, cursor: &mut usize, start: usize, end: usize) {
+    let (from, to) = (byte_offset(buf, start), byte_offset(buf, end));
+    buf.replace_range(from..to, "");
+    *cursor = start;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_multibyte_at_arbitrary_positions() {
+        let mut buf = String::new();
+        let mut cursor = 0;
+        insert_str(&mut buf, &mut cursor, "héllo");
+        assert_eq!(cursor, 5);
+
+        cursor = 1;
+        insert_str(&mut buf, &mut cursor, "🦀");
+        assert_eq!(buf, "h🦀éllo");
+        assert_eq!(cursor, 2);
+
+        cursor = 3;
+        insert_str(&mut buf, &mut cursor, "日本");
+        assert_eq!(buf, "h🦀é日本llo");
+        assert_eq!(cursor, 5);
+    }
+
+    #[test]
+    fn backspace_removes_whole_chars() {
+        let mut buf = String::from("aé🦀b");
+        let mut cursor = 3;
+        assert!(delete_before(&mut buf, &mut cursor));
+        assert_eq!(buf, "aéb");
+        assert_eq!(cursor, 2);
+        assert!(delete_before(&mut buf, &mut cursor));
+        assert_eq!(buf, "ab");
+        assert_eq!(cursor, 1);
+
+        cursor = 0;
+        assert!(!delete_before(&mut buf, &mut cursor));
+        assert_eq!(buf, "ab");
+    }
+
+    #[test]
+    fn delete_under_cursor_removes_whole_chars() {
+        let mut buf = String::from("ñ日x");
+        assert!(delete_at(&mut buf, 1));
+        assert_eq!(buf, "ñx");
+        assert!(delete_at(&mut buf, 0));
+        assert_eq!(buf, "x");
+        assert!(!delete_at(&mut buf, 1));
+    }
+
+    #[test]
+    fn split_and_offsets_respect_char_boundaries() {
+        let buf = "añb🦀";
+        assert_eq!(byte_offset(buf, 2), 3);
+        assert_eq!(byte_offset(buf, 4), buf.len());
+        assert_eq!(byte_offset(buf, 10), buf.len());
+        assert_eq!(split_at_cursor(buf, 3), ("añb", "🦀"));
+    }
+
+    #[test]
+    fn cells_keep_combining_marks_and_joined_emoji_together() {
+        // "e" + combining acute, then woman + ZWJ + laptop, then "x".
+        let buf = "e\u{301}\u{1F469}\u{200D}\u{1F4BB}x";
+        assert_eq!(next_cell(buf, 0), 2);
+        assert_eq!(next_cell(buf, 2), 5);
+        assert_eq!(prev_cell(buf, 5), 2);
+        assert_eq!(prev_cell(buf, 2), 0);
+
+        let mut owned = buf.to_string();
+        let mut cursor = 5;
+        assert!(delete_before(&mut owned, &mut cursor));
+        assert_eq!(owned, "e\u{301}x");
+        assert_eq!(cursor, 2);
+        assert!(delete_at(&mut owned, 0));
+        assert_eq!(owned, "x");
+    }
+
+    #[test]
+    fn widths_count_terminal_columns() {
+        assert_eq!(display_width("ls"), 2);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("🦀 ok"), 5);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(pad("toolong", 3), "toolong");
+    }
+
+    #[test]
+    fn brackets_and_quotes_pair_and_skip() {
+        let type_all = |text: &str| {
+            let (mut buf, mut cursor) = (String::new(), 0);
+            for c in text.chars() {
+                insert_paired(&mut buf, &mut cursor, c);
+            }
+            (buf, cursor)
+        };
+        assert_eq!(type_all("echo $("), ("echo $()".to_string(), 7));
+        assert_eq!(type_all("f(x)"), ("f(x)".to_string(), 4));
+        assert_eq!(type_all("echo \"a b\""), ("echo \"a b\"".to_string(), 10));
+        assert_eq!(type_all("echo don't"), ("echo don't".to_string(), 10));
+        assert_eq!(type_all("echo '(x"), ("echo '(x'".to_string(), 8));
+        assert_eq!(type_all("echo \\("), ("echo \\(".to_string(), 7));
+
+        let mut buf = "f()".to_string();
+        let mut cursor = 2;
+        assert!(delete_pair(&mut buf, &mut cursor));
+        assert_eq!((buf.as_str(), cursor), ("f", 1));
+        assert!(!delete_pair(&mut buf, &mut cursor));
+    }
+
+    #[test]
+    fn word_motion_over_multibyte_words() {
+        let buf = "echo héllo wörld";
+        assert_eq!(word_start_before(buf, 16), 11);
+        assert_eq!(word_start_before(buf, 11), 5);
+        assert_eq!(word_end_after(buf, 4), 10);
+
+        let mut owned = buf.to_string();
+        let mut cursor = 16;
+        delete_range(&mut owned, &mut cursor, 11, 16);
+        assert_eq!(owned, "echo héllo ");
+        assert_eq!(cursor, 11);
+    }
+}
@@ -0,0 +1,296 @@
+use std::ops::Range;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use eframe::egui;
+use egui::text::{LayoutJob, LayoutSection, TextFormat};
+use egui::{Color32, FontId};
+
+use crate::input;
+use crate::links::FileLink;
+use crate::theme::Theme;
+use crate::timing::format_duration;
+
+pub const OUTPUT_COLOR: Color32 = Color32::from_rgb(220, 220, 220);
+pub const INPUT_COLOR: Color32 = Color32::from_rgb(100, 200, 100);
+pub const HEADING_COLOR: Color32 = Color32::from_rgb(120, 170, 255);
+pub const ERROR_COLOR: Color32 = Color32::from_rgb(255, 100, 100);
+pub const MATCH_COLOR: Color32 = Color32::from_rgb(255, 85, 85);
+pub const MATCH_BACKGROUND: Color32 = Color32::from_rgb(70, 30, 30);
+pub const PATH_COLOR: Color32 = Color32::from_rgb(200, 120, 220);
+pub const INSERTED_COLOR: Color32 = Color32::from_rgb(110, 210, 110);
+pub const DELETED_COLOR: Color32 = Color32::from_rgb(240, 100, 100);
+pub const LINE_NUMBER_COLOR: Color32 = Color32::from_rgb(100, 200, 100);
+pub const TIMESTAMP_COLOR: Color32 = Color32::from_rgb(130, 130, 130);
+
+/// A run of text within a line that shares one style.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub text: String,
+    pub color: Color32,
+    pub background: Color32,
+}
+
+impl Segment {
+    pub fn new(text: impl Into<String>, color: Color32) -> Self {
+        Self {
+            text: text.into(),
+            color,
+            background: Color32::TRANSPARENT,
+        }
+    }
+
+    pub fn highlighted(text: impl Into<String>, color: Color32, background: Color32) -> Self {
+        Self {
+            background,
+            ..Self::new(text, color)
+        }
+    }
+}
+
+/// Applies `style` to `range` of `job`, splitting the sections it falls
+/// across.
+pub fn restyle(job: &mut LayoutJob, range: Range<usize>, style: impl Fn(&mut TextFormat)) {
+    let mut sections = Vec::with_capacity(job.sections.len() + 2);
+    for section in job.sections.drain(..) {
+        let whole = section.byte_range.clone();
+        let start = range.start.clamp(whole.start, whole.end);
+        let end = range.end.clamp(whole.start, whole.end);
+        if start == end {
+            sections.push(section);
+            continue;
+        }
+        let pieces = [
+            (whole.start..start, false),
+            (start..end, true),
+            (end..whole.end, false),
+        ];
+        for (piece, styled) in pieces {
+            if piece.is_empty() {
+                continue;
+            }
+            let mut split = section.clone();
+            if piece.start != whole.start {
+                split.leading_space = 0.0;
+            }
+            if styled {
+                style(&mut split.format);
+            }
+            split.byte_range = piece;
+            sections.push(split);
+        }
+    }
+    job.sections = sections;
+}
+
+/// When the command on an input line ran.
+#[derive(Clone, Copy, Debug)]
+pub struct CommandTiming {
+    pub started: DateTime<Local>,
+    /// Set once the command, including any streamed output, has finished.
+    pub duration: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TerminalLine {
+    pub segments: Vec<Segment>,
+    /// True for the echoed prompt and command, false for output.
+    pub is_input: bool,
+    /// Clickable file paths, found when the line is added to the scrollback.
+    pub links: Vec<FileLink>,
+    /// Set on input lines typed this session; drawn as the header of the
+    /// command's output.
+    pub timing: Option<CommandTiming>,
+    /// On an input line, hides the output up to the next input line.
+    pub collapsed: bool,
+    /// A command line run when the line is clicked, as for a suggested
+    /// correction.
+    pub action: Option<String>,
+    /// The text as printed, when tabs were expanded for display; copying
+    /// uses it so the tabs survive.
+    pub original: Option<String>,
+    /// When the line was added to the scrollback, for `timestamps`.
+    pub added: Option<DateTime<Local>>,
+    /// What the `:highlight` rules match, in the order to paint it; found
+    /// when the line is added or the rules change.
+    pub highlights: Vec<(Range<usize>, Color32)>,
+}
+
+impl TerminalLine {
+    pub fn plain(text: impl Into<String>, color: Color32) -> Self {
+        Self::from_segments(vec![Segment::new(text, color)])
+    }
+
+    pub fn input(text: impl Into<String>) -> Self {
+        Self {
+            is_input: true,
+            ..Self::plain(text, INPUT_COLOR)
+        }
+    }
+
+    pub fn from_segments(segments: Vec<Segment>) -> Self {
+        Self {
+            segments,
+            is_input: false,
+            links: Vec::new(),
+            timing: None,
+            collapsed: false,
+            action: None,
+            original: None,
+            added: None,
+            highlights: Vec::new(),
+        }
+    }
+
+    /// Output printed in the error color, such as a command's stderr.
+    pub fn is_error(&self) -> bool {
+        !self.is_input
+            && self
+                .segments
+                .first()
+                .is_some_and(|s| s.color == ERROR_COLOR)
+    }
+
+    pub fn link_at(&self, byte: usize) -> Option<&FileLink> {
+        self.links.iter().find(|link| link.range.contains(&byte))
+    }
+
+    /// The line's text with all styling dropped.
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// The text for the clipboard: as printed, tabs included.
+    pub fn copy_text(&self) -> String {
+        self.original.clone().unwrap_or_else(|| self.text())
+    }
+
+    /// Replaces each tab with spaces up to the next multiple of `width`
+    /// columns, counting across segments, so tab-separated columns line up.
+    pub fn expand_tabs(&mut self, width: usize) {
+        if !self.segments.iter().any(|s| s.text.contains('\t')) {
+            return;
+        }
+        self.original = Some(self.text());
+        let mut column = 0;
+        for segment in &mut self.segments {
+            let mut expanded = String::with_capacity(segment.text.len());
+            for c in segment.text.chars() {
+                if c == '\t' {
+                    let fill = width - column % width;
+                    expanded.extend(std::iter::repeat_n(' ', fill));
+                    column += fill;
+                } else {
+                    expanded.push(c);
+                    column += input::display_width(c.encode_utf8(&mut [0; 4]));
+                }
+            }
+            segment.text = expanded;
+        }
+    }
+
+    /// Lays the line out for `theme`. With `color` off every segment uses
+    /// the theme's foreground and highlights are dropped.
+    pub fn layout(&self, font: &FontId, theme: Theme, color: bool) -> LayoutJob {
+        let mut job = LayoutJob::default();
+        for segment in &self.segments {
+            let (foreground, background) = if color {
+                (
+                    theme.adapt(segment.color),
+                    theme.adapt_background(segment.background),
+                )
+            } else {
+                (theme.foreground(), Color32::TRANSPARENT)
+            };
+            job.append(
+                &segment.text,
+                0.0,
+                TextFormat {
+                    background,
+                    ..TextFormat::simple(font.clone(), foreground)
+                },
+            );
+        }
+        job
+    }
+
+    /// `HH:MM:SS` when the line was added, if it has been.
+    pub fn timestamp(&self) -> Option<String> {
+        self.added.map(|added| added.format("%H:%M:%S").to_string())
+    }
+
+    /// Puts the dim time the line was added in front of `job`. Returns how
+    /// many bytes that shifted the line's own text by.
+    pub fn prepend_timestamp(
+        &self,
+        job: &mut LayoutJob,
+        font: &FontId,
+        theme: Theme,
+        color: bool,
+    ) -> usize {
+        let Some(timestamp) = self.timestamp() else {
+            return 0;
+        };
+        let prefix = format!("{timestamp} ");
+        let shift = prefix.len();
+        let foreground = if color {
+            theme.adapt(TIMESTAMP_COLOR)
+        } else {
+            theme.foreground()
+        };
+        job.text.insert_str(0, &prefix);
+        for section in &mut job.sections {
+            section.byte_range = section.byte_range.start + shift..section.byte_range.end + shift;
+        }
+        job.sections.insert(
+            0,
+            LayoutSection {
+                leading_space: 0.0,
+                byte_range: 0..shift,
+                format: TextFormat::simple(font.clone(), foreground),
+            },
+        );
+        shift
+    }
+
+    /// Lays out an input line as the header of its output: the command,
+    /// then a fold marker with when it ran and how long it took.
+    pub fn header_layout(&self, font: &FontId, theme: Theme, color: bool) -> LayoutJob {
+        let mut job = self.layout(font, theme, color);
+        let Some(timing) = self.timing else {
+            return job;
+        };
+        let marker = if self.collapsed { '▸' } else { '▾' };
+        let mut header = format!("   {marker} {}", timing.started.format("%H:%M:%S"));
+        if let Some(duration) = timing.duration {
+            header.push_str(&format!(" · {}", format_duration(duration)));
+        }
+        let foreground = if color {
+            theme.adapt(TIMESTAMP_COLOR)
+        } else {
+            theme.foreground()
+        };
+        job.append(&header, 0.0, TextFormat::simple(font.clone(), foreground));
+        job
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_tabs_to_stops_across_segments() {
+        let mut line = TerminalLine::from_segments(vec![
+            Segment::new("ab\t", OUTPUT_COLOR),
+            Segment::new("界\tc\t", ERROR_COLOR),
+        ]);
+        line.expand_tabs(4);
+        assert_eq!(line.text(), "ab  界  c   ");
+        assert_eq!(line.copy_text(), "ab\t界\tc\t");
+
+        let mut plain = TerminalLine::plain("no tabs", OUTPUT_COLOR);
+        plain.expand_tabs(8);
+        assert_eq!(plain.original, None);
+    }
+}
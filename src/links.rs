@@ -0,0 +1,267 @@
+//! File paths in output, such as `src/main.rs:10:5` from a compiler, that
+//! open in an editor when clicked.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+use eframe::egui::text::LayoutJob;
+use eframe::egui::Stroke;
+use regex::Regex;
+
+use crate::{line, parse, paths};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileLink {
+    /// Byte range of the link within the line's text.
+    pub range: Range<usize>,
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// How many tokens of one line are checked against the file system. Lines
+/// are scanned as they arrive, so a flood of dotted words mustn't turn
+/// into a flood of `stat` calls.
+const MAX_CHECKS: usize = 8;
+
+fn pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"[\w~./+@-]*[\w/+@-](?::(\d+))?(?::(\d+))?").expect("valid link regex")
+    })
+}
+
+/// Whether `token` is worth looking for on disk: it has a directory in it,
+/// ends in an extension such as `.rs`, or came with a `:line` position.
+/// Version numbers, sentences' last words and the like don't.
+fn looks_like_path(token: &str, has_position: bool) -> bool {
+    if token.chars().all(|c| c == '.' || c == '/') {
+        return false;
+    }
+    if token.contains('/') || has_position {
+        return true;
+    }
+    token.rsplit_once('.').is_some_and(|(stem, extension)| {
+        !stem.is_empty()
+            && (1..=10).contains(&extension.len())
+            && extension.chars().all(|c| c.is_ascii_alphanumeric())
+            && extension.chars().any(|c| c.is_ascii_alphabetic())
+    })
+}
+
+/// Finds tokens in `text` that name an existing file, resolved against
+/// `cwd`, optionally followed by `:line` or `:line:col`.
+pub fn find(text: &str, cwd: &Path) -> Vec<FileLink> {
+    let mut links = Vec::new();
+    let mut checks = 0;
+    for caps in pattern().captures_iter(text) {
+        let whole = caps.get(0).expect("group 0 always matches");
+        let path_end = caps.get(1).map_or(whole.end(), |m| m.start() - 1);
+        let token = &text[whole.start()..path_end];
+        if !looks_like_path(token, caps.get(1).is_some()) {
+            continue;
+        }
+        if checks == MAX_CHECKS {
+            break;
+        }
+        checks += 1;
+        let path = paths::resolve(cwd, token);
+        if !path.is_file() {
+            continue;
+        }
+        let number = |group: usize| caps.get(group).and_then(|m| m.as_str().parse().ok());
+        links.push(FileLink {
+            range: whole.range(),
+            path,
+            line: number(1),
+            column: number(2),
+        });
+    }
+    links
+}
+
+/// Underlines `range` of `job`, splitting the sections it falls across.
+pub fn underline(job: &mut LayoutJob, range: Range<usize>) {
+    line::restyle(job, range, |format| {
+        format.underline = Stroke::new(1.0, format.color);
+    });
+}
+
+/// The editor command to use: the `editor` option, else `$VISUAL`, else
+/// `$EDITOR`.
+fn editor_command(configured: &str) -> Option<String> {
+    Some(configured.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .or_else(|| std::env::var("VISUAL").ok().filter(|c| !c.is_empty()))
+        .or_else(|| std::env::var("EDITOR").ok().filter(|c| !c.is_empty()))
+}
+
+/// Builds the editor's argv. `{file}`, `{line}` and `{col}` are filled in
+/// where they appear; otherwise `+LINE FILE` is appended, which vi, emacs,
+/// nano and most GUI editors' command-line wrappers accept.
+fn editor_args(command: &str, link: &FileLink) -> Vec<String> {
+    let file = link.path.display().to_string();
+    let line = link.line.unwrap_or(1).to_string();
+    let col = link.column.unwrap_or(1).to_string();
+    let mut args = parse::split_args(command);
+    if command.contains("{file}") {
+        for arg in &mut args {
+            *arg = arg
+                .replace("{file}", &file)
+                .replace("{line}", &line)
+                .replace("{col}", &col);
+        }
+    } else {
+        if link.line.is_some() {
+            args.push(format!("+{line}"));
+        }
+        args.push(file);
+    }
+    args
+}
+
+/// Opens `link` in the configured editor without blocking the UI.
+pub fn open(link: &FileLink, configured_editor: &str) -> Result<(), String> {
+    let command = editor_command(configured_editor)
+        .ok_or("no editor configured; set one with :set editor CMD or $EDITOR")?;
+    let args = editor_args(&command, link);
+    let Some((program, rest)) = args.split_first() else {
+        return Err(format!("invalid editor command '{command}'"));
+    };
+    spawn_detached(program, rest)
+}
+
+/// `text`, trimmed, if it's a single web address and nothing more.
+pub fn as_url(text: &str) -> Option<&str> {
+    let url = text.trim();
+    let rest = ["http://", "https://", "ftp://"]
+        .iter()
+        .find_map(|scheme| url.strip_prefix(scheme))?;
+    (!rest.is_empty() && !url.contains(char::is_whitespace)).then_some(url)
+}
+
+/// The file a download of `url` is saved as: the last part of its path,
+/// or `index.html` when the path doesn't name a file.
+pub fn download_name(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let path = url.split_once("://").map_or(url, |(_, rest)| rest);
+    match path.split_once('/') {
+        Some((_, path)) => match path.rsplit('/').next() {
+            Some(name) if !name.is_empty() && name != "." && name != ".." => name.to_string(),
+            _ => "index.html".to_string(),
+        },
+        None => "index.html".to_string(),
+    }
+}
+
+/// A web search for `query`, percent-encoded.
+pub fn search_url(query: &str) -> String {
+    let mut url = String::from("https://duckduckgo.com/?q=");
+    for byte in query.trim().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char);
+            }
+            b' ' => url.push('+'),
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    url
+}
+
+/// Opens a file or URL with the desktop's default application.
+pub fn open_default(target: &str) -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        spawn_detached("open", &[target.to_string()])
+    } else if cfg!(windows) {
+        // `start` is a cmd builtin; its first quoted argument is a title.
+        let args = ["/C", "start", "", target].map(String::from);
+        spawn_detached("cmd", &args)
+    } else {
+        spawn_detached("xdg-open", &[target.to_string()])
+    }
+}
+
+fn spawn_detached(program: &str, args: &[String]) -> Result<(), String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to start '{program}': {e}"))?;
+    // Reap it when it exits so it doesn't linger as a zombie.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_existing_paths_with_positions() {
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let text = "  --> src/main.rs:3:7 and missing.rs:1, then Cargo.toml.";
+        let links = find(text, cwd);
+        assert_eq!(links.len(), 2);
+        assert_eq!(&text[links[0].range.clone()], "src/main.rs:3:7");
+        assert_eq!((links[0].line, links[0].column), (Some(3), Some(7)));
+        assert_eq!(&text[links[1].range.clone()], "Cargo.toml");
+        assert_eq!(links[1].line, None);
+
+        let args = editor_args("code --goto {file}:{line}:{col}", &links[0]);
+        assert_eq!(
+            args[2],
+            format!("{}:3:7", cwd.join("src/main.rs").display())
+        );
+        assert_eq!(
+            editor_args("vi", &links[1])[1..],
+            [cwd.join("Cargo.toml").display().to_string()]
+        );
+    }
+
+    #[test]
+    fn only_path_like_tokens_are_checked() {
+        assert!(looks_like_path("src/main.rs", false));
+        assert!(looks_like_path("Cargo.toml", false));
+        assert!(looks_like_path("Makefile", true));
+        assert!(!looks_like_path("Makefile", false));
+        assert!(!looks_like_path("1.2.3", false));
+        assert!(!looks_like_path("v0.27", false));
+        assert!(!looks_like_path("../..", false));
+        assert!(!looks_like_path(".bashrc", false));
+
+        // Past the cap, later tokens are left alone.
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let text = format!("{}Cargo.toml", "x.rs ".repeat(MAX_CHECKS));
+        assert!(find(&text, cwd).is_empty());
+    }
+
+    #[test]
+    fn pasted_urls_and_their_download_names() {
+        assert_eq!(
+            as_url(" https://x.org/a.tar.gz\n"),
+            Some("https://x.org/a.tar.gz")
+        );
+        assert_eq!(as_url("see https://x.org"), None);
+        assert_eq!(as_url("https://"), None);
+        assert_eq!(
+            download_name("https://x.org/dl/a.tar.gz?v=2#top"),
+            "a.tar.gz"
+        );
+        assert_eq!(download_name("https://x.org/dl/"), "index.html");
+        assert_eq!(download_name("https://x.org"), "index.html");
+    }
+
+    #[test]
+    fn search_url_encodes_the_query() {
+        assert_eq!(
+            search_url(" error[E0502]: can't borrow "),
+            "https://duckduckgo.com/?q=error%5BE0502%5D%3A+can%27t+borrow"
+        );
+    }
+}
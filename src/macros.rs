@@ -0,0 +1,151 @@
+//! Keyboard macros: keys and typed text recorded at the prompt, replayed
+//! as if typed again. Named ones are kept in `<config>/macros`, one per
+//! line as the name and then its steps, tab-separated.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use eframe::egui::{Key, Modifiers};
+
+use crate::paths::Profile;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Key(Key, Modifiers),
+    Text(String),
+}
+
+/// `Ctrl+Shift+Left`, `text:ls -la`.
+pub fn encode(step: &Step) -> String {
+    match step {
+        Step::Key(key, modifiers) => {
+            let mut out = String::new();
+            for (held, name) in [
+                (modifiers.ctrl, "Ctrl+"),
+                (modifiers.alt, "Alt+"),
+                (modifiers.shift, "Shift+"),
+                (modifiers.mac_cmd, "Cmd+"),
+            ] {
+                if held {
+                    out.push_str(name);
+                }
+            }
+            out.push_str(key.name());
+            out
+        }
+        Step::Text(text) => {
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n");
+            format!("text:{escaped}")
+        }
+    }
+}
+
+fn decode(step: &str) -> Option<Step> {
+    if let Some(text) = step.strip_prefix("text:") {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next()? {
+                't' => out.push('\t'),
+                'n' => out.push('\n'),
+                other => out.push(other),
+            }
+        }
+        return Some(Step::Text(out));
+    }
+    let mut modifiers = Modifiers::default();
+    let mut rest = step;
+    loop {
+        if let Some(after) = rest.strip_prefix("Ctrl+") {
+            modifiers.ctrl = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("Alt+") {
+            modifiers.alt = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("Shift+") {
+            modifiers.shift = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("Cmd+") {
+            modifiers.mac_cmd = true;
+            rest = after;
+        } else {
+            break;
+        }
+    }
+    Some(Step::Key(Key::from_name(rest)?, modifiers))
+}
+
+#[derive(Default)]
+pub struct Macros {
+    pub named: BTreeMap<String, Vec<Step>>,
+    path: Option<PathBuf>,
+}
+
+impl Macros {
+    /// Reads the saved macros, skipping any line that doesn't parse.
+    pub fn load(profile: &Profile) -> Self {
+        let path = profile.config_dir().map(|dir| dir.join("macros"));
+        let text = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        let named = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let name = fields.next().filter(|name| !name.is_empty())?;
+                let steps = fields.map(decode).collect::<Option<Vec<_>>>()?;
+                Some((name.to_string(), steps))
+            })
+            .collect();
+        Self { named, path }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .named
+            .iter()
+            .map(|(name, steps)| {
+                let steps: Vec<String> = steps.iter().map(encode).collect();
+                format!("{name}\t{}\n", steps.join("\t"))
+            })
+            .collect();
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_round_trip() {
+        let steps = [
+            Step::Text("git commit -m \"a\\b\"\t".to_string()),
+            Step::Key(Key::ArrowLeft, Modifiers::CTRL | Modifiers::SHIFT),
+            Step::Key(Key::Plus, Modifiers::CTRL),
+            Step::Key(Key::Enter, Modifiers::NONE),
+        ];
+        for step in steps {
+            assert_eq!(decode(&encode(&step)), Some(step));
+        }
+        assert_eq!(encode(&Step::Key(Key::A, Modifiers::CTRL)), "Ctrl+A");
+        assert_eq!(decode("Ctrl+Nope"), None);
+    }
+}
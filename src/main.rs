@@ -1,24 +1,81 @@
+mod alias;
+mod app;
+mod banner;
+mod builtins;
+mod cli;
+mod command_form;
+mod completion;
+mod completion_spec;
+mod config;
+mod find;
+mod fonts;
+mod frecency;
+mod git;
+mod help;
+mod highlight;
+mod history;
+mod hooks;
+mod html;
+mod input;
+mod line;
+mod links;
+mod macros;
+mod parse;
+mod paths;
+mod procs;
+#[cfg(unix)]
+mod pty;
+mod rprompt;
+mod script;
+mod session_log;
+mod shell;
+mod snapshot;
+mod stream;
+mod suggest;
+mod theme;
+mod timing;
+mod tty;
+mod vt;
+mod window;
+
+use app::TerminalApp;
+use cli::Args;
 use eframe::egui;
+use paths::Profile;
 
 fn main() -> Result<(), eframe::Error> {
-    let options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "Terminal App",
-        options,
-        Box::new(|_cc| Box::new(TerminalApp::default())),
-    )
-}
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("ai_terminal: {message}\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-#[derive(Default)]
-struct TerminalApp {
-    input: String,
-}
+    #[cfg(unix)]
+    builtins::chmod::capture_umask();
 
-impl eframe::App for TerminalApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Terminal");
-            ui.text_edit_singleline(&mut self.input);
-        });
+    let geometry = window::Geometry::load(&args.profile);
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(geometry.size)
+        .with_maximized(geometry.maximized);
+    if let Some(position) = geometry.position {
+        viewport = viewport.with_position(position);
     }
+    let options = eframe::NativeOptions {
+        viewport,
+        // The app picks its own visuals from --theme, the config, or the
+        // system preference; don't let eframe override them.
+        follow_system_theme: false,
+        ..Default::default()
+    };
+    let title = match args.profile.name() {
+        Profile::DEFAULT => "Terminal App".to_string(),
+        name => format!("Terminal App — {name}"),
+    };
+    eframe::run_native(
+        &title,
+        options,
+        Box::new(|cc| Box::new(TerminalApp::new(cc, args))),
+    )
 }
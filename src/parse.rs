@@ -0,0 +1,447 @@
+//! Minimal shell-style parsing of a command line.
+
+use std::path::Path;
+
+/// Splits a command line on unquoted `|` into trimmed pipeline stages.
+/// `||` is left alone so it can't be mistaken for an empty stage.
+pub fn split_pipeline(line: &str) -> Vec<String> {
+    let mut stages = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '\'' | '"' if quote.is_none() => {
+                quote = Some(c);
+                current.push(c);
+            }
+            c if quote == Some(c) => {
+                quote = None;
+                current.push(c);
+            }
+            '|' if quote.is_none() => {
+                if chars.peek() == Some(&'|') {
+                    chars.next();
+                    current.push_str("||");
+                } else {
+                    stages.push(current.trim().to_string());
+                    current.clear();
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    stages.push(current.trim().to_string());
+    stages
+}
+
+/// If `line` ends with an unquoted, unescaped `&` (but not `&&`), returns
+/// the command before it, to be run in the background.
+pub fn strip_background(line: &str) -> Option<&str> {
+    let rest = line.trim_end().strip_suffix('&')?;
+    if rest.ends_with('&') {
+        return None;
+    }
+    let mut quote: Option<char> = None;
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // A backslash with nothing after it escapes the `&` itself.
+            '\\' if quote != Some('\'') => {
+                chars.next()?;
+            }
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            c if quote == Some(c) => quote = None,
+            _ => {}
+        }
+    }
+    quote.is_none().then(|| rest.trim_end())
+}
+
+/// True if `program`, by itself or as the last part of a path, is in the
+/// comma- or space-separated `list`.
+pub fn in_list(program: &str, list: &str) -> bool {
+    let name = Path::new(program)
+        .file_name()
+        .map_or(program.into(), |n| n.to_string_lossy());
+    list.split([',', ' '])
+        .map(str::trim)
+        .any(|entry| !entry.is_empty() && entry == name)
+}
+
+/// Splits one command into words, honoring single quotes, double quotes and
+/// backslash escapes the way a POSIX shell would for simple cases.
+pub fn split_args(s: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => current.push(c),
+            (Some('"'), '"') => quote = None,
+            (Some('"'), '\\') => match chars.next() {
+                Some(next @ ('"' | '\\' | '$' | '`')) => current.push(next),
+                Some(next) => {
+                    current.push('\\');
+                    current.push(next);
+                }
+                None => current.push('\\'),
+            },
+            (Some(_), _) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, _) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    args
+}
+
+/// Replaces `$NAME` and `${NAME}` with `lookup(NAME)`, or nothing if it's
+/// unset. As with substitutions, single-quoted and backslash-escaped `$`
+/// are left alone and values are escaped to read back as literal text,
+/// word-split only when unquoted. A `$` not followed by a name stays put.
+pub fn expand_variables(line: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    replace_variables(line, &|name, in_double| {
+        let mut out = String::new();
+        push_output(&mut out, &lookup(name).unwrap_or_default(), in_double);
+        Some(out)
+    })
+}
+
+/// The scanner behind `expand_variables`: each reference is replaced by
+/// `replace(NAME, in_double_quotes)`, inserted as is, or left as typed when
+/// that's `None`.
+pub fn replace_variables(line: &str, replace: &impl Fn(&str, bool) -> Option<String>) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_double = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '\'' if !in_double => {
+                out.push(c);
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                in_double = !in_double;
+                out.push(c);
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                let replaced = if closed && is_name(&name) {
+                    replace(&name, in_double)
+                } else {
+                    None
+                };
+                if let Some(replaced) = replaced {
+                    out.push_str(&replaced);
+                } else {
+                    out.push_str("${");
+                    out.push_str(&name);
+                    if closed {
+                        out.push('}');
+                    }
+                }
+            }
+            '$' if chars
+                .peek()
+                .is_some_and(|&c| c == '_' || c.is_ascii_alphabetic()) =>
+            {
+                let mut name = String::new();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|&&c| c == '_' || c.is_ascii_alphanumeric())
+                {
+                    name.push(c);
+                    chars.next();
+                }
+                match replace(&name, in_double) {
+                    Some(replaced) => out.push_str(&replaced),
+                    None => {
+                        out.push('$');
+                        out.push_str(&name);
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic())
+        && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// Lines that act on the terminal or its jobs rather than starting work of
+/// their own: `kill`, `jobs` and the `:` commands. These run at once even
+/// while a foreground command holds the prompt, so one can still stop it.
+pub fn runs_while_busy(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with(':') {
+        return true;
+    }
+    let program = split_words(line).first().copied().unwrap_or("");
+    matches!(program, "kill" | "jobs") && split_pipeline(line).len() == 1
+}
+
+/// Splits the leading `NAME=VALUE` words off `line`, unquoting the
+/// values, and returns them with the rest of the line.
+pub fn take_assignments(line: &str) -> (Vec<(String, String)>, &str) {
+    let mut assignments = Vec::new();
+    let mut rest = line.trim_start();
+    loop {
+        let word = &rest[..word_end(rest)];
+        let Some((name, value)) = word.split_once('=').filter(|(name, _)| is_name(name)) else {
+            break;
+        };
+        assignments.push((name.to_string(), split_args(value).concat()));
+        rest = rest[word.len()..].trim_start();
+    }
+    (assignments, rest)
+}
+
+/// The words of `line` as typed, quotes and escapes kept.
+pub fn split_words(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let end = word_end(rest);
+        words.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    words
+}
+
+/// Byte offset of the first unquoted, unescaped whitespace in `line`.
+fn word_end(line: &str) -> usize {
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                chars.next();
+            }
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            c if quote == Some(c) => quote = None,
+            c if quote.is_none() && c.is_whitespace() => return i,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+/// Replaces each `$(...)` and `` `...` `` span with the output of `run` for
+/// the inner command, innermost first. Substitutions inside single quotes or
+/// escaped with a backslash are left alone; the output is escaped so it
+/// reads back as literal text, word-split only when unquoted.
+pub fn substitute_commands(
+    line: &str,
+    run: &mut impl FnMut(&str) -> String,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(line.len());
+    let mut in_double = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '\'' if !in_double => {
+                out.push(c);
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                in_double = !in_double;
+                out.push(c);
+            }
+            '$' if chars.peek() == Some(&'(') => {
+                chars.next();
+                let inner = take_parenthesized(&mut chars)?;
+                let inner = substitute_commands(&inner, run)?;
+                push_output(&mut out, &run(&inner), in_double);
+            }
+            '`' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('`') => break,
+                        Some('\\') => inner.extend(chars.next()),
+                        Some(c) => inner.push(c),
+                        None => return Err("unterminated `".to_string()),
+                    }
+                }
+                let inner = substitute_commands(&inner, run)?;
+                push_output(&mut out, &run(&inner), in_double);
+            }
+            _ => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Consumes up to the `)` closing an already-consumed `$(`, returning the
+/// text in between. Nested parentheses and quoted `)` don't end the span.
+pub fn take_parenthesized(chars: &mut impl Iterator<Item = char>) -> Result<String, String> {
+    let mut inner = String::new();
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                inner.push(c);
+                if let Some(next) = chars.next() {
+                    inner.push(next);
+                }
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Ok(inner),
+            (None, ')') => depth -= 1,
+            (None, _) => {}
+        }
+        inner.push(c);
+    }
+    Err("unterminated $(".to_string())
+}
+
+/// Appends command output with trailing newlines removed, escaped for the
+/// quoting context it lands in.
+fn push_output(out: &mut String, output: &str, in_double: bool) {
+    for c in output.trim_end_matches(['\n', '\r']).chars() {
+        let special = if in_double {
+            matches!(c, '"' | '\\' | '$' | '`')
+        } else {
+            matches!(c, '\'' | '"' | '\\' | '|' | '$' | '`')
+        };
+        if special {
+            out.push('\\');
+        }
+        out.push(if !in_double && c == '\n' { ' ' } else { c });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(command: &str) -> String {
+        format!("{}\n", command.strip_prefix("echo ").unwrap_or(command))
+    }
+
+    #[test]
+    fn substitutes_nested_and_quoted_spans() {
+        let sub = |line| substitute_commands(line, &mut echo);
+        assert_eq!(sub("echo \"a $(echo b)\"").unwrap(), "echo \"a b\"");
+        assert_eq!(sub("x $(echo $(echo y))").unwrap(), "x y");
+        assert_eq!(sub("x `echo y`").unwrap(), "x y");
+        assert_eq!(sub("'$(echo y)' \\$(z)").unwrap(), "'$(echo y)' \\$(z)");
+        assert_eq!(sub("x $(echo a|b)").unwrap(), "x a\\|b");
+        assert!(sub("x $(echo y").is_err());
+    }
+
+    #[test]
+    fn expands_variables_outside_single_quotes() {
+        let lookup = |name: &str| (name == "HOME").then(|| "/home/a b".to_string());
+        let expand = |line| expand_variables(line, &lookup);
+        assert_eq!(expand("cd $HOME/x"), "cd /home/a b/x");
+        assert_eq!(expand("ls \"${HOME}\""), "ls \"/home/a b\"");
+        assert_eq!(
+            expand("echo '$HOME' \\$HOME $UNSET."),
+            "echo '$HOME' \\$HOME ."
+        );
+        assert_eq!(expand("grep a$ $(pwd) ${1}"), "grep a$ $(pwd) ${1}");
+    }
+
+    #[test]
+    fn leading_assignments() {
+        let (assignments, rest) = take_assignments("RUST_LOG=debug A='x y' cargo run B=1");
+        let pairs = [("RUST_LOG", "debug"), ("A", "x y")].map(|(n, v)| (n.into(), v.into()));
+        assert_eq!(assignments, pairs);
+        assert_eq!(rest, "cargo run B=1");
+        assert_eq!(take_assignments("X=").0, [("X".into(), String::new())]);
+        assert_eq!(take_assignments("1X=a b").1, "1X=a b");
+        assert_eq!(take_assignments("ls").0, []);
+    }
+
+    #[test]
+    fn control_commands_skip_the_queue() {
+        assert!(runs_while_busy("kill 1234"));
+        assert!(runs_while_busy("  jobs"));
+        assert!(runs_while_busy(":set busy-input stdin"));
+        assert!(!runs_while_busy("killall foo"));
+        assert!(!runs_while_busy("jobs | grep sleep"));
+        assert!(!runs_while_busy("make"));
+        assert!(!runs_while_busy(""));
+    }
+
+    #[test]
+    fn background_suffix() {
+        assert_eq!(strip_background("sleep 5 &"), Some("sleep 5"));
+        assert_eq!(strip_background("make&"), Some("make"));
+        assert_eq!(strip_background("a && b"), None);
+        assert_eq!(strip_background("echo 'x &'"), None);
+        assert_eq!(strip_background("echo \\&"), None);
+    }
+}
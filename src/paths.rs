@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Resolves a user-typed path against `cwd`, expanding a leading `~`.
+pub fn resolve(cwd: &Path, path: &str) -> PathBuf {
+    if path == "~" {
+        return home_dir().unwrap_or_else(|| cwd.to_path_buf());
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = home_dir() {
+            return home.join(rest);
+        }
+    }
+    cwd.join(path)
+}
+
+/// Directory holding the terminal's config file, following XDG on Unix.
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("ai_terminal"))
+}
+
+/// Directory for state the terminal accumulates, such as directory history.
+fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".local/share")))
+        .map(|dir| dir.join("ai_terminal"))
+}
+
+/// Directory for downloaded data that can be fetched again if deleted.
+pub fn cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".cache")))
+        .map(|dir| dir.join("ai_terminal"))
+}
+
+/// A named set of config and data files selected with `--profile`. The
+/// `default` profile uses the top-level directories, so setups from before
+/// profiles existed keep working; others live under `profiles/<name>`.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    name: String,
+}
+
+impl Profile {
+    pub const DEFAULT: &'static str = "default";
+
+    /// Profile names become directory names, so they're limited to
+    /// letters, digits, `-` and `_`.
+    pub fn new(name: &str) -> Result<Self, String> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if !valid {
+            return Err(format!(
+                "invalid profile name '{name}' (use letters, digits, - and _)"
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn scope(&self, base: Option<PathBuf>) -> Option<PathBuf> {
+        if self.name == Self::DEFAULT {
+            base
+        } else {
+            base.map(|dir| dir.join("profiles").join(&self.name))
+        }
+    }
+
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        self.scope(config_dir())
+    }
+
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.scope(data_dir())
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: Self::DEFAULT.to_string(),
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Process CPU and memory use for the built-in `top` and `ps`. On Linux
+//! they're read from `/proc`; on other Unix systems (macOS, the BSDs) from
+//! the system `ps -axo`. Windows has neither, so `Snapshot::take` returns
+//! `None` there and both commands say they aren't supported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// One process as of a snapshot.
+#[derive(Clone, Debug, PartialEq)]
+struct Process {
+    name: String,
+    ppid: u32,
+    /// User plus system time, in milliseconds.
+    cpu_ms: u64,
+    /// Resident memory in bytes.
+    memory: u64,
+}
+
+/// Every process's counters at one moment, with the time all CPUs had
+/// between them to measure them against.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// CPU time available so far, in milliseconds across all CPUs. Only
+    /// the difference between two snapshots means anything.
+    total_ms: u64,
+    cpus: usize,
+    processes: HashMap<u32, Process>,
+}
+
+/// A process's use between two snapshots.
+#[derive(Debug, PartialEq)]
+pub struct Row {
+    pub pid: u32,
+    pub name: String,
+    /// Percent of one CPU, so a busy multithreaded process can pass 100.
+    pub cpu: f32,
+    /// Resident memory in bytes.
+    pub memory: u64,
+}
+
+/// A process as `ps` lists it.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    /// CPU time used so far, in seconds.
+    pub seconds: u64,
+    /// Resident memory in bytes.
+    pub memory: u64,
+}
+
+/// The total ticks across all CPUs and the number of CPUs, from the
+/// text of `/proc/stat`.
+fn parse_cpu_totals(stat: &str) -> Option<(u64, usize)> {
+    let total = stat
+        .lines()
+        .find_map(|line| line.strip_prefix("cpu "))?
+        .split_whitespace()
+        .filter_map(|n| n.parse::<u64>().ok())
+        .sum();
+    let cpus = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu") && !line.starts_with("cpu "))
+        .count();
+    Some((total, cpus.max(1)))
+}
+
+/// A process from the text of `/proc/PID/stat`, whose times are in
+/// `clock_ticks` per second and memory in pages of `page_size`. The name is
+/// in parentheses and may itself contain spaces or parentheses, so fields
+/// are counted from the last `)`.
+fn parse_process(stat: &str, clock_ticks: u64, page_size: u64) -> Option<Process> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1..close)?.to_string();
+    // After the name: state is field 3, ppid 4, utime 14, stime 15, rss 24.
+    let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(Process {
+        name,
+        ppid: field(4)? as u32,
+        cpu_ms: (field(14)? + field(15)?) * 1000 / clock_ticks,
+        memory: field(24)? * page_size,
+    })
+}
+
+fn page_size() -> u64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf only reads a configuration value.
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if size > 0 {
+            return size as u64;
+        }
+    }
+    4096
+}
+
+fn clock_ticks() -> u64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf only reads a configuration value.
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            return ticks as u64;
+        }
+    }
+    100
+}
+
+/// CPU time from `ps -o time`: `[[DD-]HH:]MM:SS`, with a fraction of a
+/// second on macOS.
+fn parse_cpu_time(time: &str) -> Option<u64> {
+    let (days, clock) = match time.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, time),
+    };
+    let mut fields = clock.rsplit(':');
+    let seconds: f64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next()?.parse().ok()?;
+    let hours: u64 = fields.next().map_or(Some(0), |h| h.parse().ok())?;
+    let whole = ((days * 24 + hours) * 60 + minutes) * 60;
+    Some(whole * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Processes from `ps -axo pid=,ppid=,rss=,time=,comm=`, with `rss` in
+/// KiB. The command comes last and may contain spaces; macOS gives its
+/// whole path, of which only the name is kept.
+fn parse_ps(text: &str) -> HashMap<u32, Process> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let rss: u64 = fields.next()?.parse().ok()?;
+            let cpu_ms = parse_cpu_time(fields.next()?)?;
+            let command = fields.collect::<Vec<_>>().join(" ");
+            let name = command.rsplit('/').next().unwrap_or(&command).to_string();
+            Some((
+                pid,
+                Process {
+                    name,
+                    ppid,
+                    cpu_ms,
+                    memory: rss * 1024,
+                },
+            ))
+        })
+        .collect()
+}
+
+impl Snapshot {
+    /// Reads `/proc`, or asks the system `ps` where there's none. `None`
+    /// where neither works, as on Windows.
+    pub fn take() -> Option<Self> {
+        Self::read(Path::new("/proc")).or_else(Self::from_ps)
+    }
+
+    /// Reads a `/proc`-like directory: `stat`, and `PID/stat` for each
+    /// process.
+    fn read(proc: &Path) -> Option<Self> {
+        let (total_ticks, cpus) = parse_cpu_totals(&fs::read_to_string(proc.join("stat")).ok()?)?;
+        let (clock_ticks, page_size) = (clock_ticks(), page_size());
+        let processes = fs::read_dir(proc)
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_str()?.parse().ok()?;
+                let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+                Some((pid, parse_process(&stat, clock_ticks, page_size)?))
+            })
+            .collect();
+        Some(Self {
+            total_ms: total_ticks * 1000 / clock_ticks,
+            cpus,
+            processes,
+        })
+    }
+
+    /// Runs `ps`, measuring CPU use against the time that has passed on
+    /// the clock, times the number of CPUs.
+    #[cfg(unix)]
+    fn from_ps() -> Option<Self> {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        let output = std::process::Command::new("ps")
+            .args(["-axo", "pid=,ppid=,rss=,time=,comm="])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+        let elapsed = START.get_or_init(std::time::Instant::now).elapsed();
+        Some(Self {
+            total_ms: elapsed.as_millis() as u64 * cpus as u64,
+            cpus,
+            processes: parse_ps(&String::from_utf8_lossy(&output.stdout)),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn from_ps() -> Option<Self> {
+        None
+    }
+
+    /// Every process, by pid.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self
+            .processes
+            .iter()
+            .map(|(&pid, process)| Entry {
+                pid,
+                ppid: process.ppid,
+                name: process.name.clone(),
+                seconds: process.cpu_ms / 1000,
+                memory: process.memory,
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.pid);
+        entries
+    }
+
+    /// Each process still running in `after`, busiest first. Processes
+    /// that started in between are measured from zero.
+    pub fn rows(&self, after: &Snapshot) -> Vec<Row> {
+        let elapsed = after.total_ms.saturating_sub(self.total_ms).max(1) as f32;
+        let mut rows: Vec<Row> = after
+            .processes
+            .iter()
+            .map(|(&pid, process)| {
+                let before = self.processes.get(&pid).map_or(0, |p| p.cpu_ms);
+                let used = process.cpu_ms.saturating_sub(before) as f32;
+                Row {
+                    pid,
+                    name: process.name.clone(),
+                    cpu: used / elapsed * after.cpus as f32 * 100.0,
+                    memory: process.memory,
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            b.cpu
+                .total_cmp(&a.cpu)
+                .then(b.memory.cmp(&a.memory))
+                .then(a.pid.cmp(&b.pid))
+        });
+        rows
+    }
+}
+
+/// `bytes` in the largest unit that keeps it at 1 or more, as `top`
+/// shows memory.
+pub fn format_memory(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    for unit in ["B", "K", "M", "G"] {
+        if value < 1024.0 {
+            return if unit == "B" {
+                format!("{bytes}B")
+            } else {
+                format!("{value:.1}{unit}")
+            };
+        }
+        value /= 1024.0;
+    }
+    format!("{value:.1}T")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_and_ranks_by_cpu() {
+        let stat = "cpu  100 0 50 850 0 0 0 0 0 0\ncpu0 50 0 25 425\ncpu1 50 0 25 425\nintr 1\n";
+        assert_eq!(parse_cpu_totals(stat), Some((1000, 2)));
+
+        let line = "42 (Web (Content)) S 1 42 42 0 -1 4194304 10 0 0 0 30 20 0 0 20 0 \
+                    3 0 100 1000000 256 18446744073709551615";
+        let process = parse_process(line, 100, 4096).unwrap();
+        assert_eq!(process.name, "Web (Content)");
+        assert_eq!(
+            (process.ppid, process.cpu_ms, process.memory),
+            (1, 500, 256 * 4096)
+        );
+
+        let snapshot = |total_ms, ticks: &[(u32, u64)]| Snapshot {
+            total_ms,
+            cpus: 2,
+            processes: ticks
+                .iter()
+                .map(|&(pid, ticks)| {
+                    let process = Process {
+                        name: format!("p{pid}"),
+                        ppid: 1,
+                        cpu_ms: ticks,
+                        memory: 1,
+                    };
+                    (pid, process)
+                })
+                .collect(),
+        };
+        let before = snapshot(1000, &[(1, 10), (2, 10)]);
+        let after = snapshot(1100, &[(1, 20), (2, 60), (3, 5)]);
+        let cpu: Vec<(u32, f32)> = before.rows(&after).iter().map(|r| (r.pid, r.cpu)).collect();
+        assert_eq!(cpu, [(2, 100.0), (1, 20.0), (3, 10.0)]);
+
+        assert_eq!(format_memory(512), "512B");
+        assert_eq!(format_memory(3 * 1024 * 1024 / 2), "1.5M");
+    }
+
+    #[test]
+    fn reads_a_proc_directory_or_nothing() {
+        let dir = std::env::temp_dir().join(format!("procs-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("7")).unwrap();
+        fs::create_dir_all(dir.join("self")).unwrap();
+        // Without `stat`, as on a system with no /proc, there's no snapshot.
+        let missing = Snapshot::read(&dir);
+        fs::write(dir.join("stat"), "cpu  1 2 3 4\ncpu0 1 2 3 4\n").unwrap();
+        fs::write(
+            dir.join("7/stat"),
+            "7 (sh) S 1 7 7 0 -1 0 0 0 0 0 3 2 0 0 20 0 1 0 5 100 10",
+        )
+        .unwrap();
+        let snapshot = Snapshot::read(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(missing.is_none());
+        let entries = snapshot.unwrap().entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].pid, entries[0].ppid), (7, 1));
+        assert_eq!(entries[0].name, "sh");
+    }
+
+    #[test]
+    fn reads_ps_output() {
+        let text = "    1     0  1024 1-02:03:04 /sbin/launchd\n\
+                      311     1   512   0:01.25 /Applications/Google Chrome.app/Chrome Helper\n\
+                      312     1     8  00:00:02 kworker/0:1\n\
+                    junk\n";
+        let processes = parse_ps(text);
+        assert_eq!(processes.len(), 3);
+        let launchd = &processes[&1];
+        assert_eq!((launchd.name.as_str(), launchd.ppid), ("launchd", 0));
+        assert_eq!(launchd.cpu_ms, (((24 + 2) * 60 + 3) * 60 + 4) * 1000);
+        assert_eq!(launchd.memory, 1024 * 1024);
+        assert_eq!(processes[&311].name, "Chrome Helper");
+        assert_eq!(processes[&311].cpu_ms, 1250);
+        assert_eq!(processes[&312].cpu_ms, 2000);
+        assert_eq!(parse_cpu_time("5"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_system_ps_lists_this_process() {
+        let Some(snapshot) = Snapshot::from_ps() else {
+            return;
+        };
+        let own = std::process::id();
+        assert!(snapshot.entries().iter().any(|entry| entry.pid == own));
+    }
+}
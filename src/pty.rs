@@ -0,0 +1,92 @@
+//! Running a program on a pseudo-terminal, for the ones that need a real
+//! terminal: editors, pagers, `top`. The program sees a tty of the view's
+//! size; `vt` interprets what it draws.
+
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Stdio};
+
+use crate::shell::{self, ExecMode};
+
+/// What programs on the pty are told they're talking to.
+const TERM: &str = "xterm-256color";
+
+fn window_size(cols: usize, rows: usize) -> libc::winsize {
+    libc::winsize {
+        ws_row: rows.min(u16::MAX as usize) as u16,
+        ws_col: cols.min(u16::MAX as usize) as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+/// Spawns `args` in `cwd` as the session leader of a new pty, with `env`
+/// added to its environment, returning the child and the pty's master side
+/// to talk to it through.
+pub fn spawn(
+    args: &[String],
+    cwd: &Path,
+    mode: ExecMode,
+    env: &[(String, String)],
+    cols: usize,
+    rows: usize,
+) -> io::Result<(Child, File)> {
+    let (mut master, mut slave) = (0, 0);
+    let size = window_size(cols, rows);
+    // SAFETY: openpty writes the two descriptors it opens, which are then
+    // owned here; the name and termios arguments may be null.
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            &size,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: both descriptors were just opened and nothing else owns them.
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    // SAFETY: fcntl on a descriptor we own. The child mustn't inherit the
+    // master, or the pty would stay open after the program exits.
+    unsafe { libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    let mut command = shell::command(args, mode);
+    command
+        .current_dir(cwd)
+        .envs(env.iter().cloned())
+        .env("TERM", TERM)
+        .env("COLUMNS", cols.to_string())
+        .env("LINES", rows.to_string())
+        .stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+    // SAFETY: only async-signal-safe calls between fork and exec. The new
+    // session makes the pty the child's controlling terminal, so Ctrl+C
+    // typed to it reaches the program as SIGINT.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    // The command holds the slave copies; dropping it once spawned leaves
+    // only the child with the pty open.
+    let child = command.spawn()?;
+    Ok((child, File::from(master)))
+}
+
+/// Tells the program on `master` its terminal is now `cols` × `rows`; the
+/// kernel sends it SIGWINCH.
+pub fn resize(master: &File, cols: usize, rows: usize) {
+    let size = window_size(cols, rows);
+    // SAFETY: TIOCSWINSZ only reads the winsize passed.
+    unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ as _, &size) };
+}
@@ -0,0 +1,49 @@
+//! The right prompt: text drawn flush right on the input line, as zsh's
+//! `RPROMPT`, from a format such as `{status} {branch}{dirty} {time}`.
+//! Fields with nothing to show drop out along with the space around them.
+
+/// The placeholders a format can use, for help and validation.
+pub const FIELDS: &[&str] = &["time", "date", "branch", "dirty", "status", "sep"];
+
+/// `format` with each `{name}` replaced by `lookup(name)`. Unknown names
+/// are left as typed.
+pub fn render(format: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if FIELDS.contains(&&after[..end]) => {
+                out.push_str(&lookup(&after[..end]).unwrap_or_default());
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_fields_drop_out() {
+        let lookup = |name: &str| match name {
+            "branch" => Some("main".to_string()),
+            "dirty" => Some("*".to_string()),
+            "time" => Some("09:30:00".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            render("{status} {branch}{dirty}  {time}", lookup),
+            "main* 09:30:00"
+        );
+        assert_eq!(render("{nope} {time", lookup), "{nope} {time");
+    }
+}
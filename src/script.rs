@@ -0,0 +1,183 @@
+//! `for NAME in WORDS; do COMMANDS; done`, the one compound command the
+//! terminal understands, on one line or spread over several.
+
+use crate::{parse, shell};
+
+const USAGE: &str = "for: usage: for NAME in WORDS...; do COMMANDS; done";
+
+#[derive(Debug, PartialEq)]
+pub struct ForLoop {
+    pub var: String,
+    /// The words after `in`, not yet expanded.
+    pub list: String,
+    /// Commands run once per word, in order.
+    pub body: Vec<String>,
+}
+
+/// Splits `line` on unquoted `;` and newlines into trimmed, non-empty
+/// statements. `;` inside `$(...)` stays with its command.
+fn statements(line: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut depth = 0usize;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                current.push(c);
+                current.extend(chars.next());
+                continue;
+            }
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            c if quote == Some(c) => quote = None,
+            '(' if quote.is_none() => depth += 1,
+            ')' if quote.is_none() => depth = depth.saturating_sub(1),
+            ';' | '\n' if quote.is_none() && depth == 0 => {
+                statements.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    statements.push(current);
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// `statement` without its leading `keyword`, if it starts with it as a
+/// whole word.
+fn strip_keyword<'a>(statement: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = statement.strip_prefix(keyword)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim_start())
+}
+
+/// True if `line` starts a `for` loop.
+pub fn is_for(line: &str) -> bool {
+    strip_keyword(line.trim_start(), "for").is_some()
+}
+
+/// True if `line` is a `for` loop still waiting for its `done`, so Enter
+/// should start another line rather than run it.
+pub fn incomplete(line: &str) -> bool {
+    is_for(line) && statements(line).last().is_none_or(|s| s != "done")
+}
+
+/// Parses a `for` loop. `None` if `line` isn't one.
+pub fn parse_for(line: &str) -> Option<Result<ForLoop, String>> {
+    if !is_for(line) {
+        return None;
+    }
+    let mut statements = statements(line).into_iter();
+    let header = statements.next().unwrap_or_default();
+    let header = strip_keyword(&header, "for").unwrap_or_default();
+    let (var, list) = header
+        .split_once(char::is_whitespace)
+        .unwrap_or((header, ""));
+    let Some(list) = strip_keyword(list.trim_start(), "in").filter(|_| parse::is_name(var)) else {
+        return Some(Err(USAGE.to_string()));
+    };
+
+    let first = statements.next().unwrap_or_default();
+    let Some(first) = strip_keyword(&first, "do") else {
+        return Some(Err(USAGE.to_string()));
+    };
+    let mut body: Vec<String> = Some(first.to_string())
+        .filter(|s| !s.is_empty())
+        .into_iter()
+        .chain(statements)
+        .collect();
+    if body.pop().is_none_or(|last| last != "done") {
+        return Some(Err("for: missing `done`".to_string()));
+    }
+    if body
+        .iter()
+        .any(|command| is_for(command) || command == "done")
+    {
+        return Some(Err("for: nested loops aren't supported".to_string()));
+    }
+    Some(Ok(ForLoop {
+        var: var.to_string(),
+        list: list.to_string(),
+        body,
+    }))
+}
+
+/// `command` with `$var` and `${var}` replaced by `value`, quoted so it
+/// stays one literal word: the command is expanded again when it runs, and
+/// nothing in a list word may be substituted, expanded or split on `|`
+/// then. Other variables are left for that pass.
+pub fn bind(command: &str, var: &str, value: &str) -> String {
+    parse::replace_variables(command, &|name, in_double| {
+        (name == var).then(|| {
+            if in_double {
+                value
+                    .chars()
+                    .flat_map(|c| {
+                        let escape = matches!(c, '"' | '\\' | '$' | '`').then_some('\\');
+                        escape.into_iter().chain([c])
+                    })
+                    .collect()
+            } else {
+                shell::quote(value)
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_one_line_and_multi_line_loops() {
+        let expected = ForLoop {
+            var: "f".to_string(),
+            list: "a.txt 'b c'".to_string(),
+            body: vec!["echo $f".to_string(), "wc -l \"$f\"".to_string()],
+        };
+        let one_line = "for f in a.txt 'b c'; do echo $f; wc -l \"$f\"; done";
+        assert_eq!(parse_for(one_line), Some(Ok(expected)));
+        let multi_line = "for f in a.txt 'b c'\ndo\n  echo $f\n  wc -l \"$f\"\ndone";
+        assert_eq!(parse_for(multi_line).unwrap().unwrap().body.len(), 2);
+
+        assert!(incomplete("for f in a b"));
+        assert!(incomplete("for f in a b; do\necho $f"));
+        assert!(!incomplete(one_line));
+        assert!(!incomplete("format disk"));
+        assert_eq!(parse_for("format disk"), None);
+        assert!(parse_for("for 1 in a; do x; done").unwrap().is_err());
+        assert!(parse_for("for f in a; do for g in b; do x; done; done")
+            .unwrap()
+            .is_err());
+
+        let bound = bind("echo $f '$f' ${f}.bak $g", "f", "x");
+        assert_eq!(bound, "echo x '$f' x.bak $g");
+    }
+
+    #[test]
+    fn bound_words_stay_literal() {
+        let word = "$(touch pwned) | x &";
+        let bound = bind("echo $f \"<$f>\"", "f", word);
+        assert_eq!(
+            bound,
+            "echo '$(touch pwned) | x &' \"<\\$(touch pwned) | x &>\""
+        );
+        let expanded = parse::expand_variables(&bound, &|_| Some("no".to_string()));
+        let substituted = parse::substitute_commands(&expanded, &mut |inner| {
+            panic!("ran {inner}");
+        })
+        .unwrap();
+        assert_eq!(parse::split_pipeline(&substituted).len(), 1);
+        assert_eq!(parse::strip_background(&substituted), None);
+        assert_eq!(
+            parse::split_args(&substituted),
+            ["echo", word, &format!("<{word}>")]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Tees the session to a file for `--log-file`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct SessionLog {
+    writer: BufWriter<File>,
+    timestamp_format: String,
+    last_flush: Instant,
+    /// Lines have been written since the last flush.
+    unflushed: bool,
+}
+
+impl SessionLog {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &Path, timestamp_format: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            timestamp_format: timestamp_format.to_string(),
+            last_flush: Instant::now(),
+            unflushed: false,
+        })
+    }
+
+    pub fn set_timestamp_format(&mut self, format: &str) {
+        self.timestamp_format = format.to_string();
+    }
+
+    /// Writes one timestamped line. Errors are ignored so a full disk never
+    /// interrupts the session itself.
+    pub fn write_line(&mut self, text: &str) {
+        let timestamp = chrono::Local::now().format(&self.timestamp_format);
+        let _ = writeln!(self.writer, "[{timestamp}] {text}");
+        self.unflushed = true;
+        self.flush_if_due();
+    }
+
+    /// Flushes at most once per `FLUSH_INTERVAL`; called on every write and
+    /// every frame so output reaches disk shortly after it's produced.
+    /// Returns how long until lines still waiting are due, so the caller
+    /// can wake up for them.
+    pub fn flush_if_due(&mut self) -> Option<Duration> {
+        if !self.unflushed {
+            return None;
+        }
+        let elapsed = self.last_flush.elapsed();
+        if elapsed >= FLUSH_INTERVAL {
+            self.flush();
+            return None;
+        }
+        Some(FLUSH_INTERVAL - elapsed)
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.writer.flush();
+        self.last_flush = Instant::now();
+        self.unflushed = false;
+    }
+}
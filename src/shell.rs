@@ -0,0 +1,84 @@
+//! How external commands are started: directly, or through the user's
+//! login shell so the PATH and functions from their shell config apply.
+
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecMode {
+    /// Spawn the program with the arguments as parsed.
+    Direct,
+    /// Run it as `$SHELL -lc 'program' 'arg'...`.
+    Login,
+}
+
+impl ExecMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "direct" => Some(Self::Direct),
+            "login" => Some(Self::Login),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Login => "login",
+        }
+    }
+}
+
+/// Quotes `arg` for a POSIX shell so it reads back as exactly one word.
+pub fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// The argv that runs `args` as `mode` says. Windows has no login shell,
+/// so there it's always direct.
+pub fn argv(args: &[String], mode: ExecMode) -> Vec<String> {
+    if mode == ExecMode::Direct || cfg!(windows) {
+        return args.to_vec();
+    }
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string());
+    let script: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    vec![shell, "-lc".to_string(), script.join(" ")]
+}
+
+/// A `Command` for `args`, which must not be empty.
+pub fn command(args: &[String], mode: ExecMode) -> Command {
+    let argv = argv(args, mode);
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_the_login_shell() {
+        assert_eq!(quote("ls"), "ls");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote("$HOME;rm"), "'$HOME;rm'");
+        assert_eq!(quote(""), "''");
+
+        let args: Vec<String> = ["grep", "-r", "a b"].map(String::from).to_vec();
+        assert_eq!(argv(&args, ExecMode::Direct), args);
+        if cfg!(unix) {
+            assert_eq!(argv(&args, ExecMode::Login)[1..], ["-lc", "grep -r 'a b'"]);
+        }
+    }
+}
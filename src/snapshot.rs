@@ -0,0 +1,130 @@
+//! `watch-fs`: the current directory's listing before and after a command,
+//! compared by size and modification time.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub const DEFAULT_COMMANDS: &str = "git,make,cargo,npm,cp,mv,rm,tar,unzip";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Entry {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// One directory's entries by name, not descending into subdirectories.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub dir: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub created: Vec<String>,
+    pub deleted: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.deleted.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl Snapshot {
+    /// Lists `dir`; an unreadable directory gives an empty snapshot.
+    pub fn take(dir: &Path) -> Self {
+        let entries = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let metadata = entry.metadata().ok()?;
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        let entry = Entry {
+                            is_dir: metadata.is_dir(),
+                            len: metadata.len(),
+                            modified: metadata.modified().ok(),
+                        };
+                        Some((name, entry))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            dir: dir.to_path_buf(),
+            entries,
+        }
+    }
+
+    /// What changed between `self` and `after`, each list sorted by name.
+    /// Directories are listed with a trailing `/`.
+    pub fn diff(&self, after: &Snapshot) -> Changes {
+        let label = |name: &str, entry: &Entry| {
+            if entry.is_dir {
+                format!("{name}/")
+            } else {
+                name.to_string()
+            }
+        };
+        let mut changes = Changes::default();
+        for (name, entry) in &after.entries {
+            match self.entries.get(name) {
+                None => changes.created.push(label(name, entry)),
+                Some(old) if old != entry => changes.modified.push(label(name, entry)),
+                Some(_) => {}
+            }
+        }
+        for (name, entry) in &self.entries {
+            if !after.entries.contains_key(name) {
+                changes.deleted.push(label(name, entry));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(entries: &[(&str, bool, u64)]) -> Snapshot {
+        Snapshot {
+            dir: PathBuf::from("/project"),
+            entries: entries
+                .iter()
+                .map(|&(name, is_dir, len)| {
+                    let entry = Entry {
+                        is_dir,
+                        len,
+                        modified: None,
+                    };
+                    (name.to_string(), entry)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn reports_created_deleted_and_modified() {
+        let before = snapshot(&[("Cargo.toml", false, 10), ("old.txt", false, 3)]);
+        let after = snapshot(&[
+            ("Cargo.toml", false, 12),
+            ("target", true, 0),
+            ("new.txt", false, 0),
+        ]);
+        assert_eq!(
+            before.diff(&after),
+            Changes {
+                created: vec!["new.txt".to_string(), "target/".to_string()],
+                deleted: vec!["old.txt".to_string()],
+                modified: vec!["Cargo.toml".to_string()],
+            }
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+//! Assembling program output into lines the way a terminal displays it.
+//!
+//! Output arrives in arbitrary chunks that may split UTF-8 sequences and
+//! lines. A `\r` returns to the start of the line so following text
+//! overwrites it, which is how progress bars from `curl` or `pip` redraw
+//! in place.
+
+#[derive(Debug, PartialEq)]
+pub enum LineEvent {
+    /// The unfinished last line now reads this; it replaces the previous
+    /// partial text.
+    Partial(String),
+    /// The line is done and later output starts a new one.
+    Complete(String),
+}
+
+#[derive(Default)]
+pub struct LineAssembler {
+    /// Bytes of an incomplete UTF-8 sequence from the end of the last chunk.
+    pending: Vec<u8>,
+    current: Vec<char>,
+    column: usize,
+    /// True when `current` changed since the last event was emitted.
+    dirty: bool,
+    /// Bytes received so far.
+    pub bytes: usize,
+    /// Bytes that weren't valid UTF-8 and were shown as U+FFFD.
+    pub invalid: usize,
+    /// Whether a NUL byte was seen, which text output never contains.
+    pub nul: bool,
+}
+
+impl LineAssembler {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<LineEvent> {
+        self.bytes += bytes.len();
+        self.nul |= bytes.contains(&0);
+        self.pending.extend_from_slice(bytes);
+        let ready = decodable_len(&self.pending);
+        let bytes: Vec<u8> = self.pending.drain(..ready).collect();
+        self.invalid += invalid_len(&bytes);
+        let text = String::from_utf8_lossy(&bytes);
+
+        let mut events = Vec::new();
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    events.push(LineEvent::Complete(self.current.drain(..).collect()));
+                    self.column = 0;
+                    self.dirty = false;
+                }
+                '\r' => self.column = 0,
+                c => {
+                    if self.column < self.current.len() {
+                        self.current[self.column] = c;
+                    } else {
+                        self.current.push(c);
+                    }
+                    self.column += 1;
+                    self.dirty = true;
+                }
+            }
+        }
+        if self.dirty {
+            events.push(LineEvent::Partial(self.current.iter().collect()));
+            self.dirty = false;
+        }
+        events
+    }
+
+    /// Ends the stream, completing any unterminated last line.
+    pub fn finish(&mut self) -> Option<LineEvent> {
+        if !self.pending.is_empty() {
+            let bytes = std::mem::take(&mut self.pending);
+            self.invalid += invalid_len(&bytes);
+            self.current.extend(String::from_utf8_lossy(&bytes).chars());
+        }
+        if self.current.is_empty() {
+            return None;
+        }
+        self.column = 0;
+        Some(LineEvent::Complete(self.current.drain(..).collect()))
+    }
+
+    /// Mostly undecodable, or containing NULs: binary data rather than text
+    /// with a few stray bytes.
+    pub fn looks_binary(&self) -> bool {
+        self.nul || self.invalid * 10 > self.bytes
+    }
+}
+
+/// How much of `bytes` can be decoded now: everything but a truncated
+/// sequence at the end, which waits for the rest of its bytes. Invalid
+/// sequences before it are decoded lossily rather than held forever.
+fn decodable_len(bytes: &[u8]) -> usize {
+    let mut end = 0;
+    loop {
+        match std::str::from_utf8(&bytes[end..]) {
+            Ok(_) => return bytes.len(),
+            Err(e) => match e.error_len() {
+                Some(len) => end += e.valid_up_to() + len,
+                None => return end + e.valid_up_to(),
+            },
+        }
+    }
+}
+
+/// How many bytes of `bytes` `from_utf8_lossy` would replace.
+fn invalid_len(bytes: &[u8]) -> usize {
+    bytes.utf8_chunks().map(|chunk| chunk.invalid().len()).sum()
+}
+
+/// Splits complete output into displayed lines, applying `\r` overwrites.
+pub fn split_lines(bytes: &[u8]) -> Vec<String> {
+    let mut assembler = LineAssembler::default();
+    assembler
+        .push(bytes)
+        .into_iter()
+        .chain(assembler.finish())
+        .filter_map(|event| match event {
+            LineEvent::Complete(line) => Some(line),
+            LineEvent::Partial(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carriage_return_overwrites_in_place() {
+        assert_eq!(split_lines(b" 10%\r 55%\r100%\ndone\r\n"), ["100%", "done"]);
+        assert_eq!(split_lines(b"abcdef\r12"), ["12cdef"]);
+
+        let mut assembler = LineAssembler::default();
+        assert_eq!(
+            assembler.push(b"1%\r"),
+            [LineEvent::Partial("1%".to_string())]
+        );
+        assert_eq!(
+            assembler.push(b"2%\r3"),
+            [LineEvent::Partial("3%".to_string())]
+        );
+        // "é" split across chunks.
+        assert_eq!(
+            assembler.push(&[b'\n', 0xc3]),
+            [LineEvent::Complete("3%".to_string())]
+        );
+        assert_eq!(
+            assembler.push(&[0xa9]),
+            [LineEvent::Partial("é".to_string())]
+        );
+        assert_eq!(
+            assembler.finish(),
+            Some(LineEvent::Complete("é".to_string()))
+        );
+        assert_eq!((assembler.bytes, assembler.invalid), (10, 0));
+
+        let mut assembler = LineAssembler::default();
+        assembler.push(b"un caf\xe9 cr\xe8me, s'il vous pla\xeet\n");
+        assert_eq!(assembler.invalid, 3);
+        assert!(!assembler.looks_binary());
+        assembler.push(b"\x7fELF\x02\x01\x00\x00");
+        assert!(assembler.looks_binary());
+    }
+
+    #[test]
+    fn split_sequences_survive_an_invalid_byte_before_them() {
+        let mut assembler = LineAssembler::default();
+        assert_eq!(
+            assembler.push(b"a\xff\xc3"),
+            [LineEvent::Partial("a\u{fffd}".to_string())]
+        );
+        assert_eq!(
+            assembler.push(b"\xa9\n"),
+            [LineEvent::Complete("a\u{fffd}é".to_string())]
+        );
+        assert_eq!(assembler.invalid, 1);
+        assert_eq!(decodable_len(b"\xe2\x82"), 0);
+        assert_eq!(decodable_len(b"\xff\xfe"), 2);
+    }
+}
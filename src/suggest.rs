@@ -0,0 +1,179 @@
+//! Typo suggestions by edit distance.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long a listing of `$PATH` is trusted, so programs installed
+/// mid-session show up without a restart.
+const PATH_REFRESH: Duration = Duration::from_secs(30);
+
+/// Levenshtein distance between `a` and `b`, counted in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Up to `limit` candidates within `max_distance` of `word`, nearest first.
+/// An exact match is never suggested.
+pub fn closest<'a>(
+    word: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    max_distance: usize,
+    limit: usize,
+) -> Vec<String> {
+    let unique: BTreeSet<&str> = candidates.into_iter().collect();
+    let mut scored: Vec<(usize, &str)> = unique
+        .into_iter()
+        .filter(|c| *c != word)
+        .map(|c| (edit_distance(word, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, c)| c.to_string())
+        .collect()
+}
+
+/// Every candidate at the least distance from `word`, if that's within
+/// `max_distance`; more than one means a tie. An exact match is never
+/// suggested.
+pub fn nearest<'a>(
+    word: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    max_distance: usize,
+) -> Vec<String> {
+    let scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|c| *c != word)
+        .map(|c| (edit_distance(word, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect();
+    let Some(least) = scored.iter().map(|(d, _)| *d).min() else {
+        return Vec::new();
+    };
+    let nearest: BTreeSet<&str> = scored
+        .into_iter()
+        .filter(|(d, _)| *d == least)
+        .map(|(_, c)| c)
+        .collect();
+    nearest.into_iter().map(str::to_string).collect()
+}
+
+/// Names of the executables found in the directories on `$PATH`.
+pub fn path_executables() -> Vec<String> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if is_executable(&entry.path()) {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+    names
+}
+
+/// The executables on `$PATH`, listed once and again whenever `PATH`
+/// changes, as after `export PATH=...`, or the listing goes stale.
+pub struct PathCommands {
+    /// The `PATH` listed and when.
+    listed: (OsString, Instant),
+    names: Vec<String>,
+}
+
+impl PathCommands {
+    pub fn new() -> Self {
+        let mut names = path_executables();
+        names.sort();
+        names.dedup();
+        Self {
+            listed: (env::var_os("PATH").unwrap_or_default(), Instant::now()),
+            names,
+        }
+    }
+
+    /// The names, sorted, re-reading `$PATH` first if needed.
+    pub fn names(&mut self) -> &[String] {
+        let (path, at) = &self.listed;
+        if env::var_os("PATH").unwrap_or_default() != *path || at.elapsed() >= PATH_REFRESH {
+            *self = Self::new();
+        }
+        &self.names
+    }
+}
+
+/// True if `name` is an executable path, or names one in a `$PATH`
+/// directory.
+pub fn on_path(name: &str) -> bool {
+    if name.contains(std::path::MAIN_SEPARATOR) {
+        return is_executable(Path::new(name));
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| is_executable(&dir.join(name))))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_counts_single_edits() {
+        assert_eq!(edit_distance("grep", "grep"), 0);
+        assert_eq!(edit_distance("grpe", "grep"), 2);
+        assert_eq!(edit_distance("gti", "git"), 2);
+        assert_eq!(edit_distance("lss", "ls"), 1);
+        assert_eq!(edit_distance("", "cd"), 2);
+    }
+
+    #[test]
+    fn closest_orders_by_distance_and_limits() {
+        let candidates = ["grep", "egrep", "gre", "cargo", "git", "grep"];
+        assert_eq!(closest("grpe", candidates, 2, 3), vec!["gre", "grep"]);
+        assert_eq!(closest("gre", candidates, 2, 1), vec!["grep"]);
+        assert!(closest("zzzz", candidates, 2, 3).is_empty());
+    }
+
+    #[test]
+    fn nearest_keeps_only_ties() {
+        let dirs = ["Documents", "Downloads", "Desktop", "docs", "Dokuments"];
+        assert_eq!(nearest("Docuemnts", dirs, 2), vec!["Documents"]);
+        assert_eq!(nearest("Documents", dirs, 2), vec!["Dokuments"]);
+        assert_eq!(nearest("Dcs", dirs, 2), vec!["docs"]);
+        assert_eq!(nearest("Downlaods", dirs, 1), Vec::<String>::new());
+        assert_eq!(nearest("Documentss", dirs, 2), vec!["Documents"]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui;
+use egui::Color32;
+
+use crate::line::OUTPUT_COLOR;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const NAMES: &'static [&'static str] = &["dark", "light"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            Self::Dark => egui::Visuals::dark(),
+            Self::Light => egui::Visuals::light(),
+        }
+    }
+
+    pub fn background(self) -> Color32 {
+        match self {
+            Self::Dark => Color32::from_rgb(20, 20, 20),
+            Self::Light => Color32::from_rgb(250, 250, 248),
+        }
+    }
+
+    pub fn foreground(self) -> Color32 {
+        match self {
+            Self::Dark => OUTPUT_COLOR,
+            Self::Light => Color32::from_rgb(30, 30, 30),
+        }
+    }
+
+    /// Maps a line color, which is picked for a dark background, to one that
+    /// stays readable on this theme's background.
+    pub fn adapt(self, color: Color32) -> Color32 {
+        match self {
+            Self::Dark => color,
+            Self::Light if color == OUTPUT_COLOR => self.foreground(),
+            Self::Light if color == Color32::TRANSPARENT => color,
+            Self::Light => {
+                let darken = |c: u8| (c as f32 * 0.6) as u8;
+                Color32::from_rgb(darken(color.r()), darken(color.g()), darken(color.b()))
+            }
+        }
+    }
+
+    /// Like `adapt`, for highlight backgrounds: lightens them on light themes.
+    pub fn adapt_background(self, color: Color32) -> Color32 {
+        match self {
+            Self::Light if color != Color32::TRANSPARENT => {
+                let lighten = |c: u8| 255 - ((255 - c) as f32 * 0.3) as u8;
+                Color32::from_rgb(lighten(color.r()), lighten(color.g()), lighten(color.b()))
+            }
+            _ => color,
+        }
+    }
+}
+
+/// How often the OS preference is re-read while running.
+const SYSTEM_THEME_POLL: Duration = Duration::from_secs(5);
+
+fn detect_system_theme() -> Option<Theme> {
+    match dark_light::detect() {
+        dark_light::Mode::Dark => Some(Theme::Dark),
+        dark_light::Mode::Light => Some(Theme::Light),
+        dark_light::Mode::Default => None,
+    }
+}
+
+/// Tracks the OS dark/light preference on a background thread, since
+/// detection can involve a D-Bus round trip on Linux.
+pub struct SystemTheme {
+    current: Arc<Mutex<Option<Theme>>>,
+}
+
+impl SystemTheme {
+    /// Starts polling, repainting `ctx` whenever the preference changes.
+    pub fn watch(ctx: egui::Context) -> Self {
+        let current = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&current);
+        thread::spawn(move || loop {
+            let detected = detect_system_theme();
+            let changed = {
+                let mut current = shared.lock().unwrap();
+                let changed = *current != detected;
+                *current = detected;
+                changed
+            };
+            if changed {
+                ctx.request_repaint();
+            }
+            thread::sleep(SYSTEM_THEME_POLL);
+        });
+        Self { current }
+    }
+
+    /// The last detected preference, or `None` if unknown.
+    pub fn get(&self) -> Option<Theme> {
+        *self.current.lock().unwrap()
+    }
+}
@@ -0,0 +1,72 @@
+//! Formatting and summarizing command durations.
+
+use std::time::Duration;
+
+/// Human-scaled duration: `850.0 µs`, `12.3 ms`, `1.234 s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 1e-3 {
+        format!("{:.1} µs", secs * 1e6)
+    } else if secs < 1.0 {
+        format!("{:.1} ms", secs * 1e3)
+    } else {
+        format!("{secs:.3} s")
+    }
+}
+
+/// Summary statistics over a set of timed runs.
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    /// Sample standard deviation; zero for a single run.
+    pub stddev: Duration,
+}
+
+impl Stats {
+    pub fn from_runs(runs: &[Duration]) -> Option<Self> {
+        let mut sorted = runs.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let (&min, &max) = (sorted.first()?, sorted.last()?);
+
+        let secs: Vec<f64> = sorted.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let median = if n.is_multiple_of(2) {
+            (secs[n / 2 - 1] + secs[n / 2]) / 2.0
+        } else {
+            secs[n / 2]
+        };
+        let variance = if n > 1 {
+            secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Self {
+            min,
+            max,
+            mean: Duration::from_secs_f64(mean),
+            median: Duration::from_secs_f64(median),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_over_runs() {
+        let ms = Duration::from_millis;
+        let stats = Stats::from_runs(&[ms(30), ms(10), ms(20), ms(40)]).unwrap();
+        assert_eq!(stats.min, ms(10));
+        assert_eq!(stats.max, ms(40));
+        assert_eq!(stats.mean, ms(25));
+        assert_eq!(stats.median, ms(25));
+        assert_eq!(format_duration(stats.stddev), "12.9 ms");
+        assert!(Stats::from_runs(&[]).is_none());
+    }
+}
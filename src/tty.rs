@@ -0,0 +1,120 @@
+//! Programs that need a real terminal: run here on a pty, launched in an
+//! external terminal window, or refused with a message.
+
+use std::path::Path;
+
+use crate::suggest;
+
+pub const DEFAULT_COMMANDS: &str =
+    "vi,vim,nvim,nano,emacs,less,more,top,htop,btop,watch,tmux,screen";
+
+/// What to do with a command from the `tty-commands` list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TtyFallback {
+    /// Run it here on a pseudo-terminal.
+    Pty,
+    /// Explain that it can't run here.
+    Message,
+    /// Run it in a new window of the desktop's terminal emulator.
+    External,
+}
+
+impl TtyFallback {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pty" => Some(Self::Pty),
+            "message" => Some(Self::Message),
+            "external" => Some(Self::External),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pty => "pty",
+            Self::Message => "message",
+            Self::External => "external",
+        }
+    }
+}
+
+/// True if `program` is in the `tty-commands` `list`.
+pub fn needs_tty(program: &str, list: &str) -> bool {
+    crate::parse::in_list(program, list)
+}
+
+/// Emulators tried in order when none is configured, with the arguments
+/// that make each run a given program.
+const EMULATORS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("wezterm", &["start", "--"]),
+    ("xterm", &["-e"]),
+];
+
+/// The argv that runs `command` through `sh -c` in a new terminal window.
+/// `configured` is a prefix such as `xterm -e`; when empty, `$TERMINAL`
+/// and then the usual emulators are tried. `None` if none is available.
+pub fn external_args(configured: &str, command: &str, cwd: &Path) -> Option<Vec<String>> {
+    let shell = ["sh".to_string(), "-c".to_string(), command.to_string()];
+    if !configured.trim().is_empty() {
+        let mut args = crate::parse::split_args(configured);
+        args.extend(shell);
+        return Some(args);
+    }
+    if cfg!(target_os = "macos") {
+        let script = format!("cd '{}' && {command}", cwd.display());
+        let escaped = script.replace('\\', "\\\\").replace('"', "\\\"");
+        return Some(vec![
+            "osascript".to_string(),
+            "-e".to_string(),
+            format!("tell application \"Terminal\" to do script \"{escaped}\""),
+        ]);
+    }
+    if cfg!(windows) {
+        return Some(
+            ["cmd", "/C", "start", "", "cmd", "/K", command]
+                .map(String::from)
+                .to_vec(),
+        );
+    }
+    let from_env = std::env::var("TERMINAL")
+        .ok()
+        .filter(|t| suggest::on_path(t))
+        .map(|t| vec![t, "-e".to_string()]);
+    let found = from_env.or_else(|| {
+        EMULATORS
+            .iter()
+            .find(|(name, _)| suggest::on_path(name))
+            .map(|(name, args)| {
+                std::iter::once(*name)
+                    .chain(args.iter().copied())
+                    .map(String::from)
+                    .collect()
+            })
+    });
+    found.map(|mut args: Vec<String>| {
+        args.extend(shell);
+        args
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_program_names_in_list() {
+        assert!(needs_tty("vim", DEFAULT_COMMANDS));
+        assert!(needs_tty("/usr/bin/less", DEFAULT_COMMANDS));
+        assert!(!needs_tty("lesskey", DEFAULT_COMMANDS));
+        assert!(needs_tty("mc", "ranger, mc"));
+
+        let args = external_args("xterm -e", "vim 'a b'", Path::new("/")).unwrap();
+        assert_eq!(args, ["xterm", "-e", "sh", "-c", "vim 'a b'"]);
+    }
+}
@@ -0,0 +1,790 @@
+//! A small VT100/xterm screen emulator for programs run in a pty. It keeps
+//! the grid of cells they draw with cursor movement, erasing, scrolling and
+//! colors, which is enough for `less`, `top` and `vi`. Lines that scroll off
+//! the top of the main screen are handed back for the scrollback; the
+//! alternate screen full-screen programs switch to never is.
+
+use std::mem;
+
+use eframe::egui::{Color32, Key, Modifiers};
+
+use crate::line::{Segment, TerminalLine, OUTPUT_COLOR};
+
+/// Text drawn reversed in the default colors, e.g. the cursor.
+const REVERSE_FOREGROUND: Color32 = Color32::from_rgb(20, 20, 20);
+const REVERSE_BACKGROUND: Color32 = Color32::from_rgb(150, 150, 150);
+
+/// The 16 ANSI colors: normal, then bright.
+const PALETTE: [Color32; 16] = [
+    Color32::from_rgb(0, 0, 0),
+    Color32::from_rgb(205, 49, 49),
+    Color32::from_rgb(13, 188, 121),
+    Color32::from_rgb(229, 229, 16),
+    Color32::from_rgb(36, 114, 200),
+    Color32::from_rgb(188, 63, 188),
+    Color32::from_rgb(17, 168, 205),
+    Color32::from_rgb(229, 229, 229),
+    Color32::from_rgb(102, 102, 102),
+    Color32::from_rgb(241, 76, 76),
+    Color32::from_rgb(35, 209, 139),
+    Color32::from_rgb(245, 245, 67),
+    Color32::from_rgb(59, 142, 234),
+    Color32::from_rgb(214, 112, 214),
+    Color32::from_rgb(41, 184, 219),
+    Color32::from_rgb(255, 255, 255),
+];
+
+/// A color from the 256-color palette: the ANSI colors, a 6×6×6 cube,
+/// then a gray ramp.
+fn indexed_color(index: usize) -> Color32 {
+    match index {
+        0..=15 => PALETTE[index],
+        16..=231 => {
+            let level = |n: usize| [0, 95, 135, 175, 215, 255][n % 6];
+            let n = index - 16;
+            Color32::from_rgb(level(n / 36), level(n / 6), level(n))
+        }
+        _ => {
+            let gray = (8 + 10 * (index.min(255) - 232)) as u8;
+            Color32::from_rgb(gray, gray, gray)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    /// `None` is the default color.
+    pub foreground: Option<Color32>,
+    pub background: Option<Color32>,
+    pub reverse: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            c: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    /// After `ESC [`, collecting parameters.
+    Csi,
+    /// An operating system command, such as a window title; ignored.
+    Osc,
+    /// `ESC` inside an OSC, which `\` ends.
+    OscEscape,
+    /// After `ESC (` and the like, which name a character set.
+    Charset,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Saved {
+    row: usize,
+    col: usize,
+    style: Style,
+}
+
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    grid: Vec<Vec<Cell>>,
+    /// The main screen, while the alternate one is shown.
+    main: Option<Vec<Vec<Cell>>>,
+    row: usize,
+    col: usize,
+    /// A character was written in the last column; the next one wraps.
+    wrap_pending: bool,
+    style: Style,
+    saved: Saved,
+    /// The scroll region's first and last rows.
+    top: usize,
+    bottom: usize,
+    /// Arrow keys send `ESC O` rather than `ESC [`, as `vi` asks.
+    pub app_cursor: bool,
+    pub cursor_visible: bool,
+    state: State,
+    params: String,
+    /// The start of a UTF-8 sequence split across reads.
+    partial: Vec<u8>,
+    /// Answers owed to the program, e.g. to a cursor position query.
+    pub replies: Vec<u8>,
+    scrolled: Vec<Vec<Cell>>,
+}
+
+impl Screen {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        Self {
+            cols,
+            rows,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            main: None,
+            row: 0,
+            col: 0,
+            wrap_pending: false,
+            style: Style::default(),
+            saved: Saved::default(),
+            top: 0,
+            bottom: rows - 1,
+            app_cursor: false,
+            cursor_visible: true,
+            state: State::Ground,
+            params: String::new(),
+            partial: Vec::new(),
+            replies: Vec::new(),
+            scrolled: Vec::new(),
+        }
+    }
+
+    /// Interprets output from the program, returning the lines it scrolled
+    /// off the top of the main screen.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Vec<Cell>> {
+        let mut data = mem::take(&mut self.partial);
+        data.extend_from_slice(bytes);
+        let mut rest = &data[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.put(c));
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    let valid = std::str::from_utf8(valid).unwrap_or_default();
+                    valid.chars().for_each(|c| self.put(c));
+                    match e.error_len() {
+                        Some(len) => {
+                            self.put(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.partial = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        mem::take(&mut self.scrolled)
+    }
+
+    /// Changes the size, keeping the text around the cursor in view. Rows
+    /// cut from the top of the main screen go to the scrollback.
+    pub fn resize(&mut self, cols: usize, rows: usize) -> Vec<Vec<Cell>> {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        while self.grid.len() > rows {
+            if self.row > 0 {
+                let line = self.grid.remove(0);
+                if self.main.is_none() {
+                    self.scrolled.push(line);
+                }
+                self.row -= 1;
+            } else {
+                self.grid.pop();
+            }
+        }
+        if let Some(main) = &mut self.main {
+            main.truncate(rows);
+            main.resize(rows, vec![Cell::default(); cols]);
+            main.iter_mut()
+                .for_each(|line| line.resize(cols, Cell::default()));
+        }
+        self.grid.resize(rows, vec![Cell::default(); cols]);
+        self.grid
+            .iter_mut()
+            .for_each(|line| line.resize(cols, Cell::default()));
+        self.cols = cols;
+        self.rows = rows;
+        self.top = 0;
+        self.bottom = rows - 1;
+        self.row = self.row.min(rows - 1);
+        self.col = self.col.min(cols - 1);
+        self.wrap_pending = false;
+        mem::take(&mut self.scrolled)
+    }
+
+    /// The screen's rows as lines, the cursor drawn reversed.
+    pub fn lines(&self) -> Vec<TerminalLine> {
+        self.grid
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                if i == self.row && self.cursor_visible {
+                    let mut row = row.clone();
+                    row[self.col].style.reverse ^= true;
+                    to_line(&row)
+                } else {
+                    to_line(row)
+                }
+            })
+            .collect()
+    }
+
+    /// The main screen's rows down to the cursor or the last one written,
+    /// for the scrollback once the program exits.
+    pub fn remaining(&self) -> Vec<Vec<Cell>> {
+        let grid = self.main.as_ref().unwrap_or(&self.grid);
+        let used = grid
+            .iter()
+            .rposition(|row| row.iter().any(|cell| *cell != Cell::default()))
+            .map_or(0, |i| i + 1);
+        let end = if self.main.is_some() {
+            used
+        } else {
+            used.max(self.row + usize::from(self.col > 0))
+        };
+        grid[..end.min(grid.len())].to_vec()
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            c: ' ',
+            style: Style {
+                background: self.style.background,
+                ..Style::default()
+            },
+        }
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![self.blank(); self.cols]
+    }
+
+    fn put(&mut self, c: char) {
+        match self.state {
+            State::Ground => self.ground(c),
+            State::Escape => self.escape(c),
+            State::Csi => match c {
+                '0'..='?' | ' '..='/' => self.params.push(c),
+                '@'..='~' => {
+                    self.state = State::Ground;
+                    self.csi(c);
+                }
+                '\x1b' => self.state = State::Escape,
+                _ => {}
+            },
+            State::Osc => match c {
+                '\x07' => self.state = State::Ground,
+                '\x1b' => self.state = State::OscEscape,
+                _ => {}
+            },
+            State::OscEscape | State::Charset => self.state = State::Ground,
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\x1b' => self.state = State::Escape,
+            '\r' => self.move_to(self.row, 0),
+            '\n' | '\x0b' | '\x0c' => self.linefeed(),
+            '\x08' => self.move_to(self.row, self.col.saturating_sub(1)),
+            '\t' => self.move_to(self.row, (self.col / 8 + 1) * 8),
+            c if c.is_control() => {}
+            c => {
+                if self.wrap_pending {
+                    self.col = 0;
+                    self.linefeed();
+                }
+                self.grid[self.row][self.col] = Cell {
+                    c,
+                    style: self.style,
+                };
+                if self.col + 1 == self.cols {
+                    self.wrap_pending = true;
+                } else {
+                    self.col += 1;
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        self.state = State::Ground;
+        match c {
+            '[' => {
+                self.params.clear();
+                self.state = State::Csi;
+            }
+            ']' => self.state = State::Osc,
+            '(' | ')' | '*' | '+' => self.state = State::Charset,
+            '7' => self.save_cursor(),
+            '8' => self.restore_cursor(),
+            'D' => self.linefeed(),
+            'E' => {
+                self.move_to(self.row, 0);
+                self.linefeed();
+            }
+            'M' => self.reverse_index(),
+            'c' => {
+                let (cols, rows) = (self.cols, self.rows);
+                *self = Self::new(cols, rows);
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the cursor, kept on the screen.
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.rows - 1);
+        self.col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn linefeed(&mut self) {
+        self.wrap_pending = false;
+        if self.row == self.bottom {
+            self.scroll_up(1);
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.row == self.top {
+            self.scroll_down(1);
+        } else {
+            self.move_to(self.row.saturating_sub(1), self.col);
+        }
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n.min(self.bottom - self.top + 1) {
+            let line = self.grid.remove(self.top);
+            self.grid.insert(self.bottom, self.blank_row());
+            if self.top == 0 && self.main.is_none() {
+                self.scrolled.push(line);
+            }
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        for _ in 0..n.min(self.bottom - self.top + 1) {
+            self.grid.remove(self.bottom);
+            self.grid.insert(self.top, self.blank_row());
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = Saved {
+            row: self.row,
+            col: self.col,
+            style: self.style,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved;
+        self.style = saved.style;
+        self.move_to(saved.row, saved.col);
+    }
+
+    fn set_alternate(&mut self, on: bool) {
+        if on && self.main.is_none() {
+            let blank = vec![vec![Cell::default(); self.cols]; self.rows];
+            self.main = Some(mem::replace(&mut self.grid, blank));
+        } else if !on {
+            if let Some(main) = self.main.take() {
+                self.grid = main;
+            }
+        }
+    }
+
+    fn csi(&mut self, action: char) {
+        let private = self.params.starts_with('?');
+        let query = self.params.starts_with(['>', '=']);
+        let params: Vec<usize> = self
+            .params
+            .trim_start_matches(['?', '>', '='])
+            .split(';')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        // A count or position, where 0 and missing both mean `default`.
+        let arg =
+            |i: usize, default: usize| params.get(i).copied().filter(|&n| n > 0).unwrap_or(default);
+        let n = arg(0, 1);
+        let (row, col) = (self.row, self.col);
+        match action {
+            'A' => self.move_to(row.saturating_sub(n), col),
+            'B' | 'e' => self.move_to(row + n, col),
+            'C' | 'a' => self.move_to(row, col + n),
+            'D' => self.move_to(row, col.saturating_sub(n)),
+            'E' => self.move_to(row + n, 0),
+            'F' => self.move_to(row.saturating_sub(n), 0),
+            'G' | '`' => self.move_to(row, n - 1),
+            'd' => self.move_to(n - 1, col),
+            'H' | 'f' => self.move_to(arg(0, 1) - 1, arg(1, 1) - 1),
+            'J' => {
+                let blank = self.blank();
+                let (start, end) = match params[0] {
+                    0 => ((row, col), (self.rows, 0)),
+                    1 => ((0, 0), (row, col + 1)),
+                    _ => ((0, 0), (self.rows, 0)),
+                };
+                for r in start.0..self.rows.min(end.0 + 1) {
+                    let from = if r == start.0 { start.1 } else { 0 };
+                    let to = if r == end.0 { end.1 } else { self.cols };
+                    for cell in &mut self.grid[r][from..to.min(self.cols)] {
+                        *cell = blank;
+                    }
+                }
+            }
+            'K' => {
+                let blank = self.blank();
+                let range = match params[0] {
+                    0 => col..self.cols,
+                    1 => 0..col + 1,
+                    _ => 0..self.cols,
+                };
+                self.grid[row][range].fill(blank);
+            }
+            'L' | 'M' if (self.top..=self.bottom).contains(&row) => {
+                for _ in 0..n.min(self.bottom - row + 1) {
+                    if action == 'L' {
+                        self.grid.remove(self.bottom);
+                        self.grid.insert(row, self.blank_row());
+                    } else {
+                        self.grid.remove(row);
+                        self.grid.insert(self.bottom, self.blank_row());
+                    }
+                }
+                self.move_to(row, 0);
+            }
+            'P' | '@' | 'X' => {
+                let blank = self.blank();
+                let n = n.min(self.cols - col);
+                let line = &mut self.grid[row];
+                match action {
+                    'P' => {
+                        line.drain(col..col + n);
+                        line.resize(self.cols, blank);
+                    }
+                    '@' => {
+                        line.splice(col..col, vec![blank; n]);
+                        line.truncate(self.cols);
+                    }
+                    _ => line[col..col + n].fill(blank),
+                }
+            }
+            'S' => self.scroll_up(n),
+            'T' => self.scroll_down(n),
+            'r' => {
+                let (top, bottom) = (arg(0, 1) - 1, arg(1, self.rows) - 1);
+                if top < bottom && bottom < self.rows {
+                    self.top = top;
+                    self.bottom = bottom;
+                } else {
+                    self.top = 0;
+                    self.bottom = self.rows - 1;
+                }
+                self.move_to(0, 0);
+            }
+            'm' => self.select_graphic_rendition(&params),
+            'h' | 'l' if private => {
+                let on = action == 'h';
+                for mode in &params {
+                    match mode {
+                        1 => self.app_cursor = on,
+                        25 => self.cursor_visible = on,
+                        47 | 1047 => self.set_alternate(on),
+                        1049 => {
+                            if on {
+                                self.save_cursor();
+                                self.set_alternate(true);
+                                self.move_to(0, 0);
+                            } else {
+                                self.set_alternate(false);
+                                self.restore_cursor();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            'n' if params[0] == 5 => self.replies.extend_from_slice(b"\x1b[0n"),
+            'n' if params[0] == 6 => {
+                let report = format!("\x1b[{};{}R", row + 1, col + 1);
+                self.replies.extend_from_slice(report.as_bytes());
+            }
+            'c' if !query => self.replies.extend_from_slice(b"\x1b[?1;2c"),
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[usize]) {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            let style = &mut self.style;
+            match param {
+                0 => *style = Style::default(),
+                7 => style.reverse = true,
+                27 => style.reverse = false,
+                30..=37 => style.foreground = Some(PALETTE[param - 30]),
+                39 => style.foreground = None,
+                40..=47 => style.background = Some(PALETTE[param - 40]),
+                49 => style.background = None,
+                90..=97 => style.foreground = Some(PALETTE[param - 90 + 8]),
+                100..=107 => style.background = Some(PALETTE[param - 100 + 8]),
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(5) => params.next().map(indexed_color),
+                        Some(2) => {
+                            let mut channel = || params.next().map(|c| c.min(255) as u8);
+                            match (channel(), channel(), channel()) {
+                                (Some(r), Some(g), Some(b)) => Some(Color32::from_rgb(r, g, b)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    if param == 38 {
+                        style.foreground = color;
+                    } else {
+                        style.background = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The bytes a terminal sends for `key`, or `None` for keys it doesn't
+/// send anything for. Text keys arrive separately as typed text.
+pub fn encode_key(key: Key, modifiers: Modifiers, app_cursor: bool) -> Option<Vec<u8>> {
+    let name = key.name();
+    if modifiers.ctrl && name.len() == 1 && name.as_bytes()[0].is_ascii_uppercase() {
+        return Some(vec![name.as_bytes()[0] - b'A' + 1]);
+    }
+    let cursor = |c: char| {
+        let prefix = if app_cursor { "\x1bO" } else { "\x1b[" };
+        Some(format!("{prefix}{c}").into_bytes())
+    };
+    let text = |text: &str| Some(text.as_bytes().to_vec());
+    match key {
+        Key::Enter => text("\r"),
+        Key::Tab if modifiers.shift => text("\x1b[Z"),
+        Key::Tab => text("\t"),
+        Key::Backspace => text("\x7f"),
+        Key::Escape => text("\x1b"),
+        Key::ArrowUp => cursor('A'),
+        Key::ArrowDown => cursor('B'),
+        Key::ArrowRight => cursor('C'),
+        Key::ArrowLeft => cursor('D'),
+        Key::Home => cursor('H'),
+        Key::End => cursor('F'),
+        Key::Insert => text("\x1b[2~"),
+        Key::Delete => text("\x1b[3~"),
+        Key::PageUp => text("\x1b[5~"),
+        Key::PageDown => text("\x1b[6~"),
+        Key::F1 => text("\x1bOP"),
+        Key::F2 => text("\x1bOQ"),
+        Key::F3 => text("\x1bOR"),
+        Key::F4 => text("\x1bOS"),
+        Key::F5 => text("\x1b[15~"),
+        Key::F6 => text("\x1b[17~"),
+        Key::F7 => text("\x1b[18~"),
+        Key::F8 => text("\x1b[19~"),
+        Key::F9 => text("\x1b[20~"),
+        Key::F10 => text("\x1b[21~"),
+        Key::F11 => text("\x1b[23~"),
+        Key::F12 => text("\x1b[24~"),
+        _ => None,
+    }
+}
+
+/// A row of cells as a scrollback line, without its trailing blanks.
+pub fn to_line(row: &[Cell]) -> TerminalLine {
+    let end = row
+        .iter()
+        .rposition(|cell| *cell != Cell::default())
+        .map_or(0, |i| i + 1);
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut current: Option<Style> = None;
+    for cell in &row[..end] {
+        if current != Some(cell.style) {
+            current = Some(cell.style);
+            let style = cell.style;
+            let (mut color, mut background) = (
+                style.foreground.unwrap_or(OUTPUT_COLOR),
+                style.background.unwrap_or(Color32::TRANSPARENT),
+            );
+            if style.reverse {
+                (color, background) = (
+                    style.background.unwrap_or(REVERSE_FOREGROUND),
+                    style.foreground.unwrap_or(REVERSE_BACKGROUND),
+                );
+            }
+            segments.push(Segment::highlighted("", color, background));
+        }
+        if let Some(segment) = segments.last_mut() {
+            segment.text.push(cell.c);
+        }
+    }
+    if segments.is_empty() {
+        return TerminalLine::plain("", OUTPUT_COLOR);
+    }
+    TerminalLine::from_segments(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(screen: &Screen) -> Vec<String> {
+        screen.grid.iter().map(|row| to_line(row).text()).collect()
+    }
+
+    #[test]
+    fn draws_moves_and_scrolls() {
+        let mut screen = Screen::new(5, 3);
+        let scrolled = screen.feed(b"one\r\ntwo\r\nthree\r\nfour");
+        assert_eq!(to_line(&scrolled[0]).text(), "one");
+        assert_eq!(text(&screen), ["two", "three", "four"]);
+
+        // Cursor addressing, erase to end of line, and a split UTF-8 é.
+        screen.feed(b"\x1b[1;2H\x1b[K\xc3");
+        screen.feed(b"\xa9\x1b[2J\x1b[Hab");
+        assert_eq!(text(&screen), ["ab", "", ""]);
+
+        // Colors end up on the segments; reverse swaps them.
+        screen.feed(b"\x1b[31mr\x1b[0;7mv\x1b[m");
+        let line = to_line(&screen.grid[0]);
+        assert_eq!(line.segments[1].color, PALETTE[1]);
+        assert_eq!(line.segments[2].background, REVERSE_BACKGROUND);
+
+        // The alternate screen is separate and never scrolls back.
+        assert!(screen.feed(b"\x1b[?1049hx\r\n\n\n\n").is_empty());
+        assert!(screen.main.is_some());
+        screen.feed(b"\x1b[?1049l");
+        assert_eq!(text(&screen)[0], "abrv");
+
+        screen.feed(b"\x1b[6n");
+        assert_eq!(screen.replies, b"\x1b[1;5R");
+
+        // Wrapping at the last column, and remaining rows at exit.
+        let mut screen = Screen::new(3, 4);
+        screen.feed(b"abcd");
+        assert_eq!(text(&screen)[..2], ["abc", "d"]);
+        assert_eq!(screen.remaining().len(), 2);
+    }
+
+    #[test]
+    fn scroll_regions_keep_the_rows_outside() {
+        let mut screen = Screen::new(4, 5);
+        screen.feed(b"a\r\nb\r\nc\r\nd\r\ne");
+        // Rows 2-4 scroll; the first and last stay, and nothing leaves the
+        // screen for the scrollback.
+        screen.feed(b"\x1b[2;4r\x1b[4;1H\n");
+        assert_eq!(text(&screen), ["a", "c", "d", "", "e"]);
+        assert!(screen.feed(b"\x1b[2;1H\x1bM").is_empty());
+        assert_eq!(text(&screen), ["a", "", "c", "d", "e"]);
+        // A region that doesn't make sense resets to the whole screen.
+        screen.feed(b"\x1b[4;2r");
+        assert_eq!((screen.top, screen.bottom), (0, 4));
+    }
+
+    #[test]
+    fn inserts_and_deletes_lines_and_characters() {
+        let mut screen = Screen::new(5, 4);
+        screen.feed(b"1\r\n2\r\n3\r\n4");
+        screen.feed(b"\x1b[2;3H\x1b[L");
+        assert_eq!(text(&screen), ["1", "", "2", "3"]);
+        assert_eq!(screen.col, 0);
+        screen.feed(b"\x1b[2M");
+        assert_eq!(text(&screen), ["1", "3", "", ""]);
+        // Inside a region, lines only move between its margins.
+        screen.feed(b"\x1b[H\x1b[2J1\r\n2\r\n3\r\n4\x1b[1;3r\x1b[1;1H\x1b[L");
+        assert_eq!(text(&screen), ["", "1", "2", "4"]);
+        // Outside the region IL does nothing.
+        screen.feed(b"\x1b[4;1H\x1b[L");
+        assert_eq!(text(&screen), ["", "1", "2", "4"]);
+
+        let mut screen = Screen::new(5, 1);
+        screen.feed(b"abcde\x1b[1;2H\x1b[2P");
+        assert_eq!(text(&screen), ["ade"]);
+        screen.feed(b"\x1b[@");
+        assert_eq!(text(&screen), ["a de"]);
+        screen.feed(b"\x1b[9@");
+        assert_eq!(text(&screen), ["a"]);
+        screen.feed(b"\rxyz\x1b[1;1H\x1b[2X");
+        assert_eq!(text(&screen), ["  z"]);
+    }
+
+    #[test]
+    fn alternate_screen_restores_the_main_one() {
+        let mut screen = Screen::new(4, 2);
+        screen.feed(b"main\x1b[1;2H");
+        screen.feed(b"\x1b[?1049h");
+        assert_eq!(text(&screen), ["", ""]);
+        assert_eq!((screen.row, screen.col), (0, 0));
+        screen.feed(b"alt\x1b[2;3H");
+        // Still the main screen's rows that the program leaves behind.
+        assert_eq!(to_line(&screen.remaining()[0]).text(), "main");
+        screen.feed(b"\x1b[?1049l");
+        assert_eq!(text(&screen), ["main", ""]);
+        assert_eq!((screen.row, screen.col), (0, 1));
+        // 47 switches without saving the cursor; leaving twice is harmless.
+        screen.feed(b"\x1b[?47hx\x1b[?47l\x1b[?47l");
+        assert_eq!(text(&screen), ["main", ""]);
+    }
+
+    #[test]
+    fn resizing_keeps_the_cursor_in_view() {
+        let mut screen = Screen::new(4, 3);
+        screen.feed(b"a\r\nb\r\nc");
+        // Shrinking cuts rows above the cursor into the scrollback.
+        let cut = screen.resize(2, 2);
+        assert_eq!(cut.len(), 1);
+        assert_eq!(to_line(&cut[0]).text(), "a");
+        assert_eq!(text(&screen), ["b", "c"]);
+        assert_eq!((screen.row, screen.col), (1, 1));
+        // Growing adds blank rows and columns and resets the region.
+        screen.feed(b"\x1b[1;1r");
+        assert!(screen.resize(6, 4).is_empty());
+        assert_eq!(text(&screen), ["b", "c", "", ""]);
+        assert_eq!((screen.top, screen.bottom), (0, 3));
+        assert!(screen.grid.iter().all(|row| row.len() == 6));
+
+        // With the cursor at the top, rows are cut from the bottom.
+        let mut screen = Screen::new(3, 3);
+        screen.feed(b"x\r\ny\x1b[H");
+        assert!(screen.resize(3, 1).is_empty());
+        assert_eq!(text(&screen), ["x"]);
+
+        // The main screen resizes too while the alternate one is shown.
+        let mut screen = Screen::new(3, 2);
+        screen.feed(b"abc\x1b[?1049h");
+        screen.resize(5, 3);
+        screen.feed(b"\x1b[?1049l");
+        assert_eq!(text(&screen), ["abc", "", ""]);
+        assert!(screen.grid.iter().all(|row| row.len() == 5));
+    }
+
+    #[test]
+    fn encodes_keys() {
+        let ctrl = Modifiers::CTRL;
+        assert_eq!(encode_key(Key::C, ctrl, false), Some(vec![3]));
+        assert_eq!(encode_key(Key::C, Modifiers::NONE, false), None);
+        assert_eq!(
+            encode_key(Key::ArrowUp, Modifiers::NONE, false).unwrap(),
+            b"\x1b[A"
+        );
+        assert_eq!(
+            encode_key(Key::ArrowUp, Modifiers::NONE, true).unwrap(),
+            b"\x1bOA"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! The window's size and position, saved on exit and restored at startup.
+//!
+//! Stored as one line: `width height [x y] [maximized]`. The position is
+//! left out where the platform doesn't report one, as on Wayland.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::paths::Profile;
+
+const DEFAULT_SIZE: [f32; 2] = [1000.0, 700.0];
+
+/// Smallest size restored, so a window shrunk to nothing comes back usable.
+const MIN_SIZE: [f32; 2] = [320.0, 200.0];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Geometry {
+    /// Inner size in points.
+    pub size: [f32; 2],
+    /// Outer top-left corner in points.
+    pub position: Option<[f32; 2]>,
+    pub maximized: bool,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_SIZE,
+            position: None,
+            maximized: false,
+        }
+    }
+}
+
+fn path(profile: &Profile) -> Option<PathBuf> {
+    profile.data_dir().map(|dir| dir.join("window"))
+}
+
+impl Geometry {
+    fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.split_whitespace();
+        let mut number = || fields.next()?.parse::<f32>().ok().filter(|n| n.is_finite());
+        let size = [number()?, number()?];
+        let position = number().zip(number()).map(|(x, y)| [x, y]);
+        Some(Self {
+            size: [size[0].max(MIN_SIZE[0]), size[1].max(MIN_SIZE[1])],
+            position,
+            maximized: text.split_whitespace().any(|f| f == "maximized"),
+        })
+    }
+
+    fn format(&self) -> String {
+        let mut fields = vec![self.size[0].to_string(), self.size[1].to_string()];
+        if let Some([x, y]) = self.position {
+            fields.extend([x.to_string(), y.to_string()]);
+        }
+        if self.maximized {
+            fields.push("maximized".to_string());
+        }
+        fields.join(" ") + "\n"
+    }
+
+    /// The saved geometry, or the default size if there is none.
+    pub fn load(profile: &Profile) -> Self {
+        path(profile)
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, profile: &Profile) -> io::Result<()> {
+        let path =
+            path(profile).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.format())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_tolerates_bad_input() {
+        let geometry = Geometry {
+            size: [1200.0, 800.5],
+            position: Some([-1920.0, 40.0]),
+            maximized: true,
+        };
+        assert_eq!(Geometry::parse(&geometry.format()), Some(geometry));
+        let sized = Geometry::parse("900 600\n").unwrap();
+        assert_eq!((sized.size, sized.position), ([900.0, 600.0], None));
+        assert_eq!(Geometry::parse("10 10").unwrap().size, MIN_SIZE);
+        assert!(Geometry::parse("wide tall").is_none());
+        assert!(Geometry::parse("NaN 600").is_none());
+    }
+}